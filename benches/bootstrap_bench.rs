@@ -2,8 +2,11 @@
 
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use gsw_rs::bootstrap::{bootstrap, gen_evaluation_key};
+use gsw_rs::kernels::{available_kernels, matrix_product_with};
 use gsw_rs::params::{Params, SecurityLevel};
-use gsw_rs::{encrypt, gsw_keygen, homomorphic_mult};
+use gsw_rs::{
+    decrypt, encrypt, gsw_keygen, homomorphic_mult, DecryptionContext, EncryptionContext,
+};
 use rand::SeedableRng;
 use rand_chacha::ChaCha20Rng;
 
@@ -40,5 +43,111 @@ fn gen_evaluation_key_128bit(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bootstrap_128bit, gen_evaluation_key_128bit);
+/// One homomorphic product's worth of matrix multiplication, per kernel.
+fn matrix_product_kernels(c: &mut Criterion) {
+    let params = Params::new(SecurityLevel::Medium);
+    let mut rng = ChaCha20Rng::seed_from_u64(42);
+    let (_, pk) = gsw_keygen(&mut rng, &params);
+    let ct1 = encrypt(&mut rng, &pk, 1);
+    let ct2 = encrypt(&mut rng, &pk, 0);
+
+    let mut group = c.benchmark_group("matrix_product");
+    for kernel in available_kernels() {
+        group.bench_function(kernel, |b| {
+            b.iter(|| matrix_product_with(kernel, black_box(&ct1), black_box(&ct2), params.q))
+        });
+    }
+    group.finish();
+}
+
+/// A toy-sized product, below the default parallel threshold: should cost
+/// the same with and without the `rayon` feature.
+fn homomorphic_mult_toy(c: &mut Criterion) {
+    let params = Params::toy();
+    let mut rng = ChaCha20Rng::seed_from_u64(42);
+    let (_, pk) = gsw_keygen(&mut rng, &params);
+    let ct1 = encrypt(&mut rng, &pk, 1);
+    let ct2 = encrypt(&mut rng, &pk, 0);
+
+    c.bench_function("homomorphic_mult_toy", |b| {
+        b.iter(|| homomorphic_mult(black_box(&params), black_box(&ct1), black_box(&ct2)))
+    });
+}
+
+/// Decrypting a 1000-ciphertext batch: a fresh PowersOf2(s) per call versus
+/// one shared context. Every entry is the same ciphertext to keep memory flat.
+fn decrypt_batch(c: &mut Criterion) {
+    let params = Params::new(SecurityLevel::Medium);
+    let mut rng = ChaCha20Rng::seed_from_u64(42);
+    let (sk, pk) = gsw_keygen(&mut rng, &params);
+    let ct = encrypt(&mut rng, &pk, 1);
+    let batch = vec![&ct; 1000];
+
+    let mut group = c.benchmark_group("decrypt_batch_1000");
+    group.bench_function("per_call", |b| {
+        b.iter(|| {
+            batch
+                .iter()
+                .map(|ct| decrypt(black_box(&sk), ct))
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("context", |b| {
+        b.iter(|| DecryptionContext::new(black_box(&sk)).decrypt_many(&batch))
+    });
+    group.finish();
+}
+
+/// Encrypting a batch of bits, where sampling the N×m binary R matrix is a
+/// large share of the cost next to R·A.
+fn encrypt_many(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encrypt_many");
+    group.sample_size(10);
+    for (name, level, count) in [
+        ("toy_100", SecurityLevel::Toy, 100),
+        ("medium_4", SecurityLevel::Medium, 4),
+    ] {
+        let params = Params::new(level);
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let (_, pk) = gsw_keygen(&mut rng, &params);
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                (0..count)
+                    .map(|i| encrypt(&mut rng, black_box(&pk), (i % 2) as u8))
+                    .collect::<Vec<_>>()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// One Medium encryption through `encrypt` and through a prebuilt
+/// `EncryptionContext`.
+fn encryption_context(c: &mut Criterion) {
+    let params = Params::new(SecurityLevel::Medium);
+    let mut rng = ChaCha20Rng::seed_from_u64(42);
+    let (_, pk) = gsw_keygen(&mut rng, &params);
+    let ctx = EncryptionContext::build(&pk, &params).unwrap();
+
+    let mut group = c.benchmark_group("encryption_context");
+    group.sample_size(10);
+    group.bench_function("encrypt", |b| {
+        b.iter(|| encrypt(&mut rng, black_box(&pk), 1))
+    });
+    group.bench_function("context", |b| {
+        b.iter(|| black_box(&ctx).encrypt(&mut rng, 1))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bootstrap_128bit,
+    gen_evaluation_key_128bit,
+    matrix_product_kernels,
+    homomorphic_mult_toy,
+    decrypt_batch,
+    encrypt_many,
+    encryption_context
+);
 criterion_main!(benches);
@@ -0,0 +1,57 @@
+//! Averaging three parties' private 4-bit values on an untrusted server.
+//!
+//! Each party encrypts its value under the client's public key; the server
+//! sees only ciphertexts, sums them with a balanced adder tree (two bits of
+//! growth, so the 6-bit sum cannot overflow) and divides by the public count
+//! of 3. The client decrypts floor(sum / 3).
+//!
+//! The division feeds on the sum's carry bits, so the noise budget has to
+//! cover both circuits back to back; the parameters come from
+//! `Params::for_depth`, which picks a 50-bit modulus for this budget. No
+//! bootstrapping is done: this crate's bootstrap does not yet reduce noise at
+//! these parameters, so the budget is bought with a wider modulus instead.
+//!
+//! Run with `cargo run --release --example private_average`.
+
+use std::time::Instant;
+
+use gsw_rs::{decrypt_uint, encrypt_uint, gsw_keygen, homomorphic_mean, EncryptedUint, Params};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+const PARTIES: usize = 3;
+const WIDTH: usize = 4;
+
+/// Multiplicative depth requested from the parameter search.
+const DEPTH: u32 = 4;
+
+fn main() {
+    let params = Params::for_depth(DEPTH, -40).expect("no parameters for the requested depth");
+    println!(
+        "Parameters: n = {}, q = 2^{}, N = {} (depth {})",
+        params.n, params.l, params.n_expanded, DEPTH
+    );
+
+    let mut rng = ChaCha20Rng::seed_from_u64(2024);
+    let (sk, pk) = gsw_keygen(&mut rng, &params);
+
+    for _ in 0..3 {
+        let inputs: Vec<u64> = (0..PARTIES).map(|_| rng.gen_range(0..16)).collect();
+        let values: Vec<EncryptedUint> = inputs
+            .iter()
+            .map(|&v| encrypt_uint(&mut rng, &pk, v, WIDTH))
+            .collect();
+
+        let start = Instant::now();
+        let mean = homomorphic_mean(&params, &values).expect("there are values");
+        let elapsed = start.elapsed();
+
+        let got = decrypt_uint(&sk, &mean);
+        let expected = inputs.iter().sum::<u64>() / PARTIES as u64;
+        println!(
+            "inputs {:?}: homomorphic mean {}, clear {} ({:.2?})",
+            inputs, got, expected, elapsed
+        );
+        assert_eq!(got, expected, "homomorphic evaluation diverged");
+    }
+}
@@ -0,0 +1,101 @@
+//! Splitting one circuit evaluation across two servers.
+//!
+//! "Server A" runs the first half of a 4-bit ripple-carry adder on the
+//! client's encrypted operands, suspends the evaluation, and serializes it;
+//! "server B" checks the blob belongs to the same circuit and parameters,
+//! runs the remaining gates, and hands the sum back to the client. Neither
+//! server holds the secret key, and the handoff carries only the wires the
+//! second half still reads.
+//!
+//! As in `private_average`, the noise budget comes from
+//! `Params::for_depth` rather than bootstrapping: the carry chain is four
+//! products deep.
+//!
+//! Run with `cargo run --release --example two_server`.
+
+use std::time::Instant;
+
+use gsw_rs::{gsw_keygen, Circuit, EncBool, EvaluationState, Evaluator, Params};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+const WIDTH: usize = 4;
+
+/// Multiplicative depth of the carry chain.
+const DEPTH: u32 = 4;
+
+/// `a + b` for `WIDTH`-bit operands: inputs a (LSB first), then b; outputs
+/// the sum with the carry out on top.
+fn adder() -> Circuit {
+    let mut circuit = Circuit::new(2 * WIDTH);
+    let mut carry = circuit.constant(false);
+    let mut outputs = Vec::new();
+    for i in 0..WIDTH {
+        let (a, b) = (circuit.input(i), circuit.input(WIDTH + i));
+        let half = circuit.xor(a, b);
+        outputs.push(circuit.xor(half, carry));
+        // Generate and propagate never both hold, so XOR stands in for OR.
+        let generate = circuit.and(a, b);
+        let propagate = circuit.and(half, carry);
+        carry = circuit.xor(generate, propagate);
+    }
+    outputs.push(carry);
+    circuit.set_outputs(&outputs);
+    circuit
+}
+
+fn main() {
+    let params = Params::for_depth(DEPTH, -40).expect("no parameters for the requested depth");
+    let circuit = adder();
+    let gates = circuit.gates().len();
+    let halfway = (circuit.input_count() + gates) / 2;
+    println!(
+        "Parameters: n = {}, q = 2^{}, N = {}; adder of {} wires, split after wire {}",
+        params.n, params.l, params.n_expanded, gates, halfway
+    );
+
+    let mut rng = ChaCha20Rng::seed_from_u64(2024);
+    let (sk, pk) = gsw_keygen(&mut rng, &params);
+    let (a, b) = (rng.gen_range(0..16u64), rng.gen_range(0..16u64));
+
+    // Server A: the first half, then the handoff.
+    let server_a = Evaluator::new(&params);
+    let inputs: Vec<EncBool> = (0..WIDTH)
+        .map(|i| a >> i & 1 == 1)
+        .chain((0..WIDTH).map(|i| b >> i & 1 == 1))
+        .map(|bit| server_a.encrypt(&mut rng, &pk, bit))
+        .collect();
+    let start = Instant::now();
+    let state = server_a
+        .suspend_after(&circuit, &inputs, halfway)
+        .expect("server A evaluates the first half");
+    let blob = state.to_bytes();
+    println!(
+        "server A: {} gates in {:.2?}, handing over {} ciphertexts ({} bytes)",
+        state.next_gate() - circuit.input_count(),
+        start.elapsed(),
+        state.ciphertext_count(),
+        blob.len()
+    );
+
+    // Server B: validate the blob and finish.
+    let server_b = Evaluator::new(&params);
+    let start = Instant::now();
+    let state = EvaluationState::from_bytes(&blob).expect("the handoff is intact");
+    let sum = server_b
+        .resume(&circuit, &state)
+        .expect("server B finishes the circuit");
+    println!(
+        "server B: {} gates in {:.2?}",
+        gates - state.next_gate(),
+        start.elapsed()
+    );
+
+    let got: u64 = sum
+        .iter()
+        .enumerate()
+        .map(|(i, bit)| (bit.decrypt(&sk) as u64) << i)
+        .sum();
+    println!("client: {} + {} = {}", a, b, got);
+    assert_eq!(got, a + b, "homomorphic evaluation diverged");
+}
@@ -0,0 +1,210 @@
+//! Structural measurements of ciphertext matrices.
+//!
+//! The noise bounds in [`noise_model`](crate::noise_model) assume that the left
+//! operand of every product is binary with at most N ones per row, which is
+//! what Flatten guarantees. These helpers measure that directly, and compute
+//! the exact error a fresh ciphertext carries from the keys and the
+//! encryption randomness.
+
+use crate::gsw::Ciphertext;
+use crate::lwe::{PublicKey, SecretKey};
+use crate::modular::mod_q_centered;
+use crate::params::Params;
+
+/// Largest row sum of |entry|, with entries read as centered representatives
+/// in (-q/2, q/2]. For a flattened ciphertext this is its largest row weight.
+pub fn ciphertext_infinity_norm(params: &Params, ct: &Ciphertext) -> u64 {
+    ct.iter()
+        .map(|row| {
+            row.iter()
+                .map(|&x| mod_q_centered(x as i64, params.q).unsigned_abs())
+                .sum()
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// `hist[w]` is the number of rows with exactly w nonzero entries; the
+/// histogram has one bucket per possible weight, 0 through the row length.
+pub fn row_weight_histogram(ct: &Ciphertext) -> Vec<usize> {
+    let cols = ct.first().map_or(0, |row| row.len());
+    let mut hist = vec![0; cols + 1];
+    for row in ct {
+        hist[row.iter().filter(|&&x| x != 0).count().min(cols)] += 1;
+    }
+    hist
+}
+
+/// The public key's error vector e = A · s, centered: entry k is the error
+/// of LWE sample k.
+pub fn public_key_error(sk: &SecretKey, pk: &PublicKey) -> Vec<i64> {
+    let q = pk.params().q;
+    pk.a.iter()
+        .map(|row| {
+            // Wrapping is exact mod q because q is a power of two.
+            let dot = row
+                .iter()
+                .zip(&sk.s)
+                .fold(0u64, |dot, (&a, &s)| dot.wrapping_add(a.wrapping_mul(s)));
+            mod_q_centered((dot % q) as i64, q)
+        })
+        .collect()
+}
+
+/// The exact error of a fresh encryption with randomness `r`, computed from
+/// the keys alone: row i of C · v - μ · v is r_i · e, with e the
+/// [`public_key_error`] and v = PowersOf2(s).
+///
+/// For C = `encrypt_with_randomness(pk, bit, r)` this equals each row's
+/// phase minus μ·v_i, centered in (-q/2, q/2], for either bit: μ·I
+/// contributes exactly μ·v. `r` must be a binary N×m matrix.
+pub fn fresh_ciphertext_error(sk: &SecretKey, pk: &PublicKey, r: &[Vec<u64>]) -> Vec<i64> {
+    let e = public_key_error(sk, pk);
+    r.iter()
+        .map(|r_row| {
+            assert_eq!(r_row.len(), e.len(), "R must have m columns");
+            r_row
+                .iter()
+                .zip(&e)
+                .map(|(&r_ik, &e_k)| r_ik as i64 * e_k)
+                .sum()
+        })
+        .collect()
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::bootstrap::decrypt_linear_parts_all;
+    use crate::gsw::{
+        encrypt, encrypt_with_randomness, gsw_keygen, homomorphic_mult,
+        sample_encryption_randomness, trivial_ciphertext,
+    };
+    use crate::noise_model;
+    use crate::params::SecurityLevel;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn test_trivial_ciphertext_shape() {
+        let params = Params::toy();
+        let n = params.n_expanded;
+        let one = trivial_ciphertext(&params, 1);
+        assert_eq!(ciphertext_infinity_norm(&params, &one), 1);
+        let mut expected = vec![0; n + 1];
+        expected[1] = n;
+        assert_eq!(row_weight_histogram(&one), expected);
+        assert_eq!(row_weight_histogram(&trivial_ciphertext(&params, 0))[0], n);
+    }
+
+    #[test]
+    fn test_flattened_rows_are_binary_and_bounded() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let (_, pk) = gsw_keygen(&mut rng, &params);
+        let ct = encrypt(&mut rng, &pk, 1);
+        let product = homomorphic_mult(&params, &ct, &encrypt(&mut rng, &pk, 0));
+        for c in [&ct, &product] {
+            assert!(c.iter().flatten().all(|&x| x <= 1));
+            let hist = row_weight_histogram(c);
+            assert_eq!(hist.iter().sum::<usize>(), params.n_expanded);
+            let heaviest = hist.iter().rposition(|&count| count > 0).unwrap();
+            assert_eq!(ciphertext_infinity_norm(&params, c), heaviest as u64);
+            assert!(heaviest <= params.n_expanded);
+        }
+    }
+
+    #[test]
+    fn test_fresh_error_matches_ciphertext() {
+        let params = Params::toy();
+        let q = params.q;
+        let mut rng = ChaCha20Rng::seed_from_u64(12);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let v = sk.decryption_vector();
+        for bit in [0u8, 1] {
+            let r = sample_encryption_randomness(&mut rng, &params);
+            let ct = encrypt_with_randomness(&pk, bit, &r);
+            let measured: Vec<i64> = decrypt_linear_parts_all(&sk, &ct)
+                .iter()
+                .zip(v.iter())
+                .map(|(&phase, &v_i)| {
+                    let diff = phase.wrapping_sub(bit as u64 * v_i) % q;
+                    mod_q_centered(diff as i64, q)
+                })
+                .collect();
+            assert_eq!(measured, fresh_ciphertext_error(&sk, &pk, &r));
+        }
+    }
+
+    #[test]
+    fn test_fresh_error_within_analytical_bound() {
+        // The bound covers all N rows of a ciphertext at once.
+        for level in [
+            SecurityLevel::Toy,
+            SecurityLevel::Low,
+            SecurityLevel::Medium,
+        ] {
+            let params = Params::new(level);
+            let bound = noise_model::log2_error_bound(&params, 0, -40).exp2();
+            let mut rng = ChaCha20Rng::seed_from_u64(13);
+            let (sk, pk) = gsw_keygen(&mut rng, &params);
+            for _ in 0..1000 {
+                let r = sample_encryption_randomness(&mut rng, &params);
+                let worst = fresh_ciphertext_error(&sk, &pk, &r)
+                    .iter()
+                    .map(|e| e.unsigned_abs())
+                    .max()
+                    .unwrap();
+                assert!(
+                    (worst as f64) < bound,
+                    "{:?}: {} >= {}",
+                    level,
+                    worst,
+                    bound
+                );
+            }
+        }
+    }
+
+    /// Pearson's statistic for `observed` counts against equal expected ones.
+    fn chi_square(observed: &[u64]) -> f64 {
+        let expected = observed.iter().sum::<u64>() as f64 / observed.len() as f64;
+        observed
+            .iter()
+            .map(|&o| (o as f64 - expected).powi(2) / expected)
+            .sum()
+    }
+
+    #[test]
+    fn test_encryption_randomness_is_unbiased() {
+        // Each byte of the RNG output supplies eight entries, so a bias in
+        // the expansion would show up at some bit position within a byte or
+        // as a correlation between neighbours. Critical values are for
+        // p = 0.001; the seed is fixed, so the test is deterministic.
+        let params = Params::toy();
+        // Rows start on a byte boundary, so column j sits at bit j % 8.
+        assert_eq!(params.m % 8, 0);
+        let mut rng = ChaCha20Rng::seed_from_u64(488);
+        let mut by_position = [[0u64; 2]; 8];
+        let mut pairs = [0u64; 4];
+        for _ in 0..200 {
+            let r = sample_encryption_randomness(&mut rng, &params);
+            for row in &r {
+                assert!(row.iter().all(|&x| x <= 1));
+                for (j, &x) in row.iter().enumerate() {
+                    by_position[j % 8][x as usize] += 1;
+                }
+                for pair in row.chunks_exact(2) {
+                    pairs[(2 * pair[0] + pair[1]) as usize] += 1;
+                }
+            }
+        }
+        let ones: Vec<u64> = by_position.iter().map(|c| c[1]).collect();
+        let total: u64 = by_position.iter().flatten().sum();
+        assert_eq!(total, 200 * (params.n_expanded * params.m) as u64);
+        let per_position: f64 = by_position.iter().map(|c| chi_square(c)).sum();
+        assert!(per_position < 26.12, "{} {:?}", per_position, ones);
+        let neighbours = chi_square(&pairs);
+        assert!(neighbours < 16.27, "{} {:?}", neighbours, pairs);
+    }
+}
@@ -0,0 +1,107 @@
+//! Conversions to and from `ndarray` matrices (the `ndarray` feature).
+//!
+//! For analysing ciphertexts and public keys with standard linear algebra
+//! tooling. Conversions into the crate check the shape the parameters imply
+//! and that every entry lies in [0, q).
+
+use std::sync::Arc;
+
+use ndarray::Array2;
+
+use crate::error::GswError;
+use crate::gsw::Ciphertext;
+use crate::lwe::PublicKey;
+use crate::params::Params;
+
+fn to_array(rows: &[Vec<u64>]) -> Array2<u64> {
+    let cols = rows.first().map_or(0, |row| row.len());
+    Array2::from_shape_fn((rows.len(), cols), |(i, j)| rows[i][j])
+}
+
+fn from_array(
+    arr: &Array2<u64>,
+    shape: (usize, usize),
+    q: u64,
+    what: &str,
+) -> Result<Vec<Vec<u64>>, GswError> {
+    if arr.dim() != shape {
+        return Err(GswError::MalformedData(format!(
+            "{}: expected shape {:?}, found {:?}",
+            what,
+            shape,
+            arr.dim()
+        )));
+    }
+    if let Some(&x) = arr.iter().find(|&&x| x >= q) {
+        return Err(GswError::MalformedData(format!(
+            "{}: entry {} not below q",
+            what, x
+        )));
+    }
+    Ok(arr.outer_iter().map(|row| row.to_vec()).collect())
+}
+
+/// The ciphertext as an N×N array.
+pub fn ciphertext_to_ndarray(ct: &Ciphertext) -> Array2<u64> {
+    to_array(ct)
+}
+
+/// A ciphertext from an N×N array with entries in [0, q).
+pub fn ciphertext_from_ndarray(arr: &Array2<u64>, params: &Params) -> Result<Ciphertext, GswError> {
+    let n = params.n_expanded;
+    from_array(arr, (n, n), params.q, "ciphertext")
+}
+
+/// The public key matrix as an m×(n+1) array, b in column 0.
+pub fn public_key_to_ndarray(pk: &PublicKey) -> Array2<u64> {
+    to_array(&pk.a)
+}
+
+/// A public key from an m×(n+1) array with entries in [0, q).
+pub fn public_key_from_ndarray(arr: &Array2<u64>, params: &Params) -> Result<PublicKey, GswError> {
+    let a = from_array(arr, (params.m, params.n + 1), params.q, "public key")?;
+    Ok(PublicKey::from_parts(a, Arc::new(params.clone())))
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::testing::{pseudo_ciphertext, pseudo_public_key};
+
+    #[test]
+    fn test_round_trips() {
+        let params = Params::toy();
+        let pk = pseudo_public_key(&params, 42);
+        let ct = pseudo_ciphertext(&params, 42);
+
+        let arr = ciphertext_to_ndarray(&ct);
+        assert_eq!(arr.dim(), (params.n_expanded, params.n_expanded));
+        assert_eq!(ciphertext_from_ndarray(&arr, &params).unwrap(), ct);
+
+        let arr = public_key_to_ndarray(&pk);
+        assert_eq!(arr.dim(), (params.m, params.n + 1));
+        let back = public_key_from_ndarray(&arr, &params).unwrap();
+        assert_eq!(back.a, pk.a);
+        assert_eq!(back.params(), pk.params());
+    }
+
+    #[test]
+    fn test_rejects_bad_shape_and_entries() {
+        let params = Params::toy();
+        let n = params.n_expanded;
+        assert!(matches!(
+            ciphertext_from_ndarray(&Array2::zeros((n, n - 1)), &params),
+            Err(GswError::MalformedData(_))
+        ));
+        assert!(matches!(
+            public_key_from_ndarray(&Array2::zeros((params.m + 1, params.n + 1)), &params),
+            Err(GswError::MalformedData(_))
+        ));
+        let mut arr = Array2::zeros((n, n));
+        arr[[3, 4]] = params.q;
+        assert!(matches!(
+            ciphertext_from_ndarray(&arr, &params),
+            Err(GswError::MalformedData(_))
+        ));
+    }
+}
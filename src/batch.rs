@@ -0,0 +1,93 @@
+//! Encrypting and decrypting plain slices of bits, one ciphertext per bit.
+//!
+//! For integers kept together with their width, see
+//! [`EncryptedUint`](crate::integer::EncryptedUint); these functions are for
+//! callers that hold bits as `&[u8]`.
+
+use rand::Rng;
+
+use crate::circuits::EncryptedBits;
+use crate::gsw::{encrypt, Ciphertext, DecryptionContext, GswPublicKey, GswSecretKey};
+use crate::integer::encrypt_uint;
+
+/// Encrypt each of `bits`, in order.
+///
+/// Panics if a bit is not 0 or 1, before anything is encrypted.
+///
+/// ```
+/// use gsw_rs::{decrypt_bits, encrypt_bits, gsw_keygen, Params};
+/// use rand::SeedableRng;
+/// use rand_chacha::ChaCha20Rng;
+///
+/// let params = Params::toy();
+/// let mut rng = ChaCha20Rng::seed_from_u64(1);
+/// let (sk, pk) = gsw_keygen(&mut rng, &params);
+///
+/// let byte = 0b1011_0010u8;
+/// let bits: Vec<u8> = (0..8).map(|i| (byte >> i) & 1).collect();
+/// let cts = encrypt_bits(&mut rng, &pk, &bits);
+/// assert_eq!(cts.len(), 8);
+/// assert_eq!(decrypt_bits(&sk, &cts), bits);
+/// ```
+pub fn encrypt_bits<R: Rng>(rng: &mut R, pk: &GswPublicKey, bits: &[u8]) -> EncryptedBits {
+    if let Some(i) = bits.iter().position(|&bit| bit > 1) {
+        panic!("bit {} is {}, not 0 or 1", i, bits[i]);
+    }
+    bits.iter().map(|&bit| encrypt(rng, pk, bit)).collect()
+}
+
+/// Decrypt each of `cts`, in order.
+pub fn decrypt_bits(sk: &GswSecretKey, cts: &[Ciphertext]) -> Vec<u8> {
+    let ctx = DecryptionContext::new(sk);
+    cts.iter().map(|ct| ctx.decrypt(ct)).collect()
+}
+
+/// Encrypt the low `width` bits of `value`, least significant first (width
+/// at most 64): the bits of [`encrypt_uint`].
+pub fn encrypt_u64_bits<R: Rng>(
+    rng: &mut R,
+    pk: &GswPublicKey,
+    value: u64,
+    width: usize,
+) -> EncryptedBits {
+    encrypt_uint(rng, pk, value, width).into_bits()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gsw::gsw_keygen;
+    use crate::params::Params;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn test_bits_round_trip() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(510);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        for len in [0, 1, 5] {
+            let mut bytes = vec![0u8; len];
+            rng.fill(&mut bytes[..]);
+            let bits: Vec<u8> = bytes
+                .iter()
+                .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1))
+                .collect();
+            let cts = encrypt_bits(&mut rng, &pk, &bits);
+            assert_eq!(cts.len(), 8 * len);
+            assert_eq!(decrypt_bits(&sk, &cts), bits);
+        }
+
+        let cts = encrypt_u64_bits(&mut rng, &pk, 0x2d, 7);
+        assert_eq!(decrypt_bits(&sk, &cts), [1, 0, 1, 1, 0, 1, 0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "bit 2 is 2, not 0 or 1")]
+    fn test_encrypt_bits_rejects_non_bits() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(510);
+        let (_, pk) = gsw_keygen(&mut rng, &params);
+        encrypt_bits(&mut rng, &pk, &[1, 0, 2]);
+    }
+}
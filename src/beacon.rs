@@ -0,0 +1,98 @@
+//! Encryption randomness derived from a public beacon.
+//!
+//! The R matrix of encryption number `counter` is drawn from ChaCha20 keyed
+//! with the 32-byte beacon value, using the counter as the stream id. A third
+//! party holding the beacon can re-derive R for any counter and check that a
+//! ciphertext is exactly the encryption of a claimed bit. Such ciphertexts
+//! hide nothing from whoever knows the beacon; use this only where
+//! auditability is the point.
+
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use crate::gsw::{encrypt_with_randomness, sample_encryption_randomness, Ciphertext, GswPublicKey};
+use crate::params::Params;
+
+/// Sequence of encryption randomness derived from a beacon value.
+#[derive(Clone, Debug)]
+pub struct BeaconRandomness {
+    beacon: [u8; 32],
+    counter: u64,
+}
+
+impl BeaconRandomness {
+    pub fn new(beacon: [u8; 32]) -> Self {
+        Self { beacon, counter: 0 }
+    }
+
+    /// Counter the next call to
+    /// [`next_encryption_randomness`](Self::next_encryption_randomness) uses.
+    pub fn counter(&self) -> u64 {
+        self.counter
+    }
+
+    /// The R matrix for the current counter; advances the counter.
+    pub fn next_encryption_randomness(&mut self, params: &Params) -> Vec<Vec<u64>> {
+        let r = randomness_at(&self.beacon, self.counter, params);
+        self.counter += 1;
+        r
+    }
+
+    /// Encrypt with the next beacon-derived R. Returns the counter used.
+    pub fn encrypt(&mut self, pk: &GswPublicKey, bit: u8) -> (u64, Ciphertext) {
+        let counter = self.counter;
+        let r = self.next_encryption_randomness(pk.params());
+        (counter, encrypt_with_randomness(pk, bit, &r))
+    }
+}
+
+/// The R matrix for encryption number `counter` under `beacon`.
+pub fn randomness_at(beacon: &[u8; 32], counter: u64, params: &Params) -> Vec<Vec<u64>> {
+    let mut rng = ChaCha20Rng::from_seed(*beacon);
+    rng.set_stream(counter);
+    sample_encryption_randomness(&mut rng, params)
+}
+
+/// Whether `ct` is exactly the encryption of `bit` under `pk` with the
+/// randomness for `counter` under `beacon`.
+pub fn verify_encryption(
+    pk: &GswPublicKey,
+    ct: &Ciphertext,
+    bit: u8,
+    beacon: &[u8; 32],
+    counter: u64,
+) -> bool {
+    let r = randomness_at(beacon, counter, pk.params());
+    encrypt_with_randomness(pk, bit, &r) == *ct
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gsw::{decrypt, gsw_keygen};
+
+    #[test]
+    fn test_verify_beacon_encryptions() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let beacon = [7u8; 32];
+        let mut source = BeaconRandomness::new(beacon);
+
+        let (c0, ct0) = source.encrypt(&pk, 0);
+        let (c1, ct1) = source.encrypt(&pk, 1);
+        assert_eq!((c0, c1, source.counter()), (0, 1, 2));
+        assert_eq!(decrypt(&sk, &ct0), 0);
+        assert_eq!(decrypt(&sk, &ct1), 1);
+
+        assert!(verify_encryption(&pk, &ct0, 0, &beacon, c0));
+        assert!(verify_encryption(&pk, &ct1, 1, &beacon, c1));
+        assert!(!verify_encryption(&pk, &ct1, 0, &beacon, c1));
+        assert!(!verify_encryption(&pk, &ct1, 1, &beacon, c0));
+        assert!(!verify_encryption(&pk, &ct1, 1, &[8u8; 32], c1));
+
+        let mut tampered = ct1.clone();
+        tampered[3][5] ^= 1;
+        assert!(!verify_encryption(&pk, &tampered, 1, &beacon, c1));
+    }
+}
@@ -0,0 +1,296 @@
+//! Timings of the core operations as a JSON report, for comparing branches
+//! (the `bench-report` feature).
+//!
+//! [`run_suite`] times encryption, addition, multiplication, NAND, and
+//! bootstrapping at the given presets with a plain wall-clock timer and
+//! records the median and median absolute deviation of each, with the
+//! active matrix kernel and a version string the caller supplies (typically
+//! `git describe`). [`compare`] matches two reports op by op and flags the
+//! ops whose median grew past a threshold. The `gsw-benchdiff` binary drives
+//! both.
+//!
+//! Criterion remains the tool for careful measurements (`cargo bench`); this
+//! is for quick before-and-after checks whose output diffs cleanly.
+
+use std::time::Instant;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::bootstrap::{bootstrap, gen_evaluation_key};
+use crate::error::GswError;
+use crate::gsw::{encrypt, gsw_keygen, homomorphic_add, homomorphic_mult, homomorphic_nand};
+use crate::kernels;
+use crate::params::{Params, SecurityLevel};
+
+/// Version of the report layout, bumped when fields change meaning.
+pub const REPORT_SCHEMA: u32 = 1;
+
+/// One run of the suite.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BenchReport {
+    pub schema: u32,
+    /// What was measured, as the caller described it (e.g. `git describe`).
+    pub describe: String,
+    /// [`kernels::active_kernel_name`] during the run.
+    pub kernel: String,
+    pub results: Vec<BenchResult>,
+}
+
+/// Timing of one operation at one preset.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BenchResult {
+    pub op: String,
+    /// The preset's name, e.g. `Toy`.
+    pub params: String,
+    pub median_ns: u64,
+    /// Median absolute deviation from the median.
+    pub mad_ns: u64,
+    pub iterations: u32,
+}
+
+impl BenchReport {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a report always serializes")
+    }
+
+    /// Fails with [`GswError::MalformedData`] on invalid JSON or a schema
+    /// other than [`REPORT_SCHEMA`].
+    pub fn from_json(json: &str) -> Result<Self, GswError> {
+        let report: Self = serde_json::from_str(json)
+            .map_err(|e| GswError::MalformedData(format!("invalid report: {}", e)))?;
+        if report.schema != REPORT_SCHEMA {
+            return Err(GswError::MalformedData(format!(
+                "report schema {}, expected {}",
+                report.schema, REPORT_SCHEMA
+            )));
+        }
+        Ok(report)
+    }
+}
+
+/// Median and median absolute deviation of `iterations` timed calls to `f`,
+/// after one untimed warm-up call.
+///
+/// Panics if `iterations` is 0.
+pub fn time<F: FnMut()>(iterations: u32, mut f: F) -> (u64, u64) {
+    assert!(iterations > 0, "at least one iteration is needed");
+    f();
+    let samples: Vec<u64> = (0..iterations)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed().as_nanos() as u64
+        })
+        .collect();
+    let mid = median(samples.clone());
+    let mad = median(samples.iter().map(|&s| s.abs_diff(mid)).collect());
+    (mid, mad)
+}
+
+fn median(mut samples: Vec<u64>) -> u64 {
+    samples.sort_unstable();
+    let mid = samples.len() / 2;
+    if samples.len().is_multiple_of(2) {
+        samples[mid - 1] + (samples[mid] - samples[mid - 1]) / 2
+    } else {
+        samples[mid]
+    }
+}
+
+/// Time `encrypt`, `add`, `mult`, `nand`, and `bootstrap` at each level,
+/// `iterations` calls apiece, on fixed-seed keys and ciphertexts.
+pub fn run_suite(levels: &[SecurityLevel], iterations: u32, describe: &str) -> BenchReport {
+    let mut results = Vec::new();
+    for &level in levels {
+        let params = Params::new(level);
+        let mut rng = ChaCha20Rng::seed_from_u64(503);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ek = gen_evaluation_key(&mut rng, &sk, &pk);
+        let a = encrypt(&mut rng, &pk, 1);
+        let b = encrypt(&mut rng, &pk, 0);
+        let mut record = |op: &str, f: &mut dyn FnMut()| {
+            let (median_ns, mad_ns) = time(iterations, f);
+            results.push(BenchResult {
+                op: op.to_string(),
+                params: format!("{:?}", level),
+                median_ns,
+                mad_ns,
+                iterations,
+            });
+        };
+        record("encrypt", &mut || {
+            encrypt(&mut rng, &pk, 1);
+        });
+        record("add", &mut || {
+            homomorphic_add(&params, &a, &b);
+        });
+        record("mult", &mut || {
+            homomorphic_mult(&params, &a, &b);
+        });
+        record("nand", &mut || {
+            homomorphic_nand(&params, &a, &b);
+        });
+        record("bootstrap", &mut || {
+            bootstrap(&params, &a, &ek);
+        });
+    }
+    BenchReport {
+        schema: REPORT_SCHEMA,
+        describe: describe.to_string(),
+        kernel: kernels::active_kernel_name().to_string(),
+        results,
+    }
+}
+
+/// How one op's median moved between two reports.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpDelta {
+    pub op: String,
+    pub params: String,
+    pub baseline_ns: u64,
+    pub candidate_ns: u64,
+    /// candidate / baseline.
+    pub ratio: f64,
+    pub regressed: bool,
+}
+
+/// Result of [`compare`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comparison {
+    /// Ops present in both reports, in the baseline's order.
+    pub deltas: Vec<OpDelta>,
+    /// `op@params` of ops present in only one report.
+    pub unmatched: Vec<String>,
+}
+
+impl Comparison {
+    pub fn has_regression(&self) -> bool {
+        self.deltas.iter().any(|delta| delta.regressed)
+    }
+}
+
+/// Match the reports' results by op and preset. An op regressed if its
+/// candidate median exceeds the baseline's by more than `threshold` (0.1
+/// for 10%).
+pub fn compare(baseline: &BenchReport, candidate: &BenchReport, threshold: f64) -> Comparison {
+    let key = |r: &BenchResult| format!("{}@{}", r.op, r.params);
+    let find = |report: &BenchReport, wanted: &BenchResult| {
+        report
+            .results
+            .iter()
+            .find(|r| (&r.op, &r.params) == (&wanted.op, &wanted.params))
+            .cloned()
+    };
+    let mut deltas = Vec::new();
+    let mut unmatched = Vec::new();
+    for base in &baseline.results {
+        match find(candidate, base) {
+            Some(cand) => {
+                let ratio = cand.median_ns as f64 / base.median_ns.max(1) as f64;
+                deltas.push(OpDelta {
+                    op: base.op.clone(),
+                    params: base.params.clone(),
+                    baseline_ns: base.median_ns,
+                    candidate_ns: cand.median_ns,
+                    ratio,
+                    regressed: ratio > 1.0 + threshold,
+                });
+            }
+            None => unmatched.push(key(base)),
+        }
+    }
+    unmatched.extend(
+        candidate
+            .results
+            .iter()
+            .filter(|cand| find(baseline, cand).is_none())
+            .map(key),
+    );
+    Comparison { deltas, unmatched }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(medians: &[u64]) -> BenchReport {
+        BenchReport {
+            schema: REPORT_SCHEMA,
+            describe: "v0.1.0-3-gabc1234".into(),
+            kernel: "scalar".into(),
+            results: ["add", "mult"]
+                .iter()
+                .zip(medians)
+                .map(|(op, &median_ns)| BenchResult {
+                    op: op.to_string(),
+                    params: "Toy".into(),
+                    median_ns,
+                    mad_ns: median_ns / 20,
+                    iterations: 15,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_report_round_trips() {
+        let report = run_suite(&[SecurityLevel::Toy], 1, "test");
+        let ops: Vec<&str> = report.results.iter().map(|r| r.op.as_str()).collect();
+        assert_eq!(ops, ["encrypt", "add", "mult", "nand", "bootstrap"]);
+        assert_eq!(report.kernel, kernels::active_kernel_name());
+        assert_eq!(BenchReport::from_json(&report.to_json()).unwrap(), report);
+
+        let mut future = report.clone();
+        future.schema += 1;
+        assert!(BenchReport::from_json(&future.to_json()).is_err());
+        assert!(BenchReport::from_json("{\"schema\": 1}").is_err());
+    }
+
+    #[test]
+    fn test_compare_flags_regressions() {
+        let baseline = report(&[1_000, 50_000]);
+        let same = compare(&baseline, &baseline, 0.1);
+        assert!(!same.has_regression() && same.unmatched.is_empty());
+        assert!(same.deltas.iter().all(|delta| delta.ratio == 1.0));
+
+        let slower = compare(&baseline, &report(&[1_050, 100_000]), 0.1);
+        assert!(slower.has_regression());
+        let regressed: Vec<&str> = slower
+            .deltas
+            .iter()
+            .filter(|delta| delta.regressed)
+            .map(|delta| delta.op.as_str())
+            .collect();
+        assert_eq!(regressed, ["mult"]);
+        assert_eq!(slower.deltas[1].ratio, 2.0);
+
+        let partial = compare(&baseline, &report(&[1_000]), 0.1);
+        assert!(!partial.has_regression());
+        assert_eq!(partial.unmatched, ["mult@Toy"]);
+    }
+
+    /// Repeated medians of a Toy multiplication agree to within 50%, a loose
+    /// bound that still catches a timer measuring the wrong thing.
+    #[test]
+    fn test_medians_are_stable() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(503);
+        let (_, pk) = gsw_keygen(&mut rng, &params);
+        let (a, b) = (encrypt(&mut rng, &pk, 1), encrypt(&mut rng, &pk, 1));
+        let medians: Vec<f64> = (0..5)
+            .map(|_| {
+                time(9, || {
+                    homomorphic_mult(&params, &a, &b);
+                })
+                .0 as f64
+            })
+            .collect();
+        let mean = medians.iter().sum::<f64>() / medians.len() as f64;
+        let variance =
+            medians.iter().map(|m| (m - mean).powi(2)).sum::<f64>() / medians.len() as f64;
+        assert!(mean > 0.0);
+        assert!(variance.sqrt() / mean < 0.5, "medians {:?}", medians);
+    }
+}
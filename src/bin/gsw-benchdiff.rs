@@ -0,0 +1,125 @@
+//! Record and compare timing reports (see [`gsw_rs::bench_report`]).
+//!
+//! `gsw-benchdiff run [--describe TEXT] [--iterations N] [--levels toy,low] OUT`
+//! times the core operations and writes the JSON report to OUT.
+//! `gsw-benchdiff compare [--threshold PERCENT] BASE NEW` prints each op's
+//! change and exits with status 1 if any slowed down by more than PERCENT
+//! (10 by default). A typical comparison of two branches:
+//!
+//! ```text
+//! cargo run --release --features bench-report --bin gsw-benchdiff -- \
+//!     run --describe "$(git describe --always --dirty)" main.json
+//! git switch my-branch
+//! cargo run --release --features bench-report --bin gsw-benchdiff -- \
+//!     run --describe "$(git describe --always --dirty)" branch.json
+//! cargo run --release --features bench-report --bin gsw-benchdiff -- \
+//!     compare main.json branch.json
+//! ```
+
+use gsw_rs::bench_report::{compare, run_suite, BenchReport};
+use gsw_rs::params::SecurityLevel;
+
+const USAGE: &str = "usage: gsw-benchdiff run [--describe TEXT] [--iterations N] \
+                     [--levels toy,low] OUT\n       \
+                     gsw-benchdiff compare [--threshold PERCENT] BASE NEW";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(2)
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1)
+}
+
+fn read_report(path: &str) -> BenchReport {
+    let json =
+        std::fs::read_to_string(path).unwrap_or_else(|e| fail(format!("reading {}: {}", path, e)));
+    BenchReport::from_json(&json).unwrap_or_else(|e| fail(format!("{}: {}", path, e)))
+}
+
+fn run(flags: &[String], out: &str) {
+    let mut describe = String::from("unknown");
+    let mut iterations = 5;
+    let mut levels = vec![SecurityLevel::Toy, SecurityLevel::Low];
+    for pair in flags.chunks(2) {
+        match pair {
+            [flag, value] if flag == "--describe" => describe = value.clone(),
+            [flag, n] if flag == "--iterations" => {
+                iterations = n.parse().ok().filter(|&n| n > 0).unwrap_or_else(|| usage())
+            }
+            [flag, names] if flag == "--levels" => {
+                levels = names
+                    .split(',')
+                    .map(|name| match name {
+                        "toy" => SecurityLevel::Toy,
+                        "low" => SecurityLevel::Low,
+                        "medium" => SecurityLevel::Medium,
+                        _ => usage(),
+                    })
+                    .collect()
+            }
+            _ => usage(),
+        }
+    }
+    let report = run_suite(&levels, iterations, &describe);
+    std::fs::write(out, report.to_json())
+        .unwrap_or_else(|e| fail(format!("writing {}: {}", out, e)));
+    for result in &report.results {
+        println!(
+            "{:<10} {:<6} {:>14} ns  ± {} ns",
+            result.op, result.params, result.median_ns, result.mad_ns
+        );
+    }
+}
+
+fn compare_files(flags: &[String], base: &str, new: &str) {
+    let mut threshold = 10.0;
+    for pair in flags.chunks(2) {
+        match pair {
+            [flag, percent] if flag == "--threshold" => {
+                threshold = percent.parse().unwrap_or_else(|_| usage())
+            }
+            _ => usage(),
+        }
+    }
+    let (base, new) = (read_report(base), read_report(new));
+    if base.kernel != new.kernel {
+        println!("note: kernels differ ({} vs {})", base.kernel, new.kernel);
+    }
+    println!("{} -> {}", base.describe, new.describe);
+    let comparison = compare(&base, &new, threshold / 100.0);
+    for delta in &comparison.deltas {
+        println!(
+            "{:<10} {:<6} {:>14} -> {:>14} ns  {:>+7.1}%{}",
+            delta.op,
+            delta.params,
+            delta.baseline_ns,
+            delta.candidate_ns,
+            (delta.ratio - 1.0) * 100.0,
+            if delta.regressed { "  REGRESSION" } else { "" }
+        );
+    }
+    for key in &comparison.unmatched {
+        println!("{:<17} only in one report", key);
+    }
+    if comparison.has_regression() {
+        std::process::exit(1);
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.split_first() {
+        Some((command, rest)) if command == "run" => match rest.split_last() {
+            Some((out, flags)) => run(flags, out),
+            None => usage(),
+        },
+        Some((command, rest)) if command == "compare" && rest.len() >= 2 => {
+            let (flags, files) = rest.split_at(rest.len() - 2);
+            compare_files(flags, &files[0], &files[1]);
+        }
+        _ => usage(),
+    }
+}
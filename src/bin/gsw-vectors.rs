@@ -0,0 +1,48 @@
+//! Write a set of golden files (see [`gsw_rs::testing::vectors`]).
+//!
+//! `gsw-vectors [--tag TAG] [--seed N] DIR` writes the set for seed N
+//! (the committed vectors' seed by default), labelled TAG (the current wire
+//! format version by default), into DIR. To update the committed vectors
+//! after a deliberate format or algorithm change:
+//!
+//! ```text
+//! cargo run --bin gsw-vectors -- tests/golden/wire-v1
+//! ```
+
+use std::path::PathBuf;
+
+use gsw_rs::testing::vectors::{current_tag, GoldenVectors, DEFAULT_SEED};
+
+fn main() {
+    let usage = || -> ! {
+        eprintln!("usage: gsw-vectors [--tag TAG] [--seed N] DIR");
+        std::process::exit(2)
+    };
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some((dir, flags)) = args.split_last() else {
+        usage()
+    };
+    let mut tag = current_tag();
+    let mut seed = DEFAULT_SEED;
+    for pair in flags.chunks(2) {
+        match pair {
+            [flag, value] if flag == "--tag" => tag = value.clone(),
+            [flag, n] if flag == "--seed" => seed = n.parse().unwrap_or_else(|_| usage()),
+            _ => usage(),
+        }
+    }
+
+    let vectors = GoldenVectors::generate(&tag, seed);
+    let dir = PathBuf::from(dir);
+    if let Err(e) = vectors.write_to(&dir) {
+        eprintln!("writing {}: {}", dir.display(), e);
+        std::process::exit(1);
+    }
+    println!(
+        "wrote {} files ({} seed {}) to {}",
+        vectors.files.len(),
+        tag,
+        seed,
+        dir.display()
+    );
+}
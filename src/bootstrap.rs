@@ -4,121 +4,895 @@
 //! the decryption circuit. This requires an evaluation key containing
 //! encryptions of the secret key bits under the same secret key (circular security).
 
+use std::borrow::Cow;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+#[cfg(feature = "client")]
 use rand::Rng;
+#[cfg(feature = "client")]
+use zeroize::Zeroizing;
 
-use crate::gadget::{bit_decomp, flatten_matrix, powers_of_2};
-use crate::gsw::{encrypt, homomorphic_add, Ciphertext, GswPublicKey, GswSecretKey};
+use crate::digest::{digest, DefaultHash, Digest, GswHash};
+use crate::error::GswError;
+#[cfg(feature = "client")]
+use crate::gadget::bit_decomp;
+use crate::gadget::flatten;
+use crate::gsw::{
+    check_cancel, check_dimensions, is_canonical_ciphertext, trivial_ciphertext, Ciphertext,
+    LINEAR_CHECK_ROWS,
+};
+#[cfg(feature = "client")]
+use crate::gsw::{decrypt, encrypt, homomorphic_add, AsCiphertextRows, GswPublicKey, GswSecretKey};
 use crate::modular::mod_q;
+#[cfg(feature = "client")]
+use crate::modular::mod_q_centered;
+#[cfg(feature = "client")]
+use crate::noise_model;
 use crate::params::Params;
+use crate::wire::{self, ObjectKind, Reader};
 
 /// Evaluation key: encryption of each bit of the secret key.
+///
+/// Entry i encrypts bit i of BitDecomp(s), so entries are always stored,
+/// iterated, and serialized in that order. The key remembers the fingerprint
+/// of the public key its entries were encrypted under.
 #[derive(Clone, Debug)]
 pub struct EvaluationKey {
-    pub encryptions: Vec<Ciphertext>,
-    #[allow(dead_code)]
-    params: Params,
+    encryptions: Vec<Ciphertext>,
+    params: Arc<Params>,
+    pk_fingerprint: Digest,
+}
+
+impl EvaluationKey {
+    pub(crate) fn from_parts(
+        encryptions: Vec<Ciphertext>,
+        params: Arc<Params>,
+        pk_fingerprint: Digest,
+    ) -> Self {
+        Self {
+            encryptions,
+            params,
+            pk_fingerprint,
+        }
+    }
+
+    /// The entries, with the key's parameters and fingerprint dropped.
+    pub(crate) fn into_entries(self) -> Vec<Ciphertext> {
+        self.encryptions
+    }
+
+    pub fn params(&self) -> &Params {
+        &self.params
+    }
+
+    /// The shared parameter handle, for pointer-equality checks.
+    pub(crate) fn params_arc(&self) -> &Arc<Params> {
+        &self.params
+    }
+
+    /// [`digest`](crate::digest::digest) of the public key the entries were
+    /// encrypted under.
+    pub fn pk_fingerprint(&self) -> &Digest {
+        &self.pk_fingerprint
+    }
+
+    /// Number of entries, N for a complete key.
+    pub fn len(&self) -> usize {
+        self.encryptions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.encryptions.is_empty()
+    }
+
+    /// Entry `i`: Enc(bit i of BitDecomp(s)).
+    pub fn entry(&self, i: usize) -> Option<&Ciphertext> {
+        self.encryptions.get(i)
+    }
+
+    /// Entries in index order.
+    pub fn entries(&self) -> std::slice::Iter<'_, Ciphertext> {
+        self.encryptions.iter()
+    }
+
+    /// All entries as a slice, in index order.
+    pub fn encryptions(&self) -> &[Ciphertext] {
+        &self.encryptions
+    }
+
+    fn check_index(&self, i: usize) -> Result<(), GswError> {
+        if i >= self.len() {
+            return Err(GswError::EntryOutOfRange {
+                index: i,
+                len: self.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Replace entry `i` with `ct`, returning the old entry.
+    ///
+    /// `ct` must be a canonical (N×N, binary) ciphertext for this key's
+    /// parameters. Whether it encrypts the right bit under the right key can
+    /// only be checked with the secret key; see [`verify`](Self::verify).
+    pub fn replace_entry(&mut self, i: usize, ct: Ciphertext) -> Result<Ciphertext, GswError> {
+        self.check_index(i)?;
+        if !is_canonical_ciphertext(&self.params, &ct) {
+            return Err(GswError::MalformedData(format!(
+                "replacement for entry {} is not a canonical {}x{} ciphertext",
+                i, self.params.n_expanded, self.params.n_expanded
+            )));
+        }
+        Ok(std::mem::replace(&mut self.encryptions[i], ct))
+    }
+
+    /// Re-encrypt the secret-key bits at `indices` with fresh randomness,
+    /// leaving every other entry untouched.
+    ///
+    /// Fails without changing anything if `sk` or `pk` uses other parameters,
+    /// if `pk` is not the key this one was generated under, or if an index is
+    /// out of range.
+    #[cfg(feature = "client")]
+    pub fn refresh_entries<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        sk: &GswSecretKey,
+        pk: &GswPublicKey,
+        indices: &[usize],
+    ) -> Result<(), GswError> {
+        if !Params::same(sk.params_arc(), &self.params)
+            || !Params::same(pk.params_arc(), &self.params)
+        {
+            return Err(GswError::ParamsMismatch);
+        }
+        if digest(pk) != self.pk_fingerprint {
+            return Err(GswError::KeyMismatch);
+        }
+        for &i in indices {
+            self.check_index(i)?;
+        }
+        let bits = Zeroizing::new(bit_decomp(&sk.s, &self.params));
+        for &i in indices {
+            self.encryptions[i] = encrypt(rng, pk, bits[i] as u8);
+        }
+        Ok(())
+    }
+
+    /// Check that every entry decrypts to its bit of BitDecomp(s), reporting
+    /// the first that does not.
+    #[cfg(feature = "client")]
+    pub fn verify(&self, sk: &GswSecretKey) -> Result<(), GswError> {
+        let bits = Zeroizing::new(bit_decomp(&sk.s, sk.params()));
+        if self.len() != bits.len() {
+            return Err(GswError::ParamsMismatch);
+        }
+        match self
+            .entries()
+            .zip(bits.iter())
+            .position(|(ct, &bit)| decrypt(sk, ct) as u64 != bit)
+        {
+            Some(index) => Err(GswError::CorruptEntry { index }),
+            None => Ok(()),
+        }
+    }
+
+    /// Serialize: header, public-key fingerprint, the key (params, count,
+    /// entries in index order), then a [`DefaultHash`] of everything before
+    /// it.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = wire::buffer(
+            (wire::HEADER_LEN + 2 * 32) as u64 + wire::evaluation_key_len(&self.params),
+        );
+        wire::write_header(&mut out, ObjectKind::EvaluationKey);
+        out.extend_from_slice(&self.pk_fingerprint);
+        wire::write_evaluation_key(&mut out, self);
+        let integrity = DefaultHash::hash(&out);
+        out.extend_from_slice(&integrity);
+        out
+    }
+
+    /// Deserialize, rejecting data whose integrity hash does not match.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GswError> {
+        let body_len = bytes
+            .len()
+            .checked_sub(32)
+            .ok_or_else(|| GswError::MalformedData("unexpected end of data".into()))?;
+        let (body, integrity) = bytes.split_at(body_len);
+        let mut reader = Reader::new(body);
+        reader.header(ObjectKind::EvaluationKey)?;
+        if DefaultHash::hash(body) != integrity {
+            return Err(GswError::MalformedData(
+                "evaluation key integrity hash mismatch".into(),
+            ));
+        }
+        let pk_fingerprint = reader.hash()?;
+        let ek = reader.evaluation_key(pk_fingerprint)?;
+        reader.finish()?;
+        Ok(ek)
+    }
+}
+
+/// Serialized as [`to_bytes`](EvaluationKey::to_bytes).
+#[cfg(feature = "serde")]
+impl serde::Serialize for EvaluationKey {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.to_bytes())
+    }
+}
+
+/// Checks the bytes as [`from_bytes`](EvaluationKey::from_bytes) does.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for EvaluationKey {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = <Vec<u8> as serde::Deserialize>::deserialize(deserializer)?;
+        Self::from_bytes(&bytes).map_err(serde::de::Error::custom)
+    }
 }
 
 /// Generate the evaluation key for bootstrapping.
+#[cfg(feature = "client")]
 pub fn gen_evaluation_key<R: Rng>(
     rng: &mut R,
     sk: &GswSecretKey,
     pk: &GswPublicKey,
 ) -> EvaluationKey {
     let params = sk.params();
-    let bits = bit_decomp(&sk.s, params);
+    let bits = Zeroizing::new(bit_decomp(&sk.s, params));
 
-    let encryptions: Vec<Ciphertext> = bits
-        .iter()
-        .map(|&b| encrypt(rng, pk, b as u8))
-        .collect();
+    let encryptions: Vec<Ciphertext> = bits.iter().map(|&b| encrypt(rng, pk, b as u8)).collect();
 
     EvaluationKey {
         encryptions,
-        params: params.clone(),
+        params: Arc::clone(sk.params_arc()),
+        pk_fingerprint: digest(pk),
+    }
+}
+
+/// Like [`gen_evaluation_key`], but every entry also carries a flooding error
+/// of magnitude up to 2^`flood_bits`.
+///
+/// Each entry is summed with an encryption of zero made directly under the
+/// secret key, whose rows have errors uniform in [-2^flood_bits, 2^flood_bits],
+/// so the entries' noise is dominated by fresh wide randomness rather than by
+/// the structure of the public key's error. The extra noise comes out of the
+/// budget of everything bootstrapped with the key: fails with
+/// [`GswError::InvalidFlooding`] unless a flooded entry still supports one
+/// multiplication at the parameters' failure target, according to
+/// [`noise_model::supports_flooding`].
+#[cfg(feature = "client")]
+pub fn gen_evaluation_key_flooded<R: Rng>(
+    rng: &mut R,
+    sk: &GswSecretKey,
+    pk: &GswPublicKey,
+    flood_bits: u32,
+) -> Result<EvaluationKey, GswError> {
+    let params = sk.params();
+    if !noise_model::supports_flooding(params, flood_bits, 1, params.target_failure_exp) {
+        return Err(GswError::InvalidFlooding { flood_bits });
     }
+    let mut ek = gen_evaluation_key(rng, sk, pk);
+    for entry in &mut ek.encryptions {
+        let zero = encrypt_zero_with_error(rng, sk, flood_bits);
+        *entry = homomorphic_add(params, entry, &zero);
+    }
+    Ok(ek)
+}
+
+/// Enc(0) under the secret key with row errors uniform in [-2^bits, 2^bits]:
+/// the rows are BitDecomp of LWE samples (b, a) with (b, a) · s = e.
+#[cfg(feature = "client")]
+fn encrypt_zero_with_error<R: Rng>(rng: &mut R, sk: &GswSecretKey, bits: u32) -> Ciphertext {
+    let params = sk.params();
+    let q = params.q;
+    let bound = 1i64 << bits;
+    (0..params.n_expanded)
+        .map(|_| {
+            let mut sample: Vec<u64> = (0..=params.n).map(|_| rng.gen_range(0..q)).collect();
+            // s_0 = 1, so b = e - Σ_{j≥1} a_j·s_j makes the inner product e.
+            // Wrapping is exact mod q because q is a power of two.
+            let dot = sample[1..]
+                .iter()
+                .zip(&sk.s[1..])
+                .fold(0u64, |dot, (&a, &s)| dot.wrapping_add(a.wrapping_mul(s)));
+            let e = rng.gen_range(-bound..=bound);
+            sample[0] = mod_q(e, q).wrapping_sub(dot) % q;
+            bit_decomp(&sample, params)
+        })
+        .collect()
 }
 
 /// Homomorphic linear combination: compute Enc(sum of c_i * x_i) from Enc(x_i).
-fn homomorphic_linear_fixed(
+pub(crate) fn homomorphic_linear_fixed(
     params: &Params,
     cts: &[Ciphertext],
     coefficients: &[u64],
 ) -> Ciphertext {
+    let never = AtomicBool::new(false);
+    homomorphic_linear_fixed_observed(params, cts, coefficients, &never, |_, _| {})
+        .expect("never cancelled")
+}
+
+/// [`homomorphic_linear_fixed`], calling `observe(i, acc)` with the running
+/// sum after each term i with a nonzero coefficient is added, and checking
+/// `cancel` every [`LINEAR_CHECK_ROWS`] rows of each term.
+///
+/// Each term is Flatten(acc + Flatten(c_i · x_i)), which Flatten computes row
+/// by row, so the rows are built one block at a time.
+fn homomorphic_linear_fixed_observed(
+    params: &Params,
+    cts: &[Ciphertext],
+    coefficients: &[u64],
+    cancel: &AtomicBool,
+    mut observe: impl FnMut(usize, &Ciphertext),
+) -> Result<Ciphertext, GswError> {
     assert_eq!(cts.len(), coefficients.len());
     let n = params.n_expanded;
     let q = params.q;
 
-    let mut result = None;
+    let mut result: Option<Ciphertext> = None;
 
-    for (ct, &coeff) in cts.iter().zip(coefficients.iter()) {
+    for (i, (ct, &coeff)) in cts.iter().zip(coefficients.iter()).enumerate() {
         if coeff == 0 {
             continue;
         }
 
-        let mut scaled = vec![vec![0u64; n]; n];
-        for i in 0..n {
-            for j in 0..n {
-                scaled[i][j] = mod_q((ct[i][j] as i64) * (coeff as i64), q);
+        let first = result.is_none();
+        let acc = result.get_or_insert_with(|| vec![Vec::new(); n]);
+        for (acc_rows, ct_rows) in acc
+            .chunks_mut(LINEAR_CHECK_ROWS)
+            .zip(ct.chunks(LINEAR_CHECK_ROWS))
+        {
+            check_cancel(cancel)?;
+            for (acc_row, ct_row) in acc_rows.iter_mut().zip(ct_rows) {
+                let scaled: Vec<u64> = ct_row
+                    .iter()
+                    .map(|&x| mod_q((x as i64) * (coeff as i64), q))
+                    .collect();
+                let scaled_flat = flatten(&scaled, params);
+                *acc_row = if first {
+                    scaled_flat
+                } else {
+                    let sum: Vec<u64> = acc_row
+                        .iter()
+                        .zip(&scaled_flat)
+                        .map(|(&a, &b)| mod_q((a as i64) + (b as i64), q))
+                        .collect();
+                    flatten(&sum, params)
+                };
             }
         }
-        let scaled_flat = flatten_matrix(&scaled, params);
-
-        result = Some(match result {
-            None => scaled_flat,
-            Some(acc) => homomorphic_add(params, &acc, &scaled_flat),
-        });
+        observe(i, acc);
     }
 
-    result.unwrap_or_else(|| vec![vec![0u64; n]; n])
+    // No terms: the empty sum, Enc(0).
+    Ok(result.unwrap_or_else(|| trivial_ciphertext(params, 0)))
 }
 
 /// Compute the decryption linear part in the clear (for verification).
-/// Matches decrypt: C[l-1] · v
-pub fn decrypt_linear_part_clear(sk: &GswSecretKey, ct: &Ciphertext) -> u64 {
+/// Returns C[row] · v with v = PowersOf2(s); `None` selects row l-1, the one
+/// decrypt uses.
+///
+/// Fails if the row does not exist or does not have N entries.
+#[cfg(feature = "client")]
+pub fn decrypt_linear_part_clear<C: AsCiphertextRows + ?Sized>(
+    sk: &GswSecretKey,
+    ct: &C,
+    row: Option<usize>,
+) -> Result<u64, GswError> {
     let params = sk.params();
-    let q = params.q;
+    let row = row.unwrap_or(params.l - 1);
+    let c_row = ct.row(row).ok_or(GswError::RowOutOfRange {
+        row,
+        rows: ct.row_range().end,
+    })?;
+    if c_row.len() != params.n_expanded {
+        return Err(GswError::MalformedData(format!(
+            "ciphertext row {} has {} entries, expected {}",
+            row,
+            c_row.len(),
+            params.n_expanded
+        )));
+    }
+    let v = sk.decryption_vector();
+    Ok(linear_part(c_row, &v, params.q))
+}
+
+/// C[i] · v for every row i of the ciphertext.
+#[cfg(feature = "client")]
+pub fn decrypt_linear_parts_all(sk: &GswSecretKey, ct: &Ciphertext) -> Vec<u64> {
+    let params = sk.params();
+    let v = sk.decryption_vector();
+    ct.iter()
+        .map(|row| linear_part(row, &v, params.q))
+        .collect()
+}
+
+#[cfg(feature = "client")]
+fn linear_part(row: &[u64], v: &[u64], q: u64) -> u64 {
+    // Wrapping is exact mod q because q is a power of two.
+    row.iter()
+        .zip(v)
+        .fold(0u64, |dot, (&c, &v)| dot.wrapping_add(c.wrapping_mul(v)))
+        % q
+}
+
+/// The coefficients c with Σ c_i · bit_i = C[l-1] · v, where bit_i is bit i
+/// of BitDecomp(s): c_i = Σ_j C[l-1][bl + j] · 2^(k+j) for i = bl + k.
+///
+/// One coefficient per entry of the row, so a row spanning several
+/// concatenated keys gets one per digit of each key.
+pub(crate) fn bootstrap_coefficients(params: &Params, noisy_ct: &Ciphertext) -> Vec<u64> {
     let l = params.l;
-    let n_expanded = params.n_expanded;
     let row_idx = l - 1;
+    let q = params.q;
+
+    // The products below assume 0/1 entries; Flatten keeps the row's value
+    // (see the `gsw` module docs).
+    let c_row = &noisy_ct[row_idx];
+    let c_row = if c_row.iter().all(|&x| x <= 1) {
+        Cow::Borrowed(c_row)
+    } else {
+        Cow::Owned(flatten(c_row, params))
+    };
+    let mut coefficients = vec![0u64; c_row.len()];
 
-    let v = powers_of_2(&sk.s, params);
-    let mut dot: i64 = 0;
-    for j in 0..n_expanded {
-        dot += (ct[row_idx][j] as i64) * (v[j] as i64);
+    for (i, coefficient) in coefficients.iter_mut().enumerate() {
+        let block = i / l;
+        let k = i % l;
+        // Wrapping is exact mod q because q is a power of two, and 2^(k+j)
+        // vanishes mod q once the shift passes 63.
+        let coef = (0..l).fold(0u64, |coef, j_bit| {
+            let power = 1u64.checked_shl((k + j_bit) as u32).unwrap_or(0);
+            coef.wrapping_add(c_row[block * l + j_bit].wrapping_mul(power))
+        });
+        *coefficient = coef % q;
     }
-    mod_q(dot, q)
+    coefficients
 }
 
 /// Bootstrap a noisy ciphertext to reduce its noise.
 /// Homomorphically computes C[l-1] · v where v = PowersOf2(s).
-pub fn bootstrap(
+///
+/// This does not refresh the bit yet. The sum encrypts the parity of the
+/// phase C[l-1] · v, not its rounding to a multiple of q/2, and carries the
+/// noise of up to N summed encryptions, so the output decrypts to the input's
+/// bit only by chance; [`diagnose_bootstrap`] shows what it computes.
+pub fn bootstrap(params: &Params, noisy_ct: &Ciphertext, ek: &EvaluationKey) -> Ciphertext {
+    let coefficients = bootstrap_coefficients(params, noisy_ct);
+    homomorphic_linear_fixed(params, &ek.encryptions, &coefficients)
+}
+
+/// [`bootstrap`], failing before any homomorphic work if `ek` was made
+/// under other parameters ([`GswError::ParamsMismatch`]), or if the
+/// ciphertext or an evaluation key entry is not N×N
+/// ([`GswError::DimensionMismatch`]) or the key has the wrong number of
+/// entries ([`GswError::MalformedData`]).
+pub fn try_bootstrap(
     params: &Params,
     noisy_ct: &Ciphertext,
     ek: &EvaluationKey,
-) -> Ciphertext {
-    let l = params.l;
-    let n_expanded = params.n_expanded;
-    let row_idx = l - 1;
+) -> Result<Ciphertext, GswError> {
+    if digest(params) != digest(ek.params()) {
+        return Err(GswError::ParamsMismatch);
+    }
+    check_dimensions(params, noisy_ct)?;
+    if ek.encryptions.len() != params.n_expanded {
+        return Err(GswError::MalformedData(format!(
+            "evaluation key has {} entries, expected {}",
+            ek.encryptions.len(),
+            params.n_expanded
+        )));
+    }
+    for entry in &ek.encryptions {
+        check_dimensions(params, entry)?;
+    }
+    Ok(bootstrap(params, noisy_ct, ek))
+}
+
+/// [`bootstrap`], checking `cancel` every 64 rows of each evaluation key
+/// term and failing with [`GswError::Cancelled`] once it is set. At Medium a
+/// check comes about every millisecond; the result, if any, is bit-identical
+/// to [`bootstrap`]'s.
+pub fn bootstrap_cancellable(
+    params: &Params,
+    noisy_ct: &Ciphertext,
+    ek: &EvaluationKey,
+    cancel: &AtomicBool,
+) -> Result<Ciphertext, GswError> {
+    check_cancel(cancel)?;
+    let coefficients = bootstrap_coefficients(params, noisy_ct);
+    homomorphic_linear_fixed_observed(params, &ek.encryptions, &coefficients, cancel, |_, _| {})
+}
+
+/// One term of the bootstrap's linear combination, checked against the
+/// clear running sum.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TermCheck {
+    /// Evaluation key entry of the term.
+    pub index: usize,
+    /// Parity of the clear running sum Σ c_j · bit_j over j ≤ `index`, which
+    /// is what the running sum decrypts to while its noise is small.
+    pub expected_bit: u8,
+    pub decrypted_bit: u8,
+    /// Error of the running sum relative to `expected_bit`, centered.
+    pub noise: i64,
+}
+
+/// Step-by-step account of one bootstrap; see [`diagnose_bootstrap`].
+#[derive(Clone, Debug)]
+pub struct BootstrapDiagnosis {
+    pub input_bit: u8,
+    pub input_noise: i64,
+    /// q/4 - |input noise|; negative if the input already decrypts wrongly.
+    pub input_margin: i64,
+    /// Whether Σ c_i · bit_i over the clear key bits equals the input's
+    /// phase C[l-1] · v, as the coefficients are meant to ensure.
+    pub coefficients_match: bool,
+    /// Every term with a nonzero coefficient, in accumulation order.
+    pub terms: Vec<TermCheck>,
+    /// Entry index of the first term whose running sum decrypts wrongly.
+    pub first_divergence: Option<usize>,
+    pub output_bit: u8,
+    /// Error of the output relative to `input_bit`, centered.
+    pub output_noise: i64,
+}
+
+/// Run [`bootstrap`] with the secret key at hand, decrypting every
+/// intermediate value.
+///
+/// The running sum of the linear combination is decrypted after each term
+/// and compared with the parity of the same sum over the clear key bits, so
+/// a corrupted evaluation key entry shows up as the first divergent term
+/// (unless its coefficient is even). If every term matches but the output
+/// bit is still wrong, the fault is in the phase itself: the bootstrap
+/// output encrypts the parity of C[l-1] · v, not its rounding.
+///
+/// Fails if the input's decryption row is missing or the wrong length.
+#[cfg(feature = "client")]
+pub fn diagnose_bootstrap(
+    sk: &GswSecretKey,
+    params: &Params,
+    noisy_ct: &Ciphertext,
+    ek: &EvaluationKey,
+) -> Result<BootstrapDiagnosis, GswError> {
     let q = params.q;
+    let scale = sk.decryption_scale();
+    let error = |ct: &Ciphertext, bit: u8| -> Result<i64, GswError> {
+        let phase = decrypt_linear_part_clear(sk, ct, None)?;
+        let diff = phase.wrapping_sub(bit as u64 * scale) % q;
+        Ok(mod_q_centered(diff as i64, q))
+    };
 
-    let mut coefficients = vec![0u64; n_expanded];
-    let c_row = &noisy_ct[row_idx];
+    let phase = decrypt_linear_part_clear(sk, noisy_ct, None)?;
+    let input_bit = decrypt(sk, noisy_ct);
+    let input_noise = error(noisy_ct, input_bit)?;
 
-    for i in 0..n_expanded {
-        let block = i / l;
-        let k = i % l;
-        let mut coef: i64 = 0;
-        for j_bit in 0..l {
-            let j = block * l + j_bit;
-            let term = mod_q(
-                (c_row[j] as i64) * ((1i64 << (k + j_bit)) as i64),
-                q,
-            ) as i64;
-            coef = mod_q(coef + term, q) as i64;
+    let coefficients = bootstrap_coefficients(params, noisy_ct);
+    let bits = Zeroizing::new(bit_decomp(&sk.s, params));
+    // Wrapping is exact mod q because q is a power of two.
+    let clear_sum = |upto: usize| {
+        coefficients[..upto]
+            .iter()
+            .zip(bits.iter())
+            .fold(0u64, |sum, (&c, &b)| sum.wrapping_add(c.wrapping_mul(b)))
+            % q
+    };
+
+    let mut terms = Vec::new();
+    let never = AtomicBool::new(false);
+    let output = homomorphic_linear_fixed_observed(
+        params,
+        &ek.encryptions,
+        &coefficients,
+        &never,
+        |i, acc| {
+            let expected_bit = (clear_sum(i + 1) & 1) as u8;
+            terms.push(TermCheck {
+                index: i,
+                expected_bit,
+                decrypted_bit: decrypt(sk, acc),
+                noise: error(acc, expected_bit).expect("running sums are N×N"),
+            });
+        },
+    )
+    .expect("never cancelled");
+
+    let output_bit = decrypt(sk, &output);
+    Ok(BootstrapDiagnosis {
+        input_bit,
+        input_noise,
+        input_margin: (q / 4) as i64 - input_noise.abs(),
+        coefficients_match: clear_sum(coefficients.len()) == phase,
+        first_divergence: terms
+            .iter()
+            .find(|term| term.decrypted_bit != term.expected_bit)
+            .map(|term| term.index),
+        terms,
+        output_bit,
+        output_noise: error(&output, input_bit)?,
+    })
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::gsw::{gsw_keygen, homomorphic_mult, trivial_ciphertext, DecryptionContext};
+    use crate::params::{PlaintextEncoding, DEFAULT_FAILURE_EXP};
+    use crate::session::Session;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    /// Bootstrap Enc(1)·Enc(1) and decrypt, as in the crate-level test.
+    fn bootstraps_one(
+        rng: &mut ChaCha20Rng,
+        sk: &GswSecretKey,
+        pk: &GswPublicKey,
+        ek: &EvaluationKey,
+    ) -> bool {
+        let params = sk.params();
+        let ct = encrypt(rng, pk, 1);
+        let noisy = homomorphic_mult(params, &ct, &ct);
+        decrypt(sk, &bootstrap(params, &noisy, ek)) == 1
+    }
+
+    #[test]
+    fn test_replace_and_refresh_entries() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(47);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let mut ek = gen_evaluation_key(&mut rng, &sk, &pk);
+        assert_eq!(ek.len(), params.n_expanded);
+        assert_eq!(ek.entries().count(), ek.len());
+
+        let bits = bit_decomp(&sk.s, &params);
+        let i = 5;
+        let old = ek.entry(i).unwrap().clone();
+        let replaced = ek
+            .replace_entry(i, encrypt(&mut rng, &pk, bits[i] as u8))
+            .unwrap();
+        assert_eq!(replaced, old);
+        assert_ne!(ek.entry(i), Some(&old));
+        ek.refresh_entries(&mut rng, &sk, &pk, &[0, 17, params.n_expanded - 1])
+            .unwrap();
+        assert!(ek.verify(&sk).is_ok());
+        assert!(bootstraps_one(&mut rng, &sk, &pk, &ek));
+    }
+
+    #[test]
+    fn test_invalid_replacements_are_caught() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(43);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let mut ek = gen_evaluation_key(&mut rng, &sk, &pk);
+        let bits = bit_decomp(&sk.s, &params);
+
+        let i = 9;
+        let wrong = encrypt(&mut rng, &pk, 1 - bits[i] as u8);
+        ek.replace_entry(i, wrong).unwrap();
+        assert!(matches!(
+            ek.verify(&sk),
+            Err(GswError::CorruptEntry { index }) if index == i
+        ));
+        ek.refresh_entries(&mut rng, &sk, &pk, &[i]).unwrap();
+        assert!(ek.verify(&sk).is_ok());
+
+        let len = ek.len();
+        assert!(matches!(
+            ek.replace_entry(len, ek.entry(0).unwrap().clone()),
+            Err(GswError::EntryOutOfRange { index, .. }) if index == len
+        ));
+        let raw = vec![vec![2u64; params.n_expanded]; params.n_expanded];
+        assert!(matches!(
+            ek.replace_entry(0, raw),
+            Err(GswError::MalformedData(_))
+        ));
+        let short = EvaluationKey::from_parts(
+            ek.encryptions()[1..].to_vec(),
+            Arc::clone(ek.params_arc()),
+            *ek.pk_fingerprint(),
+        );
+        let ct = encrypt(&mut rng, &pk, 1);
+        assert!(matches!(
+            try_bootstrap(&params, &ct, &short),
+            Err(GswError::MalformedData(_))
+        ));
+
+        let (_, other_pk) = gsw_keygen(&mut rng, &params);
+        assert!(matches!(
+            ek.refresh_entries(&mut rng, &sk, &other_pk, &[0]),
+            Err(GswError::KeyMismatch)
+        ));
+        assert!(matches!(
+            Session::from_parts(params.clone(), None, other_pk, Some(ek)),
+            Err(GswError::KeyMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_flooded_evaluation_key() {
+        let params = Params::toy();
+        // Bootstrap is no more reliable at toy parameters with flooding than
+        // without (see the crate-level tests); like those, this pins a seed.
+        let mut rng = ChaCha20Rng::seed_from_u64(52);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ek = gen_evaluation_key_flooded(&mut rng, &sk, &pk, 4).unwrap();
+        assert!(bootstraps_one(&mut rng, &sk, &pk, &ek));
+
+        let flood_bits = 8;
+        let ek = gen_evaluation_key_flooded(&mut rng, &sk, &pk, flood_bits).unwrap();
+        assert!(ek.verify(&sk).is_ok());
+        let ctx = DecryptionContext::new(&sk);
+        let max_noise = ek
+            .entries()
+            .map(|ct| ctx.measure_noise(ct).unsigned_abs())
+            .max()
+            .unwrap();
+        // Fresh rows add at most m·B to the flooding error.
+        let fresh = params.m as u64 * params.error_bound as u64;
+        assert!(max_noise >= 1 << (flood_bits - 1), "{}", max_noise);
+        assert!(max_noise <= (1 << flood_bits) + fresh, "{}", max_noise);
+
+        for flood_bits in [params.l as u32, 64, u32::MAX] {
+            assert!(matches!(
+                gen_evaluation_key_flooded(&mut rng, &sk, &pk, flood_bits),
+                Err(GswError::InvalidFlooding { .. })
+            ));
         }
-        coefficients[i] = mod_q(coef, q) as u64;
     }
 
-    homomorphic_linear_fixed(params, &ek.encryptions, &coefficients)
+    #[test]
+    fn test_diagnose_bootstrap_finds_corrupted_entry() {
+        // Noiseless entries keep every running sum exact, so only a corrupted
+        // entry can make one diverge.
+        let params = Params::pedagogical();
+        let mut rng = ChaCha20Rng::seed_from_u64(59);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let bits = bit_decomp(&sk.s, &params);
+        let entries = bits
+            .iter()
+            .map(|&b| trivial_ciphertext(&params, b as u8))
+            .collect();
+        let mut ek = EvaluationKey::from_parts(entries, Arc::clone(sk.params_arc()), digest(&pk));
+        let ct = encrypt(&mut rng, &pk, 1);
+
+        let clean = diagnose_bootstrap(&sk, &params, &ct, &ek).unwrap();
+        assert_eq!(clean.input_bit, 1);
+        assert!(clean.input_margin > 0);
+        assert!(clean.coefficients_match);
+        assert!(!clean.terms.is_empty());
+        assert_eq!(clean.first_divergence, None);
+
+        // Flipping the bit of an entry with an odd coefficient flips the
+        // parity of every later running sum.
+        let coefficients = bootstrap_coefficients(&params, &ct);
+        let corrupt = (1..ek.len()).find(|&i| coefficients[i] % 2 == 1).unwrap();
+        ek.replace_entry(
+            corrupt,
+            trivial_ciphertext(&params, 1 - bits[corrupt] as u8),
+        )
+        .unwrap();
+        let diagnosis = diagnose_bootstrap(&sk, &params, &ct, &ek).unwrap();
+        assert!(diagnosis.coefficients_match);
+        assert_eq!(diagnosis.first_divergence, Some(corrupt));
+        assert!(diagnosis
+            .terms
+            .iter()
+            .take_while(|term| term.index < corrupt)
+            .all(|term| term.noise == 0));
+    }
+
+    #[test]
+    fn test_coefficients_on_the_widest_vetted_modulus() {
+        // At l = 62 the powers 2^(k+j) run past 2^63; noiseless entries keep
+        // the key cheap to build and every running sum exact.
+        let params = Params::for_depth(5, DEFAULT_FAILURE_EXP).unwrap();
+        assert_eq!(params.l, 62);
+        let mut rng = ChaCha20Rng::seed_from_u64(430);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let entries = bit_decomp(&sk.s, &params)
+            .iter()
+            .map(|&b| trivial_ciphertext(&params, b as u8))
+            .collect();
+        let ek = EvaluationKey::from_parts(entries, Arc::clone(sk.params_arc()), digest(&pk));
+
+        for bit in [0, 1] {
+            let ct = encrypt(&mut rng, &pk, bit);
+            let diagnosis = diagnose_bootstrap(&sk, &params, &ct, &ek).unwrap();
+            assert_eq!(diagnosis.input_bit, bit);
+            assert!(diagnosis.coefficients_match);
+            assert_eq!(diagnosis.first_divergence, None);
+        }
+    }
+
+    #[test]
+    fn test_cancellation_stops_bootstrap_promptly() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(468);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ek = gen_evaluation_key(&mut rng, &sk, &pk);
+        let ct = encrypt(&mut rng, &pk, 1);
+        let noisy = homomorphic_mult(&params, &ct, &ct);
+
+        let unset = AtomicBool::new(false);
+        assert_eq!(
+            bootstrap_cancellable(&params, &noisy, &ek, &unset).unwrap(),
+            bootstrap(&params, &noisy, &ek)
+        );
+        assert_eq!(
+            crate::gsw::homomorphic_mult_cancellable(&params, &ct, &noisy, &unset).unwrap(),
+            homomorphic_mult(&params, &ct, &noisy)
+        );
+
+        // Bootstrap back to back while another thread sets the flag, so it
+        // lands mid-bootstrap however fast one is.
+        let cancel = AtomicBool::new(false);
+        let (result, set_at) = std::thread::scope(|scope| {
+            let setter = scope.spawn(|| {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                let set_at = std::time::Instant::now();
+                cancel.store(true, std::sync::atomic::Ordering::Relaxed);
+                set_at
+            });
+            let result = loop {
+                match bootstrap_cancellable(&params, &noisy, &ek, &cancel) {
+                    Ok(_) => continue,
+                    Err(e) => break e,
+                }
+            };
+            (result, setter.join().unwrap())
+        });
+        assert!(matches!(result, GswError::Cancelled));
+        assert!(set_at.elapsed() < std::time::Duration::from_secs(1));
+        assert!(matches!(
+            crate::gsw::homomorphic_mult_cancellable(&params, &ct, &noisy, &cancel),
+            Err(GswError::Cancelled)
+        ));
+    }
+
+    #[test]
+    fn test_serialization_round_trip() {
+        let params = Params::pedagogical();
+        let mut rng = ChaCha20Rng::seed_from_u64(44);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ek = gen_evaluation_key(&mut rng, &sk, &pk);
+
+        let bytes = ek.to_bytes();
+        let loaded = EvaluationKey::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.encryptions, ek.encryptions);
+        assert_eq!(loaded.params(), ek.params());
+        assert_eq!(loaded.pk_fingerprint(), &digest(&pk));
+        assert_eq!(loaded.to_bytes(), bytes);
+
+        for pos in [wire::HEADER_LEN, bytes.len() / 2, bytes.len() - 1] {
+            let mut corrupted = bytes.clone();
+            corrupted[pos] ^= 1;
+            assert!(matches!(
+                EvaluationKey::from_bytes(&corrupted),
+                Err(GswError::MalformedData(_))
+            ));
+        }
+        assert!(EvaluationKey::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_round_trip_under_msb_threshold() {
+        let params = Params::pedagogical().with_encoding(PlaintextEncoding::MsbThreshold);
+        let mut rng = ChaCha20Rng::seed_from_u64(487);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ek = gen_evaluation_key(&mut rng, &sk, &pk);
+
+        // The encoding is not saved, but the reloaded parameters still equal
+        // the originals and share their fingerprint.
+        let loaded = EvaluationKey::from_bytes(&ek.to_bytes()).unwrap();
+        assert_eq!(loaded.params(), &params);
+        assert_eq!(digest(loaded.params()), digest(&params));
+        assert_eq!(loaded.params().encoding, PlaintextEncoding::LsbOfScale);
+
+        let ct = encrypt(&mut rng, &pk, 1);
+        assert_eq!(
+            try_bootstrap(&params, &ct, &loaded).unwrap().len(),
+            ct.len()
+        );
+    }
 }
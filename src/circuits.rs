@@ -0,0 +1,856 @@
+//! Boolean circuits over encrypted bits, built from the GSW gates.
+//!
+//! Multi-bit values are carried as [`EncryptedBits`]: one bit ciphertext per
+//! position, least significant bit first.
+
+use std::collections::HashMap;
+
+use crate::error::GswError;
+use crate::gadget::flatten_matrix;
+use crate::gsw::{
+    difference, homomorphic_add, homomorphic_mult, homomorphic_not, trivial_ciphertext, Ciphertext,
+};
+use crate::modular::mod_q;
+use crate::params::Params;
+
+mod bristol;
+mod compat;
+mod encodings;
+mod graph;
+mod suspend;
+
+pub use compat::CompatReport;
+pub use encodings::{binary_to_gray, binary_to_onehot, gray_to_binary, onehot_to_binary};
+#[cfg(feature = "metrics")]
+pub(crate) use graph::GateObserver;
+pub use graph::{Circuit, Gate, Wire};
+pub use suspend::EvaluationState;
+pub(crate) use suspend::{advance, capture, check_origin};
+
+/// Encrypted multi-bit value: `bits[i]` encrypts bit i (LSB first).
+pub type EncryptedBits = Vec<Ciphertext>;
+
+/// Largest input width accepted by [`homomorphic_table_lookup`].
+pub const MAX_LOOKUP_INPUT_WIDTH: usize = 8;
+
+/// Homomorphic XOR of many ciphertexts: Flatten(C_1 + ... + C_k).
+///
+/// Sums the raw matrices and flattens once. Empty input gives Enc(0).
+pub(crate) fn homomorphic_sum(params: &Params, cts: &[&Ciphertext]) -> Ciphertext {
+    let q = params.q;
+    let n = params.n_expanded;
+    if cts.is_empty() {
+        return trivial_ciphertext(params, 0);
+    }
+
+    let mut sum = vec![vec![0u64; n]; n];
+    for ct in cts {
+        for (sum_row, ct_row) in sum.iter_mut().zip(ct.iter()) {
+            for (s, &c) in sum_row.iter_mut().zip(ct_row.iter()) {
+                *s = mod_q((*s as i64) + (c as i64), q);
+            }
+        }
+    }
+    flatten_matrix(&sum, params)
+}
+
+/// Enc(x + offset mod 2^w) for encrypted x of width w and a public offset,
+/// with the carry chain described at [`homomorphic_extract_bit`]. The carry
+/// out of the top bit is not computed.
+pub(crate) fn homomorphic_add_const(
+    params: &Params,
+    x_bits: &[Ciphertext],
+    offset: u64,
+) -> EncryptedBits {
+    let offset_bit = |i: usize| i < 64 && (offset >> i) & 1 == 1;
+    let mut sum = Vec::with_capacity(x_bits.len());
+    // Enc(c_i); None while the carry is known to be 0.
+    let mut carry: Option<Ciphertext> = None;
+    for (i, x_i) in x_bits.iter().enumerate() {
+        let s_i = match &carry {
+            Some(c) => homomorphic_add(params, c, x_i),
+            None => x_i.clone(),
+        };
+        sum.push(if offset_bit(i) {
+            homomorphic_not(params, &s_i)
+        } else {
+            s_i
+        });
+        if i + 1 == x_bits.len() {
+            break;
+        }
+        carry = match (carry, offset_bit(i)) {
+            (None, false) => None,
+            (None, true) => Some(x_i.clone()),
+            (Some(c), false) => Some(homomorphic_mult(params, &c, x_i)),
+            (Some(c), true) => {
+                let neither = homomorphic_mult(
+                    params,
+                    &homomorphic_not(params, &c),
+                    &homomorphic_not(params, x_i),
+                );
+                Some(homomorphic_not(params, &neither))
+            }
+        };
+    }
+    sum
+}
+
+/// Enc(bit `bit_index` of x + offset) for encrypted x and a public offset.
+///
+/// The sum is taken mod 2^w, where w = `x_bits.len()`; with w = log2(q) that
+/// is (x + offset) mod q. Adding a plaintext bit k_i needs no full adder: the
+/// carry becomes x_i·c_i where k_i = 0 and x_i OR c_i = NOT(NOT c_i · NOT x_i)
+/// where k_i = 1, one multiplication either way with the accumulated carry on
+/// the left. Carries are computed only up to `bit_index`, and stay trivially
+/// zero below the lowest set bit of the offset.
+pub fn homomorphic_extract_bit(
+    params: &Params,
+    x_bits: &[Ciphertext],
+    offset: u64,
+    bit_index: usize,
+) -> Ciphertext {
+    assert!(
+        bit_index < x_bits.len(),
+        "bit index {} out of range for a {}-bit value",
+        bit_index,
+        x_bits.len()
+    );
+    homomorphic_add_const(params, &x_bits[..=bit_index], offset)
+        .pop()
+        .expect("at least one bit")
+}
+
+/// One-hot decoding: returns 2^w ciphertexts where line k encrypts [x == k].
+///
+/// Lines are built by prefix expansion, one input bit at a time, so lines share
+/// their prefixes: 2^2 + ... + 2^w multiplications in total. Each line is a chain
+/// of w - 1 ANDs in which the fresh input literal is always the right operand,
+/// so noise grows additively along the chain rather than multiplying.
+///
+/// An empty input decodes to the single line Enc(1).
+pub fn homomorphic_one_hot(params: &Params, input: &[Ciphertext]) -> EncryptedBits {
+    let mut lines = vec![trivial_ciphertext(params, 1)];
+    for (i, bit) in input.iter().enumerate() {
+        let not_bit = homomorphic_not(params, bit);
+        if i == 0 {
+            lines = vec![not_bit, bit.clone()];
+            continue;
+        }
+        let mut next = Vec::with_capacity(lines.len() * 2);
+        next.extend(
+            lines
+                .iter()
+                .map(|line| homomorphic_mult(params, line, &not_bit)),
+        );
+        next.extend(lines.iter().map(|line| homomorphic_mult(params, line, bit)));
+        lines = next;
+    }
+    lines
+}
+
+/// Look up an encrypted index in a public table: returns Enc(table[x]).
+///
+/// `input` holds the bits of x (LSB first, at most [`MAX_LOOKUP_INPUT_WIDTH`]),
+/// `table` must have exactly 2^w entries, and the low `out_width` bits (≤ 64)
+/// of each entry are returned, LSB first. Higher entry bits are ignored.
+///
+/// The one-hot decoding of x is computed once; output bit j is the XOR of the
+/// lines k whose entry has bit j set. Depth: w - 1 chained ANDs for the
+/// decoding, after which the XOR layer is linear and only adds noise in
+/// proportion to the number of selected lines.
+pub fn homomorphic_table_lookup(
+    params: &Params,
+    input: &[Ciphertext],
+    table: &[u64],
+    out_width: usize,
+) -> EncryptedBits {
+    let width = input.len();
+    assert!(
+        width <= MAX_LOOKUP_INPUT_WIDTH,
+        "input width {} exceeds {}",
+        width,
+        MAX_LOOKUP_INPUT_WIDTH
+    );
+    assert_eq!(
+        table.len(),
+        1 << width,
+        "table must have 2^input_width entries"
+    );
+    assert!(out_width <= 64, "out_width must be at most 64");
+
+    let lines = homomorphic_one_hot(params, input);
+    (0..out_width)
+        .map(|j| {
+            let selected: Vec<&Ciphertext> = lines
+                .iter()
+                .zip(table.iter())
+                .filter(|(_, &entry)| (entry >> j) & 1 == 1)
+                .map(|(line, _)| line)
+                .collect();
+            homomorphic_sum(params, &selected)
+        })
+        .collect()
+}
+
+/// Largest counter width accepted by [`EncryptedHistogram`].
+pub const MAX_HISTOGRAM_COUNTER_WIDTH: usize = 8;
+
+/// Encrypted histogram of a stream of encrypted k-bit values.
+///
+/// Keeps 2^k counters of `counter_width` bits (counts wrap modulo
+/// 2^counter_width). Each absorbed value is one-hot decoded and its line is
+/// added into the matching counter.
+///
+/// A ripple-carry increment would multiply accumulated counter bits with each
+/// other, and GSW noise grows multiplicatively when both operands carry noise.
+/// Instead each counter stores the elementary symmetric polynomials
+/// e_1 .. e_{2^(w-1)} of its flags: by Lucas' theorem bit i of the count c is
+/// C(c, 2^i) mod 2 = e_{2^i}. Absorbing flag f updates e_j ← e_j ⊕ e_{j-1}·f,
+/// where the product is formed by ANDing the fresh input literals onto e_{j-1}
+/// one at a time, so noise grows additively with the number of items.
+///
+/// Without bootstrapping, which does not preserve bits yet (see
+/// [`bootstrap`](crate::bootstrap::bootstrap)), that growth caps the stream
+/// length, and it is fastest in the high counter bits: e_j takes on the
+/// noise of e_{j-1} with every item counted, so the noise of e_{2^i} grows
+/// like the count to the power 2^i. Under [`Params::toy`] with k = 2, seeded
+/// runs of random values first miscounted after 18 items with 3-bit
+/// counters and after 35 with 2-bit counters; 1-bit counters lasted past
+/// 300. Wider moduli hold longer streams. [`absorb`](Self::absorb) does not
+/// check the limit.
+#[derive(Clone, Debug)]
+pub struct EncryptedHistogram {
+    params: Params,
+    k: usize,
+    counter_width: usize,
+    /// `symmetric[v][j]` encrypts e_{j+1} of the flags seen for value v.
+    symmetric: Vec<Vec<Ciphertext>>,
+}
+
+impl EncryptedHistogram {
+    /// Empty histogram over k-bit values with `counter_width`-bit counters.
+    ///
+    /// Each counter holds 2^(counter_width - 1) ciphertexts of state.
+    pub fn new(params: &Params, k: usize, counter_width: usize) -> Self {
+        assert!(
+            (1..=MAX_LOOKUP_INPUT_WIDTH).contains(&k),
+            "value width must be between 1 and {}",
+            MAX_LOOKUP_INPUT_WIDTH
+        );
+        assert!(
+            (1..=MAX_HISTOGRAM_COUNTER_WIDTH).contains(&counter_width),
+            "counter width must be between 1 and {}",
+            MAX_HISTOGRAM_COUNTER_WIDTH
+        );
+        let zero = trivial_ciphertext(params, 0);
+        Self {
+            params: params.clone(),
+            k,
+            counter_width,
+            symmetric: vec![vec![zero; 1 << (counter_width - 1)]; 1 << k],
+        }
+    }
+
+    /// Count one encrypted value, given as k bits (LSB first).
+    ///
+    /// Costs 2^k one-hot lines plus, per counter, k multiplications for each
+    /// of the 2^(w-1) - 1 higher symmetric polynomials.
+    pub fn absorb(&mut self, value_bits: &[Ciphertext]) {
+        assert_eq!(value_bits.len(), self.k, "value must have k bits");
+        let params = &self.params;
+        let lines = homomorphic_one_hot(params, value_bits);
+        let literals: [EncryptedBits; 2] = [
+            value_bits
+                .iter()
+                .map(|bit| homomorphic_not(params, bit))
+                .collect(),
+            value_bits.to_vec(),
+        ];
+
+        for (v, (state, line)) in self.symmetric.iter_mut().zip(lines.iter()).enumerate() {
+            // Highest degree first, so each update reads the previous e_{j-1}.
+            for j in (1..state.len()).rev() {
+                let gated = (0..self.k).fold(state[j - 1].clone(), |acc, i| {
+                    homomorphic_mult(params, &acc, &literals[(v >> i) & 1][i])
+                });
+                state[j] = homomorphic_add(params, &state[j], &gated);
+            }
+            state[0] = homomorphic_add(params, &state[0], line);
+        }
+    }
+
+    /// The 2^k encrypted counters, each `counter_width` bits LSB first.
+    pub fn into_counters(self) -> Vec<EncryptedBits> {
+        let width = self.counter_width;
+        self.symmetric
+            .into_iter()
+            .map(|state| (0..width).map(|i| state[(1 << i) - 1].clone()).collect())
+            .collect()
+    }
+}
+
+/// Largest state count accepted by [`EncryptedDfa`].
+pub const MAX_DFA_STATES: usize = 8;
+
+/// A deterministic finite automaton over input bits, run on encrypted input.
+///
+/// The transition table is public; the current state is kept encrypted and
+/// one-hot (exactly one indicator encrypts 1). State 0 is the start state.
+///
+/// Per step, each indicator s is ANDed with the input bit x once, giving
+/// s·x for the 1-transition; s·(1 - x) = s - s·x is linear, and taking the
+/// difference (not the XOR) cancels the noise s and s·x share. A state whose
+/// two transitions agree passes s on unchanged. Each new indicator is the XOR
+/// of the terms that lead into it, which is their OR because at most one is
+/// set. So a step costs at most one multiplication per state and one level of
+/// depth, with the state on the left and the fresh input bit on the right:
+/// noise grows additively in the number of steps, scaled by how many
+/// transitions merge into one state.
+///
+/// As with [`EncryptedHistogram`], no state is bootstrapped, so this growth
+/// limits the input length. A step adds at most N times a fresh
+/// ciphertext's error per transition into a state, which the worst-case
+/// bounds of [`noise_model`](crate::noise_model) allow only about 7 times
+/// under [`Params::toy`]. The added errors are independent and of either
+/// sign, so in practice they grow like the square root of the step count: a
+/// seeded 3-state automaton under Toy still decrypted correctly after each
+/// of 3000 steps. [`step`](Self::step) checks neither limit.
+#[derive(Clone, Debug)]
+pub struct EncryptedDfa {
+    params: Params,
+    /// `transitions[s]` is (next state on 0, next state on 1).
+    transitions: Vec<(usize, usize)>,
+    accepting: Vec<bool>,
+    state: Vec<Ciphertext>,
+}
+
+impl EncryptedDfa {
+    /// Automaton in its start state, with `transitions[s]` = (on 0, on 1).
+    pub fn new(params: &Params, transitions: &[(usize, usize)], accepting: &[bool]) -> Self {
+        let states = transitions.len();
+        assert!(
+            (1..=MAX_DFA_STATES).contains(&states),
+            "state count must be between 1 and {}",
+            MAX_DFA_STATES
+        );
+        assert_eq!(accepting.len(), states, "one accepting flag per state");
+        assert!(
+            transitions
+                .iter()
+                .all(|&(t0, t1)| t0 < states && t1 < states),
+            "transition target out of range"
+        );
+        let mut state = vec![trivial_ciphertext(params, 0); states];
+        state[0] = trivial_ciphertext(params, 1);
+        Self {
+            params: params.clone(),
+            transitions: transitions.to_vec(),
+            accepting: accepting.to_vec(),
+            state,
+        }
+    }
+
+    /// Consume one encrypted input bit.
+    pub fn step(&mut self, input_bit: &Ciphertext) {
+        let params = &self.params;
+        // (target, indicator) pairs for every transition taken.
+        let mut terms: Vec<(usize, Ciphertext)> = Vec::with_capacity(2 * self.state.len());
+        for (s, &(t0, t1)) in self.state.iter().zip(&self.transitions) {
+            if t0 == t1 {
+                terms.push((t0, s.clone()));
+                continue;
+            }
+            let on_one = homomorphic_mult(params, s, input_bit);
+            terms.push((t0, difference(params, s, &on_one)));
+            terms.push((t1, on_one));
+        }
+        self.state = (0..self.state.len())
+            .map(|target| {
+                let incoming: Vec<&Ciphertext> = terms
+                    .iter()
+                    .filter(|(t, _)| *t == target)
+                    .map(|(_, ct)| ct)
+                    .collect();
+                homomorphic_sum(params, &incoming)
+            })
+            .collect();
+    }
+
+    /// Enc(1) if the current state is accepting: the XOR (here, OR) of the
+    /// accepting indicators. Linear, so free of multiplications.
+    pub fn accepts(&self) -> Ciphertext {
+        let accepting: Vec<&Ciphertext> = self
+            .state
+            .iter()
+            .zip(&self.accepting)
+            .filter(|(_, &accept)| accept)
+            .map(|(ct, _)| ct)
+            .collect();
+        homomorphic_sum(&self.params, &accepting)
+    }
+}
+
+/// A Boolean function in algebraic normal form: the XOR of monomials, each
+/// the AND of some input variables.
+///
+/// Each monomial is computed with a balanced AND tree (larger half on the
+/// left), so a degree-d monomial is ⌈log2 d⌉ multiplications deep. Trees are
+/// split the same way everywhere, so sub-monomials shared between terms, such
+/// as x0·x1 in both x0·x1 and x0·x1·x2, are computed once. The monomials are
+/// XORed with a single flatten at the end.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnfPolynomial {
+    inputs: usize,
+    /// Sorted, deduplicated variable sets; the empty set is the constant 1.
+    terms: Vec<Vec<usize>>,
+}
+
+impl AnfPolynomial {
+    /// The XOR of `terms` over `inputs` variables, each term listing the
+    /// variables it ANDs. An empty term is the constant 1.
+    ///
+    /// Repeated variables within a term collapse (x·x = x) and repeated terms
+    /// cancel in pairs (t ⊕ t = 0). Fails with
+    /// [`GswError::VariableOutOfRange`] if a term names a variable at or
+    /// past `inputs`.
+    pub fn new(inputs: usize, terms: &[Vec<usize>]) -> Result<Self, GswError> {
+        let mut normalized: Vec<Vec<usize>> = Vec::with_capacity(terms.len());
+        for term in terms {
+            if let Some(&index) = term.iter().find(|&&i| i >= inputs) {
+                return Err(GswError::VariableOutOfRange { index, inputs });
+            }
+            let mut term = term.clone();
+            term.sort_unstable();
+            term.dedup();
+            match normalized.iter().position(|t| *t == term) {
+                Some(pos) => {
+                    normalized.swap_remove(pos);
+                }
+                None => normalized.push(term),
+            }
+        }
+        normalized.sort();
+        Ok(Self {
+            inputs,
+            terms: normalized,
+        })
+    }
+
+    /// Number of input variables.
+    pub fn input_count(&self) -> usize {
+        self.inputs
+    }
+
+    /// Largest monomial degree; 0 for a constant or zero polynomial.
+    pub fn degree(&self) -> usize {
+        self.terms.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// Evaluate on encrypted `inputs`, one per variable.
+    pub fn evaluate(&self, params: &Params, inputs: &[Ciphertext]) -> Ciphertext {
+        assert_eq!(inputs.len(), self.inputs, "one input per variable");
+        let one = trivial_ciphertext(params, 1);
+        let mut products: HashMap<&[usize], Ciphertext> = HashMap::new();
+        for term in &self.terms {
+            monomial(params, inputs, term, &mut products);
+        }
+        let monomials: Vec<&Ciphertext> = self
+            .terms
+            .iter()
+            .map(|term| match term.as_slice() {
+                [] => &one,
+                &[i] => &inputs[i],
+                vars => &products[vars],
+            })
+            .collect();
+        homomorphic_sum(params, &monomials)
+    }
+}
+
+/// Ensure `products` holds the product of `vars` (at least two variables) and
+/// of every sub-monomial of its balanced split.
+fn monomial<'a>(
+    params: &Params,
+    inputs: &[Ciphertext],
+    vars: &'a [usize],
+    products: &mut HashMap<&'a [usize], Ciphertext>,
+) {
+    if vars.len() < 2 || products.contains_key(vars) {
+        return;
+    }
+    let (left, right) = vars.split_at(vars.len().div_ceil(2));
+    monomial(params, inputs, left, products);
+    monomial(params, inputs, right, products);
+    let factor = |half: &[usize]| match half {
+        &[i] => &inputs[i],
+        half => &products[half],
+    };
+    let product = homomorphic_mult(params, factor(left), factor(right));
+    products.insert(vars, product);
+}
+
+/// Enc(done ? state : new_state), bit by bit: the MUX
+/// n_i ⊕ done·(s_i ⊕ n_i), one multiplication per bit with the flag on the
+/// left. Every bit is computed whatever the flag, so the result reveals
+/// nothing about it.
+///
+/// Panics if the states differ in width.
+pub fn homomorphic_freeze_update(
+    params: &Params,
+    state: &EncryptedBits,
+    new_state: &EncryptedBits,
+    done: &Ciphertext,
+) -> EncryptedBits {
+    assert_eq!(
+        state.len(),
+        new_state.len(),
+        "states must have the same width"
+    );
+    state
+        .iter()
+        .zip(new_state)
+        .map(|(s_i, n_i)| {
+            let differ = homomorphic_add(params, s_i, n_i);
+            homomorphic_add(params, n_i, &homomorphic_mult(params, done, &differ))
+        })
+        .collect()
+}
+
+/// A loop over encrypted state that always runs a fixed number of
+/// iterations, for algorithms whose data-dependent length must not leak.
+///
+/// Each iteration calls the step closure with the current state and "done"
+/// flag; it returns the next state and Enc(1) if the loop should stop after
+/// this step. The loop keeps the old state where the flag was already set
+/// ([`homomorphic_freeze_update`]) and ORs the returned flag into it, so once
+/// set, the state stops changing while the remaining iterations still run.
+///
+/// `step_depth` is the multiplicative depth the closure adds to its outputs;
+/// freezing and the OR add one more per iteration. The loop never
+/// bootstraps, so the state and flag keep deepening: each iteration multiplies their noise by up to N + 1, about
+/// log₂ N bits, on top of what the step adds, and the loop decrypts
+/// correctly only for as many iterations as the modulus has room for. A
+/// one-product step under a 62-bit modulus with n = 8 runs five.
+#[derive(Clone, Debug)]
+pub struct BoundedLoop {
+    params: Params,
+    iterations: usize,
+    step_depth: u32,
+}
+
+/// The result of [`BoundedLoop::run`].
+#[derive(Clone, Debug)]
+pub struct LoopOutcome {
+    pub state: EncryptedBits,
+    pub done: Ciphertext,
+    /// Depth charged to the final state and flag: `step_depth + 1` per
+    /// iteration.
+    pub depth: u32,
+}
+
+impl BoundedLoop {
+    /// A loop of `iterations` steps, each adding `step_depth` levels.
+    pub fn new(params: &Params, iterations: usize, step_depth: u32) -> Self {
+        Self {
+            params: params.clone(),
+            iterations,
+            step_depth,
+        }
+    }
+
+    /// Run every iteration from `state` and `done`, which are charged depth
+    /// 0 (fresh ciphertexts).
+    ///
+    /// Panics if the closure returns a state of a different width.
+    pub fn run<F>(&self, state: EncryptedBits, done: Ciphertext, mut step: F) -> LoopOutcome
+    where
+        F: FnMut(&Params, &EncryptedBits, &Ciphertext) -> (EncryptedBits, Ciphertext),
+    {
+        let params = &self.params;
+        let mut outcome = LoopOutcome {
+            state,
+            done,
+            depth: 0,
+        };
+        for _ in 0..self.iterations {
+            let (next, stop) = step(params, &outcome.state, &outcome.done);
+            outcome.state = homomorphic_freeze_update(params, &outcome.state, &next, &outcome.done);
+            // done OR stop = NOT(NOT done · NOT stop).
+            let neither = homomorphic_mult(
+                params,
+                &homomorphic_not(params, &outcome.done),
+                &homomorphic_not(params, &stop),
+            );
+            outcome.done = homomorphic_not(params, &neither);
+            outcome.depth += self.step_depth + 1;
+        }
+        outcome
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::gsw::{decrypt, encrypt, gsw_keygen};
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn test_one_hot() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let bits = [encrypt(&mut rng, &pk, 0), encrypt(&mut rng, &pk, 1)];
+        for x in 0..8usize {
+            let input: Vec<Ciphertext> = (0..3).map(|i| bits[(x >> i) & 1].clone()).collect();
+            let lines = homomorphic_one_hot(&params, &input);
+            assert_eq!(lines.len(), 8);
+            for (k, line) in lines.iter().enumerate() {
+                assert_eq!(decrypt(&sk, line), (k == x) as u8, "x={} line {}", x, k);
+            }
+        }
+    }
+
+    #[test]
+    fn test_table_lookup_5_to_3_exhaustive() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let table: Vec<u64> = (0..32u64).map(|x| (x * 7 + 3) % 8).collect();
+        let bits = [encrypt(&mut rng, &pk, 0), encrypt(&mut rng, &pk, 1)];
+
+        for x in 0..32usize {
+            let input: Vec<Ciphertext> = (0..5).map(|i| bits[(x >> i) & 1].clone()).collect();
+            let out = homomorphic_table_lookup(&params, &input, &table, 3);
+            let got = out
+                .iter()
+                .enumerate()
+                .fold(0u64, |acc, (j, ct)| acc | ((decrypt(&sk, ct) as u64) << j));
+            assert_eq!(got, table[x], "lookup of {}", x);
+        }
+    }
+
+    #[test]
+    fn test_histogram() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let stream = [3u64, 1, 3, 0, 3, 2];
+
+        let mut histogram = EncryptedHistogram::new(&params, 2, 3);
+        for &value in &stream {
+            let bits: Vec<Ciphertext> = (0..2)
+                .map(|i| encrypt(&mut rng, &pk, ((value >> i) & 1) as u8))
+                .collect();
+            histogram.absorb(&bits);
+        }
+
+        let counters = histogram.into_counters();
+        assert_eq!(counters.len(), 4);
+        for (v, counter) in counters.iter().enumerate() {
+            let count = counter
+                .iter()
+                .enumerate()
+                .fold(0u64, |acc, (i, ct)| acc | ((decrypt(&sk, ct) as u64) << i));
+            let expected = stream.iter().filter(|&&x| x == v as u64).count() as u64;
+            assert_eq!(count, expected, "counter for value {}", v);
+        }
+    }
+
+    #[test]
+    fn test_dfa_contains_11() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        // 0: last bit was 0, 1: last bit was 1, 2: seen "11" (absorbing).
+        let transitions = [(0, 1), (0, 2), (2, 2)];
+        let accepting = [false, false, true];
+
+        for input in [
+            0b000000u64,
+            0b010101,
+            0b000011,
+            0b101010,
+            0b011010,
+            0b110000,
+            0b111111,
+        ] {
+            let mut dfa = EncryptedDfa::new(&params, &transitions, &accepting);
+            let mut state = 0;
+            for i in 0..6 {
+                let bit = (input >> i) & 1;
+                dfa.step(&encrypt(&mut rng, &pk, bit as u8));
+                state = if bit == 1 {
+                    transitions[state].1
+                } else {
+                    transitions[state].0
+                };
+            }
+            assert_eq!(
+                decrypt(&sk, &dfa.accepts()),
+                accepting[state] as u8,
+                "input {:06b}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_extract_bit() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        for width in [8, 12] {
+            let mask = (1u64 << width) - 1;
+            for _ in 0..3 {
+                let x = rng.gen_range(0..=mask);
+                let offset = rng.gen_range(0..=mask);
+                let x_bits: Vec<Ciphertext> = (0..width)
+                    .map(|i| encrypt(&mut rng, &pk, ((x >> i) & 1) as u8))
+                    .collect();
+                let expected = (x + offset) & mask;
+                for i in 0..width {
+                    let bit = homomorphic_extract_bit(&params, &x_bits, offset, i);
+                    assert_eq!(
+                        decrypt(&sk, &bit) as u64,
+                        (expected >> i) & 1,
+                        "x = {}, offset = {}, bit {}",
+                        x,
+                        offset,
+                        i
+                    );
+                }
+            }
+        }
+    }
+
+    /// Decrypt `poly` on every assignment of encrypted inputs and compare
+    /// with its cleartext value.
+    fn check_anf(poly: &AnfPolynomial, terms: &[Vec<usize>], seed: u64) {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let vars = poly.input_count();
+        for x in 0..1usize << vars {
+            let inputs: Vec<Ciphertext> = (0..vars)
+                .map(|i| encrypt(&mut rng, &pk, ((x >> i) & 1) as u8))
+                .collect();
+            let expected = terms
+                .iter()
+                .filter(|term| term.iter().all(|&i| (x >> i) & 1 == 1))
+                .count()
+                % 2;
+            assert_eq!(
+                decrypt(&sk, &poly.evaluate(&params, &inputs)) as usize,
+                expected,
+                "inputs {:0width$b}",
+                x,
+                width = vars
+            );
+        }
+    }
+
+    #[test]
+    fn test_anf_majority() {
+        let terms = vec![vec![0, 1], vec![0, 2], vec![1, 2]];
+        let majority = AnfPolynomial::new(3, &terms).unwrap();
+        assert_eq!(majority.degree(), 2);
+        check_anf(&majority, &terms, 475);
+    }
+
+    #[test]
+    fn test_anf_random_polynomial() {
+        let mut rng = ChaCha20Rng::seed_from_u64(4);
+        // Each of the 16 monomials over 4 variables (the empty one is the
+        // constant 1) with probability 1/2, plus x0·x1·x2·x3 so the degree-4
+        // tree and its shared halves are exercised.
+        let mut terms: Vec<Vec<usize>> = (0..16usize)
+            .filter(|_| rng.gen::<bool>())
+            .map(|mask| (0..4).filter(|i| (mask >> i) & 1 == 1).collect())
+            .collect();
+        terms.retain(|term| term.len() < 4);
+        terms.push(vec![3, 2, 1, 0, 0]);
+        let poly = AnfPolynomial::new(4, &terms).unwrap();
+        assert_eq!(poly.degree(), 4);
+        check_anf(&poly, &terms, 476);
+
+        // Repeated terms cancel; variables must exist.
+        let twice = AnfPolynomial::new(2, &[vec![0, 1], vec![1, 0], vec![]]).unwrap();
+        assert_eq!(twice, AnfPolynomial::new(2, &[vec![]]).unwrap());
+        assert!(matches!(
+            AnfPolynomial::new(3, &[vec![0], vec![1, 3]]),
+            Err(GswError::VariableOutOfRange {
+                index: 3,
+                inputs: 3
+            })
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "2^input_width")]
+    fn test_table_lookup_rejects_wrong_table_size() {
+        let params = Params::toy();
+        let input = vec![trivial_ciphertext(&params, 1); 2];
+        homomorphic_table_lookup(&params, &input, &[0, 1, 2], 2);
+    }
+
+    /// Run a countdown from `start` in the low two bits of the state that
+    /// counts its live steps in the high two, stopping once the count reaches
+    /// zero. Returns the decrypted state and flag.
+    fn countdown(
+        bounded: &BoundedLoop,
+        sk: &crate::gsw::GswSecretKey,
+        pk: &crate::gsw::GswPublicKey,
+        rng: &mut ChaCha20Rng,
+        start: u64,
+    ) -> (u64, u8, LoopOutcome) {
+        let state = (0..4)
+            .map(|i| encrypt(rng, pk, (start >> i) as u8 & 1))
+            .collect();
+        let done = encrypt(rng, pk, (start == 0) as u8);
+        let outcome = bounded.run(state, done, |params, state, _| {
+            let mut next = homomorphic_add_const(params, &state[..2], 3);
+            let stop = crate::integer::homomorphic_is_zero(params, &next);
+            next.extend(homomorphic_add_const(params, &state[2..], 1));
+            (next, stop)
+        });
+        let value = outcome
+            .state
+            .iter()
+            .enumerate()
+            .map(|(i, bit)| (decrypt(sk, bit) as u64) << i)
+            .sum();
+        (value, decrypt(sk, &outcome.done), outcome)
+    }
+
+    #[test]
+    fn test_bounded_loop_countdown() {
+        // An iteration adds about 10 bits of noise, so a 62-bit modulus holds
+        // five without bootstrapping.
+        let params = Params::custom(1 << 62, 8, 256, 1, -40).unwrap();
+        let mut rng = ChaCha20Rng::seed_from_u64(490);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+
+        let old = vec![encrypt(&mut rng, &pk, 1), encrypt(&mut rng, &pk, 0)];
+        let new = vec![encrypt(&mut rng, &pk, 0), encrypt(&mut rng, &pk, 1)];
+        for (flag, expected) in [(0, [0, 1]), (1, [1, 0])] {
+            let done = encrypt(&mut rng, &pk, flag);
+            let kept = homomorphic_freeze_update(&params, &old, &new, &done);
+            assert_eq!(
+                kept.iter().map(|b| decrypt(&sk, b)).collect::<Vec<_>>(),
+                expected
+            );
+        }
+
+        // Three steps reach zero, having counted three; the extra two change
+        // nothing.
+        let exact = countdown(&BoundedLoop::new(&params, 3, 1), &sk, &pk, &mut rng, 3);
+        let longer = countdown(&BoundedLoop::new(&params, 5, 1), &sk, &pk, &mut rng, 3);
+        assert_eq!((exact.0, exact.1), (3 << 2, 1));
+        assert_eq!((longer.0, longer.1), (exact.0, exact.1));
+        assert_eq!((exact.2.depth, longer.2.depth), (6, 10));
+        let short = countdown(&BoundedLoop::new(&params, 2, 1), &sk, &pk, &mut rng, 3);
+        assert_eq!((short.0, short.1), (1 | 2 << 2, 0));
+    }
+}
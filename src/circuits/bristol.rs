@@ -0,0 +1,311 @@
+//! Reading circuits in Bristol Fashion.
+//!
+//! The text is a header of three lines (gate and wire counts, then the input
+//! and output values, each a count followed by that many bit widths) and one
+//! gate per line: input and output wire counts, the input wires, the output
+//! wires, and the operation. Inputs are the lowest wires and outputs the
+//! highest. Supported operations are XOR, AND, INV, EQ (a constant), EQW (a
+//! copy), and MAND (several ANDs at once). Blank lines are ignored.
+//!
+//! The parser never stops at the first problem: a malformed gate drives its
+//! outputs with a placeholder so later lines are still checked without
+//! reporting the same mistake again.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use super::{Circuit, Wire};
+use crate::error::{BristolError, BristolProblem, CircuitParseError};
+
+impl Circuit {
+    /// Parse a Bristol Fashion circuit. One problem gives
+    /// [`CircuitParseError::Line`]; several give
+    /// [`CircuitParseError::Multiple`] listing them all.
+    pub fn from_bristol(text: &str) -> Result<Circuit, CircuitParseError> {
+        let (circuit, errors) = parse(text);
+        if errors.is_empty() {
+            Ok(circuit)
+        } else {
+            Err(errors.into())
+        }
+    }
+
+    /// Every problem [`from_bristol`](Self::from_bristol) finds in `text`,
+    /// in line order; empty if it would accept it.
+    pub fn validate_all(text: &str) -> Vec<BristolError> {
+        parse(text).1
+    }
+}
+
+/// The fields of a header line as counts.
+fn counts(line: &str) -> Option<Vec<usize>> {
+    line.split_whitespace()
+        .map(|field| field.parse().ok())
+        .collect()
+}
+
+/// Total bit width of an input or output header line: a count k followed by
+/// k widths.
+fn total_width(line: &str) -> Option<usize> {
+    let fields = counts(line)?;
+    let (&k, widths) = fields.split_first()?;
+    (widths.len() == k).then(|| widths.iter().sum())
+}
+
+/// Whether `op` takes `ins` input wires and drives `outs`.
+fn arity_fits(op: &str, ins: usize, outs: usize) -> Option<bool> {
+    Some(match op {
+        "XOR" | "AND" => (ins, outs) == (2, 1),
+        "INV" | "EQ" | "EQW" => (ins, outs) == (1, 1),
+        "MAND" => outs > 0 && ins == 2 * outs,
+        _ => return None,
+    })
+}
+
+struct Parser {
+    circuit: Circuit,
+    /// Circuit wire for each Bristol wire driven so far.
+    wires: HashMap<usize, Wire>,
+    placeholder: Option<Wire>,
+    errors: Vec<BristolError>,
+}
+
+impl Parser {
+    fn error(&mut self, line: usize, problem: BristolProblem) {
+        self.errors.push(BristolError { line, problem });
+    }
+
+    /// A constant wire standing in for the outputs of a bad gate.
+    fn placeholder(&mut self) -> Wire {
+        match self.placeholder {
+            Some(wire) => wire,
+            None => *self.placeholder.insert(self.circuit.constant(false)),
+        }
+    }
+
+    /// Bind `outs` to `values`, reporting wires already driven.
+    fn drive(&mut self, line: usize, outs: &[usize], values: &[Wire]) {
+        for (&out, &value) in outs.iter().zip(values) {
+            match self.wires.entry(out) {
+                Entry::Occupied(_) => self.error(line, BristolProblem::RedrivenWire { wire: out }),
+                Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+            }
+        }
+    }
+
+    fn gate(&mut self, line: usize, text: &str) {
+        let fields: Vec<&str> = text.split_whitespace().collect();
+        let (op, numbers) = fields.split_last().expect("blank lines are skipped");
+        let numbers: Option<Vec<usize>> = numbers.iter().map(|f| f.parse().ok()).collect();
+        let Some(numbers) = numbers.filter(|n| n.len() >= 2 && n.len() == 2 + n[0] + n[1]) else {
+            return self.error(line, BristolProblem::BadGate);
+        };
+        let (ins, outs) = numbers[2..].split_at(numbers[0]);
+        let bad = match arity_fits(op, ins.len(), outs.len()) {
+            None => Some(BristolProblem::UnknownOp(op.to_string())),
+            Some(false) => Some(BristolProblem::BadGate),
+            Some(true) if *op == "EQ" && ins[0] > 1 => Some(BristolProblem::BadGate),
+            Some(true) => None,
+        };
+        if let Some(problem) = bad {
+            self.error(line, problem);
+            let placeholder = self.placeholder();
+            return self.drive(line, outs, &vec![placeholder; outs.len()]);
+        }
+
+        let read: Vec<Option<Wire>> = if *op == "EQ" {
+            Vec::new()
+        } else {
+            ins.iter().map(|w| self.wires.get(w).copied()).collect()
+        };
+        for (&wire, value) in ins.iter().zip(&read) {
+            if value.is_none() {
+                self.error(line, BristolProblem::UndefinedWire { wire });
+            }
+        }
+        let values: Vec<Wire> = match read.into_iter().collect::<Option<Vec<_>>>() {
+            None => vec![self.placeholder(); outs.len()],
+            Some(a) => match *op {
+                "XOR" => vec![self.circuit.xor(a[0], a[1])],
+                "AND" => vec![self.circuit.and(a[0], a[1])],
+                "INV" => vec![self.circuit.not(a[0])],
+                "EQ" => vec![self.circuit.constant(ins[0] == 1)],
+                "EQW" => vec![a[0]],
+                _ => (0..outs.len())
+                    .map(|k| self.circuit.and(a[k], a[outs.len() + k]))
+                    .collect(),
+            },
+        };
+        self.drive(line, outs, &values);
+    }
+}
+
+/// The next line parsed as a header line, or [`BristolProblem::BadHeader`]
+/// at it (or just past the end of `text` if there is none).
+fn header<'a, T>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    text: &str,
+    errors: &mut Vec<BristolError>,
+    parse: impl Fn(&str) -> Option<T>,
+) -> (usize, Option<T>) {
+    let (line, fields) = lines.next().unwrap_or((text.lines().count() + 1, ""));
+    let value = parse(fields);
+    if value.is_none() {
+        errors.push(BristolError {
+            line,
+            problem: BristolProblem::BadHeader,
+        });
+    }
+    (line, value)
+}
+
+fn parse(text: &str) -> (Circuit, Vec<BristolError>) {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty());
+    let mut errors = Vec::new();
+    let (sizes_line, sizes) = header(&mut lines, text, &mut errors, |line| {
+        match counts(line).as_deref() {
+            Some(&[gates, wires]) => Some((gates, wires)),
+            _ => None,
+        }
+    });
+    let (_, inputs) = header(&mut lines, text, &mut errors, total_width);
+    let (outputs_line, outputs) = header(&mut lines, text, &mut errors, total_width);
+
+    let inputs = inputs.unwrap_or(0);
+    let mut parser = Parser {
+        circuit: Circuit::new(inputs),
+        wires: (0..inputs).map(|i| (i, i)).collect(),
+        placeholder: None,
+        errors,
+    };
+    let mut gates = 0;
+    for (line, text) in lines {
+        parser.gate(line, text);
+        gates += 1;
+    }
+
+    if let Some((expected, wires)) = sizes {
+        if expected != gates {
+            parser.error(
+                sizes_line,
+                BristolProblem::GateCount {
+                    expected,
+                    found: gates,
+                },
+            );
+        }
+        if let Some(outputs) = outputs {
+            let mut driven = Vec::with_capacity(outputs);
+            for wire in wires.saturating_sub(outputs)..wires {
+                match parser.wires.get(&wire) {
+                    Some(&value) => driven.push(value),
+                    None => parser.error(outputs_line, BristolProblem::UndrivenOutput { wire }),
+                }
+            }
+            parser.circuit.set_outputs(&driven);
+        }
+    }
+    parser.errors.sort_by_key(|error| error.line);
+    (parser.circuit, parser.errors)
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::context::{EncBool, Evaluator};
+    use crate::params::Params;
+
+    /// A one-bit full adder: inputs a, b, c; outputs sum, carry.
+    const FULL_ADDER: &str = "5 8
+3 1 1 1
+2 1 1
+
+2 1 0 1 3 XOR
+2 1 3 2 6 XOR
+2 1 0 1 4 AND
+2 1 3 2 5 AND
+2 1 4 5 7 XOR
+";
+
+    #[test]
+    fn test_parses_and_evaluates() {
+        let circuit = Circuit::from_bristol(FULL_ADDER).unwrap();
+        assert_eq!((circuit.input_count(), circuit.outputs().len()), (3, 2));
+        let params = Params::pedagogical();
+        let eval = Evaluator::new(&params);
+        for x in 0..8usize {
+            let inputs: Vec<EncBool> = (0..3)
+                .map(|i| EncBool::constant(&eval, (x >> i) & 1 == 1))
+                .collect();
+            let out: Vec<Option<bool>> = circuit
+                .evaluate(&eval, &inputs)
+                .iter()
+                .map(EncBool::as_constant)
+                .collect();
+            let sum = x.count_ones();
+            assert_eq!(
+                out,
+                [Some(sum & 1 == 1), Some(sum >= 2)],
+                "inputs {:03b}",
+                x
+            );
+        }
+    }
+
+    #[test]
+    fn test_reports_every_bad_line() {
+        // A wrong gate count, an unknown operation, and a read of an
+        // undriven wire; none masks the others, and the NAND's output stands
+        // in for a real wire rather than cascading.
+        let text = FULL_ADDER
+            .replacen("5 8", "6 8", 1)
+            .replace("2 1 0 1 4 AND", "2 1 0 1 4 NAND")
+            .replace("2 1 3 2 5 AND", "2 1 3 9 5 AND");
+        let expected = vec![
+            BristolError {
+                line: 1,
+                problem: BristolProblem::GateCount {
+                    expected: 6,
+                    found: 5,
+                },
+            },
+            BristolError {
+                line: 7,
+                problem: BristolProblem::UnknownOp("NAND".into()),
+            },
+            BristolError {
+                line: 8,
+                problem: BristolProblem::UndefinedWire { wire: 9 },
+            },
+        ];
+        assert_eq!(Circuit::validate_all(&text), expected);
+        let err = Circuit::from_bristol(&text).unwrap_err();
+        assert_eq!(err, CircuitParseError::Multiple(expected));
+        assert_eq!(
+            err.to_string(),
+            "3 problems in the circuit:\n  - line 1: header declares 6 gates, found 5\n  \
+             - line 7: unknown operation \"NAND\"\n  - line 8: wire 9 is read before it is driven"
+        );
+
+        let text = FULL_ADDER.replace("2 1 3 2 6 XOR", "2 1 3 2 3 XOR");
+        assert!(matches!(
+            Circuit::from_bristol(&text),
+            Err(CircuitParseError::Multiple(_))
+        ));
+        let text = FULL_ADDER.replace("2 1 4 5 7 XOR", "2 1 4 5 7 OR");
+        assert_eq!(
+            Circuit::from_bristol(&text),
+            Err(CircuitParseError::Line(BristolError {
+                line: 9,
+                problem: BristolProblem::UnknownOp("OR".into()),
+            }))
+        );
+        assert!(Circuit::validate_all("").len() == 3);
+    }
+}
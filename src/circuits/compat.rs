@@ -0,0 +1,186 @@
+//! Checking a circuit against parameters before evaluating it.
+//!
+//! The check runs the circuit through a dry-run [`Evaluator`] that computes
+//! no ciphertexts but makes every scheduling decision a real one would:
+//! constant folding, operand order, and gate lowering. The resulting
+//! operation counts are priced with the parameters' [`CostModel`].
+//!
+//! No evaluation key enters the check: evaluation never bootstraps (see
+//! [`bootstrap`](crate::bootstrap::bootstrap) for why), so a circuit fits
+//! exactly when its deepest product does.
+//!
+//! Constants become trivial encryptions, which every parameter set
+//! represents, so gates need no representability check beyond their depth.
+
+use super::{Circuit, Gate};
+use crate::context::{EncBool, Evaluator, GateStrategy, OpCounts};
+use crate::cost::{CostModel, CostOp};
+use crate::error::GswError;
+use crate::params::Params;
+
+/// What evaluating a circuit would take; see [`Circuit::check_compatibility`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompatReport {
+    /// Depth of the deepest product the evaluation would compute.
+    pub deepest: u32,
+    /// Largest depth that meets the parameters' failure target.
+    pub depth_budget: u32,
+    /// Operations the evaluation would perform.
+    pub counts: OpCounts,
+    /// Total cost in multiply-add equivalents (see [`CostModel`]).
+    pub estimated_ops: u64,
+    /// Most wire values held at once, counting constants as ciphertexts and
+    /// every wire as live until the end: an upper bound, since evaluation
+    /// drops a wire after its last reader.
+    pub peak_live_wires: usize,
+    /// `peak_live_wires` full-size ciphertexts, in bytes.
+    pub peak_bytes: u64,
+}
+
+impl CompatReport {
+    /// Whether every product stays within the depth budget.
+    pub fn is_compatible(&self) -> bool {
+        self.counts.over_budget == 0
+    }
+
+    /// Estimated seconds at `throughput_hint` multiply-adds per second, as
+    /// [`calibrate`](crate::cost::calibrate) measures.
+    pub fn estimated_seconds(&self, throughput_hint: f64) -> f64 {
+        self.estimated_ops as f64 / throughput_hint
+    }
+}
+
+impl Circuit {
+    /// Check that evaluating this circuit under `params` keeps every product
+    /// within the depth budget, and report the operations, estimated cost,
+    /// and peak memory it would take.
+    ///
+    /// Fails with [`GswError::IncompatibleCircuit`], carrying the report, if
+    /// some product exceeds the budget. Scheduling follows
+    /// [`GateStrategy::Direct`]; [`Evaluator::evaluate_circuit`] checks under
+    /// the evaluator's own strategy.
+    pub fn check_compatibility(&self, params: &Params) -> Result<CompatReport, GswError> {
+        self.check_with_strategy(params, GateStrategy::Direct)
+    }
+
+    pub(crate) fn check_with_strategy(
+        &self,
+        params: &Params,
+        strategy: GateStrategy,
+    ) -> Result<CompatReport, GswError> {
+        let eval = Evaluator::dry_run(params, strategy);
+        let inputs: Vec<EncBool> = (0..self.input_count())
+            .map(|_| eval.input(Vec::new()))
+            .collect();
+        self.evaluate(&eval, &inputs);
+
+        let counts = eval.op_counts();
+        let model = CostModel::new(params);
+        let estimated_ops = counts.adds as u64 * model.ops(CostOp::Add)
+            + (counts.mults + counts.nands) as u64 * model.ops(CostOp::Mult);
+        let peak_live_wires = self.peak_live_wires();
+        let report = CompatReport {
+            deepest: counts.deepest,
+            depth_budget: eval.max_depth(),
+            counts,
+            estimated_ops,
+            peak_live_wires,
+            peak_bytes: peak_live_wires as u64 * (params.n_expanded as u64).pow(2) * 8,
+        };
+        if report.is_compatible() {
+            Ok(report)
+        } else {
+            Err(GswError::IncompatibleCircuit(Box::new(report)))
+        }
+    }
+
+    /// An upper bound on the wire values [`evaluate`](Self::evaluate) holds
+    /// at once: every
+    /// wire so far, plus, inside a conditional, its arguments and the live
+    /// values of whichever branch is running, and finally the outputs.
+    fn peak_live_wires(&self) -> usize {
+        let mut live = 0;
+        let mut peak = 0;
+        for gate in self.gates() {
+            if let Gate::CondSubcircuit {
+                then_circuit,
+                else_circuit,
+                args,
+                ..
+            } = gate
+            {
+                let branches = then_circuit
+                    .peak_live_wires()
+                    .max(then_circuit.outputs().len() + else_circuit.peak_live_wires());
+                peak = peak.max(live + args.len() + branches);
+                // The muxed outputs stay alongside the node's own wire.
+                live += then_circuit.outputs().len();
+            }
+            live += 1;
+            peak = peak.max(live);
+        }
+        peak.max(live + self.outputs().len())
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::gsw::gsw_keygen;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    /// An AND of `inputs` inputs, chained so each product deepens the last.
+    fn and_chain(inputs: usize) -> Circuit {
+        let mut circuit = Circuit::new(inputs);
+        let acc = (1..inputs).fold(0, |acc, i| circuit.and(acc, i));
+        circuit.set_outputs(&[acc]);
+        circuit
+    }
+
+    #[test]
+    fn test_depth_heavy_circuit_is_rejected() {
+        let params = Params::for_depth(2, -40).unwrap();
+        let mut rng = ChaCha20Rng::seed_from_u64(473);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+
+        let deep = and_chain(7);
+        let Err(GswError::IncompatibleCircuit(report)) = deep.check_compatibility(&params) else {
+            panic!("a depth-6 chain fits no depth-2 budget");
+        };
+        assert_eq!((report.deepest, report.depth_budget), (6, 2));
+        assert_eq!((report.counts.mults, report.counts.over_budget), (6, 4));
+        assert_eq!(report.peak_live_wires, 7 + 6 + 1);
+
+        let circuit = and_chain(3);
+        let report = circuit.check_compatibility(&params).unwrap();
+        assert_eq!((report.deepest, report.counts.mults), (2, 2));
+        assert_eq!(report.estimated_ops, 2 * params.cost_model().mult_ops());
+
+        // The evaluator runs the same check before doing any work.
+        let eval = Evaluator::new(&params);
+        let inputs: Vec<EncBool> = (0..3).map(|_| eval.encrypt(&mut rng, &pk, true)).collect();
+        let out = eval.evaluate_circuit(&circuit, &inputs).unwrap();
+        assert!(out[0].decrypt(&sk));
+        assert_eq!(eval.op_counts(), report.counts);
+        assert!(matches!(
+            eval.evaluate_circuit(&circuit, &inputs[1..]),
+            Err(GswError::InputCount {
+                expected: 3,
+                found: 2
+            })
+        ));
+
+        let eval = Evaluator::new(&params);
+        let inputs: Vec<EncBool> = (0..7).map(|_| eval.encrypt(&mut rng, &pk, true)).collect();
+        assert!(matches!(
+            eval.evaluate_circuit(&deep, &inputs),
+            Err(GswError::IncompatibleCircuit(_))
+        ));
+        assert_eq!(eval.op_counts(), OpCounts::default());
+        let eval = eval.allow_incompatible();
+        let inputs: Vec<EncBool> = (0..7).map(|_| eval.encrypt(&mut rng, &pk, true)).collect();
+        assert!(eval.evaluate_circuit(&deep, &inputs).is_ok());
+        assert_eq!(eval.op_counts().over_budget, 4);
+    }
+}
@@ -0,0 +1,135 @@
+//! Conversions between encodings of the same encrypted value.
+//!
+//! Comparisons and arithmetic want binary, table lookups want one-hot lines,
+//! and counters may prefer Gray code, where consecutive values differ in one
+//! bit. The Gray conversions and one-hot to binary are linear: sums of input
+//! bits, flattened once each, with no multiplicative depth. Binary to one-hot
+//! is [`homomorphic_one_hot`], with its w - 1 chained ANDs.
+
+use super::{homomorphic_one_hot, homomorphic_sum, EncryptedBits, MAX_LOOKUP_INPUT_WIDTH};
+use crate::error::GswError;
+use crate::gsw::{homomorphic_add, Ciphertext};
+use crate::params::Params;
+
+/// The Gray code of x: bit i is x_i XOR x_(i+1), and the top bit is x's.
+/// LSB first, like x.
+pub fn binary_to_gray(params: &Params, x: &[Ciphertext]) -> EncryptedBits {
+    x.iter()
+        .enumerate()
+        .map(|(i, bit)| match x.get(i + 1) {
+            Some(above) => homomorphic_add(params, bit, above),
+            None => bit.clone(),
+        })
+        .collect()
+}
+
+/// The binary value of Gray code g: bit i is the XOR of g_i and every bit
+/// above it. Each bit is one sum of fresh inputs, not a chain through the
+/// bits above, so noise grows with the width only additively.
+pub fn gray_to_binary(params: &Params, g: &[Ciphertext]) -> EncryptedBits {
+    (0..g.len())
+        .map(|i| homomorphic_sum(params, &g[i..].iter().collect::<Vec<_>>()))
+        .collect()
+}
+
+/// 2^w lines where line k encrypts [x == k], for x of width w.
+///
+/// Fails with [`GswError::OneHotTooWide`] above
+/// [`MAX_LOOKUP_INPUT_WIDTH`] bits, whose lines would not fit in memory
+/// long before the products finished.
+pub fn binary_to_onehot(params: &Params, x: &[Ciphertext]) -> Result<EncryptedBits, GswError> {
+    if x.len() > MAX_LOOKUP_INPUT_WIDTH {
+        return Err(GswError::OneHotTooWide { width: x.len() });
+    }
+    Ok(homomorphic_one_hot(params, x))
+}
+
+/// The binary value selected by one-hot `lines`: bit j is the XOR of the
+/// lines whose index has bit j set. Undoes [`binary_to_onehot`]; if more or
+/// fewer than one line encrypts 1, the result is that XOR all the same.
+///
+/// Fails with [`GswError::NotOneHotLength`] unless there are 2^w lines for
+/// some w up to [`MAX_LOOKUP_INPUT_WIDTH`].
+pub fn onehot_to_binary(params: &Params, lines: &[Ciphertext]) -> Result<EncryptedBits, GswError> {
+    if !lines.len().is_power_of_two() || lines.len() > 1 << MAX_LOOKUP_INPUT_WIDTH {
+        return Err(GswError::NotOneHotLength { lines: lines.len() });
+    }
+    let width = lines.len().trailing_zeros() as usize;
+    Ok((0..width)
+        .map(|j| {
+            let selected: Vec<&Ciphertext> = lines
+                .iter()
+                .enumerate()
+                .filter(|(k, _)| k >> j & 1 == 1)
+                .map(|(_, line)| line)
+                .collect();
+            homomorphic_sum(params, &selected)
+        })
+        .collect())
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::gsw::{decrypt, encrypt, gsw_keygen, trivial_ciphertext};
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+
+    fn decrypt_bits(sk: &crate::gsw::GswSecretKey, bits: &[Ciphertext]) -> usize {
+        bits.iter()
+            .enumerate()
+            .fold(0, |acc, (i, ct)| acc | (decrypt(sk, ct) as usize) << i)
+    }
+
+    #[test]
+    fn test_gray_round_trip() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(494);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        for x in 0..16usize {
+            let bits: Vec<Ciphertext> = (0..4)
+                .map(|i| encrypt(&mut rng, &pk, (x >> i & 1) as u8))
+                .collect();
+            let gray = binary_to_gray(&params, &bits);
+            assert_eq!(decrypt_bits(&sk, &gray), x ^ x >> 1, "gray of {}", x);
+            assert_eq!(decrypt_bits(&sk, &gray_to_binary(&params, &gray)), x);
+        }
+        assert!(binary_to_gray(&params, &[]).is_empty());
+        assert!(gray_to_binary(&params, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_onehot_indexes_the_value() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(494);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        for _ in 0..6 {
+            let x = rng.gen_range(0..16usize);
+            let bits: Vec<Ciphertext> = (0..4)
+                .map(|i| encrypt(&mut rng, &pk, (x >> i & 1) as u8))
+                .collect();
+            let lines = binary_to_onehot(&params, &bits).unwrap();
+            let hot: Vec<usize> = (0..lines.len())
+                .filter(|&k| decrypt(&sk, &lines[k]) == 1)
+                .collect();
+            assert_eq!(hot, [x]);
+            let back = onehot_to_binary(&params, &lines).unwrap();
+            assert_eq!(decrypt_bits(&sk, &back), x);
+        }
+
+        let zero = trivial_ciphertext(&params, 0);
+        assert!(matches!(
+            binary_to_onehot(&params, &vec![zero.clone(); MAX_LOOKUP_INPUT_WIDTH + 1]),
+            Err(GswError::OneHotTooWide { width: 9 })
+        ));
+        assert!(matches!(
+            onehot_to_binary(&params, &vec![zero.clone(); 6]),
+            Err(GswError::NotOneHotLength { lines: 6 })
+        ));
+        assert!(matches!(
+            onehot_to_binary(&params, &[]),
+            Err(GswError::NotOneHotLength { lines: 0 })
+        ));
+        assert!(onehot_to_binary(&params, &[zero]).unwrap().is_empty());
+    }
+}
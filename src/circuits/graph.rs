@@ -0,0 +1,670 @@
+//! Gate-level circuits over encrypted booleans, evaluated through an
+//! [`Evaluator`].
+//!
+//! A [`Circuit`] is a list of [`Gate`]s in topological order; gate i drives
+//! wire i, and the first `inputs` gates are the inputs. Building a circuit
+//! does no homomorphic work, so it can be rewritten first:
+//! [`Circuit::optimize`] inlines conditional subcircuits as per-output MUXes,
+//! merges structurally identical gates (hash-consing), and drops gates no
+//! output depends on. [`Evaluator::evaluate_circuit`] checks the circuit
+//! against the parameters first (see [`Circuit::check_compatibility`]).
+
+use std::collections::HashMap;
+
+use crate::context::{EncBool, Evaluator};
+
+/// Index of a gate's output in its circuit.
+pub type Wire = usize;
+
+/// One gate. Operands are wires of the same circuit, all earlier than the
+/// gate itself.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Gate {
+    Input(usize),
+    Const(bool),
+    Not(Wire),
+    And(Wire, Wire),
+    Or(Wire, Wire),
+    Xor(Wire, Wire),
+    Mux {
+        sel: Wire,
+        if_true: Wire,
+        if_false: Wire,
+    },
+    /// Both subcircuits evaluated on `args`; output k of the node is
+    /// `then_circuit`'s output k where `sel` is 1 and `else_circuit`'s where
+    /// it is 0. Read through [`Gate::Output`].
+    CondSubcircuit {
+        sel: Wire,
+        then_circuit: Box<Circuit>,
+        else_circuit: Box<Circuit>,
+        args: Vec<Wire>,
+    },
+    /// Output `index` of the [`Gate::CondSubcircuit`] at `node`.
+    Output {
+        node: Wire,
+        index: usize,
+    },
+}
+
+impl Gate {
+    fn operands(&self) -> Vec<Wire> {
+        match self {
+            Gate::Input(_) | Gate::Const(_) => Vec::new(),
+            Gate::Not(a) => vec![*a],
+            Gate::And(a, b) | Gate::Or(a, b) | Gate::Xor(a, b) => vec![*a, *b],
+            Gate::Mux {
+                sel,
+                if_true,
+                if_false,
+            } => vec![*sel, *if_true, *if_false],
+            Gate::CondSubcircuit { sel, args, .. } => {
+                std::iter::once(*sel).chain(args.iter().copied()).collect()
+            }
+            Gate::Output { node, .. } => vec![*node],
+        }
+    }
+}
+
+/// Hooks into [`Circuit::evaluate`], for accounting; `()` ignores everything.
+pub(crate) trait GateObserver<'e> {
+    /// The value of one gate, computed by `gate`.
+    fn gate(&mut self, gate: impl FnOnce() -> EncBool<'e>) -> EncBool<'e> {
+        gate()
+    }
+
+    /// `value` is now held by the evaluation.
+    fn hold(&mut self, _value: &EncBool<'e>) {}
+
+    /// `value`, previously held, was dropped.
+    fn release(&mut self, _value: &EncBool<'e>) {}
+}
+
+impl<'e> GateObserver<'e> for () {}
+
+/// A run of [`Circuit::run`] so far.
+#[derive(Default)]
+pub(crate) struct Progress<'e> {
+    /// One value per gate run, in order; dropped wires hold constant false.
+    pub(crate) wires: Vec<EncBool<'e>>,
+    /// The muxed outputs of each conditional still read by a later gate.
+    pub(crate) branch_outputs: HashMap<Wire, Vec<EncBool<'e>>>,
+}
+
+/// A circuit with a fixed number of inputs and a list of output wires.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Circuit {
+    inputs: usize,
+    gates: Vec<Gate>,
+    outputs: Vec<Wire>,
+}
+
+impl Circuit {
+    /// A circuit whose wires 0..inputs are its inputs.
+    pub fn new(inputs: usize) -> Self {
+        Self {
+            inputs,
+            gates: (0..inputs).map(Gate::Input).collect(),
+            outputs: Vec::new(),
+        }
+    }
+
+    pub fn input_count(&self) -> usize {
+        self.inputs
+    }
+
+    pub fn gates(&self) -> &[Gate] {
+        &self.gates
+    }
+
+    pub fn outputs(&self) -> &[Wire] {
+        &self.outputs
+    }
+
+    /// Wire of input `i`.
+    pub fn input(&self, i: usize) -> Wire {
+        assert!(i < self.inputs, "input {} out of range", i);
+        i
+    }
+
+    /// Append a gate, returning its wire. Panics if an operand is not an
+    /// earlier wire, or on a malformed conditional.
+    pub fn push(&mut self, gate: Gate) -> Wire {
+        let wire = self.gates.len();
+        for operand in gate.operands() {
+            assert!(
+                operand < wire,
+                "gate {} reads undefined wire {}",
+                wire,
+                operand
+            );
+        }
+        match &gate {
+            Gate::Input(_) => panic!("inputs are fixed by Circuit::new"),
+            Gate::CondSubcircuit {
+                then_circuit,
+                else_circuit,
+                args,
+                ..
+            } => {
+                assert!(
+                    then_circuit.inputs == args.len() && else_circuit.inputs == args.len(),
+                    "both branches must take {} inputs",
+                    args.len()
+                );
+                assert_eq!(
+                    then_circuit.outputs.len(),
+                    else_circuit.outputs.len(),
+                    "both branches must have the same number of outputs"
+                );
+            }
+            Gate::Output { node, index } => match &self.gates[*node] {
+                Gate::CondSubcircuit { then_circuit, .. } => {
+                    assert!(
+                        index < &then_circuit.outputs.len(),
+                        "output {} out of range",
+                        index
+                    )
+                }
+                _ => panic!("wire {} is not a conditional subcircuit", node),
+            },
+            _ => {}
+        }
+        self.gates.push(gate);
+        wire
+    }
+
+    pub fn constant(&mut self, value: bool) -> Wire {
+        self.push(Gate::Const(value))
+    }
+
+    pub fn not(&mut self, a: Wire) -> Wire {
+        self.push(Gate::Not(a))
+    }
+
+    pub fn and(&mut self, a: Wire, b: Wire) -> Wire {
+        self.push(Gate::And(a, b))
+    }
+
+    pub fn or(&mut self, a: Wire, b: Wire) -> Wire {
+        self.push(Gate::Or(a, b))
+    }
+
+    pub fn xor(&mut self, a: Wire, b: Wire) -> Wire {
+        self.push(Gate::Xor(a, b))
+    }
+
+    pub fn mux(&mut self, sel: Wire, if_true: Wire, if_false: Wire) -> Wire {
+        self.push(Gate::Mux {
+            sel,
+            if_true,
+            if_false,
+        })
+    }
+
+    /// `then_circuit(args)` where `sel` is 1, `else_circuit(args)` where it
+    /// is 0, one wire per output.
+    pub fn cond_subcircuit(
+        &mut self,
+        sel: Wire,
+        then_circuit: Circuit,
+        else_circuit: Circuit,
+        args: &[Wire],
+    ) -> Vec<Wire> {
+        let outputs = then_circuit.outputs.len();
+        let node = self.push(Gate::CondSubcircuit {
+            sel,
+            then_circuit: Box::new(then_circuit),
+            else_circuit: Box::new(else_circuit),
+            args: args.to_vec(),
+        });
+        (0..outputs)
+            .map(|index| self.push(Gate::Output { node, index }))
+            .collect()
+    }
+
+    pub fn set_outputs(&mut self, outputs: &[Wire]) {
+        for &wire in outputs {
+            assert!(
+                wire < self.gates.len(),
+                "output reads undefined wire {}",
+                wire
+            );
+        }
+        self.outputs = outputs.to_vec();
+    }
+
+    /// Evaluate gate by gate, dropping each wire's value after the last gate
+    /// that reads it, so that at most the live wires are held at once.
+    /// Conditional subcircuits are evaluated as written, both branches in
+    /// full; [`optimize`](Self::optimize) first to share work between them.
+    pub fn evaluate<'e>(&self, eval: &'e Evaluator, inputs: &[EncBool<'e>]) -> Vec<EncBool<'e>> {
+        self.evaluate_observed(eval, inputs, &mut ())
+    }
+
+    /// [`evaluate`](Self::evaluate), reporting to `observer` as gates run and
+    /// wires are filled and dropped.
+    pub(crate) fn evaluate_observed<'e>(
+        &self,
+        eval: &'e Evaluator,
+        inputs: &[EncBool<'e>],
+        observer: &mut impl GateObserver<'e>,
+    ) -> Vec<EncBool<'e>> {
+        assert_eq!(inputs.len(), self.inputs, "wrong number of inputs");
+        let mut progress = Progress::default();
+        self.run(eval, inputs, &mut progress, self.gates.len(), observer);
+        self.outputs_of(&progress)
+    }
+
+    /// The last gate reading each wire; outputs are read at the end, by
+    /// [`Wire::MAX`].
+    pub(crate) fn last_reads(&self) -> Vec<Option<Wire>> {
+        let mut last_read: Vec<Option<Wire>> = vec![None; self.gates.len()];
+        for (wire, gate) in self.gates.iter().enumerate() {
+            for operand in gate.operands() {
+                last_read[operand] = Some(wire);
+            }
+        }
+        for &wire in &self.outputs {
+            last_read[wire] = Some(Wire::MAX);
+        }
+        last_read
+    }
+
+    /// Run the gates from `progress.next` up to, not including, `stop`.
+    /// `inputs` is read only by input gates in that range.
+    pub(crate) fn run<'e>(
+        &self,
+        eval: &'e Evaluator,
+        inputs: &[EncBool<'e>],
+        progress: &mut Progress<'e>,
+        stop: Wire,
+        observer: &mut impl GateObserver<'e>,
+    ) {
+        let last_read = self.last_reads();
+        let Progress {
+            wires,
+            branch_outputs,
+        } = progress;
+        for (wire, gate) in self.gates.iter().enumerate().take(stop).skip(wires.len()) {
+            let value = observer.gate(|| match gate {
+                Gate::Input(i) => inputs[*i].clone(),
+                Gate::Const(value) => EncBool::constant(eval, *value),
+                Gate::Not(a) => wires[*a].not(),
+                Gate::And(a, b) => wires[*a].and(&wires[*b]),
+                Gate::Or(a, b) => wires[*a].or(&wires[*b]),
+                Gate::Xor(a, b) => wires[*a].xor(&wires[*b]),
+                Gate::Mux {
+                    sel,
+                    if_true,
+                    if_false,
+                } => wires[*sel].mux(&wires[*if_true], &wires[*if_false]),
+                Gate::CondSubcircuit {
+                    sel,
+                    then_circuit,
+                    else_circuit,
+                    args,
+                } => {
+                    let args: Vec<EncBool<'e>> = args.iter().map(|&a| wires[a].clone()).collect();
+                    let then_values = then_circuit.evaluate(eval, &args);
+                    let else_values = else_circuit.evaluate(eval, &args);
+                    let muxed: Vec<EncBool<'e>> = then_values
+                        .iter()
+                        .zip(&else_values)
+                        .map(|(t, e)| wires[*sel].mux(t, e))
+                        .collect();
+                    branch_outputs.insert(wire, muxed);
+                    // The node itself carries no value; its outputs do.
+                    EncBool::constant(eval, false)
+                }
+                Gate::Output { node, index } => branch_outputs[node][*index].clone(),
+            });
+            if let Some(muxed) = branch_outputs.get(&wire) {
+                muxed.iter().for_each(|value| observer.hold(value));
+            }
+            observer.hold(&value);
+            wires.push(value);
+
+            let mut operands = gate.operands();
+            operands.push(wire);
+            for operand in operands {
+                if last_read[operand].is_none_or(|last| last <= wire) {
+                    let value =
+                        std::mem::replace(&mut wires[operand], EncBool::constant(eval, false));
+                    observer.release(&value);
+                    for value in branch_outputs.remove(&operand).into_iter().flatten() {
+                        observer.release(&value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The canonical bytes [`digest`](crate::digest::digest) hashes.
+    pub(crate) fn write_canonical(&self, out: &mut Vec<u8>) {
+        fn put(out: &mut Vec<u8>, v: usize) {
+            out.extend_from_slice(&(v as u64).to_le_bytes());
+        }
+        put(out, self.inputs);
+        put(out, self.gates.len());
+        for gate in &self.gates {
+            let tag = match gate {
+                Gate::Input(_) => 0,
+                Gate::Const(_) => 1,
+                Gate::Not(_) => 2,
+                Gate::And(..) => 3,
+                Gate::Or(..) => 4,
+                Gate::Xor(..) => 5,
+                Gate::Mux { .. } => 6,
+                Gate::CondSubcircuit { .. } => 7,
+                Gate::Output { .. } => 8,
+            };
+            out.push(tag);
+            match gate {
+                Gate::Input(i) => put(out, *i),
+                Gate::Const(value) => out.push(*value as u8),
+                Gate::CondSubcircuit {
+                    sel,
+                    then_circuit,
+                    else_circuit,
+                    args,
+                } => {
+                    put(out, *sel);
+                    then_circuit.write_canonical(out);
+                    else_circuit.write_canonical(out);
+                    put(out, args.len());
+                    args.iter().for_each(|&a| put(out, a));
+                }
+                Gate::Output { node, index } => {
+                    put(out, *node);
+                    put(out, *index);
+                }
+                _ => gate.operands().into_iter().for_each(|a| put(out, a)),
+            }
+        }
+        put(out, self.outputs.len());
+        self.outputs.iter().for_each(|&w| put(out, w));
+    }
+
+    /// The output values of a finished run.
+    pub(crate) fn outputs_of<'e>(&self, progress: &Progress<'e>) -> Vec<EncBool<'e>> {
+        self.outputs
+            .iter()
+            .map(|&w| progress.wires[w].clone())
+            .collect()
+    }
+
+    /// An equivalent circuit with conditional subcircuits inlined and
+    /// lowered to one MUX per output, structurally identical gates merged
+    /// (operands of the symmetric gates are ordered first, and a MUX whose
+    /// branches merge becomes its branch), and gates no output reads
+    /// removed. Inputs keep their wires.
+    pub fn optimize(&self) -> Circuit {
+        let mut builder = HashConsing::new(self.inputs);
+        let inputs: Vec<Wire> = (0..self.inputs).collect();
+        let outputs = builder.inline(self, &inputs);
+        builder.circuit.outputs = outputs;
+        builder.circuit.without_dead_gates()
+    }
+
+    /// Keep the inputs and every gate some output depends on.
+    fn without_dead_gates(&self) -> Circuit {
+        let mut live = vec![false; self.gates.len()];
+        live[..self.inputs].fill(true);
+        let mut stack = self.outputs.clone();
+        while let Some(wire) = stack.pop() {
+            if !std::mem::replace(&mut live[wire], true) {
+                stack.extend(self.gates[wire].operands());
+            }
+        }
+        // Inputs keep their wires; every other gate moves down past the
+        // dead ones.
+        let mut renumbered: Vec<Wire> = (0..self.gates.len()).collect();
+        let mut circuit = Circuit::new(self.inputs);
+        for (wire, gate) in self.gates.iter().enumerate().skip(self.inputs) {
+            if live[wire] {
+                renumbered[wire] = circuit.gates.len();
+                circuit.gates.push(remap(gate, |w| renumbered[w]));
+            }
+        }
+        circuit.outputs = self.outputs.iter().map(|&w| renumbered[w]).collect();
+        circuit
+    }
+}
+
+/// `gate` with every operand wire passed through `f`.
+fn remap(gate: &Gate, f: impl Fn(Wire) -> Wire) -> Gate {
+    match gate {
+        Gate::Input(i) => Gate::Input(*i),
+        Gate::Const(value) => Gate::Const(*value),
+        Gate::Not(a) => Gate::Not(f(*a)),
+        Gate::And(a, b) => Gate::And(f(*a), f(*b)),
+        Gate::Or(a, b) => Gate::Or(f(*a), f(*b)),
+        Gate::Xor(a, b) => Gate::Xor(f(*a), f(*b)),
+        Gate::Mux {
+            sel,
+            if_true,
+            if_false,
+        } => Gate::Mux {
+            sel: f(*sel),
+            if_true: f(*if_true),
+            if_false: f(*if_false),
+        },
+        Gate::CondSubcircuit {
+            sel,
+            then_circuit,
+            else_circuit,
+            args,
+        } => Gate::CondSubcircuit {
+            sel: f(*sel),
+            then_circuit: then_circuit.clone(),
+            else_circuit: else_circuit.clone(),
+            args: args.iter().map(|&a| f(a)).collect(),
+        },
+        Gate::Output { node, index } => Gate::Output {
+            node: f(*node),
+            index: *index,
+        },
+    }
+}
+
+/// Builds a flat circuit in which no two gates are identical.
+struct HashConsing {
+    circuit: Circuit,
+    existing: HashMap<Gate, Wire>,
+}
+
+impl HashConsing {
+    fn new(inputs: usize) -> Self {
+        Self {
+            circuit: Circuit::new(inputs),
+            existing: HashMap::new(),
+        }
+    }
+
+    /// The wire of `gate`, reusing an identical gate if there is one.
+    fn intern(&mut self, gate: Gate) -> Wire {
+        let gate = match gate {
+            Gate::And(a, b) => Gate::And(a.min(b), a.max(b)),
+            Gate::Or(a, b) => Gate::Or(a.min(b), a.max(b)),
+            Gate::Xor(a, b) => Gate::Xor(a.min(b), a.max(b)),
+            Gate::Mux {
+                if_true, if_false, ..
+            } if if_true == if_false => return if_true,
+            gate => gate,
+        };
+        if let Some(&wire) = self.existing.get(&gate) {
+            return wire;
+        }
+        let wire = self.circuit.push(gate.clone());
+        self.existing.insert(gate, wire);
+        wire
+    }
+
+    /// Copy `circuit` in with its inputs bound to `args`, returning the
+    /// wires of its outputs.
+    fn inline(&mut self, circuit: &Circuit, args: &[Wire]) -> Vec<Wire> {
+        let mut wires: Vec<Wire> = Vec::with_capacity(circuit.gates.len());
+        let mut branch_outputs: HashMap<Wire, Vec<Wire>> = HashMap::new();
+        for (wire, gate) in circuit.gates.iter().enumerate() {
+            let mapped = match gate {
+                Gate::Input(i) => args[*i],
+                Gate::CondSubcircuit {
+                    sel,
+                    then_circuit,
+                    else_circuit,
+                    args: branch_args,
+                } => {
+                    let branch_args: Vec<Wire> = branch_args.iter().map(|&a| wires[a]).collect();
+                    let then_outputs = self.inline(then_circuit, &branch_args);
+                    let else_outputs = self.inline(else_circuit, &branch_args);
+                    let muxes = then_outputs
+                        .iter()
+                        .zip(&else_outputs)
+                        .map(|(&if_true, &if_false)| {
+                            self.intern(Gate::Mux {
+                                sel: wires[*sel],
+                                if_true,
+                                if_false,
+                            })
+                        })
+                        .collect();
+                    branch_outputs.insert(wire, muxes);
+                    // Never read: outputs resolve through `branch_outputs`.
+                    usize::MAX
+                }
+                Gate::Output { node, index } => branch_outputs[node][*index],
+                gate => self.intern(remap(gate, |w| wires[w])),
+            };
+            wires.push(mapped);
+        }
+        circuit.outputs.iter().map(|&w| wires[w]).collect()
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::gsw::gsw_keygen;
+    use crate::params::Params;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    /// A 10-gate function of four inputs with three ANDs.
+    fn shared_block() -> Circuit {
+        let mut c = Circuit::new(4);
+        let (a, b, x, y) = (c.input(0), c.input(1), c.input(2), c.input(3));
+        let ab = c.and(a, b);
+        let xy = c.xor(x, y);
+        let t = c.xor(ab, xy);
+        let ax = c.or(a, x);
+        let u = c.xor(ax, b);
+        let v = c.and(t, y);
+        let w = c.xor(u, v);
+        let nw = c.not(w);
+        let z = c.xor(nw, a);
+        let out = c.and(z, x);
+        c.set_outputs(&[out, w]);
+        c
+    }
+
+    fn shared_block_clear(a: bool, b: bool, x: bool, y: bool) -> (bool, bool) {
+        let t = (a & b) ^ (x ^ y);
+        let w = ((a | x) ^ b) ^ (t & y);
+        ((!w ^ a) & x, w)
+    }
+
+    /// Both branches run `shared_block` on the same arguments, then differ
+    /// in one gate each.
+    fn conditional() -> Circuit {
+        let branch = |last: fn(&mut Circuit, Wire, Wire) -> Wire| {
+            let mut c = Circuit::new(4);
+            let args: Vec<Wire> = (0..4).collect();
+            let block = c.cond_subcircuit(0, shared_block(), shared_block(), &args);
+            let out = last(&mut c, block[0], block[1]);
+            c.set_outputs(&[out, block[1]]);
+            c
+        };
+        let mut c = Circuit::new(5);
+        let sel = c.input(4);
+        let args: Vec<Wire> = (0..4).collect();
+        let outputs = c.cond_subcircuit(
+            sel,
+            branch(|c, p, q| c.and(p, q)),
+            branch(|c, p, q| c.xor(p, q)),
+            &args,
+        );
+        c.set_outputs(&outputs);
+        c
+    }
+
+    #[test]
+    fn test_optimize_shares_identical_branches() {
+        // Unoptimized, the shared block's products nest under MUXes deep
+        // enough to need a wide modulus.
+        let params = Params::from_raw(1 << 40, 8, 256, 1);
+        let mut rng = ChaCha20Rng::seed_from_u64(14);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let circuit = conditional();
+        let optimized = circuit.optimize();
+        assert!(optimized
+            .gates()
+            .iter()
+            .all(|gate| !matches!(gate, Gate::CondSubcircuit { .. } | Gate::Output { .. })));
+
+        for x in [0b00000usize, 0b10110, 0b01101, 0b11011, 0b01111, 0b10000] {
+            let bits: [bool; 5] = std::array::from_fn(|i| (x >> i) & 1 == 1);
+            let (block, w) = shared_block_clear(bits[0], bits[1], bits[2], bits[3]);
+            let expected = [if bits[4] { block & w } else { block ^ w }, w];
+
+            let mut mults = Vec::new();
+            for c in [&circuit, &optimized] {
+                let eval = Evaluator::new(&params);
+                let inputs: Vec<EncBool> = bits
+                    .iter()
+                    .map(|&bit| eval.encrypt(&mut rng, &pk, bit))
+                    .collect();
+                let outputs = c.evaluate(&eval, &inputs);
+                let got: Vec<bool> = outputs.iter().map(|out| out.decrypt(&sk)).collect();
+                assert_eq!(got, expected, "inputs {:05b}", x);
+                mults.push(eval.op_counts().mults);
+            }
+            // One multiplication per AND, OR and MUX. Unoptimized: the block
+            // (3 ANDs, 1 OR) four times, 2 inner MUXes in each branch, the
+            // then-branch's last AND and 2 outer MUXes. Optimized: the block
+            // once, that AND, and one MUX for the only output the branches
+            // disagree on.
+            assert_eq!(mults, [4 * 4 + 2 * 2 + 1 + 2, 4 + 1 + 1]);
+        }
+    }
+
+    #[test]
+    fn test_optimize_merges_and_prunes() {
+        let mut c = Circuit::new(2);
+        let ab = c.and(0, 1);
+        let ba = c.and(1, 0);
+        let dead = c.or(0, 1);
+        let same = c.mux(dead, ab, ba);
+        let out = c.xor(same, 0);
+        c.set_outputs(&[out]);
+
+        let optimized = c.optimize();
+        assert_eq!(
+            optimized.gates(),
+            [
+                Gate::Input(0),
+                Gate::Input(1),
+                Gate::And(0, 1),
+                Gate::Xor(0, 2)
+            ]
+        );
+        assert_eq!(optimized.outputs(), [3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "undefined wire")]
+    fn test_rejects_forward_references() {
+        let mut c = Circuit::new(1);
+        c.and(0, 1);
+    }
+}
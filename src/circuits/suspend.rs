@@ -0,0 +1,480 @@
+//! Suspending a circuit evaluation and resuming it elsewhere.
+//!
+//! [`Evaluator::suspend_after`] runs a circuit up to a gate and captures an
+//! [`EvaluationState`]: the next gate to run, every value a later gate or an
+//! output still reads, and a transcript digest chaining the gates run so
+//! far. Its bytes travel to another evaluation service, which continues with
+//! [`Evaluator::resume`] (or suspends again with
+//! [`Evaluator::resume_and_suspend_after`]) given the same circuit. Ciphertexts
+//! are deterministic functions of their operands, so a split run produces
+//! exactly the ciphertexts, and the transcript, of an unsplit one.
+//!
+//! Serialized layout, after the object header: params, the circuit's
+//! [`digest`], the next gate (u64), the transcript digest, the live wires
+//! (count, then per wire its index and value), the pending conditional
+//! outputs (count, then per node its wire, a count, and the values), and a
+//! [`DefaultHash`] of everything before it. A value is a tag byte, 0 or 1
+//! for a constant and 2 for a ciphertext, which follows with its depth
+//! (u32). The hash catches corruption in transit, not a forger, who could
+//! recompute it; the state holds nothing a server could not compute itself.
+
+use crate::context::{EncBool, Evaluator, Value};
+use crate::digest::{digest, DefaultHash, Digest, GswHash};
+use crate::error::GswError;
+use crate::params::Params;
+use crate::wire::{self, ObjectKind, Reader};
+
+use super::graph::{GateObserver, Progress};
+use super::{Circuit, Gate, Wire};
+
+/// A circuit evaluation stopped between two gates, from
+/// [`Evaluator::suspend_after`]: the next gate, the values later gates and
+/// the outputs still read, and a digest chaining the gates run so far.
+/// [`Evaluator::resume`] finishes it given the same circuit, on this or
+/// another evaluation service, with exactly the ciphertexts and transcript
+/// of an unsplit run.
+#[derive(Clone, Debug)]
+pub struct EvaluationState {
+    params: Params,
+    circuit_fingerprint: Digest,
+    next_gate: Wire,
+    transcript: Digest,
+    wires: Vec<(Wire, Value)>,
+    branch_outputs: Vec<(Wire, Vec<Value>)>,
+}
+
+/// Chains a digest of each gate's value onto the transcript.
+struct TranscriptChain {
+    digest: Digest,
+}
+
+impl<'e> GateObserver<'e> for TranscriptChain {
+    fn gate(&mut self, gate: impl FnOnce() -> EncBool<'e>) -> EncBool<'e> {
+        let value = gate();
+        let mut bytes = self.digest.to_vec();
+        match value.value() {
+            Value::Constant(bit) => bytes.push(*bit as u8),
+            Value::Encrypted { ct, depth } => {
+                bytes.push(2);
+                bytes.extend_from_slice(&depth.to_le_bytes());
+                bytes.extend_from_slice(&digest(ct));
+            }
+            Value::Cancelled => bytes.push(3),
+        }
+        self.digest = DefaultHash::hash(&bytes);
+        value
+    }
+}
+
+impl EvaluationState {
+    pub fn params(&self) -> &Params {
+        &self.params
+    }
+
+    /// [`digest`] of the circuit being evaluated.
+    pub fn circuit_fingerprint(&self) -> &Digest {
+        &self.circuit_fingerprint
+    }
+
+    /// The first gate not yet run.
+    pub fn next_gate(&self) -> Wire {
+        self.next_gate
+    }
+
+    /// Digest chaining the value of every gate run so far, in order. Equal
+    /// for two runs of one circuit on the same inputs up to the same gate,
+    /// however each was split.
+    pub fn transcript(&self) -> &Digest {
+        &self.transcript
+    }
+
+    /// Number of ciphertexts held, which dominates the state's size.
+    pub fn ciphertext_count(&self) -> usize {
+        self.wires
+            .iter()
+            .map(|(_, value)| value)
+            .chain(self.branch_outputs.iter().flat_map(|(_, values)| values))
+            .filter(|value| matches!(value, Value::Encrypted { .. }))
+            .count()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        wire::write_header(&mut out, ObjectKind::EvaluationState);
+        wire::write_params(&mut out, &self.params);
+        out.extend_from_slice(&self.circuit_fingerprint);
+        put_index(&mut out, self.next_gate);
+        out.extend_from_slice(&self.transcript);
+        put_index(&mut out, self.wires.len());
+        for (wire, value) in &self.wires {
+            put_index(&mut out, *wire);
+            write_value(&mut out, value);
+        }
+        put_index(&mut out, self.branch_outputs.len());
+        for (node, values) in &self.branch_outputs {
+            put_index(&mut out, *node);
+            put_index(&mut out, values.len());
+            values.iter().for_each(|value| write_value(&mut out, value));
+        }
+        let integrity = DefaultHash::hash(&out);
+        out.extend_from_slice(&integrity);
+        out
+    }
+
+    /// Deserialize, rejecting data whose integrity hash does not match.
+    /// Whether the state fits a circuit is checked on resuming.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GswError> {
+        let body_len = bytes
+            .len()
+            .checked_sub(32)
+            .ok_or_else(|| GswError::MalformedData("unexpected end of data".into()))?;
+        let (body, integrity) = bytes.split_at(body_len);
+        let mut reader = Reader::new(body);
+        reader.header(ObjectKind::EvaluationState)?;
+        if DefaultHash::hash(body) != integrity {
+            return Err(GswError::MalformedData(
+                "evaluation state integrity hash mismatch".into(),
+            ));
+        }
+        let params = reader.params()?;
+        let circuit_fingerprint = reader.hash()?;
+        let next_gate = reader.index()?;
+        let transcript = reader.hash()?;
+        // Smallest record: an index and a constant's tag.
+        let count = reader.count(8 + 1)?;
+        let wires = (0..count)
+            .map(|_| Ok((reader.index()?, read_value(&mut reader, &params)?)))
+            .collect::<Result<_, GswError>>()?;
+        let count = reader.count(8 + 8)?;
+        let mut branch_outputs = Vec::with_capacity(count);
+        for _ in 0..count {
+            let node = reader.index()?;
+            let outputs = reader.count(1)?;
+            let values = (0..outputs)
+                .map(|_| read_value(&mut reader, &params))
+                .collect::<Result<_, _>>()?;
+            branch_outputs.push((node, values));
+        }
+        reader.finish()?;
+        Ok(Self {
+            params,
+            circuit_fingerprint,
+            next_gate,
+            transcript,
+            wires,
+            branch_outputs,
+        })
+    }
+}
+
+fn put_index(out: &mut Vec<u8>, index: usize) {
+    out.extend_from_slice(&(index as u64).to_le_bytes());
+}
+
+fn write_value(out: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Constant(bit) => out.push(*bit as u8),
+        Value::Encrypted { ct, depth } => {
+            out.push(2);
+            out.extend_from_slice(&depth.to_le_bytes());
+            wire::write_ciphertext(out, ct);
+        }
+        Value::Cancelled => unreachable!("cancelled values are never captured"),
+    }
+}
+
+fn read_value(reader: &mut Reader, params: &Params) -> Result<Value, GswError> {
+    match reader.u8()? {
+        tag @ (0 | 1) => Ok(Value::Constant(tag == 1)),
+        2 => {
+            let depth = reader.u32()?;
+            let ct = reader.ciphertext(params)?;
+            Ok(Value::Encrypted { ct, depth })
+        }
+        tag => Err(GswError::MalformedData(format!(
+            "unknown value tag {}",
+            tag
+        ))),
+    }
+}
+
+/// Run `circuit` from `start`, or from its inputs, up to gate `stop`, and
+/// return where it stands with the transcript so far.
+pub(crate) fn advance<'e>(
+    eval: &'e Evaluator,
+    circuit: &Circuit,
+    inputs: &[EncBool<'e>],
+    start: Option<&EvaluationState>,
+    stop: Wire,
+) -> Result<(Progress<'e>, Digest), GswError> {
+    let (mut progress, mut chain) = match start {
+        Some(state) => (
+            restore(eval, circuit, state)?,
+            TranscriptChain {
+                digest: state.transcript,
+            },
+        ),
+        None => (Progress::default(), TranscriptChain { digest: [0; 32] }),
+    };
+    circuit.run(eval, inputs, &mut progress, stop, &mut chain);
+    Ok((progress, chain.digest))
+}
+
+/// The wires still read after gate `next` - 1, and the conditionals whose
+/// outputs are.
+fn live_after(circuit: &Circuit, next: Wire) -> (Vec<Wire>, Vec<Wire>) {
+    let live: Vec<Wire> = circuit
+        .last_reads()
+        .iter()
+        .take(next)
+        .enumerate()
+        .filter(|(_, last)| last.is_some_and(|last| last >= next))
+        .map(|(wire, _)| wire)
+        .collect();
+    let nodes = live
+        .iter()
+        .copied()
+        .filter(|&wire| matches!(circuit.gates()[wire], Gate::CondSubcircuit { .. }))
+        .collect();
+    (live, nodes)
+}
+
+/// Capture `progress` on `circuit` as a state.
+pub(crate) fn capture(
+    eval: &Evaluator,
+    circuit: &Circuit,
+    progress: &Progress,
+    transcript: Digest,
+) -> Result<EvaluationState, GswError> {
+    let next_gate = progress.wires.len();
+    let (live, nodes) = live_after(circuit, next_gate);
+    let stored = |value: &EncBool| match value.value() {
+        Value::Cancelled => Err(GswError::Cancelled),
+        value => Ok(value.clone()),
+    };
+    Ok(EvaluationState {
+        params: eval.params().clone(),
+        circuit_fingerprint: digest(circuit),
+        next_gate,
+        transcript,
+        wires: live
+            .into_iter()
+            .map(|wire| Ok((wire, stored(&progress.wires[wire])?)))
+            .collect::<Result<_, GswError>>()?,
+        branch_outputs: nodes
+            .into_iter()
+            .map(|node| {
+                let values = progress.branch_outputs[&node].iter().map(stored);
+                Ok((node, values.collect::<Result<_, _>>()?))
+            })
+            .collect::<Result<_, GswError>>()?,
+    })
+}
+
+/// Check `state` was captured on `circuit` under the evaluator's
+/// parameters.
+pub(crate) fn check_origin(
+    eval: &Evaluator,
+    circuit: &Circuit,
+    state: &EvaluationState,
+) -> Result<(), GswError> {
+    if digest(&state.params) != digest(eval.params()) {
+        return Err(GswError::ParamsMismatch);
+    }
+    if state.circuit_fingerprint != digest(circuit) {
+        return Err(GswError::CircuitMismatch);
+    }
+    Ok(())
+}
+
+/// The progress `state` records, after checking it belongs to `circuit`
+/// under the evaluator's parameters.
+fn restore<'e>(
+    eval: &'e Evaluator,
+    circuit: &Circuit,
+    state: &EvaluationState,
+) -> Result<Progress<'e>, GswError> {
+    check_origin(eval, circuit, state)?;
+    let next = state.next_gate;
+    let (live, nodes) = live_after(circuit, next);
+    let outputs = |node: Wire| match &circuit.gates()[node] {
+        Gate::CondSubcircuit { then_circuit, .. } => then_circuit.outputs().len(),
+        _ => unreachable!("live_after returns conditionals"),
+    };
+    let consistent = next <= circuit.gates().len()
+        && next >= circuit.input_count()
+        && state.wires.iter().map(|(wire, _)| *wire).eq(live)
+        && state
+            .branch_outputs
+            .iter()
+            .map(|(node, values)| (*node, values.len()))
+            .eq(nodes.into_iter().map(|node| (node, outputs(node))));
+    if !consistent {
+        return Err(GswError::MalformedData(format!(
+            "state does not hold the live wires of the circuit before gate {}",
+            next
+        )));
+    }
+
+    let mut progress = Progress {
+        wires: vec![EncBool::constant(eval, false); next],
+        ..Progress::default()
+    };
+    for (wire, value) in &state.wires {
+        progress.wires[*wire] = EncBool::from_value(eval, value.clone());
+    }
+    for (node, values) in &state.branch_outputs {
+        let values = values
+            .iter()
+            .map(|value| EncBool::from_value(eval, value.clone()))
+            .collect();
+        progress.branch_outputs.insert(*node, values);
+    }
+    Ok(progress)
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::gsw::gsw_keygen;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    /// A 4-bit adder with carry in: inputs a (0..4), b (4..8), and the
+    /// carry (8); 5 gates per bit, 20 in all. Generate and propagate never
+    /// both hold, so XOR combines them without the depth of an OR.
+    fn adder() -> Circuit {
+        let mut circuit = Circuit::new(9);
+        let mut carry = circuit.input(8);
+        let mut outputs = Vec::new();
+        for i in 0..4 {
+            let (a, b) = (circuit.input(i), circuit.input(4 + i));
+            let half = circuit.xor(a, b);
+            outputs.push(circuit.xor(half, carry));
+            let generate = circuit.and(a, b);
+            let propagate = circuit.and(half, carry);
+            carry = circuit.xor(generate, propagate);
+        }
+        outputs.push(carry);
+        circuit.set_outputs(&outputs);
+        circuit
+    }
+
+    #[test]
+    fn test_split_runs_match_the_unsplit_run() {
+        // The carry chain is four products deep.
+        let params = Params::for_depth(4, -40).unwrap();
+        let mut rng = ChaCha20Rng::seed_from_u64(497);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let circuit = adder();
+        assert_eq!(circuit.gates().len() - circuit.input_count(), 20);
+        let (a, b, carry) = (11u32, 6u32, 1u32);
+        let bits: Vec<bool> = (0..4)
+            .map(|i| a >> i & 1 == 1)
+            .chain((0..4).map(|i| b >> i & 1 == 1))
+            .chain([carry == 1])
+            .collect();
+
+        let server_a = Evaluator::new(&params);
+        let server_b = Evaluator::new(&params);
+        let inputs: Vec<EncBool> = bits
+            .iter()
+            .map(|&bit| server_a.encrypt(&mut rng, &pk, bit))
+            .collect();
+        let unsplit: Vec<_> = server_a
+            .evaluate_circuit(&circuit, &inputs)
+            .unwrap()
+            .into_iter()
+            .map(EncBool::into_ciphertext)
+            .collect();
+        let last = circuit.gates().len() - 1;
+        let whole = server_a.suspend_after(&circuit, &inputs, last).unwrap();
+        let sum: u32 = unsplit
+            .iter()
+            .enumerate()
+            .map(|(i, ct)| (crate::gsw::decrypt(&sk, ct) as u32) << i)
+            .sum();
+        assert_eq!(sum, a + b + carry);
+
+        for cut in [0, 8, 9, 15, 21, last - 1] {
+            let state = server_a.suspend_after(&circuit, &inputs, cut).unwrap();
+            assert_eq!(state.next_gate(), (cut + 1).max(circuit.input_count()));
+            let handed_over = EvaluationState::from_bytes(&state.to_bytes()).unwrap();
+            let outputs: Vec<_> = server_b
+                .resume(&circuit, &handed_over)
+                .unwrap()
+                .into_iter()
+                .map(EncBool::into_ciphertext)
+                .collect();
+            assert_eq!(outputs, unsplit, "cut after gate {}", cut);
+
+            // A third hop ends with the same transcript as one run.
+            let finished = server_b
+                .resume_and_suspend_after(&circuit, &handed_over, last)
+                .unwrap();
+            assert_eq!(finished.transcript(), whole.transcript());
+        }
+    }
+
+    #[test]
+    fn test_resume_rejects_foreign_or_tampered_states() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(497);
+        let (_, pk) = gsw_keygen(&mut rng, &params);
+        let eval = Evaluator::new(&params);
+        let mut circuit = Circuit::new(2);
+        let x = circuit.xor(0, 1);
+        let then_branch = {
+            let mut c = Circuit::new(1);
+            let n = c.not(0);
+            c.set_outputs(&[n, 0]);
+            c
+        };
+        let else_branch = {
+            let mut c = Circuit::new(1);
+            c.set_outputs(&[0, 0]);
+            c
+        };
+        let outs = circuit.cond_subcircuit(0, then_branch, else_branch, &[x]);
+        let y = circuit.xor(outs[0], outs[1]);
+        circuit.set_outputs(&[y]);
+        let inputs = [
+            eval.encrypt(&mut rng, &pk, true),
+            eval.encrypt(&mut rng, &pk, false),
+        ];
+
+        // Cut between the conditional and its outputs.
+        let state = eval.suspend_after(&circuit, &inputs, x + 1).unwrap();
+        assert_eq!(state.ciphertext_count(), 2);
+        let bytes = state.to_bytes();
+        let expected = eval.evaluate_circuit(&circuit, &inputs).unwrap();
+        let resumed = eval.resume(&circuit, &state).unwrap();
+        assert_eq!(
+            resumed[0].clone().into_ciphertext(),
+            expected[0].clone().into_ciphertext()
+        );
+
+        let other = Evaluator::new(&Params::pedagogical());
+        assert!(matches!(
+            other.resume(&circuit, &state),
+            Err(GswError::ParamsMismatch)
+        ));
+        let mut changed = circuit.clone();
+        changed.set_outputs(&[x]);
+        assert!(matches!(
+            eval.resume(&changed, &state),
+            Err(GswError::CircuitMismatch)
+        ));
+        let mut tampered = bytes.clone();
+        let mid = bytes.len() / 2;
+        tampered[mid] ^= 1;
+        assert!(matches!(
+            EvaluationState::from_bytes(&tampered),
+            Err(GswError::MalformedData(_))
+        ));
+        assert!(matches!(
+            EvaluationState::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(GswError::MalformedData(_))
+        ));
+    }
+}
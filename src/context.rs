@@ -0,0 +1,1037 @@
+//! Typed encrypted booleans evaluated through an [`Evaluator`].
+//!
+//! An [`EncBool`] is either a public constant or a ciphertext tagged with the
+//! multiplicative depth that produced it. Gates fold constants away without
+//! homomorphic work: AND with false is false, XOR with true is a NOT, and so
+//! on. A constant that leaves the evaluator as a ciphertext is a clone of one
+//! of two cached trivial encryptions. Multiplications go through the
+//! evaluator, which puts the deeper operand on the left (GSW product noise is
+//! C_1·e_2 + μ_2·e_1, so the left operand's noise is not amplified) and
+//! tracks each product's depth against the budget the parameters' failure
+//! target allows ([`Params::max_mult_depth`]).
+//!
+//! The evaluator never bootstraps: [`bootstrap`](crate::bootstrap::bootstrap)
+//! outputs the parity of the phase rather than the bit, with more noise than
+//! it removes (see its documentation). A circuit therefore decrypts
+//! correctly only while its deepest product stays within the budget; deeper
+//! circuits need wider parameters, which [`Params::for_depth`] picks.
+//! Products past the budget are still computed, and counted in
+//! [`OpCounts::over_budget`].
+//!
+//! A [`GateStrategy`] chooses how gates are built: directly (one product per
+//! AND, OR, or MUX; NOT and XOR linear) or lowered to NANDs. The NANDs are
+//! plain products, never bootstrapped, so a lowered circuit is deeper than
+//! the direct one (a NOT is one NAND deep, an AND two, an XOR three) and
+//! decrypts correctly only while that depth stays within
+//! [`Params::max_mult_depth`].
+//!
+//! An [`EncryptedVec`] applies gates elementwise ([`map_gate`], [`zip_with`])
+//! and folds with a balanced tree ([`reduce`]), so a reduction over n
+//! elements is log₂ n gates deep rather than n − 1. Each element keeps its
+//! own depth, and every gate goes through the evaluator, so depths are
+//! tracked exactly as for individual [`EncBool`]s. Combinators consume their
+//! inputs and drop each element once it has been used.
+//!
+//! An evaluator given a cancellation token ([`Evaluator::with_cancellation`])
+//! runs products through their cancellable forms. Once the
+//! token is set, the operation in progress stops within a few milliseconds
+//! and it and every gate after it yield a cancelled value, which propagates
+//! through later gates without homomorphic work (constant folding aside) and
+//! surfaces as [`GswError::Cancelled`] from
+//! [`EncBool::try_into_ciphertext`].
+//!
+//! [`map_gate`]: EncryptedVec::map_gate
+//! [`zip_with`]: EncryptedVec::zip_with
+//! [`reduce`]: EncryptedVec::reduce
+//!
+//! ```ignore
+//! let eval = Evaluator::new(&params);
+//! let (a, b, c) = (eval.input(ct_a), eval.input(ct_b), eval.input(ct_c));
+//! let out = a.and(&b).or(&c.not()).xor(&EncBool::constant(&eval, true));
+//! ```
+
+use std::cell::{Cell, OnceCell};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[cfg(feature = "client")]
+use rand::Rng;
+
+use crate::circuits::{self, Circuit, EvaluationState, Wire};
+use crate::error::GswError;
+#[cfg(feature = "client")]
+use crate::gsw::{decrypt, encrypt, DecryptionContext, GswPublicKey, GswSecretKey};
+use crate::gsw::{
+    homomorphic_add, homomorphic_mult, homomorphic_mult_cancellable, homomorphic_nand,
+    homomorphic_nand_cancellable, homomorphic_not, trivial_ciphertext, Ciphertext,
+};
+#[cfg(feature = "metrics")]
+use crate::metrics::{with_memory_tracking, CircuitLedger, CircuitMemoryStats};
+use crate::params::Params;
+use crate::testing::FaultHook;
+#[cfg(feature = "test-utils")]
+use crate::testing::FaultInjector;
+
+/// How [`EncBool`] gates are built.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GateStrategy {
+    /// Each gate in its cheapest form.
+    #[default]
+    Direct,
+    /// Every gate lowered to NANDs: uniform, but deeper than `Direct`.
+    NandUniversal,
+}
+
+/// Homomorphic operations performed by an [`Evaluator`] so far.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OpCounts {
+    /// Homomorphic additions, one per encrypted XOR.
+    pub adds: usize,
+    pub mults: usize,
+    pub nands: usize,
+    /// Multiplications whose result exceeds the depth budget, so may fail to
+    /// decrypt more often than the failure target allows.
+    pub over_budget: usize,
+    /// Depth of the deepest product computed.
+    pub deepest: u32,
+}
+
+/// How an [`Evaluator`] turned public constants into ciphertexts.
+///
+/// The trivial encryptions of 0 and 1 are built at most once per evaluator
+/// and cloned after that.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConstantCacheStats {
+    /// Trivial ciphertexts constructed, at most two.
+    pub built: usize,
+    /// Constants served from the cache.
+    pub reused: usize,
+}
+
+/// Parameters and the depth budget they imply.
+#[derive(Debug)]
+pub struct Evaluator {
+    params: Arc<Params>,
+    /// Schedule and count operations without computing any ciphertext.
+    dry_run: bool,
+    allow_incompatible: bool,
+    max_depth: u32,
+    strategy: GateStrategy,
+    cancel: Option<Arc<AtomicBool>>,
+    faults: FaultHook,
+    counts: Cell<OpCounts>,
+    /// Trivial encryptions of 0 and 1, built on first use.
+    trivial: [OnceCell<Ciphertext>; 2],
+    constant_stats: Cell<ConstantCacheStats>,
+}
+
+impl Evaluator {
+    /// An evaluator for `params`; depths are tracked but not acted on.
+    pub fn new(params: &Params) -> Self {
+        Self::shared(Arc::new(params.clone()), FaultHook::default())
+    }
+
+    /// An evaluator holding the same parameter handle as the session it was
+    /// made for.
+    pub(crate) fn shared(params: Arc<Params>, faults: FaultHook) -> Self {
+        Self {
+            max_depth: params.max_mult_depth(),
+            params,
+            dry_run: false,
+            allow_incompatible: false,
+            strategy: GateStrategy::Direct,
+            cancel: None,
+            faults,
+            counts: Cell::new(OpCounts::default()),
+            trivial: Default::default(),
+            constant_stats: Cell::new(ConstantCacheStats::default()),
+        }
+    }
+
+    /// This evaluator with gates built according to `strategy`.
+    pub fn with_gate_strategy(self, strategy: GateStrategy) -> Self {
+        Self { strategy, ..self }
+    }
+
+    pub fn gate_strategy(&self) -> GateStrategy {
+        self.strategy
+    }
+
+    /// This evaluator with every operation cancelled once `token` is set.
+    pub fn with_cancellation(self, token: Arc<AtomicBool>) -> Self {
+        Self {
+            cancel: Some(token),
+            ..self
+        }
+    }
+
+    /// This evaluator with faults injected into its products and NANDs; see
+    /// [`crate::testing::FaultInjector`].
+    #[cfg(feature = "test-utils")]
+    pub fn with_fault_injector(self, injector: Arc<FaultInjector>) -> Self {
+        Self {
+            faults: FaultHook::new(injector),
+            ..self
+        }
+    }
+
+    /// Whether the cancellation token is set.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|token| token.load(Ordering::Relaxed))
+    }
+
+    /// An evaluator that computes no ciphertexts, only the depths and counts
+    /// a real one would produce.
+    pub(crate) fn dry_run(params: &Params, strategy: GateStrategy) -> Self {
+        Self {
+            dry_run: true,
+            strategy,
+            ..Self::new(params)
+        }
+    }
+
+    /// This evaluator, running circuits through
+    /// [`evaluate_circuit`](Self::evaluate_circuit) even when their
+    /// compatibility check fails.
+    pub fn allow_incompatible(self) -> Self {
+        Self {
+            allow_incompatible: true,
+            ..self
+        }
+    }
+
+    /// `circuit` evaluated on `inputs`, after checking that the input count
+    /// matches and, unless [`allow_incompatible`](Self::allow_incompatible)
+    /// was set, that [`Circuit::check_compatibility`] passes under this
+    /// evaluator's gate strategy. Returns the check's error otherwise; no
+    /// homomorphic work is done.
+    pub fn evaluate_circuit<'e>(
+        &'e self,
+        circuit: &Circuit,
+        inputs: &[EncBool<'e>],
+    ) -> Result<Vec<EncBool<'e>>, GswError> {
+        self.check_circuit(circuit, inputs)?;
+        Ok(circuit.evaluate(self, inputs))
+    }
+
+    /// [`evaluate_circuit`](Self::evaluate_circuit), also reporting the
+    /// memory the run took; see [`crate::metrics`] for what is counted.
+    #[cfg(feature = "metrics")]
+    pub fn evaluate_circuit_with_memory<'e>(
+        &'e self,
+        circuit: &Circuit,
+        inputs: &[EncBool<'e>],
+    ) -> Result<(Vec<EncBool<'e>>, CircuitMemoryStats), GswError> {
+        self.check_circuit(circuit, inputs)?;
+        let mut ledger = CircuitLedger::default();
+        let (outputs, total) =
+            with_memory_tracking(|| circuit.evaluate_observed(self, inputs, &mut ledger));
+        Ok((outputs, ledger.finish(total)))
+    }
+
+    /// Run `circuit` on `inputs` through gate `gate_index` and capture the
+    /// evaluation there, to be finished by [`resume`](Self::resume), here or
+    /// on another evaluator; see [`EvaluationState`]. The input gates always
+    /// run. Checks the circuit as [`evaluate_circuit`](Self::evaluate_circuit)
+    /// does, and fails with [`GswError::Cancelled`] if a value the rest of
+    /// the run needs was cancelled.
+    ///
+    /// Panics if `gate_index` is not a wire of `circuit`.
+    pub fn suspend_after<'e>(
+        &'e self,
+        circuit: &Circuit,
+        inputs: &[EncBool<'e>],
+        gate_index: Wire,
+    ) -> Result<EvaluationState, GswError> {
+        self.check_circuit(circuit, inputs)?;
+        let stop = Self::stop_after(circuit, gate_index).max(circuit.input_count());
+        let (progress, transcript) = circuits::advance(self, circuit, inputs, None, stop)?;
+        circuits::capture(self, circuit, &progress, transcript)
+    }
+
+    /// The outputs of the evaluation of `circuit` that `state` captured.
+    ///
+    /// Fails with [`GswError::ParamsMismatch`] if the state was captured
+    /// under other parameters, [`GswError::CircuitMismatch`] on another
+    /// circuit, and [`GswError::MalformedData`] if it does not hold the
+    /// values the rest of the circuit reads; otherwise checks the circuit
+    /// as [`evaluate_circuit`](Self::evaluate_circuit) does.
+    pub fn resume<'e>(
+        &'e self,
+        circuit: &Circuit,
+        state: &EvaluationState,
+    ) -> Result<Vec<EncBool<'e>>, GswError> {
+        circuits::check_origin(self, circuit, state)?;
+        self.check_compatibility(circuit)?;
+        let stop = circuit.gates().len();
+        let (progress, _) = circuits::advance(self, circuit, &[], Some(state), stop)?;
+        Ok(circuit.outputs_of(&progress))
+    }
+
+    /// [`resume`](Self::resume) through gate `gate_index` only, capturing
+    /// the evaluation again for the next evaluator.
+    ///
+    /// Panics if `gate_index` is not a wire of `circuit` or was already run.
+    pub fn resume_and_suspend_after(
+        &self,
+        circuit: &Circuit,
+        state: &EvaluationState,
+        gate_index: Wire,
+    ) -> Result<EvaluationState, GswError> {
+        circuits::check_origin(self, circuit, state)?;
+        self.check_compatibility(circuit)?;
+        let stop = Self::stop_after(circuit, gate_index);
+        assert!(
+            stop >= state.next_gate(),
+            "gate {} already ran before the state was captured",
+            gate_index
+        );
+        let (progress, transcript) = circuits::advance(self, circuit, &[], Some(state), stop)?;
+        circuits::capture(self, circuit, &progress, transcript)
+    }
+
+    /// The gate after `gate_index`, which must be a wire of `circuit`.
+    fn stop_after(circuit: &Circuit, gate_index: Wire) -> Wire {
+        assert!(
+            gate_index < circuit.gates().len(),
+            "gate {} out of range",
+            gate_index
+        );
+        gate_index + 1
+    }
+
+    /// The checks [`evaluate_circuit`](Self::evaluate_circuit) makes before
+    /// evaluating.
+    fn check_circuit(&self, circuit: &Circuit, inputs: &[EncBool]) -> Result<(), GswError> {
+        if inputs.len() != circuit.input_count() {
+            return Err(GswError::InputCount {
+                expected: circuit.input_count(),
+                found: inputs.len(),
+            });
+        }
+        self.check_compatibility(circuit)
+    }
+
+    /// [`Circuit::check_compatibility`] under this evaluator's strategy,
+    /// unless [`allow_incompatible`](Self::allow_incompatible) was set.
+    fn check_compatibility(&self, circuit: &Circuit) -> Result<(), GswError> {
+        if !self.allow_incompatible {
+            circuit.check_with_strategy(&self.params, self.strategy)?;
+        }
+        Ok(())
+    }
+
+    /// `f()`, or an empty placeholder in a dry run.
+    fn compute(&self, f: impl FnOnce() -> Ciphertext) -> Ciphertext {
+        if self.dry_run {
+            Vec::new()
+        } else {
+            f()
+        }
+    }
+
+    pub fn params(&self) -> &Params {
+        &self.params
+    }
+
+    /// Largest depth that meets the parameters' failure target.
+    pub fn max_depth(&self) -> u32 {
+        self.max_depth
+    }
+
+    pub fn op_counts(&self) -> OpCounts {
+        self.counts.get()
+    }
+
+    pub fn constant_cache_stats(&self) -> ConstantCacheStats {
+        self.constant_stats.get()
+    }
+
+    /// The trivial encryption of `value`, from the cache.
+    fn trivial(&self, value: bool) -> Ciphertext {
+        let mut built = false;
+        let ct = self.trivial[value as usize].get_or_init(|| {
+            built = true;
+            trivial_ciphertext(&self.params, value as u8)
+        });
+        let mut stats = self.constant_stats.get();
+        if built {
+            stats.built += 1;
+        } else {
+            stats.reused += 1;
+        }
+        self.constant_stats.set(stats);
+        ct.clone()
+    }
+
+    fn count(&self, update: impl FnOnce(&mut OpCounts)) {
+        let mut counts = self.counts.get();
+        update(&mut counts);
+        self.counts.set(counts);
+    }
+
+    /// Whether gates are lowered to NANDs.
+    fn lowers_to_nand(&self) -> bool {
+        self.strategy == GateStrategy::NandUniversal
+    }
+
+    /// Wrap a fresh ciphertext.
+    pub fn input(&self, ct: Ciphertext) -> EncBool<'_> {
+        EncBool {
+            eval: self,
+            value: Value::Encrypted { ct, depth: 0 },
+        }
+    }
+
+    /// Wrap fresh ciphertexts as a vector.
+    pub fn input_vec(&self, cts: impl IntoIterator<Item = Ciphertext>) -> EncryptedVec<'_> {
+        EncryptedVec {
+            eval: self,
+            items: cts.into_iter().map(|ct| self.input(ct)).collect(),
+        }
+    }
+
+    /// Encrypt a bit as a fresh input.
+    #[cfg(feature = "client")]
+    pub fn encrypt<R: Rng>(&self, rng: &mut R, pk: &GswPublicKey, bit: bool) -> EncBool<'_> {
+        self.input(encrypt(rng, pk, bit as u8))
+    }
+
+    /// Depth of a product of operands at most `depth` deep, counted if it
+    /// exceeds the budget.
+    fn product_depth(&self, depth: u32) -> u32 {
+        if depth + 1 > self.max_depth {
+            self.count(|counts| counts.over_budget += 1);
+        }
+        self.count(|counts| counts.deepest = counts.deepest.max(depth + 1));
+        depth + 1
+    }
+
+    /// a·b, deeper operand on the left.
+    fn mult(&self, a: (&Ciphertext, u32), b: (&Ciphertext, u32)) -> Value {
+        self.try_mult(a, b).unwrap_or(Value::Cancelled)
+    }
+
+    fn try_mult(&self, a: (&Ciphertext, u32), b: (&Ciphertext, u32)) -> Result<Value, GswError> {
+        let (left, right) = if a.1 >= b.1 { (a.0, b.0) } else { (b.0, a.0) };
+        let mut planned = self.faults.begin();
+        planned.check()?;
+        let ct = match &self.cancel {
+            _ if self.dry_run => Vec::new(),
+            Some(token) => homomorphic_mult_cancellable(&self.params, left, right, token)?,
+            None => homomorphic_mult(&self.params, left, right),
+        };
+        self.count(|counts| counts.mults += 1);
+        Ok(Value::Encrypted {
+            ct: planned.output(ct),
+            depth: self.product_depth(a.1.max(b.1)),
+        })
+    }
+
+    /// NAND(a, b), deeper operand on the left.
+    fn nand(&self, a: (&Ciphertext, u32), b: (&Ciphertext, u32)) -> Value {
+        self.try_nand(a, b).unwrap_or(Value::Cancelled)
+    }
+
+    fn try_nand(&self, a: (&Ciphertext, u32), b: (&Ciphertext, u32)) -> Result<Value, GswError> {
+        let (left, right) = if a.1 >= b.1 { (a.0, b.0) } else { (b.0, a.0) };
+        let mut planned = self.faults.begin();
+        planned.check()?;
+        let ct = match &self.cancel {
+            _ if self.dry_run => Vec::new(),
+            Some(token) => homomorphic_nand_cancellable(&self.params, left, right, token)?,
+            None => homomorphic_nand(&self.params, left, right),
+        };
+        self.count(|counts| counts.nands += 1);
+        Ok(Value::Encrypted {
+            ct: planned.output(ct),
+            depth: self.product_depth(a.1.max(b.1)),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum Value {
+    Constant(bool),
+    Encrypted {
+        ct: Ciphertext,
+        depth: u32,
+    },
+    /// Produced by a gate that ran or started after cancellation.
+    Cancelled,
+}
+
+/// An encrypted (or public constant) boolean bound to an [`Evaluator`].
+#[derive(Clone, Debug)]
+pub struct EncBool<'e> {
+    eval: &'e Evaluator,
+    value: Value,
+}
+
+impl<'e> EncBool<'e> {
+    /// A public constant; gates with it cost no homomorphic work.
+    pub fn constant(eval: &'e Evaluator, value: bool) -> Self {
+        Self {
+            eval,
+            value: Value::Constant(value),
+        }
+    }
+
+    /// The value, if it is a public constant.
+    pub fn as_constant(&self) -> Option<bool> {
+        match self.value {
+            Value::Constant(value) => Some(value),
+            Value::Encrypted { .. } | Value::Cancelled => None,
+        }
+    }
+
+    /// Whether the gate producing this value (or one it depends on) was
+    /// cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.value, Value::Cancelled)
+    }
+
+    /// Multiplicative depth of the circuit that produced this value; 0 for
+    /// constants and fresh inputs.
+    pub fn depth(&self) -> u32 {
+        match self.value {
+            Value::Constant(_) | Value::Cancelled => 0,
+            Value::Encrypted { depth, .. } => depth,
+        }
+    }
+
+    /// Heap bytes of the ciphertext this value holds; 0 for constants.
+    #[cfg(feature = "metrics")]
+    pub(crate) fn ciphertext_bytes(&self) -> usize {
+        match &self.value {
+            Value::Encrypted { ct, .. } => crate::metrics::ciphertext_heap_bytes(ct),
+            Value::Constant(_) | Value::Cancelled => 0,
+        }
+    }
+
+    /// A value restored from a suspended evaluation.
+    pub(crate) fn from_value(eval: &'e Evaluator, value: Value) -> Self {
+        Self { eval, value }
+    }
+
+    pub(crate) fn value(&self) -> &Value {
+        &self.value
+    }
+
+    fn with(&self, value: Value) -> Self {
+        Self {
+            eval: self.eval,
+            value,
+        }
+    }
+
+    fn check_same_evaluator(&self, other: &Self) {
+        assert!(
+            std::ptr::eq(self.eval, other.eval),
+            "EncBool operands belong to different evaluators"
+        );
+    }
+
+    /// Whether a gate on self and `other` is lowered to NANDs.
+    fn lowers_to_nand(&self, other: &Self) -> bool {
+        self.check_same_evaluator(other);
+        self.eval.lowers_to_nand()
+    }
+
+    /// NOT self. Linear unless lowered, then NAND(self, self).
+    pub fn not(&self) -> Self {
+        if self.lowers_to_nand(self) {
+            return self.nand(self);
+        }
+        self.with(match &self.value {
+            Value::Constant(value) => Value::Constant(!value),
+            Value::Encrypted { .. } if self.eval.is_cancelled() => Value::Cancelled,
+            Value::Cancelled => Value::Cancelled,
+            Value::Encrypted { ct, depth } => Value::Encrypted {
+                ct: self.eval.compute(|| homomorphic_not(&self.eval.params, ct)),
+                depth: *depth,
+            },
+        })
+    }
+
+    /// self AND other: one multiplication unless either side is constant;
+    /// lowered, NOT(NAND(self, other)).
+    pub fn and(&self, other: &Self) -> Self {
+        if self.lowers_to_nand(other) {
+            return self.nand(other).not();
+        }
+        match (&self.value, &other.value) {
+            (Value::Constant(false), _) | (_, Value::Constant(false)) => {
+                self.with(Value::Constant(false))
+            }
+            (Value::Constant(true), _) => other.clone(),
+            (_, Value::Constant(true)) => self.clone(),
+            (Value::Cancelled, _) | (_, Value::Cancelled) => self.with(Value::Cancelled),
+            (Value::Encrypted { ct: a, depth: da }, Value::Encrypted { ct: b, depth: db }) => {
+                self.with(self.eval.mult((a, *da), (b, *db)))
+            }
+        }
+    }
+
+    /// NAND(self, other), folding constants.
+    pub fn nand(&self, other: &Self) -> Self {
+        self.check_same_evaluator(other);
+        match (&self.value, &other.value) {
+            (Value::Constant(false), _) | (_, Value::Constant(false)) => {
+                self.with(Value::Constant(true))
+            }
+            (Value::Constant(true), Value::Constant(true)) => self.with(Value::Constant(false)),
+            (Value::Constant(true), _) => other.nand(other),
+            (_, Value::Constant(true)) => self.nand(self),
+            (Value::Cancelled, _) | (_, Value::Cancelled) => self.with(Value::Cancelled),
+            (Value::Encrypted { ct: a, depth: da }, Value::Encrypted { ct: b, depth: db }) => {
+                self.with(self.eval.nand((a, *da), (b, *db)))
+            }
+        }
+    }
+
+    /// self OR other, as NOT(NOT self AND NOT other); lowered, the middle
+    /// AND and outer NOT are one NAND.
+    pub fn or(&self, other: &Self) -> Self {
+        if self.lowers_to_nand(other) {
+            return self.not().nand(&other.not());
+        }
+        self.not().and(&other.not()).not()
+    }
+
+    /// self XOR other. Linear unless lowered, then four NANDs.
+    pub fn xor(&self, other: &Self) -> Self {
+        if self.lowers_to_nand(other) {
+            let both = self.nand(other);
+            return self.nand(&both).nand(&other.nand(&both));
+        }
+        match (&self.value, &other.value) {
+            (Value::Constant(c), _) => {
+                if *c {
+                    other.not()
+                } else {
+                    other.clone()
+                }
+            }
+            (_, Value::Constant(c)) => {
+                if *c {
+                    self.not()
+                } else {
+                    self.clone()
+                }
+            }
+            (Value::Cancelled, _) | (_, Value::Cancelled) => self.with(Value::Cancelled),
+            _ if self.eval.is_cancelled() => self.with(Value::Cancelled),
+            (Value::Encrypted { ct: a, depth: da }, Value::Encrypted { ct: b, depth: db }) => {
+                self.eval.count(|counts| counts.adds += 1);
+                self.with(Value::Encrypted {
+                    ct: self
+                        .eval
+                        .compute(|| homomorphic_add(&self.eval.params, a, b)),
+                    depth: (*da).max(*db),
+                })
+            }
+        }
+    }
+
+    /// `if_true` where self is 1, `if_false` where it is 0, as
+    /// if_false ⊕ self·(if_true ⊕ if_false): one multiplication, none if the
+    /// selector or the difference is constant. Lowered, it is
+    /// NAND(NAND(self, if_true), NAND(NOT self, if_false)).
+    pub fn mux(&self, if_true: &Self, if_false: &Self) -> Self {
+        match self.value {
+            Value::Constant(true) => if_true.clone(),
+            Value::Constant(false) => if_false.clone(),
+            Value::Cancelled => self.clone(),
+            Value::Encrypted { .. } => {
+                self.check_same_evaluator(if_true);
+                self.check_same_evaluator(if_false);
+                if self.eval.lowers_to_nand() {
+                    self.nand(if_true).nand(&self.not().nand(if_false))
+                } else {
+                    if_false.xor(&self.and(&if_true.xor(if_false)))
+                }
+            }
+        }
+    }
+
+    /// The ciphertext, with constants as trivial encryptions (see
+    /// [`ConstantCacheStats`]), or [`GswError::Cancelled`].
+    pub fn try_into_ciphertext(self) -> Result<Ciphertext, GswError> {
+        match self.value {
+            Value::Constant(value) => Ok(self.eval.trivial(value)),
+            Value::Encrypted { ct, .. } => Ok(ct),
+            Value::Cancelled => Err(GswError::Cancelled),
+        }
+    }
+
+    /// The ciphertext, with constants as trivial encryptions.
+    ///
+    /// Panics if the value was cancelled.
+    pub fn into_ciphertext(self) -> Ciphertext {
+        self.try_into_ciphertext().expect("EncBool was cancelled")
+    }
+
+    /// Panics if the value was cancelled.
+    #[cfg(feature = "client")]
+    pub fn decrypt(&self, sk: &GswSecretKey) -> bool {
+        match &self.value {
+            Value::Constant(value) => *value,
+            Value::Encrypted { ct, .. } => decrypt(sk, ct) == 1,
+            Value::Cancelled => panic!("EncBool was cancelled"),
+        }
+    }
+}
+
+/// A vector of [`EncBool`]s bound to one [`Evaluator`].
+#[derive(Clone, Debug)]
+pub struct EncryptedVec<'e> {
+    eval: &'e Evaluator,
+    items: Vec<EncBool<'e>>,
+}
+
+impl<'e> EncryptedVec<'e> {
+    /// Panics if an element belongs to a different evaluator.
+    pub fn new(eval: &'e Evaluator, items: Vec<EncBool<'e>>) -> Self {
+        for item in &items {
+            assert!(
+                std::ptr::eq(eval, item.eval),
+                "EncryptedVec elements belong to different evaluators"
+            );
+        }
+        Self { eval, items }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, EncBool<'e>> {
+        self.items.iter()
+    }
+
+    /// Depth of each element.
+    pub fn depths(&self) -> Vec<u32> {
+        self.items.iter().map(EncBool::depth).collect()
+    }
+
+    /// `gate` applied to each element.
+    pub fn map_gate(self, mut gate: impl FnMut(&EncBool<'e>) -> EncBool<'e>) -> Self {
+        Self {
+            eval: self.eval,
+            items: self.items.into_iter().map(|item| gate(&item)).collect(),
+        }
+    }
+
+    /// `gate` applied to corresponding elements of self and `other`.
+    ///
+    /// Panics if the lengths or evaluators differ.
+    pub fn zip_with(
+        self,
+        other: Self,
+        mut gate: impl FnMut(&EncBool<'e>, &EncBool<'e>) -> EncBool<'e>,
+    ) -> Self {
+        assert!(
+            std::ptr::eq(self.eval, other.eval),
+            "EncryptedVec operands belong to different evaluators"
+        );
+        assert_eq!(self.len(), other.len(), "EncryptedVec lengths differ");
+        Self {
+            eval: self.eval,
+            items: self
+                .items
+                .into_iter()
+                .zip(other.items)
+                .map(|(a, b)| gate(&a, &b))
+                .collect(),
+        }
+    }
+
+    /// The elements folded with `gate` as a balanced tree: adjacent pairs are
+    /// combined level by level, an odd element out passing up unchanged, so
+    /// the result is ⌈log₂ n⌉ gates deep. `gate` must be associative for the
+    /// result to match a left fold. `None` if the vector is empty.
+    pub fn reduce(
+        self,
+        mut gate: impl FnMut(&EncBool<'e>, &EncBool<'e>) -> EncBool<'e>,
+    ) -> Option<EncBool<'e>> {
+        let mut level = self.items;
+        while level.len() > 1 {
+            let mut next = Vec::with_capacity(level.len().div_ceil(2));
+            let mut items = level.into_iter();
+            while let Some(a) = items.next() {
+                next.push(match items.next() {
+                    Some(b) => gate(&a, &b),
+                    None => a,
+                });
+            }
+            level = next;
+        }
+        level.pop()
+    }
+
+    /// The ciphertexts, with constants as trivial encryptions.
+    ///
+    /// Panics if an element was cancelled.
+    pub fn into_ciphertexts(self) -> Vec<Ciphertext> {
+        self.items
+            .into_iter()
+            .map(EncBool::into_ciphertext)
+            .collect()
+    }
+
+    /// Decrypt every element with one [`DecryptionContext`].
+    ///
+    /// Panics if an element was cancelled.
+    #[cfg(feature = "client")]
+    pub fn decrypt(&self, sk: &GswSecretKey) -> Vec<bool> {
+        let ctx = DecryptionContext::new(sk);
+        self.items
+            .iter()
+            .map(|item| match &item.value {
+                Value::Constant(value) => *value,
+                Value::Encrypted { ct, .. } => ctx.decrypt(ct) == 1,
+                Value::Cancelled => panic!("EncBool was cancelled"),
+            })
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::bootstrap::gen_evaluation_key;
+    use crate::gsw::gsw_keygen;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn test_nested_expression_matches_cleartext() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let eval = Evaluator::new(&params);
+        let bits = [encrypt(&mut rng, &pk, 0), encrypt(&mut rng, &pk, 1)];
+        let t = EncBool::constant(&eval, true);
+        let f = EncBool::constant(&eval, false);
+
+        for x in 0..64usize {
+            let [a, b, c, d, e, g] =
+                std::array::from_fn(|i| eval.input(bits[(x >> i) & 1].clone()));
+            let out = a
+                .and(&b)
+                .xor(&c.or(&d))
+                .mux(&e.xor(&g).and(&t), &a.not().and(&g).or(&f))
+                .xor(&t);
+
+            let [a, b, c, d, e, g] = std::array::from_fn(|i| (x >> i) & 1 == 1);
+            let expected = !(if (a & b) ^ (c | d) { e ^ g } else { !a & g });
+            assert_eq!(out.decrypt(&sk), expected, "inputs {:06b}", x);
+            assert_eq!(out.depth(), 2);
+        }
+    }
+
+    #[test]
+    fn test_constants_fold() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(8);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let eval = Evaluator::new(&params);
+        let x = eval.encrypt(&mut rng, &pk, true);
+        let t = EncBool::constant(&eval, true);
+        let f = EncBool::constant(&eval, false);
+
+        assert_eq!(x.and(&f).as_constant(), Some(false));
+        assert_eq!(t.or(&x).as_constant(), Some(true));
+        assert_eq!(t.xor(&f).as_constant(), Some(true));
+        assert_eq!(f.mux(&f, &t).as_constant(), Some(true));
+        assert_eq!(x.mux(&t, &t).as_constant(), Some(true));
+        let flipped = x.xor(&t);
+        assert_eq!((flipped.as_constant(), flipped.depth()), (None, 0));
+        assert!(!flipped.decrypt(&sk));
+        assert!(x.mux(&t, &f).decrypt(&sk));
+        assert_eq!(x.mux(&t, &f).depth(), 0);
+        assert_eq!(t.into_ciphertext(), trivial_ciphertext(&params, 1));
+    }
+
+    #[test]
+    fn test_constants_are_built_once() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(480);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        // Constants, gates that fold to constants, and a few ANDs with the
+        // input that do not.
+        let mut circuit = Circuit::new(1);
+        let outputs: Vec<_> = (0..64)
+            .map(|i| {
+                let a = circuit.constant(i % 3 == 0);
+                let b = circuit.constant(i % 2 == 0);
+                let folded = circuit.xor(a, b);
+                if i % 8 == 0 {
+                    circuit.and(folded, 0)
+                } else {
+                    folded
+                }
+            })
+            .collect();
+        circuit.set_outputs(&outputs);
+
+        let eval = Evaluator::new(&params);
+        let out = eval
+            .evaluate_circuit(&circuit, &[eval.encrypt(&mut rng, &pk, true)])
+            .unwrap();
+        let mut constants = 0;
+        for (i, bit) in out.into_iter().enumerate() {
+            let constant = bit.as_constant();
+            let ct = bit.into_ciphertext();
+            if let Some(value) = constant {
+                assert_eq!(ct, trivial_ciphertext(&params, value as u8));
+                constants += 1;
+            }
+            assert_eq!(decrypt(&sk, &ct) == 1, (i % 3 == 0) ^ (i % 2 == 0));
+        }
+        let stats = eval.constant_cache_stats();
+        assert_eq!((stats.built, stats.built + stats.reused), (2, constants));
+        assert!(constants > 50);
+    }
+
+    #[test]
+    fn test_gate_strategies_agree() {
+        // Lowered gates are NAND chains several levels deep, so this needs a
+        // wide modulus.
+        let params = Params::from_raw(1 << 40, 8, 256, 1);
+        let mut rng = ChaCha20Rng::seed_from_u64(10);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let bits = [encrypt(&mut rng, &pk, 0), encrypt(&mut rng, &pk, 1)];
+
+        for strategy in [GateStrategy::Direct, GateStrategy::NandUniversal] {
+            let eval = Evaluator::new(&params).with_gate_strategy(strategy);
+            for x in 0..8usize {
+                let [a, b, c] = std::array::from_fn(|i| eval.input(bits[(x >> i) & 1].clone()));
+                let out = a.and(&b).xor(&c).mux(&a.not(), &b.or(&c));
+
+                let [a, b, c] = std::array::from_fn(|i| (x >> i) & 1 == 1);
+                let expected = if (a & b) ^ c { !a } else { b | c };
+                assert_eq!(
+                    out.decrypt(&sk),
+                    expected,
+                    "{:?}, inputs {:03b}",
+                    strategy,
+                    x
+                );
+            }
+            let counts = eval.op_counts();
+            match strategy {
+                GateStrategy::NandUniversal => assert_eq!(counts.mults, 0),
+                GateStrategy::Direct => assert_eq!(counts.nands, 0),
+            }
+
+            // Lowering costs depth: NOT, AND, and XOR are one, two, and
+            // three NANDs deep.
+            let [a, b] = bits.clone().map(|ct| eval.input(ct));
+            let depths = [a.not().depth(), a.and(&b).depth(), a.xor(&b).depth()];
+            match strategy {
+                GateStrategy::NandUniversal => assert_eq!(depths, [1, 2, 3]),
+                GateStrategy::Direct => assert_eq!(depths, [0, 1, 0]),
+            }
+        }
+    }
+
+    #[test]
+    fn test_tighter_target_shrinks_the_budget() {
+        let params = Params::toy();
+        let tight = params.clone().with_target_failure_exp(-1 << 15).unwrap();
+        assert_eq!((params.max_mult_depth(), tight.max_mult_depth()), (1, 0));
+
+        // The failure target is not part of the parameters' identity: the
+        // same seed gives the same keys under both.
+        let keys = |params: &Params| {
+            let mut rng = ChaCha20Rng::seed_from_u64(12);
+            let (sk, pk) = gsw_keygen(&mut rng, params);
+            let ek = gen_evaluation_key(&mut rng, &sk, &pk);
+            (sk, pk, ek, rng)
+        };
+        let (sk, pk, ek, mut rng) = keys(&params);
+        let (_, _, tight_ek, _) = keys(&tight);
+        assert_eq!(tight_ek.to_bytes(), ek.to_bytes());
+        let inputs: Vec<Ciphertext> = (0..3).map(|_| encrypt(&mut rng, &pk, 1)).collect();
+
+        // a·b·c: the second product exceeds the default budget, both exceed
+        // the tight one.
+        let run = |eval: Evaluator| {
+            let [a, b, c] = std::array::from_fn(|i| eval.input(inputs[i].clone()));
+            let ct = a.and(&b).and(&c).into_ciphertext();
+            (eval.op_counts(), decrypt(&sk, &ct))
+        };
+        assert_eq!(
+            [&params, &tight]
+                .map(|p| run(Evaluator::new(p)))
+                .map(|(c, bit)| (c.mults, c.over_budget, bit)),
+            [(2, 1, 1), (2, 2, 1)]
+        );
+    }
+
+    #[test]
+    fn test_reduce_is_balanced() {
+        let params = Params::from_raw(1 << 40, 8, 256, 1);
+        let mut rng = ChaCha20Rng::seed_from_u64(13);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let eval = Evaluator::new(&params);
+
+        // Five inputs: levels of 3, 2, and 1 elements, the odd one out
+        // carried up unchanged.
+        let bits = [true, true, false, true, true];
+        let inputs = eval.input_vec(bits.map(|bit| encrypt(&mut rng, &pk, bit as u8)));
+        let all = inputs.clone().reduce(|a, b| a.and(b)).unwrap();
+        assert!(!all.decrypt(&sk));
+        assert_eq!(all.depth(), 3);
+        let any = inputs
+            .map_gate(|x| x.not())
+            .reduce(|a, b| a.and(b))
+            .unwrap();
+        assert!(!any.decrypt(&sk));
+        assert_eq!(eval.op_counts().mults, 8);
+        assert!(eval.input_vec([]).reduce(|a, b| a.and(b)).is_none());
+    }
+
+    #[test]
+    fn test_cancelled_token_propagates() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(14);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let token = Arc::new(AtomicBool::new(false));
+        let eval = Evaluator::new(&params).with_cancellation(Arc::clone(&token));
+        let (x, y) = (
+            eval.encrypt(&mut rng, &pk, true),
+            eval.encrypt(&mut rng, &pk, false),
+        );
+        assert!(x.nand(&y).decrypt(&sk) && !x.and(&y).decrypt(&sk));
+
+        token.store(true, Ordering::Relaxed);
+        assert!(eval.is_cancelled());
+        let f = EncBool::constant(&eval, false);
+        let out = x.and(&y).xor(&x.not());
+        assert!(out.is_cancelled() && x.nand(&y).is_cancelled());
+        assert_eq!(out.or(&f.not()).as_constant(), Some(true));
+        assert!(out.mux(&x, &y).is_cancelled());
+        assert!(matches!(
+            out.try_into_ciphertext(),
+            Err(GswError::Cancelled)
+        ));
+        assert_eq!((eval.op_counts().nands, eval.op_counts().mults), (1, 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "different evaluators")]
+    fn test_rejects_mixed_evaluators() {
+        let params = Params::pedagogical();
+        let (first, second) = (Evaluator::new(&params), Evaluator::new(&params));
+        EncBool::constant(&first, true).and(&EncBool::constant(&second, true));
+    }
+}
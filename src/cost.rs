@@ -0,0 +1,258 @@
+//! Operation cost estimates, for capacity planning before running anything.
+//!
+//! Costs are counted in multiply-add equivalents: one multiply-add of the
+//! matrix-product kernel is 1. A homomorphic multiplication is dominated by
+//! the N×N product, about N³/2 multiply-adds because the kernel skips the
+//! zero entries of its (flattened, binary) left operand. Everything else is
+//! entrywise passes over N×N matrices — sums, reductions, bit
+//! (de)composition — which go through slower scalar code and are weighted
+//! accordingly.
+//!
+//! [`calibrate`] times the product kernel once, giving the throughput to turn
+//! counts into seconds with [`CostModel::estimate_seconds`]:
+//!
+//! ```
+//! use gsw_rs::cost::{calibrate, CostOp};
+//! use gsw_rs::Params;
+//!
+//! let model = Params::toy().cost_model();
+//! let seconds = model.estimate_seconds(CostOp::Bootstrap, calibrate());
+//! assert!(seconds > 0.0);
+//! ```
+
+use std::time::Instant;
+
+use crate::kernels;
+use crate::params::Params;
+
+/// Multiply-add equivalents per entry of one entrywise pass over a matrix.
+const PASS_WEIGHT: u64 = 6;
+
+/// Multiply-add equivalents per sampled bit of the encryption randomness R.
+const RANDOM_BIT_WEIGHT: u64 = 60;
+
+/// Side of the matrices [`calibrate`] multiplies.
+const CALIBRATION_SIZE: usize = 96;
+
+/// An operation [`CostModel`] can price.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CostOp {
+    Add,
+    Mult,
+    Encrypt,
+    Bootstrap,
+}
+
+/// Estimated cost of each operation at one parameter set; see
+/// [`Params::cost_model`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CostModel {
+    n: u64,
+    m: u64,
+    l: u64,
+    n_expanded: u64,
+}
+
+impl CostModel {
+    pub fn new(params: &Params) -> Self {
+        Self {
+            n: params.n as u64,
+            m: params.m as u64,
+            l: params.l as u64,
+            n_expanded: params.n_expanded as u64,
+        }
+    }
+
+    /// One entrywise pass over an N×N matrix.
+    fn pass(&self) -> u64 {
+        PASS_WEIGHT * self.n_expanded * self.n_expanded
+    }
+
+    /// Multiply-adds of one N×N product with a binary left operand of
+    /// average density, half its entries nonzero.
+    pub fn product_multiply_adds(&self) -> u64 {
+        self.n_expanded.pow(3) / 2
+    }
+
+    /// Product, then Flatten (two passes).
+    pub fn mult_ops(&self) -> u64 {
+        self.product_multiply_adds() + 2 * self.pass()
+    }
+
+    /// Entrywise sum, then Flatten.
+    pub fn add_ops(&self) -> u64 {
+        3 * self.pass()
+    }
+
+    /// Sampling the N×m matrix R, the product R·A, then BitDecomp and the
+    /// added μ·G.
+    pub fn encrypt_ops(&self) -> u64 {
+        let samples = self.n_expanded * self.m;
+        RANDOM_BIT_WEIGHT * samples + samples * (self.n + 1) + 2 * self.pass()
+    }
+
+    /// Deriving one coefficient per key digit (l terms each), then per
+    /// evaluation-key entry a scaling pass, its Flatten, and an addition.
+    /// Assumes every coefficient is nonzero.
+    pub fn bootstrap_ops(&self) -> u64 {
+        let coefficients = self.n_expanded * self.l;
+        let per_term = self.pass() + 2 * self.pass() + self.add_ops();
+        coefficients + self.n_expanded * per_term
+    }
+
+    pub fn ops(&self, op: CostOp) -> u64 {
+        match op {
+            CostOp::Add => self.add_ops(),
+            CostOp::Mult => self.mult_ops(),
+            CostOp::Encrypt => self.encrypt_ops(),
+            CostOp::Bootstrap => self.bootstrap_ops(),
+        }
+    }
+
+    /// Seconds for one `op` at `throughput_hint` multiply-adds per second,
+    /// as [`calibrate`] measures.
+    pub fn estimate_seconds(&self, op: CostOp, throughput_hint: f64) -> f64 {
+        self.ops(op) as f64 / throughput_hint
+    }
+}
+
+impl Params {
+    /// Cost estimates for operations at these parameters.
+    pub fn cost_model(&self) -> CostModel {
+        CostModel::new(self)
+    }
+}
+
+/// Multiply-adds per second of the active product kernel on this machine,
+/// measured on a small product with a binary left operand of half density,
+/// best of a few runs. Takes a few milliseconds.
+pub fn calibrate() -> f64 {
+    let n = CALIBRATION_SIZE;
+    let q = 1u64 << 20;
+    // Fixed patterns: no RNG is needed, and the left operand's density is
+    // exactly one half.
+    let binary: Vec<Vec<u64>> = (0..n)
+        .map(|i| (0..n).map(|j| ((i + j) % 2) as u64).collect())
+        .collect();
+    let dense: Vec<Vec<u64>> = (0..n)
+        .map(|i| (0..n).map(|j| ((i * 31 + j * 17) as u64) % q).collect())
+        .collect();
+    let best = (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            std::hint::black_box(kernels::matrix_product(&binary, &dense, q));
+            start.elapsed().as_secs_f64()
+        })
+        .fold(f64::INFINITY, f64::min);
+    (n * n * n / 2) as f64 / best.max(f64::MIN_POSITIVE)
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::bootstrap::{bootstrap, gen_evaluation_key};
+    use crate::gsw::{encrypt, gsw_keygen, homomorphic_add, homomorphic_mult};
+    use crate::params::SecurityLevel;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    /// Best of `runs` timings of `f`, in seconds.
+    fn best_time(runs: usize, mut f: impl FnMut()) -> f64 {
+        (0..runs)
+            .map(|_| {
+                let start = Instant::now();
+                f();
+                start.elapsed().as_secs_f64()
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    #[test]
+    fn test_product_count_matches_the_kernel() {
+        let params = Params::toy();
+        let model = params.cost_model();
+        let mut rng = ChaCha20Rng::seed_from_u64(15);
+        let (_, pk) = gsw_keygen(&mut rng, &params);
+        let n = params.n_expanded as u64;
+
+        // Straight through the active kernel: `matrix_product` may also
+        // validate or split across threads, which other tests toggle.
+        let kernel = kernels::active_kernel_name();
+        let ct = encrypt(&mut rng, &pk, 1);
+        let nonzero = ct.iter().flatten().filter(|&&c| c != 0).count() as u64;
+        kernels::take_multiply_adds();
+        kernels::matrix_product_with(kernel, &ct, &ct, params.q);
+        let counted = kernels::take_multiply_adds();
+        assert_eq!(counted, nonzero * n);
+        let expected = model.product_multiply_adds();
+        assert!(counted.abs_diff(expected) < expected / 20, "{}", counted);
+
+        let ones = vec![vec![1u64; n as usize]; n as usize];
+        kernels::matrix_product_with(kernel, &ones, &ones, params.q);
+        assert_eq!(kernels::take_multiply_adds(), 2 * expected);
+    }
+
+    #[test]
+    fn test_costs_grow_with_the_preset() {
+        let [toy, low, medium] = [
+            SecurityLevel::Toy,
+            SecurityLevel::Low,
+            SecurityLevel::Medium,
+        ]
+        .map(|level| Params::new(level).cost_model());
+        for op in [
+            CostOp::Add,
+            CostOp::Mult,
+            CostOp::Encrypt,
+            CostOp::Bootstrap,
+        ] {
+            assert!(toy.ops(op) < low.ops(op) && low.ops(op) < medium.ops(op));
+        }
+        assert!(toy.bootstrap_ops() > toy.mult_ops());
+        assert_eq!(
+            toy.estimate_seconds(CostOp::Mult, 1e9),
+            toy.mult_ops() as f64 / 1e9
+        );
+    }
+
+    #[test]
+    fn test_calibrated_estimates_are_within_3x() {
+        let params = Params::toy();
+        let model = params.cost_model();
+        let mut rng = ChaCha20Rng::seed_from_u64(16);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ek = gen_evaluation_key(&mut rng, &sk, &pk);
+        let ct = encrypt(&mut rng, &pk, 1);
+
+        let throughput = calibrate();
+        let measured = [
+            (
+                CostOp::Add,
+                best_time(5, || drop(homomorphic_add(&params, &ct, &ct))),
+            ),
+            (
+                CostOp::Mult,
+                best_time(5, || drop(homomorphic_mult(&params, &ct, &ct))),
+            ),
+            (
+                CostOp::Encrypt,
+                best_time(5, || drop(encrypt(&mut rng, &pk, 1))),
+            ),
+            (
+                CostOp::Bootstrap,
+                best_time(2, || drop(bootstrap(&params, &ct, &ek))),
+            ),
+        ];
+        for (op, seconds) in measured {
+            let estimate = model.estimate_seconds(op, throughput);
+            let ratio = estimate / seconds;
+            assert!(
+                (1.0 / 3.0..=3.0).contains(&ratio),
+                "{:?}: estimated {:.2e} s, measured {:.2e} s",
+                op,
+                estimate,
+                seconds
+            );
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Content digests: one hash function and one canonical encoding for every
+//! fingerprint in the crate.
+//!
+//! A digest of an object is H(tag length (u8) || tag || canonical bytes), where
+//! the tag names the type and encoding version (`gsw.ct.v1`, ...) so that
+//! digests of different types never collide. The canonical bytes are the
+//! object's wire encoding without the object header (see the `wire` module
+//! docs for field order; all integers are little-endian), or for a circuit,
+//! which has no wire encoding, the one described at its `Hashable` impl:
+//!
+//! | type            | tag              | canonical bytes            |
+//! |-----------------|------------------|----------------------------|
+//! | `Params`        | `gsw.params.v1`  | q, n, m, error bound       |
+//! | `SecretKey`     | `gsw.sk.v1`      | params, s                  |
+//! | `PublicKey`     | `gsw.pk.v1`      | params, dimensions, A      |
+//! | `Ciphertext`    | `gsw.ct.v1`      | dimensions, encoding, C    |
+//! | `EvaluationKey` | `gsw.ek.v1`      | params, each ciphertext    |
+//! | `Circuit`       | `gsw.circuit.v1` | inputs, gates, outputs     |
+//!
+//! H is BLAKE3 with the `blake3` feature (on by default) and SHA-256 without
+//! it, which needs the `sha256` feature. The two never agree, so every party
+//! comparing digests must be built with the same choice.
+
+use std::fmt::Write;
+
+use crate::bootstrap::EvaluationKey;
+use crate::circuits::Circuit;
+use crate::gsw::Ciphertext;
+use crate::lwe::{PublicKey, SecretKey};
+use crate::params::Params;
+use crate::wire;
+
+/// A 32-byte digest.
+pub type Digest = [u8; 32];
+
+/// A hash function producing [`Digest`]s.
+pub trait GswHash {
+    /// Name for diagnostics, e.g. `"blake3"`.
+    const NAME: &'static str;
+
+    fn hash(bytes: &[u8]) -> Digest;
+}
+
+/// BLAKE3 (the `blake3` feature).
+#[cfg(feature = "blake3")]
+pub struct Blake3;
+
+#[cfg(feature = "blake3")]
+impl GswHash for Blake3 {
+    const NAME: &'static str = "blake3";
+
+    fn hash(bytes: &[u8]) -> Digest {
+        blake3::hash(bytes).into()
+    }
+}
+
+#[cfg(not(any(feature = "blake3", feature = "sha256")))]
+compile_error!("digests need the `blake3` or the `sha256` feature");
+
+/// SHA-256 (the `sha256` feature).
+#[cfg(feature = "sha256")]
+pub struct Sha256;
+
+#[cfg(feature = "sha256")]
+impl GswHash for Sha256 {
+    const NAME: &'static str = "sha256";
+
+    fn hash(bytes: &[u8]) -> Digest {
+        use sha2::Digest as _;
+        sha2::Sha256::digest(bytes).into()
+    }
+}
+
+/// The hash used for every fingerprint in the crate.
+#[cfg(feature = "blake3")]
+pub type DefaultHash = Blake3;
+/// The hash used for every fingerprint in the crate.
+#[cfg(not(feature = "blake3"))]
+pub type DefaultHash = Sha256;
+
+/// A type with a domain-separation tag and a canonical encoding.
+pub trait Hashable {
+    const TAG: &'static str;
+
+    fn write_canonical(&self, out: &mut Vec<u8>);
+}
+
+impl Hashable for Params {
+    const TAG: &'static str = "gsw.params.v1";
+
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        wire::write_params(out, self);
+    }
+}
+
+impl Hashable for SecretKey {
+    const TAG: &'static str = "gsw.sk.v1";
+
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        wire::write_secret_key(out, self);
+    }
+}
+
+impl Hashable for PublicKey {
+    const TAG: &'static str = "gsw.pk.v1";
+
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        wire::write_public_key(out, self);
+    }
+}
+
+/// The matrix as given: an unflattened ciphertext hashes differently from
+/// its flattening; [`ciphertext_digest`](crate::gsw::ciphertext_digest)
+/// hashes both alike.
+impl Hashable for Ciphertext {
+    const TAG: &'static str = "gsw.ct.v1";
+
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        wire::write_ciphertext(out, self);
+    }
+}
+
+impl Hashable for EvaluationKey {
+    const TAG: &'static str = "gsw.ek.v1";
+
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        wire::write_evaluation_key(out, self);
+    }
+}
+
+/// Inputs, gate count, each gate as a tag byte and its fields (wires and
+/// counts as u64; a conditional's branches nested in place), then the outputs.
+impl Hashable for Circuit {
+    const TAG: &'static str = "gsw.circuit.v1";
+
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        Circuit::write_canonical(self, out);
+    }
+}
+
+/// Digest of `value` under `H`.
+pub fn digest_with<H: GswHash, T: Hashable + ?Sized>(value: &T) -> Digest {
+    let mut bytes = Vec::with_capacity(1 + T::TAG.len());
+    bytes.push(T::TAG.len() as u8);
+    bytes.extend_from_slice(T::TAG.as_bytes());
+    value.write_canonical(&mut bytes);
+    H::hash(&bytes)
+}
+
+/// Digest of `value` under [`DefaultHash`].
+pub fn digest<T: Hashable + ?Sized>(value: &T) -> Digest {
+    digest_with::<DefaultHash, T>(value)
+}
+
+/// Lowercase hex of a digest, for logs.
+pub fn to_hex(digest: &Digest) -> String {
+    digest.iter().fold(String::with_capacity(64), |mut s, b| {
+        let _ = write!(s, "{:02x}", b);
+        s
+    })
+}
+
+/// Lowercase hex of [`digest`].
+pub fn hash_hex<T: Hashable + ?Sized>(value: &T) -> String {
+    to_hex(&digest(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gsw::trivial_ciphertext;
+    use crate::session::Session;
+
+    const FIXTURE: &[u8] = include_bytes!("../tests/fixtures/pedagogical.session");
+
+    fn hex_with<H: GswHash, T: Hashable + ?Sized>(value: &T) -> String {
+        to_hex(&digest_with::<H, T>(value))
+    }
+
+    /// Digests of the fixture session's objects and of Enc(1), in the order
+    /// params, sk, pk, ct, ek.
+    fn fixture_digests<H: GswHash>() -> Vec<String> {
+        // The fixture file ends in a 32-byte checksum footer (see `persist`).
+        let body = &FIXTURE[..FIXTURE.len() - std::mem::size_of::<Digest>()];
+        let session = Session::from_bytes(body).unwrap();
+        let params = session.params();
+        vec![
+            hex_with::<H, _>(params),
+            hex_with::<H, _>(session.secret_key().unwrap()),
+            hex_with::<H, _>(session.public_key()),
+            hex_with::<H, _>(&trivial_ciphertext(params, 1)),
+            hex_with::<H, _>(session.evaluation_key().unwrap()),
+        ]
+    }
+
+    #[test]
+    fn test_pinned_fixture_digests() {
+        #[cfg(feature = "sha256")]
+        assert_eq!(
+            fixture_digests::<Sha256>(),
+            [
+                "1a109557e1e444ac161234036ba828ad52eb19aa8cebd02d78718896c6cef147",
+                "6efe5bf91e5cc6c17f5a0a4b447339c015b5a438a278bb5bffe63436af28bc3c",
+                "68661b7d30e0c54ba0834d1bd3704598f83db151da53dac7cc7f74abed2d8025",
+                "67a475f066a371ff82aa2ea2d6abeb7810a296491396e4329afa359b560fbf1f",
+                "9e78488ac8c7db2b2b61cce56de04c40e5e8a85f3ae17ef5fb86853e27067e7a",
+            ]
+        );
+        #[cfg(feature = "blake3")]
+        assert_eq!(
+            fixture_digests::<Blake3>(),
+            [
+                "ea3cab12a8d3527f5b40b89fe189ab43e05d5776fecc39eaaaef969d5b1f4aed",
+                "32e5e446c8507542ec7bb17ce3983d65c6725ff8f132ecdf82864000c8ea4be6",
+                "565ba2d1c903d2309b40a223e4966fc3647cf06aa985ae35661bb57c0e466cd7",
+                "090ee03dd481d4fa62dd1c2f5b649aa7375a590367553cf98660e4a57c88b292",
+                "e66aab6951ff81bce00ac6e1fde648471ffb19cc13cf9a9a62422ad236cee87e",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_default_hash_and_hex() {
+        let params = Params::pedagogical();
+        assert_eq!(
+            hash_hex(&params),
+            to_hex(&digest_with::<DefaultHash, _>(&params))
+        );
+        assert_eq!(hash_hex(&params).len(), 64);
+        assert_eq!(to_hex(&[0xab; 32]), "ab".repeat(32));
+    }
+}
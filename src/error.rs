@@ -0,0 +1,554 @@
+//! Error types for fallible GSW operations.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::circuits::{CompatReport, MAX_LOOKUP_INPUT_WIDTH};
+use crate::params::{PlaintextEncoding, PRODUCTION_SECURITY_BITS};
+use crate::passphrase::KdfParams;
+
+/// Errors returned by the fallible parts of the API.
+///
+/// # Empty inputs
+///
+/// Every public function that takes a slice accepts an empty one without
+/// panicking, in one of three ways:
+///
+/// - **Empty output** from maps: the [`gadget`](crate::gadget) functions,
+///   [`homomorphic_xor_const`](crate::integer::homomorphic_xor_const),
+///   `encrypt_bits`, `decrypt_bits`, `DecryptionContext::decrypt_many`,
+///   [`decrypt_linear_parts_all`](crate::bootstrap::decrypt_linear_parts_all),
+///   and [`Circuit::evaluate`](crate::circuits::Circuit::evaluate) on a
+///   circuit without outputs.
+/// - **The identity** of folds: an empty integer is 0, so
+///   [`homomorphic_is_zero`](crate::integer::homomorphic_is_zero) gives Enc(1),
+///   comparisons and zero-width arithmetic treat it as 0, and
+///   [`homomorphic_select_uint`](crate::integer::homomorphic_select_uint)
+///   selects it from no items, and
+///   [`homomorphic_inner_product_bits`](crate::integer::homomorphic_inner_product_bits)
+///   of empty vectors is the zero-width integer;
+///   [`homomorphic_and_many`](crate::gsw::homomorphic_and_many) of no bits is
+///   Enc(1) and [`homomorphic_or_many`](crate::gsw::homomorphic_or_many)
+///   Enc(0); an empty XOR or
+///   linear combination, such as an [`AnfPolynomial`](crate::circuits::AnfPolynomial)
+///   without terms, is Enc(0); an empty monomial is Enc(1); and the one-hot
+///   decoding of no bits is the single line Enc(1).
+/// - **[`GswError::EmptyInput`]** where there is no sensible value:
+///   [`homomorphic_mean`](crate::integer::homomorphic_mean) and
+///   `experimental::multikey::concat_evaluation_keys`.
+///   [`EncryptedVec::reduce`](crate::context::EncryptedVec::reduce) returns
+///   `None` instead, since its gate has no known identity, and decoders fail
+///   on empty bytes as on any truncated data.
+///
+/// Lengths fixed by another argument (a permutation's width, a polynomial's
+/// variable count, a histogram's value width, the shape of explicit
+/// encryption randomness, a bit index) are preconditions that panic when
+/// violated, by empty and non-empty inputs alike; so are the size ranges of
+/// [`EncryptedHistogram`](crate::circuits::EncryptedHistogram) and
+/// [`EncryptedDfa`](crate::circuits::EncryptedDfa), which exclude zero.
+#[derive(Debug)]
+pub enum GswError {
+    /// The operation needs the secret key, which this holder does not have.
+    MissingSecretKey,
+    /// The operation needs an evaluation key, which this holder does not have.
+    MissingEvaluationKey,
+    /// Two components were built for different parameter sets.
+    ParamsMismatch,
+    /// A ciphertext row index is past the last row.
+    RowOutOfRange { row: usize, rows: usize },
+    /// An encrypted integer cannot be resized to `new_width` this way:
+    /// extending to a narrower width, truncating to a wider one, or
+    /// sign-extending a zero-width integer.
+    InvalidWidthChange { width: usize, new_width: usize },
+    /// Encrypted integers combined bit by bit have different widths.
+    WidthMismatch { left: usize, right: usize },
+    /// A public divisor is zero or does not fit in the dividend's width.
+    InvalidDivisor { divisor: u64, width: usize },
+    /// A public modulus is zero or has residues that do not fit in the
+    /// integer's width.
+    InvalidModulus { modulus: u64, width: usize },
+    /// Elements of Z_q combined arithmetically have different moduli.
+    ModulusMismatch { left: u64, right: u64 },
+    /// A ±1 plaintext (see `encrypt_pm1`) is neither 1 nor -1.
+    NotPlusMinusOne { value: i8 },
+    /// Parameters below the production security level were used where
+    /// that needs an explicit opt-in (see `Params::security_assessment`).
+    InsecureParams { estimated_bits: u32 },
+    /// The operands of a gate were encrypted under different plaintext
+    /// encodings.
+    EncodingMismatch {
+        left: PlaintextEncoding,
+        right: PlaintextEncoding,
+    },
+    /// An evaluation key entry index is past the last entry.
+    EntryOutOfRange { index: usize, len: usize },
+    /// An evaluation key entry does not encrypt its secret-key bit.
+    CorruptEntry { index: usize },
+    /// An evaluation key or encryption context was built from a different
+    /// public key.
+    KeyMismatch,
+    /// Flooding noise of 2^flood_bits would leave no multiplicative budget.
+    InvalidFlooding { flood_bits: u32 },
+    /// Serialized data does not start with the expected magic bytes.
+    BadMagic,
+    /// Serialized data was written by an unsupported format version.
+    UnsupportedVersion(u16),
+    /// Serialized data is truncated or internally inconsistent.
+    MalformedData(String),
+    /// A serialized ciphertext entry is not reduced mod q (see
+    /// [`EntryPolicy`](crate::gsw::EntryPolicy)); the first such entry.
+    UnreducedEntry { row: usize, col: usize, value: u64 },
+    /// A ciphertext is not N×N for the parameters: it has `rows` rows, and
+    /// `cols` is the length of the first row that is not N long (N if every
+    /// row is).
+    DimensionMismatch {
+        rows: usize,
+        cols: usize,
+        expected: usize,
+    },
+    /// A product operand has an entry other than 0 or 1, the first such
+    /// entry; see [`ensure_flattened`](crate::gsw::ensure_flattened).
+    NotFlattened { row: usize, col: usize, value: u64 },
+    /// A file's checksum footer does not match its contents: it was
+    /// truncated or altered after it was written.
+    CorruptFile {
+        path: PathBuf,
+        expected: String,
+        found: String,
+    },
+    /// The operation's cancellation flag was set before it finished.
+    Cancelled,
+    /// A ciphertext's noise has reached `limit`, half the decryption margin,
+    /// so the decrypted bit may be wrong.
+    DecryptionSuspect { noise: i64, limit: i64 },
+    /// Passphrase-protected data did not decrypt: the passphrase is wrong or
+    /// the encrypted bytes were altered, which authentication cannot tell
+    /// apart.
+    WrongPassphrase,
+    /// Passphrase-protected data names a key derivation weaker than the
+    /// import's [`KdfPolicy`](crate::passphrase::KdfPolicy) allows.
+    WeakKdf { found: KdfParams },
+    /// A passphrase-protected session was loaded without a passphrase.
+    PassphraseRequired,
+    /// An operation with no meaningful result over nothing was given an
+    /// empty input, named here (see [Empty inputs](GswError#empty-inputs)).
+    EmptyInput(&'static str),
+    /// A circuit was given the wrong number of inputs.
+    InputCount { expected: usize, found: usize },
+    /// A polynomial term names a variable past the last input.
+    VariableOutOfRange { index: usize, inputs: usize },
+    /// A value is too wide to one-hot encode (more than
+    /// `MAX_LOOKUP_INPUT_WIDTH` bits).
+    OneHotTooWide { width: usize },
+    /// A one-hot encoding does not have 2^w lines for an allowed width w.
+    NotOneHotLength { lines: usize },
+    /// An evaluation state was resumed on a different circuit from the one
+    /// it was suspended on.
+    CircuitMismatch,
+    /// A circuit's scheduled depth exceeds what the parameters support; the
+    /// report has the numbers.
+    IncompatibleCircuit(Box<CompatReport>),
+    /// Reading or writing a file failed.
+    Io(std::io::Error),
+}
+
+impl fmt::Display for GswError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GswError::MissingSecretKey => write!(f, "secret key not available"),
+            GswError::MissingEvaluationKey => write!(f, "evaluation key not available"),
+            GswError::ParamsMismatch => write!(f, "components use different parameters"),
+            GswError::RowOutOfRange { row, rows } => {
+                write!(f, "row {} out of range for {} rows", row, rows)
+            }
+            GswError::InvalidWidthChange { width, new_width } => {
+                write!(
+                    f,
+                    "cannot resize a {}-bit integer to {} bits",
+                    width, new_width
+                )
+            }
+            GswError::WidthMismatch { left, right } => {
+                write!(
+                    f,
+                    "cannot combine a {}-bit and a {}-bit integer bit by bit",
+                    left, right
+                )
+            }
+            GswError::InvalidDivisor { divisor, width } => {
+                write!(f, "cannot divide a {}-bit integer by {}", width, divisor)
+            }
+            GswError::InvalidModulus { modulus, width } => {
+                write!(f, "cannot reduce a {}-bit integer mod {}", width, modulus)
+            }
+            GswError::ModulusMismatch { left, right } => {
+                write!(f, "cannot combine elements mod {} and mod {}", left, right)
+            }
+            GswError::NotPlusMinusOne { value } => write!(f, "{} is not ±1", value),
+            GswError::InsecureParams { estimated_bits } => write!(
+                f,
+                "parameters give an estimated {} bits of security, below the {} needed for \
+                 production; call allow_insecure() to use them anyway",
+                estimated_bits, PRODUCTION_SECURITY_BITS
+            ),
+            GswError::EncodingMismatch { left, right } => write!(
+                f,
+                "cannot combine {:?} and {:?} encoded ciphertexts",
+                left, right
+            ),
+            GswError::EntryOutOfRange { index, len } => {
+                write!(f, "entry {} out of range for {} entries", index, len)
+            }
+            GswError::CorruptEntry { index } => {
+                write!(
+                    f,
+                    "evaluation key entry {} does not encrypt its key bit",
+                    index
+                )
+            }
+            GswError::KeyMismatch => {
+                write!(f, "key material was built from a different public key")
+            }
+            GswError::InvalidFlooding { flood_bits } => {
+                write!(f, "flooding with 2^{} noise leaves no budget", flood_bits)
+            }
+            GswError::BadMagic => write!(f, "not a gsw-rs serialized object"),
+            GswError::UnsupportedVersion(v) => write!(f, "unsupported format version {}", v),
+            GswError::MalformedData(msg) => write!(f, "malformed data: {}", msg),
+            GswError::UnreducedEntry { row, col, value } => {
+                write!(
+                    f,
+                    "ciphertext entry ({}, {}) = {} is not below q",
+                    row, col, value
+                )
+            }
+            GswError::DimensionMismatch {
+                rows,
+                cols,
+                expected,
+            } => write!(
+                f,
+                "ciphertext has {} rows of {} entries, expected {}x{}",
+                rows, cols, expected, expected
+            ),
+            GswError::NotFlattened { row, col, value } => write!(
+                f,
+                "ciphertext entry ({}, {}) = {} is not 0 or 1; flatten it first",
+                row, col, value
+            ),
+            GswError::CorruptFile {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} is corrupt: expected {}, found {}",
+                path.display(),
+                expected,
+                found
+            ),
+            GswError::Cancelled => write!(f, "operation cancelled"),
+            GswError::DecryptionSuspect { noise, limit } => write!(
+                f,
+                "decryption noise {} reaches the limit of {}; the bit may be wrong",
+                noise, limit
+            ),
+            GswError::WrongPassphrase => {
+                write!(f, "wrong passphrase, or the protected data was altered")
+            }
+            GswError::WeakKdf { found } => write!(
+                f,
+                "key derivation ({}) is weaker than the configured minimum",
+                found
+            ),
+            GswError::PassphraseRequired => write!(f, "the session is passphrase-protected"),
+            GswError::EmptyInput(what) => write!(f, "{} must not be empty", what),
+            GswError::InputCount { expected, found } => {
+                write!(f, "circuit takes {} inputs, given {}", expected, found)
+            }
+            GswError::VariableOutOfRange { index, inputs } => {
+                write!(f, "variable {} out of range for {} inputs", index, inputs)
+            }
+            GswError::OneHotTooWide { width } => write!(
+                f,
+                "cannot one-hot encode a {}-bit value (at most {} bits)",
+                width, MAX_LOOKUP_INPUT_WIDTH
+            ),
+            GswError::NotOneHotLength { lines } => {
+                write!(f, "{} lines is not a one-hot encoding of 2^w lines", lines)
+            }
+            GswError::CircuitMismatch => {
+                write!(f, "evaluation state belongs to a different circuit")
+            }
+            GswError::IncompatibleCircuit(report) => write!(
+                f,
+                "circuit reaches depth {}, but the parameters support {}",
+                report.deepest, report.depth_budget
+            ),
+            GswError::Io(e) => write!(f, "i/o error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for GswError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GswError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Errors from parameter selection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParamsError {
+    /// No vetted parameter set supports the requested depth and failure rate.
+    DepthUnsupported { depth: u32, max_failure_exp: i32 },
+    /// q is not a power of two at least 4, n or m is zero, or B is negative.
+    InvalidShape,
+    /// Fresh ciphertexts already fail with probability above 2^failure_exp.
+    TargetUnachievable { failure_exp: i32 },
+    /// More than one violation, in argument order (see
+    /// [`Params::validate_all`](crate::params::Params::validate_all)).
+    Multiple(Vec<ParamsViolation>),
+}
+
+/// One reason [`Params::custom`](crate::params::Params::custom) rejects its
+/// arguments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParamsViolation {
+    /// q is not a power of two at least 4.
+    Modulus { q: u64 },
+    /// The LWE dimension n is zero.
+    ZeroDimension,
+    /// The number of public-key rows m is zero.
+    ZeroRows,
+    /// The error bound B is negative.
+    NegativeErrorBound { error_bound: i64 },
+    /// Fresh ciphertexts already fail with probability above 2^failure_exp.
+    TargetUnachievable { failure_exp: i32 },
+}
+
+impl From<Vec<ParamsViolation>> for ParamsError {
+    /// The simple variant for a single violation, `Multiple` for several.
+    fn from(mut violations: Vec<ParamsViolation>) -> Self {
+        if violations.len() != 1 {
+            return ParamsError::Multiple(violations);
+        }
+        match violations.remove(0) {
+            ParamsViolation::TargetUnachievable { failure_exp } => {
+                ParamsError::TargetUnachievable { failure_exp }
+            }
+            _ => ParamsError::InvalidShape,
+        }
+    }
+}
+
+impl fmt::Display for ParamsViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamsViolation::Modulus { q } => {
+                write!(f, "q = {} is not a power of two at least 4", q)
+            }
+            ParamsViolation::ZeroDimension => write!(f, "n must be at least 1"),
+            ParamsViolation::ZeroRows => write!(f, "m must be at least 1"),
+            ParamsViolation::NegativeErrorBound { error_bound } => {
+                write!(f, "B = {} is negative", error_bound)
+            }
+            ParamsViolation::TargetUnachievable { failure_exp } => write!(
+                f,
+                "fresh ciphertexts fail with probability above 2^{}",
+                failure_exp
+            ),
+        }
+    }
+}
+
+/// `problems` as a bulleted list under `heading`, one per line.
+fn bulleted<T: fmt::Display>(
+    f: &mut fmt::Formatter<'_>,
+    heading: &str,
+    problems: impl IntoIterator<Item = T>,
+) -> fmt::Result {
+    write!(f, "{}:", heading)?;
+    for problem in problems {
+        write!(f, "\n  - {}", problem)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for ParamsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamsError::DepthUnsupported {
+                depth,
+                max_failure_exp,
+            } => write!(
+                f,
+                "no vetted parameters support depth {} with failure probability below 2^{}",
+                depth, max_failure_exp
+            ),
+            ParamsError::InvalidShape => write!(
+                f,
+                "q must be a power of two at least 4, n and m at least 1, and B non-negative"
+            ),
+            ParamsError::TargetUnachievable { failure_exp } => write!(
+                f,
+                "fresh ciphertexts fail with probability above 2^{}",
+                failure_exp
+            ),
+            ParamsError::Multiple(violations) => bulleted(
+                f,
+                &format!("{} problems with the parameters", violations.len()),
+                violations,
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParamsError {}
+
+/// One problem in a Bristol Fashion circuit, at a line number counted from 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BristolError {
+    pub line: usize,
+    pub problem: BristolProblem,
+}
+
+/// What is wrong with a line of a Bristol Fashion circuit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BristolProblem {
+    /// A header line is missing or is not a list of counts that agree.
+    BadHeader,
+    /// The header declares a different number of gates than follow it.
+    GateCount { expected: usize, found: usize },
+    /// A gate line has non-numeric fields or the wrong number for its
+    /// operation.
+    BadGate,
+    /// A gate names an operation the parser does not support.
+    UnknownOp(String),
+    /// A gate reads a wire no input or earlier gate drives.
+    UndefinedWire { wire: usize },
+    /// A gate drives a wire that is already driven.
+    RedrivenWire { wire: usize },
+    /// An output wire is never driven.
+    UndrivenOutput { wire: usize },
+}
+
+/// Why [`Circuit::from_bristol`](crate::circuits::Circuit::from_bristol)
+/// rejected its input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CircuitParseError {
+    /// A single problem.
+    Line(BristolError),
+    /// More than one problem, in line order (see
+    /// [`Circuit::validate_all`](crate::circuits::Circuit::validate_all)).
+    Multiple(Vec<BristolError>),
+}
+
+impl From<Vec<BristolError>> for CircuitParseError {
+    /// `Line` for a single problem, `Multiple` for several.
+    fn from(mut errors: Vec<BristolError>) -> Self {
+        if errors.len() == 1 {
+            CircuitParseError::Line(errors.remove(0))
+        } else {
+            CircuitParseError::Multiple(errors)
+        }
+    }
+}
+
+impl fmt::Display for BristolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.problem {
+            BristolProblem::BadHeader => write!(f, "malformed header"),
+            BristolProblem::GateCount { expected, found } => {
+                write!(f, "header declares {} gates, found {}", expected, found)
+            }
+            BristolProblem::BadGate => write!(f, "malformed gate"),
+            BristolProblem::UnknownOp(op) => write!(f, "unknown operation {:?}", op),
+            BristolProblem::UndefinedWire { wire } => {
+                write!(f, "wire {} is read before it is driven", wire)
+            }
+            BristolProblem::RedrivenWire { wire } => write!(f, "wire {} is driven twice", wire),
+            BristolProblem::UndrivenOutput { wire } => {
+                write!(f, "output wire {} is never driven", wire)
+            }
+        }
+    }
+}
+
+impl fmt::Display for CircuitParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitParseError::Line(error) => write!(f, "{}", error),
+            CircuitParseError::Multiple(errors) => bulleted(
+                f,
+                &format!("{} problems in the circuit", errors.len()),
+                errors,
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CircuitParseError {}
+
+/// Why a computation transcript failed to verify. Entry indices count gates
+/// from 0; wire indices count inputs first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TranscriptError {
+    /// The transcript was recorded under different parameters.
+    ParamsMismatch,
+    /// The verifier supplied a different number of inputs.
+    InputCount { expected: usize, found: usize },
+    /// An input does not hash to the recorded value.
+    InputMismatch { input: usize },
+    /// The entry reads a wire that no earlier entry produced.
+    UnknownWire { entry: usize, wire: usize },
+    /// The entry's operand on `wire` differs from the one recorded.
+    OperandMismatch { entry: usize, wire: usize },
+    /// Recomputing the entry gives a different output.
+    OutputMismatch { entry: usize },
+    /// The entry has the wrong number of operands for its op.
+    Malformed { entry: usize },
+}
+
+impl fmt::Display for TranscriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TranscriptError::ParamsMismatch => {
+                write!(f, "transcript was recorded under different parameters")
+            }
+            TranscriptError::InputCount { expected, found } => {
+                write!(f, "expected {} inputs, found {}", expected, found)
+            }
+            TranscriptError::InputMismatch { input } => {
+                write!(f, "input {} does not match the transcript", input)
+            }
+            TranscriptError::UnknownWire { entry, wire } => {
+                write!(f, "entry {} reads wire {} before it exists", entry, wire)
+            }
+            TranscriptError::OperandMismatch { entry, wire } => {
+                write!(
+                    f,
+                    "entry {}: operand on wire {} does not match",
+                    entry, wire
+                )
+            }
+            TranscriptError::OutputMismatch { entry } => {
+                write!(f, "entry {}: recomputed output does not match", entry)
+            }
+            TranscriptError::Malformed { entry } => {
+                write!(f, "entry {} has the wrong number of operands", entry)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TranscriptError {}
+
+impl From<std::io::Error> for GswError {
+    fn from(e: std::io::Error) -> Self {
+        GswError::Io(e)
+    }
+}
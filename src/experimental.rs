@@ -0,0 +1,16 @@
+//! Schemes still under study (the `experimental` feature).
+//!
+//! Nothing here is covered by semantic versioning: items may change or
+//! disappear in any release, and their security has not had the scrutiny the
+//! rest of the crate has. The module is marked deprecated, so each use warns
+//! until the code using it acknowledges that:
+//!
+//! ```ignore
+//! #[allow(deprecated)]
+//! mod joint {
+//!     use gsw_rs::experimental::multikey::{bootstrap_multikey, concat_evaluation_keys};
+//!     // ...
+//! }
+//! ```
+
+pub mod multikey;
@@ -0,0 +1,242 @@
+//! Bootstrapping under several parties' keys, and key pairs sharing a
+//! public matrix.
+//!
+//! Each party bootstraps with its own evaluation key; concatenating the keys
+//! gives one whose entries follow the concatenation of the parties' secret
+//! key layouts, and [`bootstrap_multikey`] reads a decryption row laid out
+//! the same way.
+
+use std::sync::Arc;
+
+#[cfg(feature = "client")]
+use rand::Rng;
+
+use crate::bootstrap::{bootstrap_coefficients, homomorphic_linear_fixed, EvaluationKey};
+use crate::digest::{DefaultHash, GswHash};
+use crate::error::GswError;
+use crate::gsw::Ciphertext;
+#[cfg(feature = "client")]
+use crate::lwe::{self, PublicKey, SecretKey};
+use crate::params::Params;
+
+/// The entries of every part in order, for bootstrapping under the
+/// concatenation of the parts' secret keys with [`bootstrap_multikey`].
+///
+/// Each part must cover whole digit blocks (a multiple of l entries) so
+/// that blocks line up with the concatenated key layout. The combined key
+/// keeps the parts' public-key fingerprint if they all share one, and
+/// otherwise carries a [`DefaultHash`] of the fingerprints in order.
+///
+/// Fails if there are no parts, if their parameters differ, or if a part
+/// does not cover whole blocks.
+pub fn concat_evaluation_keys(parts: &[&EvaluationKey]) -> Result<EvaluationKey, GswError> {
+    let first = parts.first().ok_or(GswError::EmptyInput("parts"))?;
+    let l = first.params().l;
+    for part in parts {
+        if !Params::same(part.params_arc(), first.params_arc()) {
+            return Err(GswError::ParamsMismatch);
+        }
+        if part.len() % l != 0 {
+            return Err(GswError::MalformedData(format!(
+                "evaluation key part has {} entries, not a multiple of l = {}",
+                part.len(),
+                l
+            )));
+        }
+    }
+    let pk_fingerprint = if parts
+        .iter()
+        .all(|part| part.pk_fingerprint() == first.pk_fingerprint())
+    {
+        *first.pk_fingerprint()
+    } else {
+        let fingerprints: Vec<u8> = parts
+            .iter()
+            .flat_map(|part| *part.pk_fingerprint())
+            .collect();
+        DefaultHash::hash(&fingerprints)
+    };
+    Ok(EvaluationKey::from_parts(
+        parts
+            .iter()
+            .flat_map(|part| part.entries().cloned())
+            .collect(),
+        Arc::clone(first.params_arc()),
+        pk_fingerprint,
+    ))
+}
+
+/// [`bootstrap`](crate::bootstrap::bootstrap) with an evaluation key built by [`concat_evaluation_keys`]:
+/// the decryption row is read as the concatenation of the parties' key
+/// layouts, and each digit's coefficient pairs with the entry at the same
+/// position in the combined key.
+///
+/// Fails if the decryption row is missing or its length differs from the
+/// number of entries.
+pub fn bootstrap_multikey(
+    params: &Params,
+    noisy_ct: &Ciphertext,
+    ek: &EvaluationKey,
+) -> Result<Ciphertext, GswError> {
+    let row = params.l - 1;
+    let c_row = noisy_ct.get(row).ok_or(GswError::RowOutOfRange {
+        row,
+        rows: noisy_ct.len(),
+    })?;
+    if c_row.len() != ek.len() {
+        return Err(GswError::MalformedData(format!(
+            "decryption row has {} entries, but the evaluation key has {}",
+            c_row.len(),
+            ek.len()
+        )));
+    }
+    let coefficients = bootstrap_coefficients(params, noisy_ct);
+    Ok(homomorphic_linear_fixed(
+        params,
+        ek.encryptions(),
+        &coefficients,
+    ))
+}
+
+/// A uniform m×n matrix B for [`keygen_with_shared_a`].
+///
+/// For experiments only: keys sharing B are no longer independent LWE
+/// instances (an attacker holding several sees one B with several secrets),
+/// so never use this for keys that protect real data.
+#[cfg(feature = "client")]
+pub fn sample_shared_a<R: Rng>(rng: &mut R, params: &Params) -> Vec<Vec<u64>> {
+    lwe::sample_b(rng, params)
+}
+
+/// [`keygen`](crate::lwe::keygen) reusing a caller-provided B from [`sample_shared_a`], sampling
+/// only t and e, for parameter sweeps and failure-rate experiments that need
+/// thousands of key pairs. The public keys of such pairs differ only in
+/// their b column.
+///
+/// For experiments only; see [`sample_shared_a`]. Fails with
+/// [`GswError::ParamsMismatch`] unless `shared_a` is m×n with entries below q.
+#[cfg(feature = "client")]
+pub fn keygen_with_shared_a<R: Rng>(
+    rng: &mut R,
+    shared_a: &[Vec<u64>],
+    params: &Params,
+) -> Result<(SecretKey, PublicKey), GswError> {
+    if shared_a.len() != params.m
+        || shared_a
+            .iter()
+            .any(|row| row.len() != params.n || row.iter().any(|&x| x >= params.q))
+    {
+        return Err(GswError::ParamsMismatch);
+    }
+    let t = lwe::sample_secret(rng, params);
+    Ok(lwe::key_pair(rng, params, &t, shared_a))
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::bootstrap::{bootstrap, gen_evaluation_key};
+    use crate::gsw::{decrypt, encrypt, gsw_keygen, homomorphic_mult};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn test_keygen_with_shared_a() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(469);
+        let shared = sample_shared_a(&mut rng, &params);
+        let pairs: Vec<_> = (0..2)
+            .map(|_| keygen_with_shared_a(&mut rng, &shared, &params).unwrap())
+            .collect();
+        for (sk, pk) in &pairs {
+            for bit in [0u8, 1u8] {
+                assert_eq!(decrypt(sk, &encrypt(&mut rng, pk, bit)), bit);
+            }
+            let a: Vec<&[u64]> = pk.a.iter().map(|row| &row[1..]).collect();
+            assert_eq!(a, shared.iter().map(Vec::as_slice).collect::<Vec<_>>());
+        }
+        let b = |pk: &PublicKey| pk.a.iter().map(|row| row[0]).collect::<Vec<_>>();
+        assert_ne!(b(&pairs[0].1), b(&pairs[1].1));
+        assert_ne!(pairs[0].0.s, pairs[1].0.s);
+
+        assert!(matches!(
+            keygen_with_shared_a(&mut rng, &shared[1..], &params),
+            Err(GswError::ParamsMismatch)
+        ));
+        let mut unreduced = shared.clone();
+        unreduced[0][0] = params.q;
+        assert!(keygen_with_shared_a(&mut rng, &unreduced, &params).is_err());
+    }
+
+    #[test]
+    fn test_concatenated_halves_bootstrap_like_the_original() {
+        let params = Params::pedagogical();
+        let mut rng = ChaCha20Rng::seed_from_u64(46);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ek = gen_evaluation_key(&mut rng, &sk, &pk);
+
+        // Halves on a digit-block boundary.
+        let split = ek.len() / params.l / 2 * params.l;
+        let part = |range: std::ops::Range<usize>| {
+            EvaluationKey::from_parts(
+                ek.encryptions()[range].to_vec(),
+                Arc::clone(ek.params_arc()),
+                *ek.pk_fingerprint(),
+            )
+        };
+        let (low, high) = (part(0..split), part(split..ek.len()));
+        let joined = concat_evaluation_keys(&[&low, &high]).unwrap();
+        assert_eq!(joined.encryptions(), ek.encryptions());
+        assert_eq!(joined.pk_fingerprint(), ek.pk_fingerprint());
+
+        for bit in [0, 1] {
+            let ct = encrypt(&mut rng, &pk, bit);
+            let noisy = homomorphic_mult(&params, &ct, &ct);
+            assert_eq!(
+                bootstrap_multikey(&params, &noisy, &joined).unwrap(),
+                bootstrap(&params, &noisy, &ek)
+            );
+        }
+    }
+
+    #[test]
+    fn test_concat_rejects_mismatched_parts() {
+        let params = Params::pedagogical();
+        let mut rng = ChaCha20Rng::seed_from_u64(47);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ek = gen_evaluation_key(&mut rng, &sk, &pk);
+        let other = Params::from_raw(1 << 8, 1, 4, 1);
+        let (other_sk, other_pk) = gsw_keygen(&mut rng, &other);
+        let other_ek = gen_evaluation_key(&mut rng, &other_sk, &other_pk);
+
+        assert!(matches!(
+            concat_evaluation_keys(&[]),
+            Err(GswError::EmptyInput("parts"))
+        ));
+        assert!(matches!(
+            concat_evaluation_keys(&[&ek, &other_ek]),
+            Err(GswError::ParamsMismatch)
+        ));
+        let ragged = EvaluationKey::from_parts(
+            ek.encryptions()[1..].to_vec(),
+            Arc::clone(ek.params_arc()),
+            *ek.pk_fingerprint(),
+        );
+        assert!(matches!(
+            concat_evaluation_keys(&[&ek, &ragged]),
+            Err(GswError::MalformedData(_))
+        ));
+
+        // Two parties' keys: a fresh fingerprint, and a decryption row too
+        // short for the combined key.
+        let (sk2, pk2) = gsw_keygen(&mut rng, &params);
+        let ek2 = gen_evaluation_key(&mut rng, &sk2, &pk2);
+        let joined = concat_evaluation_keys(&[&ek, &ek2]).unwrap();
+        assert_eq!(joined.len(), 2 * params.n_expanded);
+        assert_ne!(joined.pk_fingerprint(), ek.pk_fingerprint());
+        assert!(matches!(
+            bootstrap_multikey(&params, &encrypt(&mut rng, &pk, 1), &joined),
+            Err(GswError::MalformedData(_))
+        ));
+    }
+}
@@ -3,7 +3,6 @@
 //! The gadget matrix G enables efficient decomposition of Z_q elements into binary.
 //! G = (1, 2, 4, ..., 2^{l-1}) ⊗ I_{n+1} in Z_q^{N × N} where N = (n+1)*l.
 
-use crate::modular::mod_q;
 use crate::params::Params;
 
 /// BitDecomp: decompose vector v in Z_q^{k} into binary vector in {0,1}^{k*l}.
@@ -13,7 +12,7 @@ pub fn bit_decomp(v: &[u64], params: &Params) -> Vec<u64> {
     let mut result = Vec::with_capacity(v.len() * l);
     for &vi in v {
         for i in 0..l {
-            result.push(((vi >> i) & 1) as u64);
+            result.push((vi >> i) & 1);
         }
     }
     result
@@ -22,22 +21,37 @@ pub fn bit_decomp(v: &[u64], params: &Params) -> Vec<u64> {
 /// BitDecompInverse: reconstruct Z_q element from decomposition.
 /// Input vector of length k*l; each chunk of l elements encodes one Z_q value.
 /// Uses full values (not just & 1) to preserve carries from homomorphic addition.
+///
+/// Any u64 entries are accepted, reduced or not: Σ x_i·2^i is accumulated
+/// mod 2^64, and since q divides 2^64 the result is exactly the sum mod q.
+/// Sums of unflattened ciphertexts can therefore be flattened at any point
+/// without a bound on how large their entries have grown.
 pub fn bit_decomp_inverse(bits: &[u64], params: &Params) -> Vec<u64> {
     let l = params.l;
     let k = bits.len() / l;
-    let q = params.q;
+    let modulus = params.modulus();
     let mut result = Vec::with_capacity(k);
     for j in 0..k {
-        let mut sum: i64 = 0;
+        // Wrapping is exact mod q because q is a power of two.
+        let mut sum: u64 = 0;
         for i in 0..l {
             // Use full value to preserve carries (e.g. 1+1=2 in addition)
-            sum += (bits[j * l + i] as i64) * (1i64 << i);
+            sum = sum.wrapping_add(bits[j * l + i].wrapping_mul(1u64 << i));
         }
-        result.push(mod_q(sum, q));
+        result.push(modulus.reduce(sum).value());
     }
     result
 }
 
+/// Reverse the order of each l-entry block of a decomposed vector, converting
+/// between this crate's LSB-first BitDecomp and an MSB-first one. Its own
+/// inverse.
+pub fn reverse_bit_order<T: Clone>(bits: &[T], params: &Params) -> Vec<T> {
+    bits.chunks(params.l)
+        .flat_map(|block| block.iter().rev().cloned())
+        .collect()
+}
+
 /// Flatten: BitDecomp(BitDecompInverse(x)) - ensures vector is in binary form.
 pub fn flatten(v: &[u64], params: &Params) -> Vec<u64> {
     let decomposed = bit_decomp_inverse(v, params);
@@ -53,8 +67,8 @@ pub fn powers_of_2(b: &[u64], params: &Params) -> Vec<u64> {
     let mut result = Vec::with_capacity(b.len() * l);
     for &bi in b {
         for i in 0..l {
-            let p = 1u64 << i;
-            result.push(mod_q((bi as i64) * (p as i64), q));
+            // Shifting wraps mod 2^64, which q (a power of two) divides.
+            result.push((bi << i) % q);
         }
     }
     result
@@ -64,23 +78,11 @@ pub fn powers_of_2(b: &[u64], params: &Params) -> Vec<u64> {
 /// Here we compute row-wise: for each row of M, BitDecomp gives the row of X.
 /// Output is M with each row replaced by its bit decomposition (binary).
 pub fn g_inverse_matrix(matrix: &[Vec<u64>], params: &Params) -> Vec<Vec<u64>> {
-    matrix
-        .iter()
-        .map(|row| {
-            let decomp = bit_decomp(row, params);
-            decomp
-        })
-        .collect()
+    matrix.iter().map(|row| bit_decomp(row, params)).collect()
 }
 
 /// Apply Flatten to each row of a matrix (in place structure).
 /// Input: N x N matrix. Each row is flattened.
 pub fn flatten_matrix(matrix: &[Vec<u64>], params: &Params) -> Vec<Vec<u64>> {
-    matrix
-        .iter()
-        .map(|row| {
-            let flat = flatten(row, params);
-            flat
-        })
-        .collect()
+    matrix.iter().map(|row| flatten(row, params)).collect()
 }
@@ -1,94 +1,269 @@
 //! GSW (Gentry-Sahai-Waters) homomorphic encryption scheme.
+//!
+//! Key generation, encryption, and decryption live in the `client` submodule,
+//! compiled only with the `client` feature; the homomorphic operations here
+//! need no randomness and no secret key.
+//!
+//! Every operation here returns its ciphertext flattened, with 0/1 entries
+//! (see [`is_canonical_ciphertext`]). A matrix that is not, such as a raw
+//! product, a sum of raw matrices, or one read with
+//! [`EntryPolicy::ReduceModQ`], still encrypts its bit, and the functions
+//! that take a ciphertext out of the homomorphic layer finalize it rather
+//! than fail:
+//!
+//! - [`ciphertext_to_bytes`] and `encrypted_bits_to_bytes` write Flatten(C),
+//!   so what they write always reads back under [`EntryPolicy::Reject`];
+//! - `bootstrap` flattens the row it reads;
+//! - [`ciphertext_digest`] hashes Flatten(C);
+//! - `decrypt` and `extract_lwe` read row l-1 through BitDecompInverse,
+//!   which Flatten leaves unchanged, so they give the same result either way.
+//!
+//! Products are the exception: an unflattened operand multiplies the other's
+//! noise by up to q, so they panic on one in debug builds instead (see
+//! [`ensure_flattened`]).
 
-use rand::Rng;
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::gadget::{bit_decomp, flatten_matrix, powers_of_2};
-use crate::lwe::{keygen, PublicKey, SecretKey};
+use crate::digest::{digest, Digest};
+use crate::error::GswError;
+use crate::gadget::{flatten, flatten_matrix};
+use crate::kernels;
+use crate::lwe::{PublicKey, SecretKey};
 use crate::modular::mod_q;
-use crate::params::Params;
+use crate::params::{Params, PlaintextEncoding};
+use crate::wire::{self, ObjectKind, Reader};
+
+#[cfg(feature = "client")]
+mod client;
+#[cfg(feature = "client")]
+pub(crate) use client::sample_encryption_randomness;
+#[cfg(feature = "client")]
+pub use client::{
+    decrypt, decrypt_encoded, decrypt_pm1, encrypt, encrypt_encoded, encrypt_pm1,
+    encrypt_with_randomness, gsw_keygen, try_decrypt, DecryptionContext, EncryptionContext,
+};
 
 /// GSW ciphertext: an N×N matrix over Z_q.
 pub type Ciphertext = Vec<Vec<u64>>;
 
-/// GSW secret key (same as LWE secret for this construction).
-pub type GswSecretKey = SecretKey;
+/// Read access to the rows of a ciphertext by their index in the full N×N
+/// matrix, implemented by owned ciphertexts and by [`CiphertextView`].
+///
+/// Functions that only read a few rows (decryption, LWE extraction, noise
+/// measurement) take any `AsCiphertextRows`, so callers never copy a
+/// ciphertext to hand over part of it.
+///
+/// Sealed: only this crate's types implement it, so rows can gain methods
+/// without breaking anyone.
+pub trait AsCiphertextRows: sealed::Sealed {
+    /// Row `i` of the full ciphertext, or `None` if it is not present.
+    fn row(&self, i: usize) -> Option<&[u64]>;
 
-/// GSW public key.
-pub type GswPublicKey = PublicKey;
+    /// Indices of the rows present.
+    fn row_range(&self) -> Range<usize>;
+}
+
+mod sealed {
+    use super::{Ciphertext, CiphertextView};
 
-/// Generate GSW key pair.
-pub fn gsw_keygen<R: Rng>(rng: &mut R, params: &Params) -> (GswSecretKey, GswPublicKey) {
-    keygen(rng, params)
+    pub trait Sealed {}
+
+    impl Sealed for Ciphertext {}
+    impl<T: Sealed + ?Sized> Sealed for &T {}
+    impl Sealed for CiphertextView<'_> {}
 }
 
-/// Encrypt a single bit μ ∈ {0, 1}.
-///
-/// C = Flatten(μ*I + BitDecomp(R*A))
-/// where R is a random binary matrix of size N×m.
-pub fn encrypt<R: Rng>(rng: &mut R, pk: &GswPublicKey, bit: u8) -> Ciphertext {
-    let params = pk.params();
-    let n_expanded = params.n_expanded;
-    let m = params.m;
-    let q = params.q;
+impl AsCiphertextRows for Ciphertext {
+    fn row(&self, i: usize) -> Option<&[u64]> {
+        self.get(i).map(Vec::as_slice)
+    }
 
-    // R: N×m binary random matrix
-    let r: Vec<Vec<u64>> = (0..n_expanded)
-        .map(|_| (0..m).map(|_| rng.gen_range(0..=1) as u64).collect())
-        .collect();
+    fn row_range(&self) -> Range<usize> {
+        0..self.len()
+    }
+}
 
-    // RA = R * A (over Z_q)
-    let mut ra = vec![vec![0u64; params.n + 1]; n_expanded];
-    for i in 0..n_expanded {
-        for j in 0..(params.n + 1) {
-            let mut sum: i64 = 0;
-            for k in 0..m {
-                sum += (r[i][k] as i64) * (pk.a[k][j] as i64);
-            }
-            ra[i][j] = mod_q(sum, q);
+impl<T: AsCiphertextRows + ?Sized> AsCiphertextRows for &T {
+    fn row(&self, i: usize) -> Option<&[u64]> {
+        (**self).row(i)
+    }
+
+    fn row_range(&self) -> Range<usize> {
+        (**self).row_range()
+    }
+}
+
+/// A borrowed range of rows of a ciphertext. Rows keep their index in the
+/// full ciphertext, so a view over rows l-1..l decrypts like the whole.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CiphertextView<'a> {
+    rows: &'a [Vec<u64>],
+    start: usize,
+}
+
+impl<'a> CiphertextView<'a> {
+    /// A view of every row of `ct`.
+    pub fn new(ct: &'a Ciphertext) -> Self {
+        Self { rows: ct, start: 0 }
+    }
+
+    /// A view of rows `range` of `ct`.
+    ///
+    /// Fails with [`GswError::RowOutOfRange`] if the range ends past the
+    /// last row.
+    pub fn rows(ct: &'a Ciphertext, range: Range<usize>) -> Result<Self, GswError> {
+        Self::new(ct).select(range)
+    }
+
+    /// Narrow this view to rows `range`, indexed as in the full ciphertext.
+    ///
+    /// Fails with [`GswError::RowOutOfRange`] if the range is not inside
+    /// this view.
+    pub fn select(&self, range: Range<usize>) -> Result<Self, GswError> {
+        let own = self.row_range();
+        if range.start < own.start || range.end > own.end {
+            let row = if range.start < own.start {
+                range.start
+            } else {
+                range.end - 1
+            };
+            return Err(GswError::RowOutOfRange { row, rows: own.end });
         }
+        let end = range.end.max(range.start);
+        Ok(Self {
+            rows: &self.rows[range.start - own.start..end - own.start],
+            start: range.start,
+        })
     }
 
-    // BitDecomp(RA) - each row of RA is decomposed
-    let bit_decomp_ra: Vec<Vec<u64>> = ra
-        .iter()
-        .map(|row| bit_decomp(row, params))
-        .collect();
+    /// Number of rows in the view.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
 
-    // μ*I + BitDecomp(RA)
-    let mut sum = bit_decomp_ra;
-    for i in 0..n_expanded {
-        sum[i][i] = mod_q((sum[i][i] as i64) + (bit as i64), q);
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
     }
+}
 
-    // Flatten each row
-    flatten_matrix(&sum, params)
+impl AsCiphertextRows for CiphertextView<'_> {
+    fn row(&self, i: usize) -> Option<&[u64]> {
+        let i = i.checked_sub(self.start)?;
+        self.rows.get(i).map(Vec::as_slice)
+    }
+
+    fn row_range(&self) -> Range<usize> {
+        self.start..self.start + self.rows.len()
+    }
 }
 
-/// Decrypt a GSW ciphertext.
+/// A ciphertext tagged with the [`PlaintextEncoding`] it was made under.
 ///
-/// Uses C[l-1] · v / v[l-1] as in the reference implementation, where v = PowersOf2(s).
-pub fn decrypt(sk: &GswSecretKey, ct: &Ciphertext) -> u8 {
-    let params = sk.params();
-    let q = params.q;
-    let l = params.l;
-    let n_expanded = params.n_expanded;
+/// Gates on tagged ciphertexts fail with [`GswError::EncodingMismatch`]
+/// rather than combine operands whose bits are read differently, and the
+/// result carries the operands' encoding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncodedCiphertext {
+    pub ct: Ciphertext,
+    pub encoding: PlaintextEncoding,
+}
 
-    let v = powers_of_2(&sk.s, params);
-    let row_idx = l - 1;
+impl EncodedCiphertext {
+    pub fn new(ct: Ciphertext, encoding: PlaintextEncoding) -> Self {
+        Self { ct, encoding }
+    }
+
+    /// Apply a binary gate, failing if the encodings differ.
+    fn combine(
+        &self,
+        other: &Self,
+        gate: impl FnOnce(&Ciphertext, &Ciphertext) -> Ciphertext,
+    ) -> Result<Self, GswError> {
+        if self.encoding != other.encoding {
+            return Err(GswError::EncodingMismatch {
+                left: self.encoding,
+                right: other.encoding,
+            });
+        }
+        Ok(Self::new(gate(&self.ct, &other.ct), self.encoding))
+    }
+
+    /// [`homomorphic_add`] of two ciphertexts of the same encoding.
+    pub fn add(&self, params: &Params, other: &Self) -> Result<Self, GswError> {
+        self.combine(other, |a, b| homomorphic_add(params, a, b))
+    }
 
-    let mut dot: i64 = 0;
-    for j in 0..n_expanded {
-        dot += (ct[row_idx][j] as i64) * (v[j] as i64);
+    /// [`homomorphic_mult`] of two ciphertexts of the same encoding.
+    pub fn mult(&self, params: &Params, other: &Self) -> Result<Self, GswError> {
+        self.combine(other, |a, b| homomorphic_mult(params, a, b))
     }
-    let val = mod_q(dot, q) as i64;
 
-    let scale = v[l - 1] as i64;
-    if scale == 0 {
-        return 0;
+    /// [`homomorphic_nand`] of two ciphertexts of the same encoding.
+    pub fn nand(&self, params: &Params, other: &Self) -> Result<Self, GswError> {
+        self.combine(other, |a, b| homomorphic_nand(params, a, b))
     }
 
-    let msg = ((val as f64) / (scale as f64)).round() as i64;
-    (msg.rem_euclid(2)).abs() as u8
+    /// Homomorphic NOT, keeping the encoding.
+    pub fn not(&self, params: &Params) -> Self {
+        Self::new(homomorphic_not(params, &self.ct), self.encoding)
+    }
+}
+
+/// What [`ciphertext_from_bytes`] does with a raw-encoded entry at or above q.
+///
+/// This crate's writer never produces one, but other producers and older
+/// variable-width encoders can, and such entries wrap incorrectly in the
+/// arithmetic.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EntryPolicy {
+    /// Fail with [`GswError::UnreducedEntry`] naming the first such entry.
+    #[default]
+    Reject,
+    /// Reduce every entry mod q. The result is generally not canonical (see
+    /// [`is_canonical_ciphertext`]), so products reject it until it has been
+    /// passed through [`ensure_flattened`].
+    ReduceModQ,
+}
+
+/// GSW secret key (same as LWE secret for this construction).
+pub type GswSecretKey = SecretKey;
+
+/// GSW public key.
+pub type GswPublicKey = PublicKey;
+
+/// Serialize one ciphertext: header, params, then the ciphertext (see the
+/// `wire` module docs), flattened first if it is not canonical.
+pub fn ciphertext_to_bytes(params: &Params, ct: &Ciphertext) -> Vec<u8> {
+    let mut out = wire::buffer(
+        (wire::HEADER_LEN + wire::PARAMS_LEN) as u64 + wire::ciphertext_len(params, false),
+    );
+    wire::write_header(&mut out, ObjectKind::Ciphertext);
+    wire::write_params(&mut out, params);
+    wire::write_ciphertext(&mut out, &finalized(params, ct));
+    out
+}
+
+/// Deserialize a ciphertext written under `params`, handling entries not
+/// reduced mod q according to `policy`.
+pub fn ciphertext_from_bytes(
+    params: &Params,
+    bytes: &[u8],
+    policy: EntryPolicy,
+) -> Result<Ciphertext, GswError> {
+    let mut reader = Reader::new(bytes);
+    reader.header(ObjectKind::Ciphertext)?;
+    // Only the instance is stored, not the failure target.
+    let stored = reader.params()?;
+    if (stored.q, stored.n, stored.m, stored.error_bound)
+        != (params.q, params.n, params.m, params.error_bound)
+    {
+        return Err(GswError::ParamsMismatch);
+    }
+    let ct = reader.ciphertext_with(params, policy)?;
+    reader.finish()?;
+    Ok(ct)
 }
 
 /// Homomorphic addition: C_+ = C_1 + C_2 (then Flatten).
@@ -107,52 +282,442 @@ pub fn homomorphic_add(params: &Params, ct1: &Ciphertext, ct2: &Ciphertext) -> C
     flatten_matrix(&sum, params)
 }
 
+/// Fails with [`GswError::DimensionMismatch`] unless `ct` is N×N for these
+/// parameters.
+pub(crate) fn check_dimensions(params: &Params, ct: &Ciphertext) -> Result<(), GswError> {
+    let n = params.n_expanded;
+    let cols = ct.iter().map(Vec::len).find(|&len| len != n);
+    if ct.len() != n || cols.is_some() {
+        return Err(GswError::DimensionMismatch {
+            rows: ct.len(),
+            cols: cols.unwrap_or(n),
+            expected: n,
+        });
+    }
+    Ok(())
+}
+
+/// [`homomorphic_add`], failing with [`GswError::DimensionMismatch`] before
+/// reading either operand if it is not N×N.
+pub fn try_homomorphic_add(
+    params: &Params,
+    ct1: &Ciphertext,
+    ct2: &Ciphertext,
+) -> Result<Ciphertext, GswError> {
+    check_dimensions(params, ct1)?;
+    check_dimensions(params, ct2)?;
+    Ok(homomorphic_add(params, ct1, ct2))
+}
+
+/// Homomorphic difference: C_- = C_1 - C_2 (then Flatten).
+///
+/// Encrypts μ_1 - μ_2, which mod 2 is the same bit as the sum, but the noise
+/// terms subtract: when C_2 was derived from C_1 (say C_1 · C_x), the shared
+/// noise cancels instead of doubling. For fresh operands the noise is
+/// e_1 - e_2, the same size as the sum's e_1 + e_2.
+///
+/// Fails if either operand is not N×N.
+pub fn homomorphic_sub(
+    params: &Params,
+    ct1: &Ciphertext,
+    ct2: &Ciphertext,
+) -> Result<Ciphertext, GswError> {
+    check_dimensions(params, ct1)?;
+    check_dimensions(params, ct2)?;
+    Ok(difference(params, ct1, ct2))
+}
+
+/// [`homomorphic_sub`] for operands known to be well formed.
+pub(crate) fn difference(params: &Params, ct1: &Ciphertext, ct2: &Ciphertext) -> Ciphertext {
+    let q = params.q;
+    let diff: Vec<Vec<u64>> = ct1
+        .iter()
+        .zip(ct2)
+        .map(|(row1, row2)| {
+            row1.iter()
+                .zip(row2)
+                .map(|(&a, &b)| a.wrapping_sub(b) % q)
+                .collect()
+        })
+        .collect();
+    flatten_matrix(&diff, params)
+}
+
+/// Whether `ct` is N×N with every entry 0 or 1, as Flatten leaves it.
+///
+/// The noise bounds for products assume canonical operands; a raw matrix with
+/// full-size entries multiplies its partner's noise by up to q.
+pub fn is_canonical_ciphertext(params: &Params, ct: &Ciphertext) -> bool {
+    ct.len() == params.n_expanded
+        && ct.iter().all(|row| row.len() == params.n_expanded)
+        && first_non_binary(ct).is_none()
+}
+
+/// `ct` unchanged if it is already canonical, otherwise Flatten(ct).
+pub fn ensure_flattened(params: &Params, ct: Ciphertext) -> Ciphertext {
+    if first_non_binary(&ct).is_none() {
+        ct
+    } else {
+        flatten_matrix(&ct, params)
+    }
+}
+
+/// `ct`, or Flatten(ct) if it is not canonical, without copying the
+/// canonical case.
+pub(crate) fn finalized<'c>(params: &Params, ct: &'c Ciphertext) -> Cow<'c, Ciphertext> {
+    if first_non_binary(ct).is_none() {
+        Cow::Borrowed(ct)
+    } else {
+        Cow::Owned(flatten_matrix(ct, params))
+    }
+}
+
+/// Digest of Flatten(ct), so that every representation of one ciphertext
+/// hashes alike; [`digest`] hashes a matrix as given.
+pub fn ciphertext_digest(params: &Params, ct: &Ciphertext) -> Digest {
+    digest(&*finalized(params, ct))
+}
+
+fn first_non_binary(ct: &Ciphertext) -> Option<(usize, usize, u64)> {
+    ct.iter()
+        .enumerate()
+        .find_map(|(i, row)| row.iter().position(|&x| x > 1).map(|j| (i, j, row[j])))
+}
+
+/// Panics if either operand of a product is not flattened. Runs in debug
+/// builds, and in release builds with the `checked-arith` feature.
+fn check_flattened(op: &str, ct1: &Ciphertext, ct2: &Ciphertext) {
+    if !cfg!(any(debug_assertions, feature = "checked-arith")) {
+        return;
+    }
+    for (side, ct) in [("left", ct1), ("right", ct2)] {
+        if let Some((i, j, x)) = first_non_binary(ct) {
+            panic!(
+                "{}: {} operand is not flattened (entry ({}, {}) = {}); \
+                 call ensure_flattened first",
+                op, side, i, j, x
+            );
+        }
+    }
+}
+
+/// Product C_1 * C_2 over Z_q, without flattening, with the active kernel.
+fn matrix_product(params: &Params, ct1: &Ciphertext, ct2: &Ciphertext) -> Vec<Vec<u64>> {
+    kernels::matrix_product(ct1, ct2, params.q)
+}
+
 /// Homomorphic multiplication: C_× = Flatten(C_1 * C_2).
 ///
-/// Uses direct matrix multiplication (C implementation approach).
+/// The noise of the product is roughly C_1·e_2 + μ_2·e_1: it grows additively
+/// in the noise of `ct1` but is scaled by the row weight of `ct1` in the noise
+/// of `ct2`. In long chains, pass the accumulated (noisier) ciphertext as `ct1`.
 pub fn homomorphic_mult(params: &Params, ct1: &Ciphertext, ct2: &Ciphertext) -> Ciphertext {
-    let q = params.q;
-    let n_expanded = params.n_expanded;
+    check_flattened("homomorphic_mult", ct1, ct2);
+    flatten_matrix(&matrix_product(params, ct1, ct2), params)
+}
 
-    let mut prod = vec![vec![0u64; n_expanded]; n_expanded];
-    for i in 0..n_expanded {
-        for j in 0..n_expanded {
-            let mut sum: i64 = 0;
-            for k in 0..n_expanded {
-                sum += (ct1[i][k] as i64) * (ct2[k][j] as i64);
-            }
-            prod[i][j] = mod_q(sum, q);
+/// [`homomorphic_mult`], failing before the product if an operand is not
+/// N×N ([`GswError::DimensionMismatch`]) or not flattened
+/// ([`GswError::NotFlattened`]), in every build.
+pub fn try_homomorphic_mult(
+    params: &Params,
+    ct1: &Ciphertext,
+    ct2: &Ciphertext,
+) -> Result<Ciphertext, GswError> {
+    for ct in [ct1, ct2] {
+        check_dimensions(params, ct)?;
+        if let Some((row, col, value)) = first_non_binary(ct) {
+            return Err(GswError::NotFlattened { row, col, value });
         }
     }
-    flatten_matrix(&prod, params)
+    Ok(flatten_matrix(&matrix_product(params, ct1, ct2), params))
+}
+
+/// Rows of a linear pass (scaling, addition, Flatten) between cancellation
+/// checks: about a millisecond of work at Medium (N = 1274).
+pub(crate) const LINEAR_CHECK_ROWS: usize = 64;
+
+/// `Err(Cancelled)` if `cancel` is set.
+pub(crate) fn check_cancel(cancel: &AtomicBool) -> Result<(), GswError> {
+    if cancel.load(Ordering::Relaxed) {
+        return Err(GswError::Cancelled);
+    }
+    Ok(())
+}
+
+/// Flatten(f(i, row i)) for each row of `matrix`, checking `cancel` every
+/// [`LINEAR_CHECK_ROWS`] rows.
+fn flatten_rows_cancellable(
+    params: &Params,
+    matrix: &[Vec<u64>],
+    cancel: &AtomicBool,
+    f: impl Fn(usize, &[u64]) -> Vec<u64>,
+) -> Result<Ciphertext, GswError> {
+    let mut out = Vec::with_capacity(matrix.len());
+    for (start, rows) in (0..)
+        .step_by(LINEAR_CHECK_ROWS)
+        .zip(matrix.chunks(LINEAR_CHECK_ROWS))
+    {
+        check_cancel(cancel)?;
+        out.extend(
+            rows.iter()
+                .enumerate()
+                .map(|(k, row)| flatten(&f(start + k, row), params)),
+        );
+    }
+    Ok(out)
+}
+
+/// [`homomorphic_mult`], checking `cancel` between blocks of rows and
+/// failing with [`GswError::Cancelled`] once it is set. At Medium a check
+/// comes every few milliseconds; the result, if any, is bit-identical to
+/// [`homomorphic_mult`]'s.
+pub fn homomorphic_mult_cancellable(
+    params: &Params,
+    ct1: &Ciphertext,
+    ct2: &Ciphertext,
+    cancel: &AtomicBool,
+) -> Result<Ciphertext, GswError> {
+    check_flattened("homomorphic_mult", ct1, ct2);
+    let prod = kernels::matrix_product_cancellable(ct1, ct2, params.q, cancel)
+        .ok_or(GswError::Cancelled)?;
+    flatten_rows_cancellable(params, &prod, cancel, |_, row| row.to_vec())
+}
+
+/// [`homomorphic_nand`] with cancellation, as [`homomorphic_mult_cancellable`].
+pub(crate) fn homomorphic_nand_cancellable(
+    params: &Params,
+    ct1: &Ciphertext,
+    ct2: &Ciphertext,
+    cancel: &AtomicBool,
+) -> Result<Ciphertext, GswError> {
+    let q = params.q;
+    check_flattened("homomorphic_nand", ct1, ct2);
+    let prod =
+        kernels::matrix_product_cancellable(ct1, ct2, q, cancel).ok_or(GswError::Cancelled)?;
+    flatten_rows_cancellable(params, &prod, cancel, |i, row| {
+        row.iter()
+            .enumerate()
+            .map(|(j, &p)| {
+                let identity = if i == j { 1 } else { 0 };
+                mod_q(identity - (p as i64), q)
+            })
+            .collect()
+    })
+}
+
+/// I - M over Z_q, before flattening: the complement shared by the
+/// negated gates.
+fn complement(params: &Params, m: &[Vec<u64>]) -> Vec<Vec<u64>> {
+    let q = params.q;
+    m.iter()
+        .enumerate()
+        .map(|(i, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(j, &c)| {
+                    let identity = if i == j { 1 } else { 0 };
+                    mod_q(identity - (c as i64), q)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// C_1 + C_2 over Z_q, before flattening.
+fn sum(params: &Params, ct1: &Ciphertext, ct2: &Ciphertext) -> Vec<Vec<u64>> {
+    let q = params.q;
+    ct1.iter()
+        .zip(ct2)
+        .map(|(row1, row2)| {
+            row1.iter()
+                .zip(row2)
+                .map(|(&a, &b)| mod_q(a as i64 + b as i64, q))
+                .collect()
+        })
+        .collect()
+}
+
+/// C_1 + C_2 - C_1 * C_2 over Z_q, before flattening.
+fn disjunction(params: &Params, ct1: &Ciphertext, ct2: &Ciphertext) -> Vec<Vec<u64>> {
+    let q = params.q;
+    let prod = matrix_product(params, ct1, ct2);
+    prod.iter()
+        .zip(sum(params, ct1, ct2))
+        .map(|(p_row, s_row)| {
+            p_row
+                .iter()
+                .zip(s_row)
+                .map(|(&p, s)| mod_q(s as i64 - p as i64, q))
+                .collect()
+        })
+        .collect()
 }
 
 /// Homomorphic NAND: C_nand = Flatten(I - C_1 * C_2).
 pub fn homomorphic_nand(params: &Params, ct1: &Ciphertext, ct2: &Ciphertext) -> Ciphertext {
-    let q = params.q;
-    let n_expanded = params.n_expanded;
+    check_flattened("homomorphic_nand", ct1, ct2);
+    let prod = matrix_product(params, ct1, ct2);
+    flatten_matrix(&complement(params, &prod), params)
+}
 
-    let mut prod = vec![vec![0u64; n_expanded]; n_expanded];
-    for i in 0..n_expanded {
-        for j in 0..n_expanded {
-            let mut sum: i64 = 0;
-            for k in 0..n_expanded {
-                sum += (ct1[i][k] as i64) * (ct2[k][j] as i64);
-            }
-            prod[i][j] = mod_q(sum, q);
+/// Homomorphic OR: C_or = Flatten(C_1 + C_2 - C_1 * C_2).
+///
+/// One multiplication, like [`homomorphic_mult`]. The noise is
+/// e_1 + e_2 minus the product's, so roughly C_1·e_2 + (1 - μ_2)·e_1 + e_2:
+/// one fresh ciphertext's worth above AND, and the same multiplicative depth.
+pub fn homomorphic_or(params: &Params, ct1: &Ciphertext, ct2: &Ciphertext) -> Ciphertext {
+    check_flattened("homomorphic_or", ct1, ct2);
+    flatten_matrix(&disjunction(params, ct1, ct2), params)
+}
+
+/// Homomorphic NOR: C_nor = Flatten(I - (C_1 + C_2 - C_1 * C_2)).
+///
+/// [`homomorphic_or`] complemented in the same pass: one multiplication,
+/// and the noise of OR negated.
+pub fn homomorphic_nor(params: &Params, ct1: &Ciphertext, ct2: &Ciphertext) -> Ciphertext {
+    check_flattened("homomorphic_nor", ct1, ct2);
+    flatten_matrix(&complement(params, &disjunction(params, ct1, ct2)), params)
+}
+
+/// Homomorphic XNOR: C_xnor = Flatten(I - (C_1 + C_2)).
+///
+/// Linear, like [`homomorphic_add`]: no multiplication, and the noise is
+/// -(e_1 + e_2). The plaintext is 1 - μ_1 - μ_2, which decrypts as XNOR
+/// because -1 and 1 agree mod 2.
+pub fn homomorphic_xnor(params: &Params, ct1: &Ciphertext, ct2: &Ciphertext) -> Ciphertext {
+    flatten_matrix(&complement(params, &sum(params, ct1, ct2)), params)
+}
+
+/// Homomorphic multiplexer: `a` where `sel` encrypts 1, `b` where it
+/// encrypts 0, as b + (a - b)·sel.
+///
+/// One multiplication, with the difference as the left operand: the noise is
+/// e_b plus roughly (A - B)·e_sel + μ_sel·(e_a - e_b), additive in the
+/// noise of `a` and `b`. Chained selects should pass the previous result as
+/// `a` or `b` and keep `sel` fresh.
+pub fn homomorphic_mux(
+    params: &Params,
+    sel: &Ciphertext,
+    a: &Ciphertext,
+    b: &Ciphertext,
+) -> Ciphertext {
+    let diff = difference(params, a, b);
+    homomorphic_add(params, b, &homomorphic_mult(params, &diff, sel))
+}
+
+/// Homomorphic majority of three bits: Flatten(AB + BC + CA).
+///
+/// Three multiplications at depth 1. The sum is 0, 1, or 3 on bits, and 3
+/// decrypts as 1, so no correction term is needed; the noise is the sum of
+/// the three products' noise.
+pub fn homomorphic_majority3(
+    params: &Params,
+    a: &Ciphertext,
+    b: &Ciphertext,
+    c: &Ciphertext,
+) -> Ciphertext {
+    let ab = homomorphic_mult(params, a, b);
+    let bc = homomorphic_mult(params, b, c);
+    let ca = homomorphic_mult(params, c, a);
+    homomorphic_add(params, &homomorphic_add(params, &ab, &bc), &ca)
+}
+
+/// Homomorphic AND of any number of bits; trivial Enc(1) for none.
+///
+/// The products are chained left to right with the accumulator as the left
+/// operand, so each step adds the row weight of the accumulator times a
+/// fresh ciphertext's noise: growth is additive in k, at depth k - 1. A
+/// balanced tree would be ⌈log2 k⌉ deep but multiply two accumulated
+/// ciphertexts at every level, scaling the noise by up to N per level, and
+/// runs out of budget long before the chain does.
+pub fn homomorphic_and_many(params: &Params, cts: &[Ciphertext]) -> Ciphertext {
+    match cts {
+        [] => trivial_ciphertext(params, 1),
+        [first, rest @ ..] => rest
+            .iter()
+            .fold(first.clone(), |acc, ct| homomorphic_mult(params, &acc, ct)),
+    }
+}
+
+/// Homomorphic OR of any number of bits; trivial Enc(0) for none.
+///
+/// By De Morgan, the complement of [`homomorphic_and_many`] over the
+/// complemented bits: NOT is linear, so the cost and noise are the AND's.
+pub fn homomorphic_or_many(params: &Params, cts: &[Ciphertext]) -> Ciphertext {
+    match cts {
+        [] => trivial_ciphertext(params, 0),
+        [only] => only.clone(),
+        _ => {
+            let complements: Vec<Ciphertext> =
+                cts.iter().map(|ct| homomorphic_not(params, ct)).collect();
+            homomorphic_not(params, &homomorphic_and_many(params, &complements))
         }
     }
+}
 
-    let mut result = vec![vec![0u64; n_expanded]; n_expanded];
-    for i in 0..n_expanded {
-        for j in 0..n_expanded {
-            let val = if i == j {
-                mod_q(1 - (prod[i][j] as i64), q)
-            } else {
-                mod_q(-(prod[i][j] as i64), q)
-            };
-            result[i][j] = val;
+/// Homomorphic NOT: C_not = Flatten(I - C).
+///
+/// Linear, so the noise is only negated; no multiplication is spent. Prefer
+/// this to `homomorphic_nand(params, ct, ct)`, which computes the same bit
+/// but pays for a multiplication and its noise growth.
+pub fn homomorphic_not(params: &Params, ct: &Ciphertext) -> Ciphertext {
+    flatten_matrix(&complement(params, ct), params)
+}
+
+/// Enc(μ XOR bit) for a public bit: a clone for 0 and NOT, Flatten(I - C),
+/// for 1. Costs no multiplication either way.
+///
+/// Fails if `ct` is not N×N.
+pub fn homomorphic_xor_const_bit(
+    params: &Params,
+    ct: &Ciphertext,
+    bit: u8,
+) -> Result<Ciphertext, GswError> {
+    check_dimensions(params, ct)?;
+    Ok(if bit & 1 == 1 {
+        homomorphic_not(params, ct)
+    } else {
+        ct.clone()
+    })
+}
+
+/// ±1 plaintexts are encrypted as their sign bit μ, the value being
+/// (-1)^μ: 1 as Enc(0) and -1 as Enc(1) (see [`encrypt_pm1`]). This converts
+/// one to the {0, 1} plaintext b with value 2b - 1, so b = 1 - μ: the affine
+/// map Flatten(I - C), a trivial Enc(1) minus the ciphertext, with no
+/// multiplication and only the noise negated.
+pub fn pm1_to_bit(params: &Params, ct: &Ciphertext) -> Ciphertext {
+    homomorphic_not(params, ct)
+}
+
+/// The inverse of [`pm1_to_bit`]: the {0, 1} plaintext b as the ±1 value
+/// 2b - 1, by the same affine map.
+pub fn bit_to_pm1(params: &Params, ct: &Ciphertext) -> Ciphertext {
+    homomorphic_not(params, ct)
+}
+
+/// The product of two ±1-encoded values.
+///
+/// (-1)^μ · (-1)^ν = (-1)^(μ ⊕ ν), so multiplying ±1 values is XOR of their
+/// sign bits: [`homomorphic_add`], which spends no multiplication. Applying
+/// [`homomorphic_mult`] to ±1 ciphertexts would instead compute the AND of
+/// the sign bits, which is -1 only when both values are.
+pub fn homomorphic_mult_pm1(params: &Params, ct1: &Ciphertext, ct2: &Ciphertext) -> Ciphertext {
+    homomorphic_add(params, ct1, ct2)
+}
+
+/// Noiseless encryption of a public bit: the identity matrix for 1, zero for 0.
+///
+/// Needs no key material. Useful as a constant input or padding in circuits;
+/// it carries no secrecy, since anyone can tell the two apart.
+pub fn trivial_ciphertext(params: &Params, bit: u8) -> Ciphertext {
+    let n_expanded = params.n_expanded;
+    let mut ct = vec![vec![0u64; n_expanded]; n_expanded];
+    if bit & 1 == 1 {
+        for (i, row) in ct.iter_mut().enumerate() {
+            row[i] = 1;
         }
     }
-    flatten_matrix(&result, params)
+    ct
 }
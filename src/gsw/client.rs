@@ -0,0 +1,377 @@
+//! Key generation, encryption, and decryption: the operations that need
+//! randomness or the secret key.
+
+use std::path::Path;
+
+use rand::Rng;
+use zeroize::Zeroizing;
+
+use super::{AsCiphertextRows, Ciphertext, EncodedCiphertext, GswPublicKey, GswSecretKey};
+use crate::digest::{digest, DefaultHash, Digest, GswHash};
+use crate::error::GswError;
+use crate::gadget::{bit_decomp, flatten_matrix};
+use crate::lwe::keygen;
+use crate::modular::{mod_q, mod_q_centered};
+use crate::params::{Params, PlaintextEncoding};
+use crate::persist;
+use crate::wire::{self, ObjectKind, Reader};
+
+/// Generate GSW key pair.
+pub fn gsw_keygen<R: Rng>(rng: &mut R, params: &Params) -> (GswSecretKey, GswPublicKey) {
+    keygen(rng, params)
+}
+
+/// Encrypt a single bit μ ∈ {0, 1}.
+///
+/// C = Flatten(μ*I + BitDecomp(R*A))
+/// where R is a random binary matrix of size N×m. The same under every
+/// [`PlaintextEncoding`]; only decoding differs.
+pub fn encrypt<R: Rng>(rng: &mut R, pk: &GswPublicKey, bit: u8) -> Ciphertext {
+    let params = pk.params();
+    encrypt_with_randomness(pk, bit, &sample_encryption_randomness(rng, params))
+}
+
+/// Sample the binary N×m matrix R used by [`encrypt`].
+///
+/// One `fill_bytes` call supplies all N·m bits, taken row-major and least
+/// significant bit first within each byte. The bit order is part of the
+/// known-answer vectors: changing it changes every ciphertext drawn from a
+/// given seed.
+pub(crate) fn sample_encryption_randomness<R: Rng>(rng: &mut R, params: &Params) -> Vec<Vec<u64>> {
+    let m = params.m;
+    let mut bytes = vec![0u8; (params.n_expanded * m).div_ceil(8)];
+    rng.fill_bytes(&mut bytes);
+    (0..params.n_expanded)
+        .map(|i| {
+            (i * m..(i + 1) * m)
+                .map(|idx| ((bytes[idx / 8] >> (idx % 8)) & 1) as u64)
+                .collect()
+        })
+        .collect()
+}
+
+/// Encrypt with caller-supplied randomness: `r` must be a binary N×m matrix.
+///
+/// Deterministic in its inputs, so anyone who can reproduce R can recompute
+/// the ciphertext.
+pub fn encrypt_with_randomness(pk: &GswPublicKey, bit: u8, r: &[Vec<u64>]) -> Ciphertext {
+    let params = pk.params();
+    let n_expanded = params.n_expanded;
+    let q = params.q;
+    assert!(
+        r.len() == n_expanded
+            && r.iter()
+                .all(|row| row.len() == params.m && row.iter().all(|&x| x <= 1)),
+        "R must be a binary N×m matrix"
+    );
+
+    // RA = R * A (over Z_q)
+    let mut ra = vec![vec![0u64; params.n + 1]; n_expanded];
+    for (r_row, ra_row) in r.iter().zip(ra.iter_mut()) {
+        for (j, entry) in ra_row.iter_mut().enumerate() {
+            // Wrapping is exact mod q because q is a power of two.
+            let mut sum: u64 = 0;
+            for (&r_ik, a_row) in r_row.iter().zip(pk.a.iter()) {
+                sum = sum.wrapping_add(r_ik * a_row[j]);
+            }
+            *entry = sum % q;
+        }
+    }
+
+    // BitDecomp(RA) - each row of RA is decomposed
+    let bit_decomp_ra: Vec<Vec<u64>> = ra.iter().map(|row| bit_decomp(row, params)).collect();
+
+    // μ*I + BitDecomp(RA)
+    let mut sum = bit_decomp_ra;
+    for (i, row) in sum.iter_mut().enumerate() {
+        row[i] = mod_q((row[i] as i64) + (bit as i64), q);
+    }
+
+    // Flatten each row
+    flatten_matrix(&sum, params)
+}
+
+/// Decrypt a GSW ciphertext.
+///
+/// Uses C[l-1] · v / v[l-1] as in the reference implementation, where v = PowersOf2(s).
+/// Builds a [`DecryptionContext`] per call; keep one around for batches.
+pub fn decrypt<C: AsCiphertextRows + ?Sized>(sk: &GswSecretKey, ct: &C) -> u8 {
+    DecryptionContext::new(sk).decrypt(ct)
+}
+
+/// [`decrypt`], failing instead of panicking or misreading when the row it
+/// reads is missing ([`GswError::RowOutOfRange`]) or not N entries long
+/// ([`GswError::DimensionMismatch`]).
+pub fn try_decrypt<C: AsCiphertextRows + ?Sized>(
+    sk: &GswSecretKey,
+    ct: &C,
+) -> Result<u8, GswError> {
+    let params = sk.params();
+    let n = params.n_expanded;
+    let row = ct.row(params.l - 1).ok_or(GswError::RowOutOfRange {
+        row: params.l - 1,
+        rows: ct.row_range().end,
+    })?;
+    if row.len() != n {
+        return Err(GswError::DimensionMismatch {
+            rows: ct.row_range().len(),
+            cols: row.len(),
+            expected: n,
+        });
+    }
+    Ok(DecryptionContext::new(sk).decrypt(ct))
+}
+
+/// Encrypt a ±1 plaintext as its sign bit: Enc(0) for 1 and Enc(1) for -1
+/// (see [`pm1_to_bit`](super::pm1_to_bit) for the encoding).
+///
+/// Fails if `value` is neither 1 nor -1.
+pub fn encrypt_pm1<R: Rng>(
+    rng: &mut R,
+    pk: &GswPublicKey,
+    value: i8,
+) -> Result<Ciphertext, GswError> {
+    match value {
+        1 => Ok(encrypt(rng, pk, 0)),
+        -1 => Ok(encrypt(rng, pk, 1)),
+        _ => Err(GswError::NotPlusMinusOne { value }),
+    }
+}
+
+/// Encrypt a bit and record the encoding of `pk`'s parameters with it.
+pub fn encrypt_encoded<R: Rng>(rng: &mut R, pk: &GswPublicKey, bit: u8) -> EncodedCiphertext {
+    EncodedCiphertext::new(encrypt(rng, pk, bit), pk.params().encoding)
+}
+
+/// Decrypt under the encoding the ciphertext records.
+pub fn decrypt_encoded(sk: &GswSecretKey, ct: &EncodedCiphertext) -> u8 {
+    DecryptionContext::new(sk).decrypt_encoded(ct)
+}
+
+/// Decrypt a ±1-encoded ciphertext: 1 - 2μ for the sign bit μ.
+pub fn decrypt_pm1(sk: &GswSecretKey, ct: &Ciphertext) -> i8 {
+    1 - 2 * decrypt(sk, ct) as i8
+}
+
+/// A secret key with v = PowersOf2(s) precomputed, for decrypting many
+/// ciphertexts without rebuilding v each time.
+#[derive(Clone, Debug)]
+pub struct DecryptionContext {
+    v: Zeroizing<Vec<u64>>,
+    scale: u64,
+    row: usize,
+    q: u64,
+    encoding: PlaintextEncoding,
+}
+
+impl DecryptionContext {
+    pub fn new(sk: &GswSecretKey) -> Self {
+        let params = sk.params();
+        Self {
+            v: sk.decryption_vector(),
+            scale: sk.decryption_scale(),
+            row: params.l - 1,
+            q: params.q,
+            encoding: params.encoding,
+        }
+    }
+
+    /// C[l-1] · v mod q.
+    ///
+    /// Panics if `ct` does not have row l-1.
+    fn phase<C: AsCiphertextRows + ?Sized>(&self, ct: &C) -> u64 {
+        let row = ct
+            .row(self.row)
+            .expect("ciphertext is missing the decryption row l-1");
+        let mut dot: u64 = 0;
+        for (&c, &v) in row.iter().zip(self.v.iter()) {
+            dot = dot.wrapping_add(c.wrapping_mul(v));
+        }
+        dot % self.q
+    }
+
+    /// Decrypt a ciphertext, or any view containing row l-1, without
+    /// allocating, under the key's [`PlaintextEncoding`].
+    pub fn decrypt<C: AsCiphertextRows + ?Sized>(&self, ct: &C) -> u8 {
+        self.decode(self.phase(ct), self.encoding)
+    }
+
+    /// Decrypt under the encoding the ciphertext records, whatever the key's.
+    pub fn decrypt_encoded(&self, ct: &EncodedCiphertext) -> u8 {
+        self.decode(self.phase(&ct.ct), ct.encoding)
+    }
+
+    fn decode(&self, phase: u64, encoding: PlaintextEncoding) -> u8 {
+        match encoding {
+            PlaintextEncoding::LsbOfScale => {
+                let scale = self.scale;
+                if scale == 0 {
+                    return 0;
+                }
+                // round(phase / scale), in integers so that phases above 2^53
+                // round exactly and every platform agrees.
+                let msg = (phase as u128 + (scale / 2) as u128) / scale as u128;
+                (msg % 2) as u8
+            }
+            PlaintextEncoding::MsbThreshold => {
+                (mod_q_centered(phase as i64, self.q).unsigned_abs() >= self.q / 4) as u8
+            }
+        }
+    }
+
+    /// Decrypt every ciphertext, in parallel with the `rayon` feature once
+    /// the batch reaches [`parallel_threshold`](crate::kernels::parallel_threshold) entries.
+    pub fn decrypt_many<C: AsCiphertextRows + Sync>(&self, cts: &[C]) -> Vec<u8> {
+        #[cfg(feature = "rayon")]
+        if crate::kernels::runs_in_parallel(cts.len() * self.v.len()) {
+            use rayon::prelude::*;
+            return cts.par_iter().map(|ct| self.decrypt(ct)).collect();
+        }
+        cts.iter().map(|ct| self.decrypt(ct)).collect()
+    }
+
+    /// Error in the decryption row: the phase minus μ·v[l-1], centered in
+    /// (-q/2, q/2], where μ is the decrypted bit. Decryption is correct while
+    /// its magnitude stays below q/4.
+    pub fn measure_noise<C: AsCiphertextRows + ?Sized>(&self, ct: &C) -> i64 {
+        let expected = self.decrypt(ct) as u64 * self.scale;
+        let diff = self.phase(ct).wrapping_sub(expected) % self.q;
+        mod_q_centered(diff as i64, self.q)
+    }
+}
+
+/// A public key prepared for encrypting many bits, and persistable so a
+/// client can build it once and reload it at startup.
+///
+/// Holds A as one contiguous row-major buffer and encrypts straight to the
+/// binary rows BitDecomp(R·A + μ·G), skipping the flatten [`encrypt`]
+/// performs. Draws the same randomness as [`encrypt`], so for a given RNG
+/// state both produce the same ciphertext.
+#[derive(Clone, Debug)]
+pub struct EncryptionContext {
+    params: Params,
+    pk_fingerprint: Digest,
+    /// A, m rows of n + 1 entries.
+    a: Vec<u64>,
+}
+
+impl EncryptionContext {
+    /// Fails with [`GswError::ParamsMismatch`] if `params` are not the ones
+    /// `pk` was generated under.
+    pub fn build(pk: &GswPublicKey, params: &Params) -> Result<Self, GswError> {
+        if digest(params) != digest(pk.params()) {
+            return Err(GswError::ParamsMismatch);
+        }
+        Ok(Self {
+            params: params.clone(),
+            pk_fingerprint: digest(pk),
+            a: pk.a.concat(),
+        })
+    }
+
+    pub fn params(&self) -> &Params {
+        &self.params
+    }
+
+    /// Encrypt a bit μ ∈ {0, 1}; rows are built in parallel with the
+    /// `rayon` feature once the ciphertext reaches
+    /// [`parallel_threshold`](crate::kernels::parallel_threshold) entries.
+    pub fn encrypt<R: Rng>(&self, rng: &mut R, bit: u8) -> Ciphertext {
+        let params = &self.params;
+        let r = sample_encryption_randomness(rng, params);
+        #[cfg(feature = "rayon")]
+        if crate::kernels::runs_in_parallel(params.n_expanded * params.n_expanded) {
+            use rayon::prelude::*;
+            return r
+                .par_iter()
+                .enumerate()
+                .map(|(i, r_row)| self.row(i, r_row, bit))
+                .collect();
+        }
+        r.iter()
+            .enumerate()
+            .map(|(i, r_row)| self.row(i, r_row, bit))
+            .collect()
+    }
+
+    /// Row i: BitDecomp(r·A + μ·2^(i mod l)·e_(i/l)), which is what
+    /// flattening row i of μ·I + BitDecomp(R·A) yields.
+    fn row(&self, i: usize, r_row: &[u64], bit: u8) -> Vec<u64> {
+        let params = &self.params;
+        let cols = params.n + 1;
+        let mut ra = vec![0u64; cols];
+        for (_, a_row) in r_row
+            .iter()
+            .zip(self.a.chunks_exact(cols))
+            .filter(|(&r_ik, _)| r_ik == 1)
+        {
+            for (sum, &a) in ra.iter_mut().zip(a_row) {
+                *sum = sum.wrapping_add(a);
+            }
+        }
+        // Wrapping is exact mod q because q is a power of two.
+        let col = i / params.l;
+        ra[col] = ra[col].wrapping_add((1u64 << (i % params.l)) * bit as u64);
+        for x in &mut ra {
+            *x %= params.q;
+        }
+        bit_decomp(&ra, params)
+    }
+
+    /// Versioned binary encoding: params, the public key's fingerprint, A,
+    /// and an integrity hash over all of it.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = wire::buffer(wire::encryption_context_len(&self.params));
+        wire::write_header(&mut out, ObjectKind::EncryptionContext);
+        wire::write_params(&mut out, &self.params);
+        out.extend_from_slice(&self.pk_fingerprint);
+        wire::write_public_matrix(&mut out, &self.params, &self.a);
+        let integrity = DefaultHash::hash(&out);
+        out.extend_from_slice(&integrity);
+        out
+    }
+
+    /// Deserialize a context for `pk`, failing with
+    /// [`GswError::ParamsMismatch`] or [`GswError::KeyMismatch`] if it was
+    /// built for other params or another key.
+    pub fn from_bytes(bytes: &[u8], pk: &GswPublicKey) -> Result<Self, GswError> {
+        let body_len = bytes
+            .len()
+            .checked_sub(32)
+            .ok_or_else(|| GswError::MalformedData("unexpected end of data".into()))?;
+        let (body, integrity) = bytes.split_at(body_len);
+        let mut reader = Reader::new(body);
+        reader.header(ObjectKind::EncryptionContext)?;
+        if DefaultHash::hash(body) != integrity {
+            return Err(GswError::MalformedData(
+                "encryption context integrity hash mismatch".into(),
+            ));
+        }
+        let params = reader.params()?;
+        if digest(&params) != digest(pk.params()) {
+            return Err(GswError::ParamsMismatch);
+        }
+        if reader.hash()? != digest(pk) {
+            return Err(GswError::KeyMismatch);
+        }
+        let a = reader.public_matrix(&params)?;
+        reader.finish()?;
+        Ok(Self {
+            params,
+            pk_fingerprint: digest(pk),
+            a,
+        })
+    }
+
+    /// Write [`to_bytes`](Self::to_bytes) and a checksum footer to `path`,
+    /// atomically replacing any existing file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), GswError> {
+        persist::write_atomic(path.as_ref(), &self.to_bytes(), false)
+    }
+
+    /// Read a file written by [`save`](Self::save) for `pk`, failing with
+    /// [`GswError::CorruptFile`] if its checksum does not match.
+    pub fn load<P: AsRef<Path>>(path: P, pk: &GswPublicKey) -> Result<Self, GswError> {
+        Self::from_bytes(&persist::read_checked(path.as_ref())?, pk)
+    }
+}
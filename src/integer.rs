@@ -0,0 +1,1913 @@
+//! Predicates and arithmetic on encrypted integers.
+//!
+//! Integers are [`EncryptedBits`] in little-endian order (bit 0 first); the
+//! fixed-width wrapper [`EncryptedUint`] carries the width with the bits.
+//! Either travels as one framed blob ([`encrypted_bits_to_bytes`],
+//! [`EncryptedUint::to_bytes`]) recording the width, bit order, and
+//! parameters, so nothing about the layout has to be agreed out of band.
+//!
+//! GSW noise after a multiplication is roughly the row weight of the left
+//! operand times the noise of the right one, plus the left operand's noise.
+//! The circuits here keep the accumulated value on the left and a fresh or
+//! shallow operand on the right, so their noise grows additively with width.
+
+#[cfg(all(feature = "client", feature = "bigint"))]
+use num_bigint::BigUint;
+#[cfg(feature = "client")]
+use rand::Rng;
+
+use crate::circuits::{homomorphic_add_const, homomorphic_sum, EncryptedBits};
+use crate::digest::digest;
+use crate::error::GswError;
+use crate::gsw::{
+    difference, finalized, homomorphic_add, homomorphic_and_many, homomorphic_mult,
+    homomorphic_mux, homomorphic_not, homomorphic_or, trivial_ciphertext, Ciphertext,
+};
+#[cfg(feature = "client")]
+use crate::gsw::{encrypt, DecryptionContext, GswPublicKey, GswSecretKey};
+use crate::params::Params;
+use crate::wire::{self, ObjectKind, Reader};
+
+/// Fixed-width encrypted unsigned integer, bit 0 first.
+///
+/// Operands of different widths are treated in two ways. The arithmetic and
+/// comparison functions ([`homomorphic_add_uint`],
+/// [`homomorphic_compare_uint`], and the rest) read them as numbers and
+/// zero-extend the narrower one, which leaves its value unchanged. The
+/// bitwise methods ([`and`](Self::and), [`or`](Self::or), [`xor`](Self::xor))
+/// read them as bit vectors lane by lane and fail with
+/// [`GswError::WidthMismatch`], since there a width mismatch is more likely a
+/// misaligned mask than a small number.
+#[derive(Clone, Debug)]
+pub struct EncryptedUint {
+    bits: EncryptedBits,
+}
+
+impl EncryptedUint {
+    /// Wrap existing bit ciphertexts (LSB first); the width is `bits.len()`.
+    pub fn from_bits(bits: EncryptedBits) -> Self {
+        Self { bits }
+    }
+
+    /// The integer 0 or 1 held by one bit ciphertext, in `width` bits: the bit
+    /// at position 0 and trivial zeros above. Panics if `width` is zero.
+    pub fn from_bit(bit: Ciphertext, width: usize, params: &Params) -> Self {
+        assert!(width > 0, "width must be at least 1");
+        let mut bits = vec![bit];
+        bits.resize(width, trivial_ciphertext(params, 0));
+        Self { bits }
+    }
+
+    pub fn width(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub fn bits(&self) -> &[Ciphertext] {
+        &self.bits
+    }
+
+    pub fn into_bits(self) -> EncryptedBits {
+        self.bits
+    }
+
+    /// Widen to `new_width` bits by appending trivial zeros.
+    ///
+    /// Fails if `new_width` is below the current width.
+    pub fn zero_extend(&self, new_width: usize, params: &Params) -> Result<Self, GswError> {
+        self.check_extend(new_width)?;
+        let mut bits = self.bits.clone();
+        bits.resize(new_width, trivial_ciphertext(params, 0));
+        Ok(Self { bits })
+    }
+
+    /// Widen to `new_width` bits by repeating the top bit, preserving the
+    /// two's-complement value. The copies are clones: no homomorphic work.
+    ///
+    /// Fails if `new_width` is below the current width, or if the integer has
+    /// no bits to take a sign from.
+    pub fn sign_extend(&self, new_width: usize) -> Result<Self, GswError> {
+        self.check_extend(new_width)?;
+        let sign = self.bits.last().ok_or(GswError::InvalidWidthChange {
+            width: 0,
+            new_width,
+        })?;
+        let mut bits = self.bits.clone();
+        bits.resize(new_width, sign.clone());
+        Ok(Self { bits })
+    }
+
+    /// The low `new_width` bits, i.e. the value mod 2^new_width.
+    ///
+    /// Fails if `new_width` exceeds the current width.
+    pub fn truncate(&self, new_width: usize) -> Result<Self, GswError> {
+        if new_width > self.width() {
+            return Err(GswError::InvalidWidthChange {
+                width: self.width(),
+                new_width,
+            });
+        }
+        Ok(Self {
+            bits: self.bits[..new_width].to_vec(),
+        })
+    }
+
+    /// Lane-wise AND: one multiplication per bit, at depth 1.
+    ///
+    /// Fails with [`GswError::WidthMismatch`] unless the widths are equal.
+    pub fn and(&self, params: &Params, other: &Self) -> Result<Self, GswError> {
+        self.lanes(other, |a, b| homomorphic_mult(params, a, b))
+    }
+
+    /// Lane-wise OR ([`homomorphic_or`]): one multiplication per bit.
+    ///
+    /// Fails with [`GswError::WidthMismatch`] unless the widths are equal.
+    pub fn or(&self, params: &Params, other: &Self) -> Result<Self, GswError> {
+        self.lanes(other, |a, b| homomorphic_or(params, a, b))
+    }
+
+    /// Lane-wise XOR: linear, no multiplication.
+    ///
+    /// Fails with [`GswError::WidthMismatch`] unless the widths are equal.
+    pub fn xor(&self, params: &Params, other: &Self) -> Result<Self, GswError> {
+        self.lanes(other, |a, b| homomorphic_add(params, a, b))
+    }
+
+    /// Lane-wise NOT, i.e. 2^width - 1 - x: linear, no multiplication.
+    pub fn not(&self, params: &Params) -> Self {
+        Self::from_bits(
+            self.bits
+                .iter()
+                .map(|bit| homomorphic_not(params, bit))
+                .collect(),
+        )
+    }
+
+    fn lanes(
+        &self,
+        other: &Self,
+        gate: impl Fn(&Ciphertext, &Ciphertext) -> Ciphertext,
+    ) -> Result<Self, GswError> {
+        if self.width() != other.width() {
+            return Err(GswError::WidthMismatch {
+                left: self.width(),
+                right: other.width(),
+            });
+        }
+        Ok(Self::from_bits(
+            self.bits
+                .iter()
+                .zip(&other.bits)
+                .map(|(a, b)| gate(a, b))
+                .collect(),
+        ))
+    }
+
+    /// Serialize as one framed blob; see [`encrypted_bits_to_bytes`].
+    pub fn to_bytes(&self, params: &Params) -> Vec<u8> {
+        encrypted_bits_to_bytes(params, &self.bits)
+    }
+
+    /// Deserialize a blob written by [`to_bytes`](Self::to_bytes) under
+    /// `params`; see [`encrypted_bits_from_bytes`].
+    pub fn from_bytes(params: &Params, bytes: &[u8]) -> Result<Self, GswError> {
+        encrypted_bits_from_bytes(params, bytes).map(Self::from_bits)
+    }
+
+    fn check_extend(&self, new_width: usize) -> Result<(), GswError> {
+        if new_width < self.width() {
+            return Err(GswError::InvalidWidthChange {
+                width: self.width(),
+                new_width,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Order of the bits in a framed blob.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum BitOrder {
+    /// Bit 0 first, as [`EncryptedBits`] holds them; what this crate writes.
+    LsbFirst = 0,
+    /// The top bit first, as some other producers write them.
+    MsbFirst = 1,
+}
+
+impl BitOrder {
+    pub(crate) fn from_code(code: u8) -> Option<Self> {
+        [BitOrder::LsbFirst, BitOrder::MsbFirst]
+            .into_iter()
+            .find(|order| *order as u8 == code)
+    }
+}
+
+/// Serialize `bits`, encrypted under `params`, as one framed blob: header,
+/// params fingerprint, width, bit order, then each ciphertext with its
+/// params (see the `wire` module for the layout). Ciphertexts that are not
+/// canonical are flattened first.
+pub fn encrypted_bits_to_bytes(params: &Params, bits: &[Ciphertext]) -> Vec<u8> {
+    let record = wire::PARAMS_LEN as u64 + wire::ciphertext_len(params, false);
+    let frame = (wire::HEADER_LEN + ObjectKind::EncryptedBits.metadata_len()) as u64;
+    let mut out = wire::buffer(frame + bits.len() as u64 * record);
+    wire::write_header(&mut out, ObjectKind::EncryptedBits);
+    out.extend_from_slice(&digest(params));
+    out.extend_from_slice(&(bits.len() as u64).to_le_bytes());
+    out.push(BitOrder::LsbFirst as u8);
+    for ct in bits {
+        wire::write_params(&mut out, params);
+        wire::write_ciphertext(&mut out, &finalized(params, ct));
+    }
+    out
+}
+
+/// Deserialize a blob written by [`encrypted_bits_to_bytes`], returning the
+/// bits LSB first whatever order the blob declares.
+///
+/// Fails with [`GswError::ParamsMismatch`] if the blob or any ciphertext in
+/// it was written under other parameters than `params`, and with
+/// [`GswError::MalformedData`] if the declared width is not the number of
+/// ciphertexts it holds.
+pub fn encrypted_bits_from_bytes(params: &Params, bytes: &[u8]) -> Result<EncryptedBits, GswError> {
+    let mut reader = Reader::new(bytes);
+    reader.header(ObjectKind::EncryptedBits)?;
+    let fingerprint = reader.hash()?;
+    let (width, order) = reader.bits_frame()?;
+    if fingerprint != digest(params) {
+        return Err(GswError::ParamsMismatch);
+    }
+    let mut bits = Vec::new();
+    while !reader.is_at_end() {
+        if digest(&reader.params()?) != fingerprint {
+            return Err(GswError::ParamsMismatch);
+        }
+        bits.push(reader.ciphertext(params)?);
+    }
+    if bits.len() != width {
+        return Err(GswError::MalformedData(format!(
+            "declared width {}, found {} ciphertexts",
+            width,
+            bits.len()
+        )));
+    }
+    if order == BitOrder::MsbFirst {
+        bits.reverse();
+    }
+    Ok(bits)
+}
+
+/// An [`EncryptedUint`] with the parameters it was encrypted under, which
+/// serde serializes as its framed blob (the `serde` feature).
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, Debug)]
+pub struct FramedUint<'a> {
+    pub params: &'a Params,
+    pub value: &'a EncryptedUint,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for FramedUint<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.value.to_bytes(self.params))
+    }
+}
+
+/// Deserializes a [`FramedUint`] back to the [`EncryptedUint`], checking the
+/// blob against these parameters as [`EncryptedUint::from_bytes`] does.
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, Debug)]
+pub struct FramedUintSeed<'a>(pub &'a Params);
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::DeserializeSeed<'de> for FramedUintSeed<'_> {
+    type Value = EncryptedUint;
+
+    fn deserialize<D: serde::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<EncryptedUint, D::Error> {
+        let bytes = <Vec<u8> as serde::Deserialize>::deserialize(deserializer)?;
+        EncryptedUint::from_bytes(self.0, &bytes).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Zero-extend the narrower of two operands so both have the wider width.
+fn match_widths(
+    params: &Params,
+    a: &EncryptedUint,
+    b: &EncryptedUint,
+) -> (EncryptedUint, EncryptedUint) {
+    let width = a.width().max(b.width());
+    let widen = |x: &EncryptedUint| {
+        x.zero_extend(width, params)
+            .expect("width is at least the operand's")
+    };
+    (widen(a), widen(b))
+}
+
+/// Encrypt the low `width` bits of `value` (width at most 64).
+#[cfg(feature = "client")]
+pub fn encrypt_uint<R: Rng>(
+    rng: &mut R,
+    pk: &GswPublicKey,
+    value: u64,
+    width: usize,
+) -> EncryptedUint {
+    assert!(width <= 64, "width must be at most 64");
+    encrypt_bits(rng, pk, width, |i| ((value >> i) & 1) as u8)
+}
+
+/// Encrypt `value` in `width` bits, least significant first as for
+/// [`encrypt_uint`] (the `bigint` feature).
+///
+/// Panics if `value` needs more than `width` bits.
+#[cfg(all(feature = "client", feature = "bigint"))]
+pub fn encrypt_uint_big<R: Rng>(
+    rng: &mut R,
+    pk: &GswPublicKey,
+    value: &BigUint,
+    width: usize,
+) -> EncryptedUint {
+    assert!(
+        value.bits() <= width as u64,
+        "value needs {} bits, more than width {}",
+        value.bits(),
+        width
+    );
+    encrypt_bits(rng, pk, width, |i| value.bit(i as u64) as u8)
+}
+
+/// Enc(bit(i)) for i in 0..width, the least significant bit first.
+#[cfg(feature = "client")]
+fn encrypt_bits<R: Rng>(
+    rng: &mut R,
+    pk: &GswPublicKey,
+    width: usize,
+    bit: impl Fn(usize) -> u8,
+) -> EncryptedUint {
+    EncryptedUint::from_bits((0..width).map(|i| encrypt(rng, pk, bit(i))).collect())
+}
+
+/// Decrypt every bit and reassemble the integer.
+#[cfg(feature = "client")]
+pub fn decrypt_uint(sk: &GswSecretKey, x: &EncryptedUint) -> u64 {
+    assert!(x.width() <= 64, "width must be at most 64");
+    decrypt_bits(sk, x).fold(0, |acc, (i, bit)| acc | (bit as u64) << i)
+}
+
+/// [`decrypt_uint`] for widths up to 128.
+#[cfg(feature = "client")]
+pub fn decrypt_uint_u128(sk: &GswSecretKey, x: &EncryptedUint) -> u128 {
+    assert!(x.width() <= 128, "width must be at most 128");
+    decrypt_bits(sk, x).fold(0, |acc, (i, bit)| acc | (bit as u128) << i)
+}
+
+/// [`decrypt_uint`] for any width (the `bigint` feature).
+#[cfg(all(feature = "client", feature = "bigint"))]
+pub fn decrypt_uint_big(sk: &GswSecretKey, x: &EncryptedUint) -> BigUint {
+    let mut value = BigUint::default();
+    for (i, bit) in decrypt_bits(sk, x) {
+        value.set_bit(i as u64, bit == 1);
+    }
+    value
+}
+
+/// Each bit's index and decryption, the least significant first.
+#[cfg(feature = "client")]
+fn decrypt_bits<'a>(
+    sk: &GswSecretKey,
+    x: &'a EncryptedUint,
+) -> impl Iterator<Item = (usize, u8)> + 'a {
+    let ctx = DecryptionContext::new(sk);
+    x.bits.iter().map(move |ct| ctx.decrypt(ct)).enumerate()
+}
+
+/// Decrypt and read the bits as a two's-complement integer (width 1 to 64).
+#[cfg(feature = "client")]
+pub fn decrypt_int(sk: &GswSecretKey, x: &EncryptedUint) -> i64 {
+    let width = x.width();
+    assert!((1..=64).contains(&width), "width must be between 1 and 64");
+    let unsigned = decrypt_uint(sk, x);
+    // Shift the sign bit into bit 63, then back with sign propagation.
+    ((unsigned << (64 - width)) as i64) >> (64 - width)
+}
+
+/// Enc([x == 0]): the NOR of all bits of x.
+///
+/// Computed by De Morgan as [`homomorphic_and_many`] of the complemented bits;
+/// NOT is linear, so this costs k - 1 multiplications with noise additive in
+/// k. The empty value is zero.
+pub fn homomorphic_is_zero(params: &Params, x: &[Ciphertext]) -> Ciphertext {
+    let complements: Vec<Ciphertext> = x.iter().map(|bit| homomorphic_not(params, bit)).collect();
+    homomorphic_and_many(params, &complements)
+}
+
+/// Enc([x != 0]): the complement of [`homomorphic_is_zero`], at no extra multiplication.
+pub fn homomorphic_is_nonzero(params: &Params, x: &[Ciphertext]) -> Ciphertext {
+    homomorphic_not(params, &homomorphic_is_zero(params, x))
+}
+
+/// A bit permutation applied by renaming wires: output bit i is input bit
+/// `map[i]`. Costs no homomorphic operations.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Permutation {
+    map: Vec<usize>,
+}
+
+impl Permutation {
+    /// Panics unless `map` is a permutation of 0..map.len().
+    pub fn new(map: Vec<usize>) -> Self {
+        let mut seen = vec![false; map.len()];
+        for &i in &map {
+            assert!(
+                i < map.len() && !std::mem::replace(&mut seen[i], true),
+                "map must be a permutation of 0..{}",
+                map.len()
+            );
+        }
+        Self { map }
+    }
+
+    pub fn identity(width: usize) -> Self {
+        Self {
+            map: (0..width).collect(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn inverse(&self) -> Self {
+        let mut map = vec![0; self.map.len()];
+        for (i, &j) in self.map.iter().enumerate() {
+            map[j] = i;
+        }
+        Self { map }
+    }
+
+    /// Shuffle wires; works on ciphertexts and on cleartext bits alike.
+    pub fn apply<T: Clone>(&self, wires: &[T]) -> Vec<T> {
+        assert_eq!(wires.len(), self.map.len(), "width mismatch");
+        self.map.iter().map(|&i| wires[i].clone()).collect()
+    }
+
+    /// Apply to the low `width` bits of a cleartext integer.
+    pub fn apply_plain(&self, x: u64) -> u64 {
+        self.map
+            .iter()
+            .enumerate()
+            .fold(0, |acc, (i, &j)| acc | ((x >> j) & 1) << i)
+    }
+}
+
+/// Enc(x XOR c) for a public constant c: flips the bits where c is set,
+/// with no multiplications.
+pub fn homomorphic_xor_const(params: &Params, x: &[Ciphertext], c: u64) -> EncryptedBits {
+    x.iter()
+        .enumerate()
+        .map(|(i, bit)| {
+            if i < 64 && (c >> i) & 1 == 1 {
+                homomorphic_not(params, bit)
+            } else {
+                bit.clone()
+            }
+        })
+        .collect()
+}
+
+/// Enc([x < c]) for a public constant c.
+///
+/// Scans from the most significant bit: x < c iff for some i with c_i = 1,
+/// x_i = 0 and x agrees with c above i. Comparing against a plaintext bit is a
+/// NOT or a pass-through, the agreement prefix is a chain of ANDs with an input
+/// literal on the right, and the terms are mutually exclusive, so they are
+/// XORed together. Constants at or above 2^w give Enc(1); on wider x, c has
+/// zeros above bit 63.
+pub fn homomorphic_lt_const(params: &Params, x: &[Ciphertext], c: u64) -> Ciphertext {
+    let width = x.len();
+    if width < 64 && c >> width != 0 {
+        return trivial_ciphertext(params, 1);
+    }
+
+    let mut terms = Vec::new();
+    // Enc([x agrees with c above bit i]); None while that is trivially true.
+    let mut prefix: Option<Ciphertext> = None;
+    for i in (0..width).rev() {
+        let c_i = i < 64 && (c >> i) & 1 == 1;
+        let not_x_i = homomorphic_not(params, &x[i]);
+        if c_i {
+            terms.push(match &prefix {
+                Some(prefix) => homomorphic_mult(params, prefix, &not_x_i),
+                None => not_x_i.clone(),
+            });
+        }
+        // No set bits of c remain below i, so no later term needs the prefix.
+        let below = if i < 64 { c & ((1 << i) - 1) } else { c };
+        if below == 0 {
+            break;
+        }
+        let literal = if c_i { x[i].clone() } else { not_x_i };
+        prefix = Some(match prefix {
+            Some(prefix) => homomorphic_mult(params, &prefix, &literal),
+            None => literal,
+        });
+    }
+    homomorphic_sum(params, &terms.iter().collect::<Vec<_>>())
+}
+
+/// Enc([x <= c]) for a public constant c, as [x < c + 1].
+pub fn homomorphic_le_const(params: &Params, x: &[Ciphertext], c: u64) -> Ciphertext {
+    match c.checked_add(1) {
+        Some(c) => homomorphic_lt_const(params, x, c),
+        // c = 2^64 - 1: x <= c iff its bits from 64 up are all zero.
+        None if x.len() > 64 => homomorphic_lt_const(params, &x[64..], 1),
+        None => trivial_ciphertext(params, 1),
+    }
+}
+
+/// Enc([x > c]) for a public constant c, as NOT [x <= c].
+pub fn homomorphic_gt_const(params: &Params, x: &[Ciphertext], c: u64) -> Ciphertext {
+    homomorphic_not(params, &homomorphic_le_const(params, x, c))
+}
+
+/// Enc([x >= c]) for a public constant c, as NOT [x < c].
+pub fn homomorphic_ge_const(params: &Params, x: &[Ciphertext], c: u64) -> Ciphertext {
+    homomorphic_not(params, &homomorphic_lt_const(params, x, c))
+}
+
+/// Enc([a < b]), Enc([a == b]), and Enc([a > b]); exactly one encrypts 1.
+#[derive(Clone, Debug)]
+pub struct ComparisonResult {
+    pub lt: Ciphertext,
+    pub eq: Ciphertext,
+    pub gt: Ciphertext,
+}
+
+/// All three order relations between two encrypted integers at once.
+///
+/// Scans from the most significant bit with one equality prefix
+/// p_i = [a and b agree above bit i], extended by XNOR(a_i, b_i) = NOT(a_i ⊕ b_i),
+/// which is linear. Then a > b iff some p_i·a_i·NOT(b_i) is 1; those terms are
+/// mutually exclusive and XORed together, each built as (p_i·a_i)·NOT(b_i) so
+/// the accumulated value stays on the left. [a == b] is the final prefix, and
+/// since exactly one relation holds, [a < b] = 1 ⊕ gt ⊕ eq for free. That is
+/// 3w - 2 multiplications, the cost of a single comparator. The narrower
+/// operand is zero-extended first.
+pub fn homomorphic_compare_uint(
+    params: &Params,
+    a: &EncryptedUint,
+    b: &EncryptedUint,
+) -> ComparisonResult {
+    let (a, b) = match_widths(params, a, b);
+    let mut gt_terms = Vec::with_capacity(a.width());
+    // Enc(p_i); None while the prefix is empty, hence trivially true.
+    let mut prefix: Option<Ciphertext> = None;
+    for (a_i, b_i) in a.bits.iter().zip(&b.bits).rev() {
+        let not_b_i = homomorphic_not(params, b_i);
+        let above = match &prefix {
+            Some(prefix) => homomorphic_mult(params, prefix, a_i),
+            None => a_i.clone(),
+        };
+        gt_terms.push(homomorphic_mult(params, &above, &not_b_i));
+
+        let agree = homomorphic_not(params, &homomorphic_add(params, a_i, b_i));
+        prefix = Some(match prefix {
+            Some(prefix) => homomorphic_mult(params, &prefix, &agree),
+            None => agree,
+        });
+    }
+    let gt = homomorphic_sum(params, &gt_terms.iter().collect::<Vec<_>>());
+    let eq = prefix.unwrap_or_else(|| trivial_ciphertext(params, 1));
+    let lt = homomorphic_not(params, &homomorphic_add(params, &gt, &eq));
+    ComparisonResult { lt, eq, gt }
+}
+
+/// Ripple-carry addition of a + b + carry_in.
+///
+/// Returns the sum bits and the carries c_0 = carry_in, ..., c_w. Each carry is
+/// c_{i+1} = a_i·b_i ⊕ c_i·(a_i ⊕ b_i), with the accumulated carry on the left.
+fn ripple_add(
+    params: &Params,
+    a: &[Ciphertext],
+    b: &[Ciphertext],
+    carry_in: Ciphertext,
+) -> (EncryptedBits, Vec<Ciphertext>) {
+    let mut sum = Vec::with_capacity(a.len());
+    let mut carries = Vec::with_capacity(a.len() + 1);
+    carries.push(carry_in);
+    for (a_i, b_i) in a.iter().zip(b) {
+        let c_i = carries.last().unwrap();
+        let propagate = homomorphic_add(params, a_i, b_i);
+        let generate = homomorphic_mult(params, a_i, b_i);
+        let carried = homomorphic_mult(params, c_i, &propagate);
+        sum.push(homomorphic_add(params, &propagate, c_i));
+        carries.push(homomorphic_add(params, &generate, &carried));
+    }
+    (sum, carries)
+}
+
+/// Enc(c_w · s_i) for every sum bit of [`ripple_add`], where c_w is the carry-out.
+///
+/// Multiplying c_w by s_i directly would pair two accumulated ciphertexts.
+/// Instead s_i = p_i ⊕ c_i gives c_w·s_i = c_w·p_i ⊕ c_i·c_w, and c_i·c_w is
+/// carried up the chain from c_i as w_{j+1} = (c_i·a_j)·b_j ⊕ w_j·p_j, whose
+/// right operands are all inputs. Costs O(w^2) multiplications.
+fn and_with_carry_out(
+    params: &Params,
+    a: &[Ciphertext],
+    b: &[Ciphertext],
+    carries: &[Ciphertext],
+) -> EncryptedBits {
+    let width = a.len();
+    let carry_out = &carries[width];
+    let propagate: Vec<Ciphertext> = a
+        .iter()
+        .zip(b)
+        .map(|(a_j, b_j)| homomorphic_add(params, a_j, b_j))
+        .collect();
+
+    (0..width)
+        .map(|i| {
+            let c_i = &carries[i];
+            let c_i_and_carry_out = (i..width).fold(c_i.clone(), |w_j, j| {
+                let generated =
+                    homomorphic_mult(params, &homomorphic_mult(params, c_i, &a[j]), &b[j]);
+                let propagated = homomorphic_mult(params, &w_j, &propagate[j]);
+                homomorphic_add(params, &generated, &propagated)
+            });
+            let carry_out_and_p_i = homomorphic_mult(params, carry_out, &propagate[i]);
+            homomorphic_add(params, &carry_out_and_p_i, &c_i_and_carry_out)
+        })
+        .collect()
+}
+
+/// Wrapping a + b: the sum mod 2^w, as a ripple-carry adder of XOR and AND
+/// gates. The narrower operand is zero-extended first.
+///
+/// Costs 2w multiplications. The carry into bit i is i multiplications deep,
+/// so the top sum bit is w - 1 deep: a w-bit sum of fresh operands is as
+/// noisy as a chain of w - 1 products, and needs bootstrapping before further
+/// arithmetic once that passes [`Params::max_mult_depth`]. Each product
+/// multiplies the accumulated carry by a fresh a_i ⊕ b_i, so in practice the
+/// noise grows additively with the width and outlasts that estimate.
+pub fn homomorphic_add_uint(
+    params: &Params,
+    a: &EncryptedUint,
+    b: &EncryptedUint,
+) -> EncryptedUint {
+    let (a, b) = match_widths(params, a, b);
+    let (sum, _) = ripple_add(params, &a.bits, &b.bits, trivial_ciphertext(params, 0));
+    EncryptedUint::from_bits(sum)
+}
+
+/// a + b + carry_in: the sum mod 2^w and the carry-out, as
+/// [`homomorphic_add_uint`] with an encrypted carry-in.
+///
+/// Costs 2w multiplications; the carry-out is w deep. Chaining the carry-out
+/// into the next call adds integers wider than one `EncryptedUint`.
+pub fn homomorphic_add_uint_with_carry(
+    params: &Params,
+    a: &EncryptedUint,
+    b: &EncryptedUint,
+    carry_in: &Ciphertext,
+) -> (EncryptedUint, Ciphertext) {
+    let (a, b) = match_widths(params, a, b);
+    let (sum, mut carries) = ripple_add(params, &a.bits, &b.bits, carry_in.clone());
+    let carry_out = carries
+        .pop()
+        .expect("ripple_add returns at least the carry-in");
+    (EncryptedUint::from_bits(sum), carry_out)
+}
+
+/// Saturating a + b: the wrapped sum, or all ones if the addition overflows.
+///
+/// Every output bit is s_i OR c_w = s_i ⊕ c_w ⊕ c_w·s_i, i.e. a MUX between
+/// the sum bit and 1 selected by the carry-out. The narrower operand is
+/// zero-extended first, so the result has the wider width. Fresh or shallow
+/// operands are assumed.
+pub fn homomorphic_add_saturating_uint(
+    params: &Params,
+    a: &EncryptedUint,
+    b: &EncryptedUint,
+) -> EncryptedUint {
+    let (a, b) = match_widths(params, a, b);
+    let (sum, carries) = ripple_add(params, &a.bits, &b.bits, trivial_ciphertext(params, 0));
+    let carry_out = &carries[a.width()];
+    let gated = and_with_carry_out(params, &a.bits, &b.bits, &carries);
+    EncryptedUint::from_bits(
+        sum.iter()
+            .zip(&gated)
+            .map(|(s_i, g_i)| {
+                homomorphic_add(params, &homomorphic_add(params, s_i, carry_out), g_i)
+            })
+            .collect(),
+    )
+}
+
+/// Saturating a - b: the difference, or zero if b > a.
+///
+/// Computed as a + NOT(b) + 1, whose carry-out is 1 exactly when no borrow
+/// occurs; every difference bit is ANDed with that carry-out. The narrower
+/// operand is zero-extended first, as for addition.
+pub fn homomorphic_sub_saturating_uint(
+    params: &Params,
+    a: &EncryptedUint,
+    b: &EncryptedUint,
+) -> EncryptedUint {
+    let (a, b) = match_widths(params, a, b);
+    let not_b: EncryptedBits = b
+        .bits
+        .iter()
+        .map(|bit| homomorphic_not(params, bit))
+        .collect();
+    let (_, carries) = ripple_add(params, &a.bits, &not_b, trivial_ciphertext(params, 1));
+    EncryptedUint::from_bits(and_with_carry_out(params, &a.bits, &not_b, &carries))
+}
+
+/// counter += flag (mod 2^w): increments the counter iff flag encrypts 1.
+///
+/// A ripple-carry add whose carry-in is the flag and whose addend bits are
+/// trivial zeros, so every full adder collapses to a half adder:
+/// s_i = x_i ⊕ c_i and c_{i+1} = c_i·x_i, one multiplication per bit below the
+/// top. A trivial Enc(0) flag leaves the counter bit-for-bit unchanged.
+///
+/// The carry multiplies counter bits with each other, so noise compounds
+/// across repeated increments: at toy parameters a 4-bit counter survives
+/// only a couple of them, and long-running counters need a larger modulus.
+pub fn homomorphic_conditional_increment(
+    params: &Params,
+    counter: &mut EncryptedUint,
+    flag: &Ciphertext,
+) {
+    let last = counter.width().saturating_sub(1);
+    let mut carry = flag.clone();
+    for (i, bit) in counter.bits.iter_mut().enumerate() {
+        let sum = homomorphic_add(params, bit, &carry);
+        if i < last {
+            carry = homomorphic_mult(params, &carry, bit);
+        }
+        *bit = sum;
+    }
+}
+
+/// counter -= flag (mod 2^w): decrements the counter iff flag encrypts 1.
+///
+/// The borrow chain mirrors [`homomorphic_conditional_increment`]:
+/// d_i = x_i ⊕ b_i and b_{i+1} = b_i·NOT(x_i), with b_0 the flag.
+pub fn homomorphic_conditional_decrement(
+    params: &Params,
+    counter: &mut EncryptedUint,
+    flag: &Ciphertext,
+) {
+    let last = counter.width().saturating_sub(1);
+    let mut borrow = flag.clone();
+    for (i, bit) in counter.bits.iter_mut().enumerate() {
+        let difference = homomorphic_add(params, bit, &borrow);
+        if i < last {
+            borrow = homomorphic_mult(params, &borrow, &homomorphic_not(params, bit));
+        }
+        *bit = difference;
+    }
+}
+
+/// (x + 1) mod N for a public modulus N, assuming x < N: a ring counter for
+/// moduli that are not powers of two.
+///
+/// The wrapped increment x + 1 is MUXed with 0 on the bit [x + 1 == N], so
+/// each output bit is (x + 1)_i · NOT([x + 1 == N]). Given x < N that bit is
+/// [x == N - 1], which is computed from x directly so that it does not wait
+/// for, or inherit the noise of, the carry chain. The complement goes on the
+/// right of every product, as in [`homomorphic_scale_by_bit`].
+///
+/// Like [`homomorphic_conditional_increment`], repeated increments compound
+/// noise, by about 16 bits a step: a counter survives one step at toy
+/// parameters and three with a 62-bit modulus. A full cycle of N steps needs
+/// the counter refreshed in between, which waits on a
+/// [`bootstrap`](crate::bootstrap::bootstrap) that preserves bits. Inputs at
+/// or above N give unspecified residues.
+///
+/// Fails if N is zero or above 2^w for the width w of x; the result has
+/// width w.
+pub fn homomorphic_increment_mod(
+    params: &Params,
+    x: &EncryptedUint,
+    modulus: u64,
+) -> Result<EncryptedUint, GswError> {
+    let width = x.width();
+    if modulus == 0 || (width < 64 && modulus > 1 << width) {
+        return Err(GswError::InvalidModulus { modulus, width });
+    }
+    let wraps = homomorphic_is_zero(params, &homomorphic_xor_const(params, &x.bits, modulus - 1));
+    let mut incremented = x.clone();
+    homomorphic_conditional_increment(params, &mut incremented, &trivial_ciphertext(params, 1));
+    Ok(homomorphic_scale_by_bit(
+        params,
+        &incremented,
+        &homomorphic_not(params, &wraps),
+    ))
+}
+
+/// bit · x: every bit of x ANDed with `bit`, so x itself where it encrypts 1
+/// and zero where it encrypts 0, at the width of x.
+///
+/// One multiplication per bit, with the bit of x on the left and `bit` on the
+/// right, so a fresh selector adds only its own noise to each bit of x.
+pub fn homomorphic_scale_by_bit(
+    params: &Params,
+    x: &EncryptedUint,
+    bit: &Ciphertext,
+) -> EncryptedUint {
+    EncryptedUint::from_bits(
+        x.bits
+            .iter()
+            .map(|x_i| homomorphic_mult(params, x_i, bit))
+            .collect(),
+    )
+}
+
+/// The item whose index the selector bits `sel_bits` (LSB first) encrypt,
+/// among up to 2^k items for k selector bits.
+///
+/// A CMux tree: level i pairs the survivors at indices 2m and 2m + 1 into
+/// a + s_i·(b - a), with each difference bit on the left of the product and
+/// s_i on the right, so a selector bit is shared by every node of its level
+/// and every bit position, and noise grows additively with k. Each node costs
+/// one multiplication per bit, (items - 1)·width in all for 2^k items. A
+/// shorter list is padded with zeros, which cost nothing where a whole
+/// subtree is padding and one multiplication per bit where a padding zero
+/// meets an item.
+///
+/// No items select the zero-width integer. Panics if there are more than 2^k
+/// items or their widths differ.
+pub fn homomorphic_select_uint(
+    params: &Params,
+    sel_bits: &[Ciphertext],
+    items: &[EncryptedUint],
+) -> EncryptedUint {
+    assert!(
+        sel_bits.len() >= usize::BITS as usize || items.len() <= 1 << sel_bits.len(),
+        "{} items need more than {} selector bits",
+        items.len(),
+        sel_bits.len()
+    );
+    let width = items.first().map_or(0, EncryptedUint::width);
+    assert!(
+        items.iter().all(|item| item.width() == width),
+        "items must all have the same width"
+    );
+
+    let mut level: Vec<EncryptedBits> = items.iter().map(|item| item.bits.clone()).collect();
+    for s in sel_bits {
+        let times_s = |bits: &[Ciphertext]| -> EncryptedBits {
+            bits.iter()
+                .map(|bit| homomorphic_mult(params, bit, s))
+                .collect()
+        };
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [a, b] => b
+                    .iter()
+                    .zip(a)
+                    .map(|(b, a)| homomorphic_mux(params, s, b, a))
+                    .collect(),
+                // Paired with padding: a + s·(0 - a).
+                [a] => a
+                    .iter()
+                    .zip(&times_s(a))
+                    .map(|(a, d)| difference(params, a, d))
+                    .collect(),
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    let bits = level
+        .pop()
+        .unwrap_or_else(|| vec![trivial_ciphertext(params, 0); width]);
+    EncryptedUint::from_bits(bits)
+}
+
+/// Largest divisor [`homomorphic_div_const_uint`] handles with a one-hot
+/// remainder; larger ones use a binary remainder.
+pub const ONE_HOT_DIVISOR_LIMIT: u64 = 16;
+
+/// Quotient and remainder of x / d for a small public divisor d.
+///
+/// Restoring division, one quotient bit per bit of x from the top: shift the
+/// next bit of x into the running remainder t = 2r + x_i, set the quotient bit
+/// to [t >= d], and keep t - d where that holds.
+///
+/// For d up to [`ONE_HOT_DIVISOR_LIMIT`] the remainder is one-hot: d
+/// ciphertexts, one per value. A step splits each line r into r·x_i and
+/// r - r·x_i (the running line on the left of the product, the input bit on
+/// the right), and routes both to their public successors, so noise grows
+/// additively with the width of x. The comparison and subtraction are a
+/// public table, and the quotient bit and final remainder bits are sums of
+/// lines.
+///
+/// Larger divisors keep the remainder in k = bits(d) bits: the quotient bit is
+/// [`homomorphic_ge_const`] of t, t - d is t plus the public constant
+/// 2^(k+1) - d, and each remainder bit is the MUX t_j ⊕ [t >= d]·(t_j ⊕ s_j).
+/// Every step there compounds the previous remainder's noise, so wide
+/// dividends need a large modulus.
+///
+/// Fails if d is zero or does not fit in the width of x; the quotient and
+/// remainder have the width of x.
+pub fn homomorphic_div_const_uint(
+    params: &Params,
+    x: &EncryptedUint,
+    d: u64,
+) -> Result<(EncryptedUint, EncryptedUint), GswError> {
+    let width = x.width();
+    if d == 0 || (width < 64 && d >> width != 0) {
+        return Err(GswError::InvalidDivisor { divisor: d, width });
+    }
+    let (quotient, mut remainder) = if d <= ONE_HOT_DIVISOR_LIMIT {
+        div_one_hot(params, x, d as usize)
+    } else {
+        div_binary(params, x, d)
+    };
+    remainder.resize(width, trivial_ciphertext(params, 0));
+    Ok((
+        EncryptedUint::from_bits(quotient),
+        EncryptedUint::from_bits(remainder),
+    ))
+}
+
+/// Division with a one-hot remainder; returns the quotient and the low
+/// bits(d) remainder bits.
+fn div_one_hot(params: &Params, x: &EncryptedUint, d: usize) -> (EncryptedBits, EncryptedBits) {
+    let width = x.width();
+    // lines[r] = Enc([remainder == r]); None while r is unreachable.
+    let mut lines: Vec<Option<Ciphertext>> = vec![None; d];
+    lines[0] = Some(trivial_ciphertext(params, 1));
+    let mut quotient = vec![trivial_ciphertext(params, 0); width];
+    for i in (0..width).rev() {
+        let x_i = &x.bits[i];
+        let mut incoming: Vec<Vec<Ciphertext>> = vec![Vec::new(); d];
+        let mut overflow = Vec::new();
+        for (r, line) in lines.iter().enumerate() {
+            let Some(line) = line else { continue };
+            let on_one = homomorphic_mult(params, line, x_i);
+            let on_zero = difference(params, line, &on_one);
+            for (t, term) in [(2 * r, on_zero), (2 * r + 1, on_one)] {
+                if t >= d {
+                    overflow.push(term.clone());
+                    incoming[t - d].push(term);
+                } else {
+                    incoming[t].push(term);
+                }
+            }
+        }
+        quotient[i] = homomorphic_sum(params, &overflow.iter().collect::<Vec<_>>());
+        lines = incoming
+            .iter()
+            .map(|terms| {
+                (!terms.is_empty())
+                    .then(|| homomorphic_sum(params, &terms.iter().collect::<Vec<_>>()))
+            })
+            .collect();
+    }
+
+    let k = (usize::BITS - d.leading_zeros()) as usize;
+    let remainder = (0..k)
+        .map(|j| {
+            let set: Vec<&Ciphertext> = lines
+                .iter()
+                .enumerate()
+                .filter(|(r, _)| (r >> j) & 1 == 1)
+                .filter_map(|(_, line)| line.as_ref())
+                .collect();
+            homomorphic_sum(params, &set)
+        })
+        .collect();
+    (quotient, remainder)
+}
+
+/// Division with a binary remainder; returns the quotient and the low bits(d)
+/// remainder bits.
+fn div_binary(params: &Params, x: &EncryptedUint, d: u64) -> (EncryptedBits, EncryptedBits) {
+    let width = x.width();
+    let k = (64 - d.leading_zeros()) as usize;
+    let minus_d = (1u64 << (k + 1)).wrapping_sub(d);
+
+    let mut quotient = vec![trivial_ciphertext(params, 0); width];
+    let mut remainder = vec![trivial_ciphertext(params, 0); k];
+    for i in (0..width).rev() {
+        // t = 2r + x_i, k + 1 bits.
+        let mut t = Vec::with_capacity(k + 1);
+        t.push(x.bits[i].clone());
+        t.extend(remainder.iter().cloned());
+
+        let ge = homomorphic_ge_const(params, &t, d);
+        let diff = homomorphic_add_const(params, &t[..k], minus_d);
+        remainder = t
+            .iter()
+            .zip(&diff)
+            .map(|(t_j, s_j)| {
+                let flip = homomorphic_mult(params, &ge, &homomorphic_add(params, t_j, s_j));
+                homomorphic_add(params, t_j, &flip)
+            })
+            .collect();
+        quotient[i] = ge;
+    }
+    (quotient, remainder)
+}
+
+/// a + b with one bit of growth, so the sum cannot overflow. The narrower
+/// operand is zero-extended first.
+fn add_growing(params: &Params, a: &EncryptedUint, b: &EncryptedUint) -> EncryptedUint {
+    let width = a.width().max(b.width()) + 1;
+    let widen = |x: &EncryptedUint| {
+        x.zero_extend(width, params)
+            .expect("width is at least the operand's")
+    };
+    let (sum, _) = ripple_add(
+        params,
+        &widen(a).bits,
+        &widen(b).bits,
+        trivial_ciphertext(params, 0),
+    );
+    EncryptedUint::from_bits(sum)
+}
+
+/// floor((x_1 + ... + x_k) / k) for k encrypted values.
+///
+/// The sum is built by a balanced adder tree, each level one bit wider than
+/// the last, so it never overflows; the mean is then its quotient by the
+/// public count (a plain shift when k is a power of two) and has the width of
+/// the widest input. Nothing is bootstrapped between the sum and the
+/// division (see [`bootstrap`](crate::bootstrap::bootstrap) for why), so the
+/// parameters must hold the whole circuit: [`Params::for_depth`]`(4, -40)`
+/// covers windows of up to four 4-bit values.
+///
+/// Fails with [`GswError::EmptyInput`] if `values` is empty.
+pub fn homomorphic_mean(
+    params: &Params,
+    values: &[EncryptedUint],
+) -> Result<EncryptedUint, GswError> {
+    if values.is_empty() {
+        return Err(GswError::EmptyInput("values"));
+    }
+    let width = values.iter().map(EncryptedUint::width).max().unwrap_or(0);
+
+    let mut level = values.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [a, b] => add_growing(params, a, b),
+                [a] => a.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    let sum = level.pop().expect("one value left");
+
+    let count = values.len() as u64;
+    let mean = if count.is_power_of_two() {
+        let shift = count.trailing_zeros() as usize;
+        EncryptedUint::from_bits(sum.bits[shift..].to_vec())
+    } else {
+        homomorphic_div_const_uint(params, &sum, count)
+            .expect("the sum is wide enough to hold the count")
+            .0
+    };
+    if mean.width() >= width {
+        mean.truncate(width)
+    } else {
+        mean.zero_extend(width, params)
+    }
+}
+
+/// Enc(Σ a_i·b_i): how many positions two encrypted bit vectors share a 1.
+///
+/// The pairs are ANDed, one multiplication each, and the products counted
+/// with the balanced adder tree of [`homomorphic_mean`], each level one bit
+/// wider than the last, so the count has 1 + ⌈log2 n⌉ bits and cannot
+/// overflow. That is as many levels of depth: the products, then one
+/// per tree level, since a ripple-carry adder multiplies bits of both of its
+/// operands. No stage is bootstrapped, so the count is correct only under
+/// parameters supporting depth 1 + ⌈log2 n⌉; the sets [`Params::for_depth`]
+/// picks from reach depth 5, or 16 pairs. Empty vectors count to the
+/// zero-width integer.
+///
+/// Panics if the vectors differ in length.
+pub fn homomorphic_inner_product_bits(
+    params: &Params,
+    a: &[Ciphertext],
+    b: &[Ciphertext],
+) -> EncryptedUint {
+    assert_eq!(a.len(), b.len(), "vectors must have the same length");
+    let mut level: Vec<EncryptedUint> = a
+        .iter()
+        .zip(b)
+        .map(|(a_i, b_i)| EncryptedUint::from_bits(vec![homomorphic_mult(params, a_i, b_i)]))
+        .collect();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [x, y] => add_growing(params, x, y),
+                [x] => x.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    level
+        .pop()
+        .unwrap_or_else(|| EncryptedUint::from_bits(Vec::new()))
+}
+
+/// A running maximum over a stream of encrypted unsigned integers.
+///
+/// Each [`update`](Self::update) compares the value with the current maximum
+/// ([`homomorphic_compare_uint`]) and selects every bit with the MUX
+/// m_i ⊕ [v > m]·(v_i ⊕ m_i), the comparison on the left of the product: 4w - 2
+/// multiplications however long the stream. The maximum is an operand of both
+/// steps, though, so each update deepens it by one level, about log2(N) bits of
+/// noise, and no modulus holds a long stream. Nothing refreshes it, so the
+/// stream length is what the modulus leaves room for: four 4-bit values under a
+/// 48-bit modulus at n = 8, five under [`Params::for_depth`]`(5, -40)`, and
+/// six under a 62-bit modulus at n = 1, whose small N slows the growth.
+/// [`update`](Self::update) does not check the limit; [`depth`](Self::depth)
+/// counts the updates so far.
+///
+/// The maximum of an empty stream is a trivial encryption of zero.
+#[derive(Clone, Debug)]
+pub struct EncryptedRunningMax {
+    params: Params,
+    max: EncryptedUint,
+    /// Depth charged to `max`, counted as the evaluator counts products.
+    depth: u32,
+}
+
+impl EncryptedRunningMax {
+    /// The running maximum of an empty stream of `width`-bit values.
+    pub fn new(params: &Params, width: usize) -> Self {
+        Self {
+            params: params.clone(),
+            max: EncryptedUint::from_bits(vec![trivial_ciphertext(params, 0); width]),
+            depth: 0,
+        }
+    }
+
+    /// Fold `value` into the maximum.
+    ///
+    /// A narrower value is zero-extended; panics if `value` is wider than the
+    /// maximum.
+    pub fn update(&mut self, value: &EncryptedUint) {
+        let value = value
+            .zero_extend(self.max.width(), &self.params)
+            .expect("value is wider than the running maximum");
+        let params = &self.params;
+        let gt = homomorphic_compare_uint(params, &value, &self.max).gt;
+        let bits = self
+            .max
+            .bits
+            .iter()
+            .zip(&value.bits)
+            .map(|(m_i, v_i)| {
+                let differ = homomorphic_add(params, v_i, m_i);
+                homomorphic_add(params, m_i, &homomorphic_mult(params, &gt, &differ))
+            })
+            .collect();
+        self.max = EncryptedUint::from_bits(bits);
+        self.depth += 1;
+    }
+
+    /// The encrypted maximum of every value so far.
+    pub fn current(&self) -> &EncryptedUint {
+        &self.max
+    }
+
+    /// Depth charged to the current maximum.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::gsw::{decrypt, encrypt, gsw_keygen};
+    use crate::kernels;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn test_is_zero() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let bits = [encrypt(&mut rng, &pk, 0), encrypt(&mut rng, &pk, 1)];
+        let encode = |value: u64, width: usize| -> Vec<Ciphertext> {
+            (0..width)
+                .map(|i| bits[((value >> i) & 1) as usize].clone())
+                .collect()
+        };
+
+        for width in [4usize, 8] {
+            let mut values = vec![0u64, (1 << width) - 1];
+            values.extend((0..width).map(|i| 1u64 << i));
+            for value in values {
+                let x = encode(value, width);
+                let expected = (value == 0) as u8;
+                assert_eq!(
+                    decrypt(&sk, &homomorphic_is_zero(&params, &x)),
+                    expected,
+                    "{}",
+                    value
+                );
+                assert_eq!(
+                    decrypt(&sk, &homomorphic_is_nonzero(&params, &x)),
+                    1 - expected,
+                    "{}",
+                    value
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_permutation_and_xor_const() {
+        let perm = Permutation::new(vec![0, 2, 4, 6, 1, 3, 5, 7]);
+        for x in 0..256u64 {
+            let bits: Vec<u64> = (0..8).map(|i| (x >> i) & 1).collect();
+            let shuffled = perm.apply(&bits);
+            let y = perm.apply_plain(x);
+            assert_eq!(shuffled, (0..8).map(|i| (y >> i) & 1).collect::<Vec<_>>());
+            assert_eq!(perm.inverse().apply_plain(y), x);
+        }
+        assert_eq!(Permutation::identity(8).apply_plain(0xa5), 0xa5);
+
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(9);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let x = encrypt_uint(&mut rng, &pk, 0b1010, 4);
+        let y = EncryptedUint::from_bits(homomorphic_xor_const(&params, x.bits(), 0b0110));
+        assert_eq!(decrypt_uint(&sk, &y), 0b1100);
+    }
+
+    #[test]
+    #[should_panic(expected = "permutation")]
+    fn test_permutation_rejects_duplicates() {
+        Permutation::new(vec![0, 1, 1]);
+    }
+
+    #[test]
+    fn test_compare_with_constant() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(5);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let bits = [encrypt(&mut rng, &pk, 0), encrypt(&mut rng, &pk, 1)];
+
+        for value in 0..16u64 {
+            let x: Vec<Ciphertext> = (0..4)
+                .map(|i| bits[((value >> i) & 1) as usize].clone())
+                .collect();
+            for c in [0u64, 1, 7, 15, 16] {
+                let check = |ct: Ciphertext, expected: bool, op: &str| {
+                    assert_eq!(decrypt(&sk, &ct), expected as u8, "{} {} {}", value, op, c);
+                };
+                check(homomorphic_lt_const(&params, &x, c), value < c, "<");
+                check(homomorphic_le_const(&params, &x, c), value <= c, "<=");
+                check(homomorphic_gt_const(&params, &x, c), value > c, ">");
+                check(homomorphic_ge_const(&params, &x, c), value >= c, ">=");
+            }
+        }
+    }
+
+    #[test]
+    fn test_compare_wide_value_with_constant() {
+        // Bits 64 and up of a 65-bit x are compared against zero. The
+        // agreement prefix runs the full width, one product per bit, which
+        // is more noise than Toy's modulus holds.
+        let params = Params::from_raw(1 << 28, 8, 256, 1);
+        let mut rng = ChaCha20Rng::seed_from_u64(429);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let bits = [encrypt(&mut rng, &pk, 0), encrypt(&mut rng, &pk, 1)];
+        let encode = |value: u128| -> Vec<Ciphertext> {
+            (0..65)
+                .map(|i| bits[((value >> i) & 1) as usize].clone())
+                .collect()
+        };
+
+        for value in [0u128, 5, u64::MAX as u128, 1 << 64, (1 << 65) - 1] {
+            let x = encode(value);
+            for c in [0u64, 5, u64::MAX] {
+                let check = |ct: Ciphertext, expected: bool, op: &str| {
+                    assert_eq!(decrypt(&sk, &ct), expected as u8, "{} {} {}", value, op, c);
+                };
+                let c_wide = c as u128;
+                check(homomorphic_lt_const(&params, &x, c), value < c_wide, "<");
+                check(homomorphic_le_const(&params, &x, c), value <= c_wide, "<=");
+                check(homomorphic_gt_const(&params, &x, c), value > c_wide, ">");
+                check(homomorphic_ge_const(&params, &x, c), value >= c_wide, ">=");
+            }
+        }
+    }
+
+    #[test]
+    fn test_compare_uint() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(6);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let bits = [encrypt(&mut rng, &pk, 0), encrypt(&mut rng, &pk, 1)];
+        let encode = |value: u64| {
+            EncryptedUint::from_bits(
+                (0..4)
+                    .map(|i| bits[((value >> i) & 1) as usize].clone())
+                    .collect(),
+            )
+        };
+
+        for a in 0..16u64 {
+            for b in 0..16u64 {
+                let result = homomorphic_compare_uint(&params, &encode(a), &encode(b));
+                let got = [&result.lt, &result.eq, &result.gt].map(|ct| decrypt(&sk, ct));
+                assert_eq!(got.iter().sum::<u8>(), 1, "{} vs {}", a, b);
+                assert_eq!(got, [a < b, a == b, a > b].map(u8::from), "{} vs {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_saturating_add_sub() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let width = 4;
+
+        for (x, y) in [(3u64, 4u64), (7, 8), (9, 9), (15, 1), (15, 15), (0, 0)] {
+            let a = encrypt_uint(&mut rng, &pk, x, width);
+            let b = encrypt_uint(&mut rng, &pk, y, width);
+            let sum = homomorphic_add_saturating_uint(&params, &a, &b);
+            assert_eq!(decrypt_uint(&sk, &sum), (x + y).min(15), "{} + {}", x, y);
+        }
+        for (x, y) in [(9u64, 4u64), (4, 9), (5, 5), (0, 15), (15, 0), (8, 7)] {
+            let a = encrypt_uint(&mut rng, &pk, x, width);
+            let b = encrypt_uint(&mut rng, &pk, y, width);
+            let diff = homomorphic_sub_saturating_uint(&params, &a, &b);
+            assert_eq!(
+                decrypt_uint(&sk, &diff),
+                x.saturating_sub(y),
+                "{} - {}",
+                x,
+                y
+            );
+        }
+    }
+
+    #[test]
+    fn test_width_conversion() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(13);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+
+        let narrow = encrypt_uint(&mut rng, &pk, 11, 4);
+        let wide = narrow.zero_extend(8, &params).unwrap();
+        assert_eq!(wide.width(), 8);
+        assert_eq!(decrypt_uint(&sk, &wide), 11);
+
+        // Mixed widths are zero-extended implicitly.
+        let native = encrypt_uint(&mut rng, &pk, 200, 8);
+        let sum = homomorphic_add_saturating_uint(&params, &native, &narrow);
+        assert_eq!(sum.width(), 8);
+        assert_eq!(decrypt_uint(&sk, &sum), 211);
+        let diff = homomorphic_sub_saturating_uint(&params, &narrow, &native);
+        assert_eq!(decrypt_uint(&sk, &diff), 0);
+        assert_eq!(decrypt_uint(&sk, &sum.truncate(4).unwrap()), 211 % 16);
+
+        let minus_three = encrypt_uint(&mut rng, &pk, 0b1101, 4);
+        let extended = minus_three.sign_extend(8).unwrap();
+        assert_eq!(decrypt_uint(&sk, &extended), 0xfd);
+        assert_eq!(decrypt_int(&sk, &extended), -3);
+        assert_eq!(decrypt_int(&sk, &narrow.sign_extend(8).unwrap()), -5);
+        assert_eq!(decrypt_int(&sk, &encrypt_uint(&mut rng, &pk, 5, 4)), 5);
+
+        assert!(matches!(
+            wide.zero_extend(4, &params),
+            Err(GswError::InvalidWidthChange {
+                width: 8,
+                new_width: 4
+            })
+        ));
+        assert!(matches!(
+            narrow.sign_extend(3),
+            Err(GswError::InvalidWidthChange { .. })
+        ));
+        assert!(matches!(
+            narrow.truncate(5),
+            Err(GswError::InvalidWidthChange { .. })
+        ));
+        assert!(matches!(
+            EncryptedUint::from_bits(Vec::new()).sign_extend(4),
+            Err(GswError::InvalidWidthChange {
+                width: 0,
+                new_width: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn test_conditional_increment_decrement() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(11);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let one = encrypt(&mut rng, &pk, 1);
+
+        let mut counter = encrypt_uint(&mut rng, &pk, 0, 4);
+        homomorphic_conditional_increment(&params, &mut counter, &one);
+        assert_eq!(decrypt_uint(&sk, &counter), 1);
+
+        let mut counter = encrypt_uint(&mut rng, &pk, 15, 4);
+        homomorphic_conditional_increment(&params, &mut counter, &one);
+        assert_eq!(decrypt_uint(&sk, &counter), 0);
+
+        let mut counter = encrypt_uint(&mut rng, &pk, 0, 4);
+        homomorphic_conditional_decrement(&params, &mut counter, &one);
+        assert_eq!(decrypt_uint(&sk, &counter), 15);
+
+        let mut counter = encrypt_uint(&mut rng, &pk, 8, 4);
+        homomorphic_conditional_decrement(&params, &mut counter, &one);
+        assert_eq!(decrypt_uint(&sk, &counter), 7);
+
+        let original = encrypt_uint(&mut rng, &pk, 9, 4);
+        let mut counter = original.clone();
+        homomorphic_conditional_increment(&params, &mut counter, &trivial_ciphertext(&params, 0));
+        assert_eq!(counter.bits(), original.bits());
+        let zero = encrypt(&mut rng, &pk, 0);
+        homomorphic_conditional_increment(&params, &mut counter, &zero);
+        assert_eq!(decrypt_uint(&sk, &counter), 9);
+    }
+
+    #[test]
+    fn test_from_bit_and_scale_by_bit() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(470);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let x = encrypt_uint(&mut rng, &pk, 11, 4);
+        for flag in [0u8, 1] {
+            let flag_ct = encrypt(&mut rng, &pk, flag);
+            let scaled = homomorphic_scale_by_bit(&params, &x, &flag_ct);
+            assert_eq!(scaled.width(), 4);
+            assert_eq!(decrypt_uint(&sk, &scaled), 11 * flag as u64);
+
+            let extended = EncryptedUint::from_bit(flag_ct.clone(), 4, &params);
+            assert_eq!(decrypt_uint(&sk, &extended), flag as u64);
+            let base = encrypt_uint(&mut rng, &pk, 6, 4);
+            let sum = homomorphic_add_saturating_uint(&params, &base, &extended);
+            let mut counter = base;
+            homomorphic_conditional_increment(&params, &mut counter, &flag_ct);
+            assert_eq!(decrypt_uint(&sk, &sum), decrypt_uint(&sk, &counter));
+            assert_eq!(decrypt_uint(&sk, &sum), 6 + flag as u64);
+        }
+    }
+
+    #[test]
+    fn test_select_uint() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(500);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let (width, k) = (4, 2);
+        let values = [9u64, 4, 15, 2];
+        let items: Vec<EncryptedUint> = values
+            .iter()
+            .map(|&v| encrypt_uint(&mut rng, &pk, v, width))
+            .collect();
+        for index in 0..values.len() as u64 {
+            let sel = encrypt_uint(&mut rng, &pk, index, k).into_bits();
+            kernels::take_products();
+            let selected = homomorphic_select_uint(&params, &sel, &items);
+            // One product per bit per node, the selector bits shared across
+            // bit positions, against width·k per item for per-bit trees.
+            let products = kernels::take_products();
+            assert_eq!(products, (values.len() as u64 - 1) * width as u64);
+            assert!(products < (values.len() * width * k) as u64);
+            assert_eq!(selected.width(), width);
+            assert_eq!(decrypt_uint(&sk, &selected), values[index as usize]);
+        }
+
+        // Three items are padded to four; one is just itself.
+        for index in 0..4u64 {
+            let sel = encrypt_uint(&mut rng, &pk, index, k).into_bits();
+            let selected = homomorphic_select_uint(&params, &sel, &items[..3]);
+            let expected = if index < 3 { values[index as usize] } else { 0 };
+            assert_eq!(decrypt_uint(&sk, &selected), expected);
+        }
+        let only = homomorphic_select_uint(&params, &[], &items[..1]);
+        assert_eq!(decrypt_uint(&sk, &only), values[0]);
+        assert_eq!(homomorphic_select_uint(&params, &[], &[]).width(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "5 items need more than 2 selector bits")]
+    fn test_select_uint_rejects_too_many_items() {
+        let params = Params::toy();
+        let item = EncryptedUint::from_bits(vec![trivial_ciphertext(&params, 1)]);
+        let sel = vec![trivial_ciphertext(&params, 0); 2];
+        homomorphic_select_uint(&params, &sel, &vec![item; 5]);
+    }
+
+    #[test]
+    fn test_add_uint() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(512);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let values: Vec<EncryptedUint> =
+            (0..16).map(|v| encrypt_uint(&mut rng, &pk, v, 4)).collect();
+        let carries = [encrypt(&mut rng, &pk, 0), encrypt(&mut rng, &pk, 1)];
+        for a in 0..16u64 {
+            for b in 0..16u64 {
+                let (x, y) = (&values[a as usize], &values[b as usize]);
+                let sum = homomorphic_add_uint(&params, x, y);
+                assert_eq!(sum.width(), 4);
+                assert_eq!(decrypt_uint(&sk, &sum), (a + b) & 0xf, "{} + {}", a, b);
+
+                let c = (a ^ b) & 1;
+                let (sum, carry_out) =
+                    homomorphic_add_uint_with_carry(&params, x, y, &carries[c as usize]);
+                assert_eq!(decrypt_uint(&sk, &sum), (a + b + c) & 0xf);
+                assert_eq!(decrypt(&sk, &carry_out) as u64, (a + b + c) >> 4);
+            }
+        }
+
+        for _ in 0..8 {
+            let (a, b) = (rng.gen_range(0..256u64), rng.gen_range(0..256u64));
+            let x = encrypt_uint(&mut rng, &pk, a, 8);
+            let y = encrypt_uint(&mut rng, &pk, b, 8);
+            let sum = homomorphic_add_uint(&params, &x, &y);
+            assert_eq!(decrypt_uint(&sk, &sum), (a + b) & 0xff, "{} + {}", a, b);
+        }
+        // The narrower operand is zero-extended.
+        let wide = encrypt_uint(&mut rng, &pk, 200, 8);
+        let narrow = homomorphic_add_uint(&params, &values[9], &wide);
+        assert_eq!((narrow.width(), decrypt_uint(&sk, &narrow)), (8, 209));
+    }
+
+    #[test]
+    fn test_bitwise_lanes() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(511);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        for _ in 0..4 {
+            let (a, b) = (rng.gen_range(0..16u64), rng.gen_range(0..16u64));
+            let x = encrypt_uint(&mut rng, &pk, a, 4);
+            let y = encrypt_uint(&mut rng, &pk, b, 4);
+            assert_eq!(decrypt_uint(&sk, &x.and(&params, &y).unwrap()), a & b);
+            assert_eq!(decrypt_uint(&sk, &x.or(&params, &y).unwrap()), a | b);
+            assert_eq!(decrypt_uint(&sk, &x.xor(&params, &y).unwrap()), a ^ b);
+            // Masked to the width, not the complement of a u64.
+            assert_eq!(decrypt_uint(&sk, &x.not(&params)), !a & 0xf);
+        }
+
+        let x = encrypt_uint(&mut rng, &pk, 5, 4);
+        let wide = encrypt_uint(&mut rng, &pk, 5, 5);
+        assert!(matches!(
+            x.and(&params, &wide),
+            Err(GswError::WidthMismatch { left: 4, right: 5 })
+        ));
+        assert!(wide.xor(&params, &x).is_err());
+    }
+
+    #[test]
+    fn test_wide_decryption() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(502);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let value: u128 = rng.gen::<u128>() >> 28;
+        let x = encrypt_bits(&mut rng, &pk, 100, |i| ((value >> i) & 1) as u8);
+        assert_eq!(decrypt_uint_u128(&sk, &x), value);
+
+        let low = x.truncate(64).unwrap();
+        assert_eq!(decrypt_uint_u128(&sk, &low) as u64, decrypt_uint(&sk, &low));
+        #[cfg(feature = "bigint")]
+        {
+            let big = BigUint::from(value);
+            assert_eq!(decrypt_uint_big(&sk, &x), big);
+            let y = encrypt_uint_big(&mut rng, &pk, &big, 100);
+            assert_eq!(decrypt_uint_u128(&sk, &y), value);
+            for width in [0, 1, 17, 64] {
+                let x = x.truncate(width).unwrap();
+                assert_eq!(decrypt_uint_big(&sk, &x), decrypt_uint(&sk, &x).into());
+            }
+        }
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn test_wide_addition() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(502);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let width = 80;
+        let random = |rng: &mut ChaCha20Rng| BigUint::from(rng.gen::<u128>() >> (128 - width));
+        let (a, b) = (random(&mut rng), random(&mut rng));
+        let x = encrypt_uint_big(&mut rng, &pk, &a, width);
+        let y = encrypt_uint_big(&mut rng, &pk, &b, width);
+        let (mut sum, carries) =
+            ripple_add(&params, &x.bits, &y.bits, trivial_ciphertext(&params, 0));
+        sum.push(carries[width].clone());
+        assert_eq!(decrypt_uint_big(&sk, &EncryptedUint::from_bits(sum)), a + b);
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    #[should_panic(expected = "value needs 9 bits, more than width 8")]
+    fn test_encrypt_uint_big_rejects_wide_value() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(502);
+        let (_, pk) = gsw_keygen(&mut rng, &params);
+        encrypt_uint_big(&mut rng, &pk, &BigUint::from(256u32), 8);
+    }
+
+    #[test]
+    fn test_div_const() {
+        // Small divisors take the one-hot path, whose noise grows additively.
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(5);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        for d in [3u64, 5] {
+            for value in (0..32).step_by(3).chain([31]) {
+                let x = encrypt_uint(&mut rng, &pk, value, 5);
+                let (q, r) = homomorphic_div_const_uint(&params, &x, d).unwrap();
+                assert_eq!(q.width(), 5);
+                assert_eq!(
+                    (decrypt_uint(&sk, &q), decrypt_uint(&sk, &r)),
+                    (value / d, value % d),
+                    "{} / {}",
+                    value,
+                    d
+                );
+            }
+        }
+
+        // Larger divisors use restoring division: each step compounds the
+        // previous remainder's noise, so like the sequential increments this
+        // needs a wide modulus.
+        let wide = Params::from_raw(1 << 40, 8, 256, 1);
+        let (wide_sk, wide_pk) = gsw_keygen(&mut rng, &wide);
+        for value in [0u64, 16, 17, 40, 63] {
+            let x = encrypt_uint(&mut rng, &wide_pk, value, 6);
+            let (q, r) = homomorphic_div_const_uint(&wide, &x, 17).unwrap();
+            assert_eq!(
+                (decrypt_uint(&wide_sk, &q), decrypt_uint(&wide_sk, &r)),
+                (value / 17, value % 17),
+                "{} / 17",
+                value
+            );
+        }
+
+        let x = encrypt_uint(&mut rng, &pk, 7, 3);
+        for d in [0, 8] {
+            assert!(matches!(
+                homomorphic_div_const_uint(&params, &x, d),
+                Err(GswError::InvalidDivisor { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_mean() {
+        // The sum bits feed the division, so this is deeper than either
+        // alone; the documented parameters hold windows of up to four.
+        let params = Params::for_depth(4, -40).unwrap();
+        let mut rng = ChaCha20Rng::seed_from_u64(11);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        for window in [2usize, 3, 4] {
+            for _ in 0..2 {
+                let clear: Vec<u64> = (0..window).map(|_| rng.gen_range(0..16)).collect();
+                let values: Vec<EncryptedUint> = clear
+                    .iter()
+                    .map(|&v| encrypt_uint(&mut rng, &pk, v, 4))
+                    .collect();
+                let mean = homomorphic_mean(&params, &values).unwrap();
+                assert_eq!(mean.width(), 4);
+                let expected = clear.iter().sum::<u64>() / window as u64;
+                assert_eq!(decrypt_uint(&sk, &mean), expected, "mean of {:?}", clear);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sequential_conditional_increments() {
+        // Carries compound noise across increments; toy q = 2^20 runs out
+        // after a few, so use a wide modulus at the toy dimension.
+        let params = Params::from_raw(1 << 40, 8, 256, 1);
+        let mut rng = ChaCha20Rng::seed_from_u64(3);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let mut counter = encrypt_uint(&mut rng, &pk, 0, 4);
+        let mut count = 0;
+        for flag in [1u8, 0, 1, 1, 0, 1, 1, 1, 0, 1] {
+            let flag_ct = encrypt(&mut rng, &pk, flag);
+            homomorphic_conditional_increment(&params, &mut counter, &flag_ct);
+            count += flag as u64;
+            assert_eq!(decrypt_uint(&sk, &counter), count);
+        }
+    }
+
+    #[test]
+    fn test_inner_product_bits() {
+        // Eight pairs take 1 + log2(8) = 4 levels, these parameters' budget.
+        let params = Params::for_depth(4, -40).unwrap();
+        let mut rng = ChaCha20Rng::seed_from_u64(482);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ctx = crate::gsw::DecryptionContext::new(&sk);
+        let bound = crate::noise_model::log2_error_bound(&params, 4, -40);
+        let mut encode = |x: u64| -> Vec<Ciphertext> {
+            (0..8)
+                .map(|i| encrypt(&mut rng, &pk, ((x >> i) & 1) as u8))
+                .collect()
+        };
+
+        for (a, b) in [
+            (0b1010_1010, 0b0101_0101),
+            (0b1110_0101, 0b0110_0011),
+            (0xff, 0xff),
+        ] {
+            let count = homomorphic_inner_product_bits(&params, &encode(a), &encode(b));
+            assert_eq!(count.width(), 4);
+            assert_eq!(decrypt_uint(&sk, &count), (a & b).count_ones() as u64);
+            for bit in count.bits() {
+                let noise = ctx.measure_noise(bit).unsigned_abs().max(1) as f64;
+                assert!(
+                    noise.log2() <= bound,
+                    "noise 2^{:.1} over 2^{:.1}",
+                    noise.log2(),
+                    bound
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "same length")]
+    fn test_inner_product_rejects_unequal_lengths() {
+        let params = Params::toy();
+        let bits = vec![trivial_ciphertext(&params, 1); 3];
+        homomorphic_inner_product_bits(&params, &bits, &bits[1..]);
+    }
+
+    #[test]
+    fn test_increment_mod() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(485);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        for x in 0..5 {
+            let ct = encrypt_uint(&mut rng, &pk, x, 3);
+            let next = homomorphic_increment_mod(&params, &ct, 5).unwrap();
+            assert_eq!(next.width(), 3);
+            assert_eq!(decrypt_uint(&sk, &next), (x + 1) % 5, "{} + 1", x);
+        }
+        let x = encrypt_uint(&mut rng, &pk, 7, 3);
+        let next = homomorphic_increment_mod(&params, &x, 8).unwrap();
+        assert_eq!(decrypt_uint(&sk, &next), 0);
+        for modulus in [0, 9] {
+            assert!(matches!(
+                homomorphic_increment_mod(&params, &x, modulus),
+                Err(GswError::InvalidModulus { width: 3, .. })
+            ));
+        }
+
+        // A step adds about 16 bits of noise, so a 62-bit modulus holds three
+        // in a row, from 3 through the wrap. The full cycle of five is blocked
+        // on a bootstrap that preserves bits.
+        let params = Params::custom(1 << 62, 8, 256, 1, -40).unwrap();
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let mut counter = encrypt_uint(&mut rng, &pk, 3, 3);
+        for step in 1..=3 {
+            counter = homomorphic_increment_mod(&params, &counter, 5).unwrap();
+            assert_eq!(decrypt_uint(&sk, &counter), (3 + step) % 5, "step {}", step);
+        }
+    }
+
+    #[test]
+    fn test_running_max() {
+        let orders = [
+            [1u64, 4, 6, 9, 13, 15],
+            [15, 13, 9, 6, 4, 1],
+            [14, 3, 9, 0, 12, 6],
+        ];
+
+        // Each update adds about log2(N) bits of noise to the maximum, and
+        // nothing refreshes it: a 62-bit modulus at n = 1 holds six.
+        let params = Params::custom(1 << 62, 1, 8, 1, -40).unwrap();
+        let mut rng = ChaCha20Rng::seed_from_u64(478);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        for order in orders {
+            let mut running = EncryptedRunningMax::new(&params, 4);
+            for (i, &v) in order.iter().enumerate() {
+                running.update(&encrypt_uint(&mut rng, &pk, v, 4));
+                let expected = order[..=i].iter().max().copied().unwrap();
+                assert_eq!(
+                    decrypt_uint(&sk, running.current()),
+                    expected,
+                    "{:?} after {} updates",
+                    order,
+                    i + 1
+                );
+            }
+            assert_eq!(running.depth(), 6);
+        }
+    }
+
+    #[test]
+    fn test_framed_bits_round_trip() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(493);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let x = encrypt_uint(&mut rng, &pk, 0xb6, 8);
+
+        let bytes = x.to_bytes(&params);
+        let restored = EncryptedUint::from_bytes(&params, &bytes).unwrap();
+        assert_eq!(restored.bits(), x.bits());
+        assert_eq!(decrypt_uint(&sk, &restored), 0xb6);
+        assert_eq!(encrypted_bits_to_bytes(&params, x.bits()), bytes);
+        let empty = encrypted_bits_to_bytes(&params, &[]);
+        assert!(encrypted_bits_from_bytes(&params, &empty)
+            .unwrap()
+            .is_empty());
+
+        // The width sits after the header and the fingerprint.
+        let width_at = wire::HEADER_LEN + 32;
+        let mut tampered = bytes.clone();
+        tampered[width_at] = 9;
+        assert!(matches!(
+            EncryptedUint::from_bytes(&params, &tampered),
+            Err(GswError::MalformedData(msg)) if msg.contains("width 9")
+        ));
+
+        // An MSB-first blob is read back LSB first.
+        let mut reversed = x.bits().to_vec();
+        reversed.reverse();
+        let mut msb_first = encrypted_bits_to_bytes(&params, &reversed);
+        msb_first[width_at + 8] = BitOrder::MsbFirst as u8;
+        let restored = EncryptedUint::from_bytes(&params, &msb_first).unwrap();
+        assert_eq!(decrypt_uint(&sk, &restored), 0xb6);
+
+        let other = Params::custom(1 << 22, 8, 256, 1, -40).unwrap();
+        assert!(matches!(
+            EncryptedUint::from_bytes(&other, &bytes),
+            Err(GswError::ParamsMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_framed_bits_reject_foreign_ciphertext() {
+        let params = Params::toy();
+        let other = Params::custom(1 << 22, 8, 256, 1, -40).unwrap();
+        let bits = [
+            trivial_ciphertext(&params, 1),
+            trivial_ciphertext(&params, 0),
+        ];
+        let bytes = encrypted_bits_to_bytes(&params, &bits);
+
+        // Replace the second bit with a standalone ciphertext's body (params,
+        // then the ciphertext) written under other parameters.
+        let first = encrypted_bits_to_bytes(&params, &bits[..1]);
+        let mut mixed = bytes[..first.len()].to_vec();
+        let foreign = crate::gsw::ciphertext_to_bytes(&other, &trivial_ciphertext(&other, 0));
+        mixed.extend_from_slice(&foreign[wire::HEADER_LEN..]);
+        mixed[wire::HEADER_LEN + 32] = 2;
+        assert!(matches!(
+            encrypted_bits_from_bytes(&params, &mixed),
+            Err(GswError::ParamsMismatch)
+        ));
+
+        // The same splice under the right parameters is accepted.
+        let mut spliced = bytes[..first.len()].to_vec();
+        let native = crate::gsw::ciphertext_to_bytes(&params, &bits[1]);
+        spliced.extend_from_slice(&native[wire::HEADER_LEN..]);
+        spliced[wire::HEADER_LEN + 32] = 2;
+        assert_eq!(encrypted_bits_from_bytes(&params, &spliced).unwrap(), bits);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_framed_uint_serde() {
+        use serde::de::DeserializeSeed;
+
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(493);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let x = encrypt_uint(&mut rng, &pk, 0x5a, 8);
+        let json = serde_json::to_string(&FramedUint {
+            params: &params,
+            value: &x,
+        })
+        .unwrap();
+        let mut deserializer = serde_json::Deserializer::from_str(&json);
+        let restored = FramedUintSeed(&params)
+            .deserialize(&mut deserializer)
+            .unwrap();
+        assert_eq!(decrypt_uint(&sk, &restored), 0x5a);
+
+        let other = Params::custom(1 << 22, 8, 256, 1, -40).unwrap();
+        let mut deserializer = serde_json::Deserializer::from_str(&json);
+        assert!(FramedUintSeed(&other)
+            .deserialize(&mut deserializer)
+            .is_err());
+    }
+}
@@ -0,0 +1,161 @@
+//! Conversion of extracted LWE ciphertexts to and from the TFHE layout.
+//!
+//! Conventions assumed on the TFHE side:
+//!
+//! - A sample is `(mask, body)` with the body last, and decrypts as
+//!   `body - <mask, key>` followed by rounding to the message scale.
+//! - The secret key is this crate's stored key without its leading 1, i.e.
+//!   `key = s[1..] = -t`. Ours decrypts as `c[0] + <c[1..], s[1..]>`, so the
+//!   mask is negated on the way out and back in.
+//! - The modulus is 2^k for some 1 ≤ k ≤ 64; 2^64 is TFHE's native torus.
+//!   One bit is encoded at scale half the modulus, matching ours.
+//!
+//! Rescaling to a larger modulus multiplies by a power of two and is exact,
+//! so the converted sample decrypts under the same key. Rescaling to a smaller
+//! modulus rounds each component; the rounding errors are multiplied by the
+//! key, which here is uniform mod q rather than small, so downward rescaled
+//! samples are only useful for moving data, not for decryption.
+
+use crate::lwe::LweCiphertext;
+use crate::params::Params;
+
+/// Rescale x from Z_{2^from_bits} to Z_{2^to_bits}, rounding to nearest.
+fn rescale(x: u64, from_bits: u32, to_bits: u32) -> u64 {
+    let x = x as u128;
+    let scaled = if to_bits >= from_bits {
+        x << (to_bits - from_bits)
+    } else {
+        let shift = from_bits - to_bits;
+        (x + (1 << (shift - 1))) >> shift
+    };
+    (scaled & ((1u128 << to_bits) - 1)) as u64
+}
+
+/// Negate x modulo 2^bits.
+fn negate(x: u64, bits: u32) -> u64 {
+    let mask = if bits == 64 {
+        u64::MAX
+    } else {
+        (1 << bits) - 1
+    };
+    x.wrapping_neg() & mask
+}
+
+fn check_bits(bits: u32) {
+    assert!(
+        (1..=64).contains(&bits),
+        "modulus must be 2^k with 1 <= k <= 64"
+    );
+}
+
+/// Convert to a TFHE `(mask, body)` pair over this crate's modulus q.
+pub fn to_tfhe_lwe(lwe_ct: &LweCiphertext, params: &Params) -> (Vec<u64>, u64) {
+    to_tfhe_lwe_with_modulus(lwe_ct, params, params.l as u32)
+}
+
+/// Convert to a TFHE `(mask, body)` pair over the modulus 2^target_bits.
+pub fn to_tfhe_lwe_with_modulus(
+    lwe_ct: &LweCiphertext,
+    params: &Params,
+    target_bits: u32,
+) -> (Vec<u64>, u64) {
+    check_bits(target_bits);
+    assert_eq!(
+        lwe_ct.c.len(),
+        params.n + 1,
+        "LWE ciphertext must have n + 1 entries"
+    );
+    let source_bits = params.l as u32;
+    let body = rescale(lwe_ct.c[0], source_bits, target_bits);
+    let mask = lwe_ct.c[1..]
+        .iter()
+        .map(|&c| negate(rescale(c, source_bits, target_bits), target_bits))
+        .collect();
+    (mask, body)
+}
+
+/// Convert a TFHE `(mask, body)` pair over this crate's modulus q.
+pub fn from_tfhe_lwe(mask: &[u64], body: u64, params: &Params) -> LweCiphertext {
+    from_tfhe_lwe_with_modulus(mask, body, params.l as u32, params)
+}
+
+/// Convert a TFHE `(mask, body)` pair over the modulus 2^source_bits.
+pub fn from_tfhe_lwe_with_modulus(
+    mask: &[u64],
+    body: u64,
+    source_bits: u32,
+    params: &Params,
+) -> LweCiphertext {
+    check_bits(source_bits);
+    assert_eq!(mask.len(), params.n, "mask must have n entries");
+    let target_bits = params.l as u32;
+    let mut c = Vec::with_capacity(params.n + 1);
+    c.push(rescale(body, source_bits, target_bits));
+    c.extend(
+        mask.iter()
+            .map(|&a| rescale(negate(a, source_bits), source_bits, target_bits)),
+    );
+    LweCiphertext { c }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::gsw::{encrypt, gsw_keygen};
+    use crate::lwe::{extract_lwe, lwe_decrypt};
+    use crate::testing::pseudo_ciphertext;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    /// TFHE-side decryption over 2^bits with key s[1..].
+    fn tfhe_decrypt(mask: &[u64], body: u64, key: &[u64], bits: u32) -> u8 {
+        let dot = mask
+            .iter()
+            .zip(key)
+            .fold(0u64, |acc, (&a, &k)| acc.wrapping_add(a.wrapping_mul(k)));
+        let phase = body.wrapping_sub(dot) as u128 & ((1u128 << bits) - 1);
+        let half = 1u128 << (bits - 1);
+        (((phase + half / 2) >> (bits - 1)) & 1) as u8
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let params = Params::toy();
+        let lwe = extract_lwe(&params, &pseudo_ciphertext(&params, 42));
+
+        let (mask, body) = to_tfhe_lwe(&lwe, &params);
+        assert_eq!(from_tfhe_lwe(&mask, body, &params), lwe);
+        let (mask, body) = to_tfhe_lwe_with_modulus(&lwe, &params, 64);
+        assert_eq!(from_tfhe_lwe_with_modulus(&mask, body, 64, &params), lwe);
+    }
+
+    #[test]
+    fn test_converted_ciphertext_decrypts() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let key = &sk.s[1..];
+        for bit in [0u8, 1] {
+            let lwe = extract_lwe(&params, &encrypt(&mut rng, &pk, bit));
+            assert_eq!(lwe_decrypt(&sk, &lwe), bit);
+
+            let (mask, body) = to_tfhe_lwe(&lwe, &params);
+            assert_eq!(tfhe_decrypt(&mask, body, key, params.l as u32), bit);
+            let (mask, body) = to_tfhe_lwe_with_modulus(&lwe, &params, 64);
+            assert_eq!(tfhe_decrypt(&mask, body, key, 64), bit);
+
+            let back = from_tfhe_lwe_with_modulus(&mask, body, 64, &params);
+            assert_eq!(lwe_decrypt(&sk, &back), bit);
+        }
+    }
+
+    #[test]
+    fn test_rescale_rounds_to_nearest() {
+        assert_eq!(rescale(0b1011, 4, 2), 0b11);
+        assert_eq!(rescale(0b1001, 4, 2), 0b10);
+        assert_eq!(rescale(0b1110, 4, 2), 0b00);
+        assert_eq!(rescale(3, 2, 64), 3 << 62);
+        assert_eq!(negate(1, 64), u64::MAX);
+        assert_eq!(negate(0, 8), 0);
+    }
+}
@@ -0,0 +1,260 @@
+//! Keys and ciphertexts in the JSON dump format of the Python reference
+//! implementation (the `serde` feature).
+//!
+//! A dump is one object with fields `q`, `n`, `l`, `sk`, `pk`, and `ct`:
+//! the modulus, the dimension, log₂ q, the secret vector (or null), the
+//! public-key rows (or null), and a list of ciphertext matrices. The
+//! reference differs from this crate in two layout conventions:
+//!
+//! - The secret is s = (t', 1) with public-key rows (a, b), i.e.
+//!   [`SecretLayout::OneLast`]; ours is s = (1, -t) with rows (b, a).
+//! - BitDecomp is MSB first within each l-entry block (see
+//!   [`reverse_bit_order`]).
+//!
+//! A ciphertext's rows and columns are both indexed by the decomposed
+//! coordinates, so both are permuted. The dump records neither m nor the
+//! error bound: on import m is the number of public-key rows (1 without a
+//! public key) and the error bound is taken as 1, which only affects the
+//! depth budget (see [`Params::max_mult_depth`]).
+//!
+//! `tests/data/reference_dump.json` is a pedagogical-size dump produced by
+//! `tests/data/gen_reference_dump.py`, a standalone script written to the
+//! reference's conventions.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::GswError;
+use crate::gadget::reverse_bit_order;
+use crate::gsw::Ciphertext;
+use crate::lwe::{PublicKey, SecretKey, SecretLayout};
+use crate::params::Params;
+
+/// The dump, fields in sorted order so that serializing it gives canonical
+/// JSON.
+#[derive(Debug, Serialize, Deserialize)]
+struct ReferenceDump {
+    ct: Vec<Vec<Vec<u64>>>,
+    l: usize,
+    n: usize,
+    pk: Option<Vec<Vec<u64>>>,
+    q: u64,
+    sk: Option<Vec<u64>>,
+}
+
+/// For each decomposed coordinate in the reference's order, the index of the
+/// same coordinate in ours.
+fn reference_order(params: &Params) -> Vec<usize> {
+    let native: Vec<usize> = (0..params.n_expanded).collect();
+    let blocks: Vec<&[usize]> = native.chunks(params.l).collect();
+    reverse_bit_order(&SecretLayout::OneLast.from_native(&blocks).concat(), params)
+}
+
+/// Fails unless `rows` is `shape` with every entry below q.
+fn check_matrix(
+    rows: &[Vec<u64>],
+    shape: (usize, usize),
+    q: u64,
+    what: &str,
+) -> Result<(), GswError> {
+    if rows.len() != shape.0 || rows.iter().any(|row| row.len() != shape.1) {
+        return Err(GswError::MalformedData(format!(
+            "{}: expected {} rows of {} entries",
+            what, shape.0, shape.1
+        )));
+    }
+    if let Some(&x) = rows.iter().flatten().find(|&&x| x >= q) {
+        return Err(GswError::MalformedData(format!(
+            "{}: entry {} not below q",
+            what, x
+        )));
+    }
+    Ok(())
+}
+
+fn params_from_dump(dump: &ReferenceDump) -> Result<Params, GswError> {
+    let q = dump.q;
+    if q < 4 || !q.is_power_of_two() || q > 1 << 62 {
+        return Err(GswError::MalformedData(format!("invalid modulus {}", q)));
+    }
+    if dump.l != q.trailing_zeros() as usize {
+        return Err(GswError::MalformedData(format!(
+            "l = {} does not match q = {}",
+            dump.l, q
+        )));
+    }
+    let m = dump.pk.as_ref().map_or(1, |pk| pk.len());
+    if dump.n == 0 || dump.n > 1 << 16 || m == 0 || m > 1 << 20 {
+        return Err(GswError::MalformedData("invalid dimensions".into()));
+    }
+    Ok(Params::from_raw(q, dump.n, m, 1))
+}
+
+/// Parameters, keys, and ciphertexts read from a dump.
+pub type Imported = (
+    Params,
+    Option<SecretKey>,
+    Option<PublicKey>,
+    Vec<Ciphertext>,
+);
+
+/// Parse a reference dump into parameters, keys, and ciphertexts in this
+/// crate's layout.
+pub fn import_reference_json(json: &str) -> Result<Imported, GswError> {
+    let dump: ReferenceDump = serde_json::from_str(json)
+        .map_err(|e| GswError::MalformedData(format!("reference JSON: {}", e)))?;
+    let params = params_from_dump(&dump)?;
+    let (q, width, n_expanded) = (params.q, params.n + 1, params.n_expanded);
+    let shared = Arc::new(params.clone());
+
+    let sk = match &dump.sk {
+        Some(sk) => {
+            check_matrix(std::slice::from_ref(sk), (1, width), q, "secret key")?;
+            let s = SecretLayout::OneLast.to_native(sk);
+            if s[0] != 1 {
+                return Err(GswError::MalformedData(
+                    "secret key does not end in 1".into(),
+                ));
+            }
+            Some(SecretKey::from_parts(s, Arc::clone(&shared)))
+        }
+        None => None,
+    };
+    let pk = match &dump.pk {
+        Some(rows) => {
+            check_matrix(rows, (params.m, width), q, "public key")?;
+            let a = rows
+                .iter()
+                .map(|row| SecretLayout::OneLast.to_native(row))
+                .collect();
+            Some(PublicKey::from_parts(a, Arc::clone(&shared)))
+        }
+        None => None,
+    };
+
+    let order = reference_order(&params);
+    let cts = dump
+        .ct
+        .iter()
+        .map(|rows| {
+            check_matrix(rows, (n_expanded, n_expanded), q, "ciphertext")?;
+            let mut ct = vec![vec![0u64; n_expanded]; n_expanded];
+            for (row, &i) in rows.iter().zip(&order) {
+                for (&x, &j) in row.iter().zip(&order) {
+                    ct[i][j] = x;
+                }
+            }
+            Ok(ct)
+        })
+        .collect::<Result<_, GswError>>()?;
+    Ok((params, sk, pk, cts))
+}
+
+/// A reference dump of the given components, as canonical JSON (keys sorted,
+/// no whitespace).
+pub fn export_reference_json(
+    params: &Params,
+    sk: Option<&SecretKey>,
+    pk: Option<&PublicKey>,
+    cts: &[Ciphertext],
+) -> String {
+    let order = reference_order(params);
+    let dump = ReferenceDump {
+        ct: cts
+            .iter()
+            .map(|ct| {
+                order
+                    .iter()
+                    .map(|&i| order.iter().map(|&j| ct[i][j]).collect())
+                    .collect()
+            })
+            .collect(),
+        l: params.l,
+        n: params.n,
+        pk: pk.map(|pk| {
+            pk.a.iter()
+                .map(|row| SecretLayout::OneLast.from_native(row))
+                .collect()
+        }),
+        q: params.q,
+        sk: sk.map(|sk| SecretLayout::OneLast.from_native(&sk.s)),
+    };
+    serde_json::to_string(&dump).expect("a dump of integers always serializes")
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::gsw::{decrypt, encrypt, gsw_keygen, homomorphic_mult};
+    use crate::lwe::{extract_lwe, lwe_decrypt};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    const FIXTURE: &str = include_str!("../tests/data/reference_dump.json");
+
+    fn canonical(json: &str) -> String {
+        serde_json::from_str::<serde_json::Value>(json)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_fixture_decrypts_and_round_trips() {
+        let (params, sk, pk, cts) = import_reference_json(FIXTURE).unwrap();
+        assert_eq!((params.q, params.n, params.m, params.l), (256, 2, 4, 8));
+        let (sk, pk) = (sk.unwrap(), pk.unwrap());
+        let bits: Vec<u8> = cts.iter().map(|ct| decrypt(&sk, ct)).collect();
+        assert_eq!(bits, [0, 1, 1, 0]);
+        assert_eq!(
+            decrypt(&sk, &homomorphic_mult(&params, &cts[1], &cts[2])),
+            1
+        );
+        for ct in &cts {
+            assert_eq!(
+                lwe_decrypt(&sk, &extract_lwe(&params, ct)),
+                decrypt(&sk, ct)
+            );
+        }
+        // The imported public key encrypts under the imported secret key.
+        let mut rng = ChaCha20Rng::seed_from_u64(467);
+        assert_eq!(decrypt(&sk, &encrypt(&mut rng, &pk, 1)), 1);
+
+        let exported = export_reference_json(&params, Some(&sk), Some(&pk), &cts);
+        assert_eq!(exported, canonical(FIXTURE));
+    }
+
+    #[test]
+    fn test_native_keys_round_trip() {
+        let params = Params::pedagogical();
+        let mut rng = ChaCha20Rng::seed_from_u64(468);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let cts: Vec<Ciphertext> = (0..2).map(|bit| encrypt(&mut rng, &pk, bit)).collect();
+        let json = export_reference_json(&params, Some(&sk), None, &cts);
+        let (imported, sk_back, pk_back, cts_back) = import_reference_json(&json).unwrap();
+        assert_eq!(
+            (imported.q, imported.n, imported.m),
+            (params.q, params.n, 1)
+        );
+        assert_eq!(sk_back.unwrap().s, sk.s);
+        assert!(pk_back.is_none());
+        assert_eq!(cts_back, cts);
+    }
+
+    #[test]
+    fn test_rejects_malformed_dumps() {
+        let mut dump: serde_json::Value = serde_json::from_str(FIXTURE).unwrap();
+        dump["l"] = 7.into();
+        assert!(matches!(
+            import_reference_json(&dump.to_string()),
+            Err(GswError::MalformedData(_))
+        ));
+        let mut dump: serde_json::Value = serde_json::from_str(FIXTURE).unwrap();
+        dump["sk"][2] = 2.into();
+        assert!(matches!(
+            import_reference_json(&dump.to_string()),
+            Err(GswError::MalformedData(_))
+        ));
+        assert!(import_reference_json("{}").is_err());
+    }
+}
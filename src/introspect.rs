@@ -0,0 +1,141 @@
+//! Serialized sizes of every object, computed from the encoders' own size
+//! functions so the numbers cannot drift from the format.
+
+use std::fmt;
+
+use crate::params::Params;
+use crate::session;
+use crate::wire;
+
+/// Encoded sizes in bytes for one parameter set, under
+/// [`format_version`](FormatReport::format_version).
+///
+/// Component sizes exclude the object header, since components are only
+/// written inside a session; session sizes include it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatReport {
+    pub params: Params,
+    pub format_version: u16,
+    pub secret_key: u64,
+    pub public_key: u64,
+    /// A flattened ciphertext, one bit per entry.
+    pub ciphertext_packed: u64,
+    /// A ciphertext with any entry above 1, eight bytes per entry.
+    pub ciphertext_raw: u64,
+    pub evaluation_key: u64,
+    /// Secret, public, and evaluation keys.
+    pub client_session: u64,
+    /// Public and evaluation keys, as [`Session::server_view`](crate::Session::server_view) gives.
+    pub server_session: u64,
+}
+
+/// The encoded sizes for `params`.
+pub fn format_report(params: &Params) -> FormatReport {
+    FormatReport {
+        params: params.clone(),
+        format_version: wire::FORMAT_VERSION,
+        secret_key: wire::secret_key_len(params),
+        public_key: wire::public_key_len(params),
+        ciphertext_packed: wire::ciphertext_len(params, true),
+        ciphertext_raw: wire::ciphertext_len(params, false),
+        evaluation_key: wire::evaluation_key_len(params),
+        client_session: session::encoded_len(params, true, true),
+        server_session: session::encoded_len(params, false, true),
+    }
+}
+
+impl fmt::Display for FormatReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let p = &self.params;
+        writeln!(
+            f,
+            "format version {}: q = 2^{}, n = {}, m = {}, N = {}",
+            self.format_version, p.l, p.n, p.m, p.n_expanded
+        )?;
+        for (name, bytes) in [
+            ("secret key", self.secret_key),
+            ("public key", self.public_key),
+            ("ciphertext (packed)", self.ciphertext_packed),
+            ("ciphertext (raw)", self.ciphertext_raw),
+            ("evaluation key", self.evaluation_key),
+            ("client session", self.client_session),
+            ("server session", self.server_session),
+        ] {
+            writeln!(f, "  {:<20} {:>14} bytes", name, bytes)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::gsw::{gsw_keygen, trivial_ciphertext};
+    use crate::params::SecurityLevel;
+    use crate::session::Session;
+    use crate::testing::pseudo_ciphertext;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    fn encoded(write: impl FnOnce(&mut Vec<u8>)) -> u64 {
+        let mut out = Vec::new();
+        write(&mut out);
+        out.len() as u64
+    }
+
+    #[test]
+    fn test_report_matches_encoders() {
+        for params in [
+            Params::pedagogical(),
+            Params::new(SecurityLevel::Toy),
+            Params::new(SecurityLevel::Low),
+            Params::new(SecurityLevel::Medium),
+        ] {
+            let report = format_report(&params);
+            let mut rng = ChaCha20Rng::seed_from_u64(42);
+            let (sk, pk) = gsw_keygen(&mut rng, &params);
+            // Encrypting at Medium is slow, and only the shape matters here.
+            let packed = pseudo_ciphertext(&params, 42);
+            let mut raw = trivial_ciphertext(&params, 1);
+            raw[0][0] = 2;
+
+            assert_eq!(
+                report.secret_key,
+                encoded(|out| wire::write_secret_key(out, &sk))
+            );
+            assert_eq!(
+                report.public_key,
+                encoded(|out| wire::write_public_key(out, &pk))
+            );
+            assert_eq!(
+                report.ciphertext_packed,
+                encoded(|out| wire::write_ciphertext(out, &packed))
+            );
+            assert_eq!(
+                report.ciphertext_raw,
+                encoded(|out| wire::write_ciphertext(out, &raw))
+            );
+        }
+    }
+
+    #[test]
+    fn test_report_matches_sessions() {
+        // Evaluation keys hold N ciphertexts of N^2 entries; build them only
+        // for the small presets.
+        for params in [Params::pedagogical(), Params::toy()] {
+            let report = format_report(&params);
+            let mut rng = ChaCha20Rng::seed_from_u64(42);
+            let (sk, pk) = gsw_keygen(&mut rng, &params);
+            let ek = crate::bootstrap::gen_evaluation_key(&mut rng, &sk, &pk);
+            assert_eq!(
+                report.evaluation_key,
+                encoded(|out| wire::write_evaluation_key(out, &ek))
+            );
+
+            let client = Session::from_parts(params.clone(), Some(sk), pk, Some(ek)).unwrap();
+            assert_eq!(report.client_session, client.to_bytes().len() as u64);
+            let server = Session::server_view(&client);
+            assert_eq!(report.server_session, server.to_bytes().len() as u64);
+        }
+    }
+}
@@ -0,0 +1,649 @@
+//! Matrix-product kernels with runtime selection.
+//!
+//! Every homomorphic multiplication is one N×N product over Z_q, so this is
+//! where the time goes. Several implementations of the same product are
+//! compiled in:
+//!
+//! - `scalar`: the reference. Walks rows of the left operand and skips zero
+//!   entries, which after Flatten is about half of them.
+//! - `avx2` (x86-64 only): the scalar loop compiled with AVX2 enabled, for
+//!   binaries built for a baseline CPU. About 1.5–2× faster than `scalar`
+//!   at N = 180 and N = 1274.
+//!
+//! On aarch64, NEON is part of the baseline target, so `scalar` already uses
+//! it and there is no separate kernel.
+//!
+//! The kernel is chosen on first use: the fastest one the CPU supports, unless
+//! the `GSW_RS_KERNEL` environment variable names another. A name that is not
+//! an available kernel is reported on stderr and the default used instead.
+//! All kernels give bit-identical results.
+//!
+//! With the `rayon` feature, products whose output has at least
+//! [`parallel_threshold`] entries are split by rows across threads, each
+//! chunk running the active kernel. Below that, thread overhead outweighs the
+//! work and the product runs serially.
+//!
+//! A [`ValidationPolicy`] can recompute some or all products with `scalar`, to
+//! check an optimized kernel in production. A product that differs is counted
+//! in [`kernel_mismatches`], passed to the handler set with
+//! [`set_mismatch_handler`], and replaced by the scalar result.
+//!
+//! A cancellable product runs in blocks of [`CANCEL_CHECK_ROWS`] rows and
+//! stops at the first block that starts after its flag is set.
+
+#[cfg(test)]
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+#[cfg(all(test, feature = "client"))]
+use std::sync::Mutex;
+use std::sync::{OnceLock, RwLock};
+
+/// Environment variable that forces a kernel by name.
+pub const KERNEL_ENV_VAR: &str = "GSW_RS_KERNEL";
+
+type ProductFn = fn(&[Vec<u64>], &[Vec<u64>], u64) -> Vec<Vec<u64>>;
+
+#[derive(Clone, Copy)]
+struct Kernel {
+    name: &'static str,
+    product: ProductFn,
+    supported: fn() -> bool,
+}
+
+/// All kernels, slowest first; the last supported one is the default.
+const KERNELS: &[Kernel] = &[
+    Kernel {
+        name: "scalar",
+        product: scalar_product,
+        supported: always,
+    },
+    #[cfg(target_arch = "x86_64")]
+    Kernel {
+        name: "avx2",
+        product: avx2::product,
+        supported: avx2::supported,
+    },
+];
+
+fn always() -> bool {
+    true
+}
+
+static ACTIVE: OnceLock<Kernel> = OnceLock::new();
+
+fn active() -> &'static Kernel {
+    ACTIVE.get_or_init(|| {
+        let requested = std::env::var(KERNEL_ENV_VAR).ok();
+        let (kernel, warning) = choose(requested.as_deref());
+        if let Some(warning) = warning {
+            eprintln!("warning: {}", warning);
+        }
+        *kernel
+    })
+}
+
+/// The kernel named by `requested`, or the default with a warning if that
+/// is not available here.
+fn choose(requested: Option<&str>) -> (&'static Kernel, Option<String>) {
+    let default = KERNELS
+        .iter()
+        .rev()
+        .find(|k| (k.supported)())
+        .expect("the scalar kernel is always supported");
+    match requested {
+        None => (default, None),
+        Some(name) => match find(name) {
+            Some(kernel) => (kernel, None),
+            None => (
+                default,
+                Some(format!(
+                    "{}={} is not an available kernel (available: {:?}); using {}",
+                    KERNEL_ENV_VAR,
+                    name,
+                    available_kernels(),
+                    default.name
+                )),
+            ),
+        },
+    }
+}
+
+fn find(name: &str) -> Option<&'static Kernel> {
+    KERNELS.iter().find(|k| k.name == name && (k.supported)())
+}
+
+/// Name of the kernel used for homomorphic products in this process.
+pub fn active_kernel_name() -> &'static str {
+    active().name
+}
+
+/// Names of the kernels this CPU can run.
+pub fn available_kernels() -> Vec<&'static str> {
+    KERNELS
+        .iter()
+        .filter(|k| (k.supported)())
+        .map(|k| k.name)
+        .collect()
+}
+
+/// A · B mod q with the named kernel, or `None` if it is not available here.
+///
+/// `a` and `b` must be square matrices of the same size. For differential
+/// tests and benchmarks; the homomorphic operations use the active kernel.
+pub fn matrix_product_with(
+    kernel: &str,
+    a: &[Vec<u64>],
+    b: &[Vec<u64>],
+    q: u64,
+) -> Option<Vec<Vec<u64>>> {
+    find(kernel).map(|k| (k.product)(a, b, q))
+}
+
+/// Default for [`parallel_threshold`]: N = 256, above the toy presets.
+pub const DEFAULT_PARALLEL_THRESHOLD: usize = 256 * 256;
+
+static PARALLEL_THRESHOLD: AtomicUsize = AtomicUsize::new(DEFAULT_PARALLEL_THRESHOLD);
+
+/// Smallest number of elements (output entries for a product, ciphertext
+/// entries read for a batch) worth splitting across threads.
+pub fn parallel_threshold() -> usize {
+    PARALLEL_THRESHOLD.load(Ordering::Relaxed)
+}
+
+/// Change [`parallel_threshold`] for the whole process. 0 parallelizes
+/// everything, `usize::MAX` nothing.
+pub fn set_parallel_threshold(elements: usize) {
+    PARALLEL_THRESHOLD.store(elements, Ordering::Relaxed);
+}
+
+/// Whether work over `elements` elements runs in parallel: only with the
+/// `rayon` feature, and only at or above the threshold.
+pub fn runs_in_parallel(elements: usize) -> bool {
+    cfg!(feature = "rayon") && elements >= parallel_threshold()
+}
+
+/// How often [`matrix_product`] checks the active kernel against `scalar`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValidationPolicy {
+    /// Never; the cost is one atomic load per product.
+    Off,
+    /// A pseudo-random fraction of products, between 0 and 1.
+    Sample(f32),
+    /// Every product.
+    Always,
+}
+
+/// Sampling rate as f32 bits; 0.0 is `Off` and 1.0 is `Always`.
+static VALIDATION_RATE: AtomicU32 = AtomicU32::new(0);
+static VALIDATION_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Set the process-wide [`ValidationPolicy`].
+pub fn set_validation_policy(policy: ValidationPolicy) {
+    let rate = match policy {
+        ValidationPolicy::Off => 0.0,
+        ValidationPolicy::Sample(rate) => rate.clamp(0.0, 1.0),
+        ValidationPolicy::Always => 1.0,
+    };
+    VALIDATION_RATE.store(rate.to_bits(), Ordering::Relaxed);
+}
+
+pub fn validation_policy() -> ValidationPolicy {
+    match f32::from_bits(VALIDATION_RATE.load(Ordering::Relaxed)) {
+        rate if rate <= 0.0 => ValidationPolicy::Off,
+        rate if rate >= 1.0 => ValidationPolicy::Always,
+        rate => ValidationPolicy::Sample(rate),
+    }
+}
+
+/// Whether to validate this product. Sampling hashes a global counter
+/// (SplitMix64), so it needs no RNG.
+fn sample_validation() -> bool {
+    let rate = f32::from_bits(VALIDATION_RATE.load(Ordering::Relaxed));
+    if rate <= 0.0 {
+        return false;
+    }
+    if rate >= 1.0 {
+        return true;
+    }
+    let mut z = VALIDATION_COUNTER
+        .fetch_add(1, Ordering::Relaxed)
+        .wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    ((z >> 40) as f32) / ((1u64 << 24) as f32) < rate
+}
+
+/// A product on which a kernel disagreed with `scalar`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KernelMismatch {
+    pub kernel: &'static str,
+    /// First row of the product that differs.
+    pub row: usize,
+    /// The product is `rows`×`inner` by `inner`×`cols`.
+    pub rows: usize,
+    pub inner: usize,
+    pub cols: usize,
+    pub q: u64,
+}
+
+impl std::fmt::Display for KernelMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "kernel {} disagrees with scalar at row {} of a {}x{} by {}x{} product mod {}",
+            self.kernel, self.row, self.rows, self.inner, self.inner, self.cols, self.q
+        )
+    }
+}
+
+impl std::error::Error for KernelMismatch {}
+
+static MISMATCHES: AtomicU64 = AtomicU64::new(0);
+static MISMATCH_HANDLER: RwLock<Option<fn(&KernelMismatch)>> = RwLock::new(None);
+
+/// Products that failed validation in this process so far.
+pub fn kernel_mismatches() -> u64 {
+    MISMATCHES.load(Ordering::Relaxed)
+}
+
+/// Call `handler` with every product that fails validation, or stop with
+/// `None`. The product itself is recomputed with `scalar` either way.
+pub fn set_mismatch_handler(handler: Option<fn(&KernelMismatch)>) {
+    *MISMATCH_HANDLER
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = handler;
+}
+
+/// Recompute `product` with the scalar kernel one row at a time, so the check
+/// holds only one extra row, and stop at the first difference.
+fn validate(
+    kernel: &'static str,
+    a: &[Vec<u64>],
+    b: &[Vec<u64>],
+    q: u64,
+    product: &[Vec<u64>],
+) -> Result<(), KernelMismatch> {
+    let mismatch = a
+        .iter()
+        .zip(product)
+        .position(|(a_row, p_row)| scalar_body(std::slice::from_ref(a_row), b, q)[0] != *p_row)
+        .or((product.len() != a.len()).then(|| product.len().min(a.len())));
+    match mismatch {
+        None => Ok(()),
+        Some(row) => Err(KernelMismatch {
+            kernel,
+            row,
+            rows: a.len(),
+            inner: b.len(),
+            cols: b.first().map_or(0, |row| row.len()),
+            q,
+        }),
+    }
+}
+
+/// `product` if it passes the check the [`ValidationPolicy`] samples, or else
+/// the scalar result, after reporting the mismatch.
+fn checked(
+    kernel: &Kernel,
+    a: &[Vec<u64>],
+    b: &[Vec<u64>],
+    q: u64,
+    product: Vec<Vec<u64>>,
+) -> Vec<Vec<u64>> {
+    if !sample_validation() {
+        return product;
+    }
+    match validate(kernel.name, a, b, q, &product) {
+        Ok(()) => product,
+        Err(mismatch) => {
+            MISMATCHES.fetch_add(1, Ordering::Relaxed);
+            let handler = *MISMATCH_HANDLER
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if let Some(handler) = handler {
+                handler(&mismatch);
+            }
+            scalar_product(a, b, q)
+        }
+    }
+}
+
+/// A · B mod q with the active kernel, validated per the [`ValidationPolicy`].
+pub(crate) fn matrix_product(a: &[Vec<u64>], b: &[Vec<u64>], q: u64) -> Vec<Vec<u64>> {
+    let kernel = active();
+    let product = run(kernel, a, b, q);
+    #[cfg(test)]
+    PRODUCTS.with(|count| count.set(count.get() + 1));
+    #[cfg(test)]
+    let product = if CORRUPT_PRODUCTS.with(Cell::get) {
+        vec![vec![0; product.len()]; product.len()]
+    } else {
+        product
+    };
+    checked(kernel, a, b, q, product)
+}
+
+/// Rows of a product computed between cancellation checks. A row costs about
+/// N²/2 multiply-adds, so at Medium (N = 1274) a block is a few milliseconds
+/// of work per thread.
+pub(crate) const CANCEL_CHECK_ROWS: usize = 4;
+
+/// [`matrix_product`], or `None` if `cancel` is set before the last block of
+/// rows starts.
+pub(crate) fn matrix_product_cancellable(
+    a: &[Vec<u64>],
+    b: &[Vec<u64>],
+    q: u64,
+    cancel: &AtomicBool,
+) -> Option<Vec<Vec<u64>>> {
+    let kernel = active();
+    let block =
+        |rows: &[Vec<u64>]| (!cancel.load(Ordering::Relaxed)).then(|| (kernel.product)(rows, b, q));
+    #[cfg(feature = "rayon")]
+    if runs_in_parallel(a.len() * b.first().map_or(0, |row| row.len())) {
+        use rayon::prelude::*;
+        let blocks = a
+            .par_chunks(CANCEL_CHECK_ROWS)
+            .map(block)
+            .collect::<Option<Vec<_>>>()?;
+        return Some(joined(kernel, a, b, q, blocks));
+    }
+    let blocks = a
+        .chunks(CANCEL_CHECK_ROWS)
+        .map(block)
+        .collect::<Option<Vec<_>>>()?;
+    Some(joined(kernel, a, b, q, blocks))
+}
+
+/// The blocks of a cancellable product as one matrix, validated per the
+/// [`ValidationPolicy`].
+fn joined(
+    kernel: &Kernel,
+    a: &[Vec<u64>],
+    b: &[Vec<u64>],
+    q: u64,
+    blocks: Vec<Vec<Vec<u64>>>,
+) -> Vec<Vec<u64>> {
+    checked(kernel, a, b, q, blocks.into_iter().flatten().collect())
+}
+
+fn run(kernel: &Kernel, a: &[Vec<u64>], b: &[Vec<u64>], q: u64) -> Vec<Vec<u64>> {
+    let product = kernel.product;
+    #[cfg(feature = "rayon")]
+    if runs_in_parallel(a.len() * b.first().map_or(0, |row| row.len())) {
+        use rayon::prelude::*;
+        let chunk = a.len().div_ceil(rayon::current_num_threads()).max(1);
+        return a
+            .par_chunks(chunk)
+            .flat_map_iter(|rows| product(rows, b, q))
+            .collect();
+    }
+    product(a, b, q)
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Multiply-adds performed by [`scalar_body`] on this thread.
+    static MULTIPLY_ADDS: Cell<u64> = const { Cell::new(0) };
+    /// Calls to [`matrix_product`] on this thread.
+    static PRODUCTS: Cell<u64> = const { Cell::new(0) };
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Whether [`matrix_product`]'s kernel outputs zeros on this thread,
+    /// standing in for a broken kernel.
+    static CORRUPT_PRODUCTS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Serializes tests that change process-wide settings (the validation policy,
+/// the mismatch handler, the parallel threshold) or depend on them.
+#[cfg(all(test, feature = "client"))]
+pub(crate) static SETTINGS: Mutex<()> = Mutex::new(());
+
+/// Make [`matrix_product`]'s kernel output zeros on this thread, ahead of
+/// validation, or stop.
+#[cfg(all(test, feature = "client"))]
+pub(crate) fn corrupt_products_on_this_thread(corrupt: bool) {
+    CORRUPT_PRODUCTS.with(|flag| flag.set(corrupt));
+}
+
+/// Multiply-adds performed on this thread since the last call.
+#[cfg(all(test, feature = "client"))]
+pub(crate) fn take_multiply_adds() -> u64 {
+    MULTIPLY_ADDS.with(|count| count.replace(0))
+}
+
+/// Products computed with [`matrix_product`] on this thread since the last
+/// call.
+#[cfg(all(test, feature = "client"))]
+pub(crate) fn take_products() -> u64 {
+    PRODUCTS.with(|count| count.replace(0))
+}
+
+/// Entries are accumulated with wrapping u64 arithmetic; since q is a power
+/// of 2 the final reduction is exact.
+#[inline(always)]
+fn scalar_body(a: &[Vec<u64>], b: &[Vec<u64>], q: u64) -> Vec<Vec<u64>> {
+    let cols = b.first().map_or(0, |row| row.len());
+    let mut prod = vec![vec![0u64; cols]; a.len()];
+    for (a_row, p_row) in a.iter().zip(prod.iter_mut()) {
+        for (&a_ik, b_row) in a_row.iter().zip(b.iter()) {
+            if a_ik == 0 {
+                continue;
+            }
+            #[cfg(test)]
+            MULTIPLY_ADDS.with(|count| count.set(count.get() + cols as u64));
+            for (p, &b_kj) in p_row.iter_mut().zip(b_row.iter()) {
+                *p = p.wrapping_add(a_ik.wrapping_mul(b_kj));
+            }
+        }
+        for p in p_row.iter_mut() {
+            *p %= q;
+        }
+    }
+    prod
+}
+
+fn scalar_product(a: &[Vec<u64>], b: &[Vec<u64>], q: u64) -> Vec<Vec<u64>> {
+    scalar_body(a, b, q)
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    pub(super) fn supported() -> bool {
+        std::is_x86_feature_detected!("avx2")
+    }
+
+    pub(super) fn product(a: &[Vec<u64>], b: &[Vec<u64>], q: u64) -> Vec<Vec<u64>> {
+        assert!(supported(), "avx2 kernel called on a CPU without AVX2");
+        // SAFETY: AVX2 support was just checked.
+        unsafe { product_avx2(a, b, q) }
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn product_avx2(a: &[Vec<u64>], b: &[Vec<u64>], q: u64) -> Vec<Vec<u64>> {
+        super::scalar_body(a, b, q)
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn test_kernels_match_scalar() {
+        let mut rng = ChaCha20Rng::seed_from_u64(17);
+        // Includes a large modulus to exercise wrapping accumulation.
+        for (n, q) in [(1, 4u64), (24, 1 << 8), (65, 1 << 20), (180, 1 << 62)] {
+            let a: Vec<Vec<u64>> = (0..n)
+                .map(|_| (0..n).map(|_| rng.gen_range(0..q)).collect())
+                .collect();
+            let binary: Vec<Vec<u64>> = (0..n)
+                .map(|_| (0..n).map(|_| rng.gen_range(0..=1)).collect())
+                .collect();
+            let b: Vec<Vec<u64>> = (0..n)
+                .map(|_| (0..n).map(|_| rng.gen_range(0..q)).collect())
+                .collect();
+            for left in [&a, &binary] {
+                let reference = matrix_product_with("scalar", left, &b, q).unwrap();
+                for name in available_kernels() {
+                    assert_eq!(
+                        matrix_product_with(name, left, &b, q).unwrap(),
+                        reference,
+                        "kernel {} at n = {}",
+                        name,
+                        n
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parallel_threshold_boundary() {
+        let _settings = SETTINGS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut rng = ChaCha20Rng::seed_from_u64(18);
+        let (n, q) = (40, 1u64 << 20);
+        let a: Vec<Vec<u64>> = (0..n)
+            .map(|_| (0..n).map(|_| rng.gen_range(0..=1)).collect())
+            .collect();
+        let b: Vec<Vec<u64>> = (0..n)
+            .map(|_| (0..n).map(|_| rng.gen_range(0..q)).collect())
+            .collect();
+        let reference = matrix_product_with("scalar", &a, &b, q).unwrap();
+        for threshold in [0, n * n - 1, n * n, n * n + 1, usize::MAX] {
+            set_parallel_threshold(threshold);
+            assert_eq!(parallel_threshold(), threshold);
+            assert_eq!(
+                runs_in_parallel(n * n),
+                cfg!(feature = "rayon") && threshold <= n * n
+            );
+            assert_eq!(
+                matrix_product(&a, &b, q),
+                reference,
+                "threshold {}",
+                threshold
+            );
+        }
+        set_parallel_threshold(DEFAULT_PARALLEL_THRESHOLD);
+    }
+
+    #[test]
+    fn test_validation_catches_corrupted_kernel() {
+        let mut rng = ChaCha20Rng::seed_from_u64(19);
+        let (n, q) = (24, 1u64 << 8);
+        let a: Vec<Vec<u64>> = (0..n)
+            .map(|_| (0..n).map(|_| rng.gen_range(0..=1)).collect())
+            .collect();
+        let b: Vec<Vec<u64>> = (0..n)
+            .map(|_| (0..n).map(|_| rng.gen_range(0..q)).collect())
+            .collect();
+        let good = matrix_product_with("scalar", &a, &b, q).unwrap();
+        assert_eq!(validate("scalar", &a, &b, q, &good), Ok(()));
+
+        // A mock kernel that is off by one in a single entry.
+        fn corrupted(a: &[Vec<u64>], b: &[Vec<u64>], q: u64) -> Vec<Vec<u64>> {
+            let mut product = scalar_product(a, b, q);
+            product[5][7] = (product[5][7] + 1) % q;
+            product
+        }
+        let mock = Kernel {
+            name: "mock",
+            product: corrupted,
+            supported: always,
+        };
+        let bad = run(&mock, &a, &b, q);
+        let mismatch = validate(mock.name, &a, &b, q, &bad).unwrap_err();
+        assert_eq!((mismatch.kernel, mismatch.row), ("mock", 5));
+        assert!(
+            mismatch
+                .to_string()
+                .starts_with("kernel mock disagrees with scalar at row 5 of a 24x24"),
+            "{}",
+            mismatch
+        );
+    }
+
+    #[test]
+    fn test_mismatch_is_reported_and_corrected() {
+        static REPORTED: AtomicUsize = AtomicUsize::new(0);
+        fn report(mismatch: &KernelMismatch) {
+            assert_eq!(mismatch.row, 0);
+            REPORTED.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let _settings = SETTINGS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut rng = ChaCha20Rng::seed_from_u64(450);
+        let (n, q) = (24, 1u64 << 8);
+        let a = vec![vec![1; n]; n];
+        let b: Vec<Vec<u64>> = (0..n)
+            .map(|_| (0..n).map(|_| rng.gen_range(0..q)).collect())
+            .collect();
+        let good = matrix_product_with("scalar", &a, &b, q).unwrap();
+
+        set_mismatch_handler(Some(report));
+        corrupt_products_on_this_thread(true);
+        set_validation_policy(ValidationPolicy::Off);
+        let unchecked = matrix_product(&a, &b, q);
+        set_validation_policy(ValidationPolicy::Always);
+        let before = kernel_mismatches();
+        let checked = matrix_product(&a, &b, q);
+        let after = kernel_mismatches();
+        corrupt_products_on_this_thread(false);
+        set_validation_policy(ValidationPolicy::Off);
+        set_mismatch_handler(None);
+
+        assert_ne!(unchecked, good);
+        assert_eq!(checked, good);
+        assert_eq!(after - before, 1);
+        assert_eq!(REPORTED.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_validation_policy_sampling() {
+        let _settings = SETTINGS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        for policy in [ValidationPolicy::Sample(0.25), ValidationPolicy::Always] {
+            set_validation_policy(policy);
+            assert_eq!(validation_policy(), policy);
+        }
+        set_validation_policy(ValidationPolicy::Sample(2.0));
+        assert_eq!(validation_policy(), ValidationPolicy::Always);
+
+        set_validation_policy(ValidationPolicy::Sample(0.25));
+        let sampled = (0..4000).filter(|_| sample_validation()).count();
+        assert!((800..1200).contains(&sampled), "{}", sampled);
+
+        set_validation_policy(ValidationPolicy::Off);
+        assert_eq!(validation_policy(), ValidationPolicy::Off);
+        assert!(!(0..100).any(|_| sample_validation()));
+    }
+
+    #[test]
+    fn test_active_kernel_is_available() {
+        assert!(available_kernels().contains(&active_kernel_name()));
+        assert!(matrix_product_with("no-such-kernel", &[], &[], 4).is_none());
+    }
+
+    #[test]
+    fn test_unknown_kernel_falls_back_to_the_default() {
+        let default = *available_kernels().last().unwrap();
+        let (kernel, warning) = choose(None);
+        assert_eq!((kernel.name, warning), (default, None));
+        let (kernel, warning) = choose(Some("scalar"));
+        assert_eq!((kernel.name, warning), ("scalar", None));
+
+        let (kernel, warning) = choose(Some("no-such-kernel"));
+        assert_eq!(kernel.name, default);
+        let warning = warning.unwrap();
+        assert!(
+            warning.starts_with("GSW_RS_KERNEL=no-such-kernel is not an available kernel"),
+            "{}",
+            warning
+        );
+        assert!(warning.ends_with(&format!("using {}", default)), "{}", warning);
+    }
+}
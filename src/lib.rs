@@ -8,8 +8,7 @@
 //! # Example
 //!
 //! ```ignore
-//! use gsw_rs::{gsw_keygen, encrypt, decrypt, homomorphic_add, homomorphic_mult};
-//! use gsw_rs::params::{Params, SecurityLevel};
+//! use gsw_rs::prelude::*;
 //! use rand::thread_rng;
 //!
 //! let params = Params::toy();
@@ -24,25 +23,169 @@
 //! let ct_and = homomorphic_mult(&params, &ct1, &ct1);
 //! assert_eq!(decrypt(&sk, &ct_and), 1);
 //! ```
+//!
+//! # Stability
+//!
+//! - [`prelude`] is the stable surface: keys, encryption, the gates,
+//!   bootstrapping, sessions, and typed evaluation. Breaking changes to it
+//!   wait for a major version.
+//! - The other public modules and root re-exports are stable in name but may
+//!   gain parameters or variants in minor versions as the lower layers
+//!   evolve. Traits such as [`AsCiphertextRows`] are sealed: the crate can
+//!   add methods to them, and nothing outside it can implement them.
+//! - `experimental` (the `experimental` feature) holds schemes still under
+//!   study, such as multi-key bootstrapping, and may change or go away in any
+//!   release. The module is marked deprecated so that using it warns until
+//!   the code doing so is marked `#[allow(deprecated)]`.
+
+// The test harness names the experimental module's tests by path.
+#![cfg_attr(all(test, feature = "experimental"), allow(deprecated))]
 
+pub mod analysis;
+#[cfg(feature = "ndarray")]
+pub mod arrays;
+#[cfg(feature = "client")]
+pub mod batch;
+#[cfg(feature = "client")]
+pub mod beacon;
+#[cfg(feature = "bench-report")]
+pub mod bench_report;
 pub mod bootstrap;
+pub mod circuits;
+pub mod context;
+pub mod cost;
+pub mod digest;
+pub mod error;
+#[cfg(feature = "experimental")]
+#[deprecated(note = "gsw_rs::experimental is outside the stability guarantee; \
+            acknowledge it with #[allow(deprecated)]")]
+pub mod experimental;
 pub mod gadget;
+pub mod integer;
+pub mod interop;
+#[cfg(feature = "serde")]
+pub mod interop_py;
+pub mod introspect;
+pub mod kernels;
 pub mod lwe;
+#[cfg(feature = "metrics")]
+pub mod metrics;
 pub mod modular;
+pub mod noise_model;
 pub mod params;
+pub mod passphrase;
+#[cfg(feature = "persist")]
+mod persist;
+pub mod prelude;
+#[cfg(feature = "client")]
+pub mod selftest;
+pub mod session;
+pub mod testing;
+pub mod transcript;
+pub mod verify;
+pub mod wire;
 
-pub use bootstrap::{bootstrap, decrypt_linear_part_clear, gen_evaluation_key, EvaluationKey};
-pub use gadget::{bit_decomp, bit_decomp_inverse, flatten, flatten_matrix, powers_of_2};
+pub use analysis::{
+    ciphertext_infinity_norm, fresh_ciphertext_error, public_key_error, row_weight_histogram,
+};
+#[cfg(feature = "ndarray")]
+pub use arrays::{
+    ciphertext_from_ndarray, ciphertext_to_ndarray, public_key_from_ndarray, public_key_to_ndarray,
+};
+#[cfg(feature = "client")]
+pub use batch::{decrypt_bits, encrypt_bits, encrypt_u64_bits};
+#[cfg(feature = "client")]
+pub use beacon::{verify_encryption, BeaconRandomness};
+pub use bootstrap::{
+    bootstrap, bootstrap_cancellable, try_bootstrap, BootstrapDiagnosis, EvaluationKey, TermCheck,
+};
+#[cfg(feature = "client")]
+pub use bootstrap::{
+    decrypt_linear_part_clear, decrypt_linear_parts_all, diagnose_bootstrap, gen_evaluation_key,
+    gen_evaluation_key_flooded,
+};
+pub use circuits::{
+    binary_to_gray, binary_to_onehot, gray_to_binary, homomorphic_extract_bit,
+    homomorphic_freeze_update, homomorphic_one_hot, homomorphic_table_lookup, onehot_to_binary,
+    AnfPolynomial, BoundedLoop, Circuit, CompatReport, EncryptedBits, EncryptedDfa,
+    EncryptedHistogram, EvaluationState, Gate, LoopOutcome, Wire,
+};
+pub use context::{ConstantCacheStats, EncBool, EncryptedVec, Evaluator, GateStrategy, OpCounts};
+pub use cost::{calibrate, CostModel, CostOp};
+pub use error::{
+    BristolError, BristolProblem, CircuitParseError, GswError, ParamsError, ParamsViolation,
+    TranscriptError,
+};
+pub use gadget::{
+    bit_decomp, bit_decomp_inverse, flatten, flatten_matrix, powers_of_2, reverse_bit_order,
+};
+pub use gsw::{
+    bit_to_pm1, ciphertext_digest, ciphertext_from_bytes, ciphertext_to_bytes, ensure_flattened,
+    homomorphic_add, homomorphic_and_many, homomorphic_majority3, homomorphic_mult,
+    homomorphic_mult_cancellable, homomorphic_mult_pm1, homomorphic_mux, homomorphic_nand,
+    homomorphic_nor, homomorphic_not, homomorphic_or, homomorphic_or_many, homomorphic_sub,
+    homomorphic_xnor, homomorphic_xor_const_bit, is_canonical_ciphertext, pm1_to_bit,
+    trivial_ciphertext, try_homomorphic_add, try_homomorphic_mult, AsCiphertextRows, Ciphertext,
+    CiphertextView, EncodedCiphertext, EntryPolicy, GswPublicKey, GswSecretKey,
+};
+#[cfg(feature = "client")]
 pub use gsw::{
-    decrypt, encrypt, gsw_keygen, homomorphic_add, homomorphic_mult, homomorphic_nand,
-    Ciphertext, GswPublicKey, GswSecretKey,
+    decrypt, decrypt_encoded, decrypt_pm1, encrypt, encrypt_encoded, encrypt_pm1,
+    encrypt_with_randomness, gsw_keygen, try_decrypt, DecryptionContext, EncryptionContext,
+};
+#[cfg(feature = "client")]
+pub use integer::{decrypt_int, decrypt_uint, decrypt_uint_u128, encrypt_uint};
+#[cfg(all(feature = "client", feature = "bigint"))]
+pub use integer::{decrypt_uint_big, encrypt_uint_big};
+pub use integer::{encrypted_bits_from_bytes, encrypted_bits_to_bytes, BitOrder};
+pub use integer::{
+    homomorphic_add_saturating_uint, homomorphic_add_uint, homomorphic_add_uint_with_carry,
+    homomorphic_compare_uint, homomorphic_conditional_decrement, homomorphic_conditional_increment,
+    homomorphic_div_const_uint, homomorphic_ge_const, homomorphic_gt_const,
+    homomorphic_increment_mod, homomorphic_inner_product_bits, homomorphic_is_nonzero,
+    homomorphic_is_zero, homomorphic_le_const, homomorphic_lt_const, homomorphic_mean,
+    homomorphic_scale_by_bit, homomorphic_select_uint, homomorphic_sub_saturating_uint,
+    homomorphic_xor_const, ComparisonResult, EncryptedRunningMax, EncryptedUint, Permutation,
+    ONE_HOT_DIVISOR_LIMIT,
+};
+#[cfg(feature = "serde")]
+pub use integer::{FramedUint, FramedUintSeed};
+pub use interop::{
+    from_tfhe_lwe, from_tfhe_lwe_with_modulus, to_tfhe_lwe, to_tfhe_lwe_with_modulus,
 };
-pub use lwe::{keygen, PublicKey, SecretKey};
-pub use params::{Params, SecurityLevel};
+#[cfg(feature = "serde")]
+pub use interop_py::{export_reference_json, import_reference_json};
+pub use introspect::{format_report, FormatReport};
+pub use lwe::{extract_lwe, LweCiphertext, PublicKey, SecretKey, SecretLayout};
+#[cfg(feature = "client")]
+pub use lwe::{keygen, lwe_decrypt};
+#[cfg(feature = "metrics")]
+pub use metrics::{with_memory_tracking, CircuitMemoryStats, MemoryStats, TrackingAllocator};
+pub use modular::{Modulus, Zq};
+pub use params::{Params, PlaintextEncoding, SecurityAssessment, SecurityLevel};
+pub use passphrase::{KdfParams, KdfPolicy};
+#[cfg(feature = "client")]
+pub use selftest::{self_test, SelfTestFailure, SelfTestReport, SelfTestStage, StageReport};
+#[cfg(feature = "client")]
+pub use session::ClientSessionBuilder;
+pub use session::{Session, SessionRole};
+pub use transcript::{
+    verify_transcript, ComputationTranscript, TranscriptEntry, TranscriptOp, TranscriptRecorder,
+};
+
+/// The license the crate is distributed under, as declared in its manifest.
+pub const LICENSE: &str = env!("CARGO_PKG_LICENSE");
+
+/// A notice for applications to show their users: what this crate is, and
+/// what it is not.
+pub const SECURITY_DISCLAIMER: &str = "gsw-rs is a research implementation of GSW \
+    homomorphic encryption. It has not been audited, its presets carry nominal security \
+    levels that have not been checked with a lattice estimator, and Toy and Low give no \
+    meaningful security. Do not rely on it to protect real data.";
 
 mod gsw;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "client"))]
 mod tests {
     use super::*;
     use crate::bootstrap::{bootstrap, gen_evaluation_key};
@@ -61,6 +204,415 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_decryption_context() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ctx = DecryptionContext::new(&sk);
+        let cts: Vec<Ciphertext> = (0..16).map(|i| encrypt(&mut rng, &pk, i % 2)).collect();
+        let expected: Vec<u8> = cts.iter().map(|ct| decrypt(&sk, ct)).collect();
+        assert_eq!(expected, (0..16).map(|i| i % 2).collect::<Vec<u8>>());
+        assert_eq!(ctx.decrypt_many(&cts), expected);
+        assert_eq!(ctx.decrypt_many(&cts.iter().collect::<Vec<_>>()), expected);
+
+        for bit in [0, 1] {
+            assert_eq!(ctx.measure_noise(&trivial_ciphertext(&params, bit)), 0);
+        }
+        let fresh = ctx.measure_noise(&cts[1]).unsigned_abs();
+        let product = homomorphic_mult(&params, &cts[1], &cts[3]);
+        assert_eq!(ctx.decrypt(&product), 1);
+        let noise = ctx.measure_noise(&product).unsigned_abs();
+        assert!(
+            noise > fresh && noise < params.q / 4,
+            "{} vs {}",
+            noise,
+            fresh
+        );
+    }
+
+    #[test]
+    fn test_decryption_vector_layout() {
+        let params = Params::from_raw(1 << 8, 1, 4, 1);
+        let sk = SecretKey::from_parts(vec![1, 3], std::sync::Arc::new(params.clone()));
+        let expected: Vec<u64> = [1u64, 2, 4, 8, 16, 32, 64, 128]
+            .iter()
+            .chain(&[3, 6, 12, 24, 48, 96, 192, 128])
+            .copied()
+            .collect();
+        assert_eq!(*sk.decryption_vector(), expected);
+        assert_eq!(sk.decryption_scale(), expected[params.l - 1]);
+
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let (sk, _) = gsw_keygen(&mut rng, &Params::toy());
+        assert_eq!(*sk.decryption_vector(), powers_of_2(&sk.s, sk.params()));
+        assert_eq!(
+            sk.decryption_scale(),
+            sk.decryption_vector()[sk.params().l - 1]
+        );
+    }
+
+    #[test]
+    fn test_decryption_vector_is_wiped() {
+        use zeroize::Zeroize;
+
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let (sk, _) = gsw_keygen(&mut rng, &params);
+        let mut v = sk.decryption_vector();
+        let (ptr, len) = (v.as_ptr(), v.len());
+        assert!(v.iter().any(|&x| x != 0));
+        // What dropping the wrapper runs before the buffer is freed.
+        v.zeroize();
+        // SAFETY: `v` still owns the allocation; zeroize clears the length,
+        // not the capacity.
+        let buffer = unsafe { std::slice::from_raw_parts(ptr, len) };
+        assert!(buffer.iter().all(|&x| x == 0));
+    }
+
+    /// `ct` serialized raw, each entry replaced by `entry(row, col, value)`.
+    fn raw_ciphertext_bytes(
+        params: &Params,
+        ct: &Ciphertext,
+        entry: impl Fn(usize, usize, u64) -> u64,
+    ) -> Vec<u8> {
+        // Header, params, rows, columns; then the encoding byte and entries.
+        let mut bytes = ciphertext_to_bytes(params, ct);
+        bytes.truncate(wire::HEADER_LEN + wire::PARAMS_LEN + 2 * 8);
+        bytes.push(0);
+        for (i, row) in ct.iter().enumerate() {
+            for (j, &x) in row.iter().enumerate() {
+                bytes.extend_from_slice(&entry(i, j, x).to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_unreduced_entries_rejected_or_repaired() {
+        let params = Params::toy();
+        let q = params.q;
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ct = encrypt(&mut rng, &pk, 1);
+        let bytes = ciphertext_to_bytes(&params, &ct);
+        for policy in [EntryPolicy::Reject, EntryPolicy::ReduceModQ] {
+            assert_eq!(ciphertext_from_bytes(&params, &bytes, policy).unwrap(), ct);
+        }
+
+        // Every entry lifted by a multiple of q: reduces back to `ct`.
+        let lifted = raw_ciphertext_bytes(&params, &ct, |i, j, x| x + ((i + j) % 3) as u64 * q);
+        assert!(matches!(
+            ciphertext_from_bytes(&params, &lifted, EntryPolicy::default()),
+            Err(GswError::UnreducedEntry { row: 0, col: 1, value }) if value == ct[0][1] + q
+        ));
+        let repaired = ciphertext_from_bytes(&params, &lifted, EntryPolicy::ReduceModQ).unwrap();
+        assert_eq!(repaired, ct);
+
+        // An unflattened equivalent: each block's packed value, plus q, in
+        // the block's first slot. Repaired, it is not canonical until
+        // flattened, after which it is `ct` again.
+        let unflattened = raw_ciphertext_bytes(&params, &ct, |i, j, _| {
+            if j % params.l == 0 {
+                bit_decomp_inverse(&ct[i], &params)[j / params.l] + q
+            } else {
+                0
+            }
+        });
+        let repaired =
+            ciphertext_from_bytes(&params, &unflattened, EntryPolicy::ReduceModQ).unwrap();
+        assert!(!is_canonical_ciphertext(&params, &repaired));
+        assert_eq!(decrypt(&sk, &repaired), 1);
+        let flattened = ensure_flattened(&params, repaired);
+        assert_eq!(flattened, ct);
+        let zero = encrypt(&mut rng, &pk, 0);
+        assert_eq!(
+            decrypt(&sk, &homomorphic_nand(&params, &flattened, &zero)),
+            1
+        );
+
+        assert!(matches!(
+            ciphertext_from_bytes(&Params::pedagogical(), &bytes, EntryPolicy::Reject),
+            Err(GswError::ParamsMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_sub_and_xor_const_bit() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ctx = DecryptionContext::new(&sk);
+        for b in [0u8, 1] {
+            let x = encrypt(&mut rng, &pk, b);
+            let y = encrypt(&mut rng, &pk, b);
+            let zero = homomorphic_sub(&params, &x, &y).unwrap();
+            assert_eq!(decrypt(&sk, &zero), 0);
+            let one = encrypt(&mut rng, &pk, 1);
+            assert_eq!(decrypt(&sk, &homomorphic_nand(&params, &zero, &one)), 1);
+
+            // Both are linear in the phase: the noise is e_x - e_y and e_x + e_y.
+            let (e_x, e_y) = (ctx.measure_noise(&x), ctx.measure_noise(&y));
+            assert_eq!(ctx.measure_noise(&zero), e_x - e_y);
+            let sum = homomorphic_add(&params, &x, &y);
+            assert_eq!(ctx.measure_noise(&sum), e_x + e_y);
+
+            for bit in [0u8, 1] {
+                let flipped = homomorphic_xor_const_bit(&params, &x, bit).unwrap();
+                assert_eq!(decrypt(&sk, &flipped), b ^ bit);
+            }
+        }
+
+        let x = encrypt(&mut rng, &pk, 1);
+        let short = x[..params.n_expanded - 1].to_vec();
+        assert!(matches!(
+            homomorphic_sub(&params, &x, &short),
+            Err(GswError::DimensionMismatch { rows, expected, .. })
+                if rows == expected - 1
+        ));
+        assert!(homomorphic_xor_const_bit(&params, &short, 1).is_err());
+    }
+
+    #[test]
+    fn test_pm1_encoding() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(479);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ctx = DecryptionContext::new(&sk);
+        for value in [1i8, -1] {
+            let ct = encrypt_pm1(&mut rng, &pk, value).unwrap();
+            assert_eq!(decrypt_pm1(&sk, &ct), value);
+
+            // b = (value + 1) / 2, and back; only the noise's sign changes.
+            let bit = pm1_to_bit(&params, &ct);
+            assert_eq!(decrypt(&sk, &bit), (value + 1) as u8 / 2);
+            assert_eq!(ctx.measure_noise(&bit), -ctx.measure_noise(&ct));
+            assert_eq!(bit_to_pm1(&params, &bit), ct);
+            let back = pm1_to_bit(&params, &bit_to_pm1(&params, &bit));
+            assert_eq!(back, bit);
+        }
+        for value in [0i8, 2, -2, i8::MIN] {
+            assert!(matches!(
+                encrypt_pm1(&mut rng, &pk, value),
+                Err(GswError::NotPlusMinusOne { value: v }) if v == value
+            ));
+        }
+
+        // ±1 multiplication is XOR of the sign bits; AND is not it.
+        for a in [1i8, -1] {
+            for b in [1i8, -1] {
+                let x = encrypt_pm1(&mut rng, &pk, a).unwrap();
+                let y = encrypt_pm1(&mut rng, &pk, b).unwrap();
+                let product = homomorphic_mult_pm1(&params, &x, &y);
+                assert_eq!(decrypt_pm1(&sk, &product), a * b, "{} · {}", a, b);
+                assert_eq!(decrypt(&sk, &product), decrypt(&sk, &x) ^ decrypt(&sk, &y));
+                let and = decrypt_pm1(&sk, &homomorphic_mult(&params, &x, &y));
+                assert_eq!(and == a * b, (a, b) == (1, 1));
+            }
+        }
+    }
+
+    #[test]
+    fn test_plaintext_encodings() {
+        let lsb = Params::toy();
+        let msb = lsb.clone().with_encoding(PlaintextEncoding::MsbThreshold);
+        let mut rng = ChaCha20Rng::seed_from_u64(487);
+        let (sk, pk) = gsw_keygen(&mut rng, &msb);
+        for bit in [0, 1] {
+            let ct = encrypt_encoded(&mut rng, &pk, bit);
+            assert_eq!(ct.encoding, PlaintextEncoding::MsbThreshold);
+            assert_eq!(decrypt_encoded(&sk, &ct), bit);
+            assert_eq!(decrypt(&sk, &ct.ct), bit);
+        }
+
+        // Gates mean the same under the threshold rule.
+        for a in [0, 1] {
+            for b in [0, 1] {
+                let x = encrypt_encoded(&mut rng, &pk, a);
+                let y = encrypt_encoded(&mut rng, &pk, b);
+                let gates = [
+                    (x.add(&msb, &y).unwrap(), a ^ b),
+                    (x.mult(&msb, &y).unwrap(), a & b),
+                    (x.nand(&msb, &y).unwrap(), 1 - (a & b)),
+                    (x.not(&msb), 1 - a),
+                ];
+                for (out, expected) in gates {
+                    assert_eq!(out.encoding, PlaintextEncoding::MsbThreshold);
+                    assert_eq!(decrypt_encoded(&sk, &out), expected, "{} {}", a, b);
+                }
+            }
+        }
+
+        // Encryption is the same under both, so a NAND chain carries the same
+        // noise; both rules decode it while that noise is below q/4.
+        let params = Params::custom(1 << 40, 8, 256, 1, -40).unwrap();
+        let (sk_lsb, pk) = gsw_keygen(&mut ChaCha20Rng::seed_from_u64(487), &params);
+        let msb_params = params
+            .clone()
+            .with_encoding(PlaintextEncoding::MsbThreshold);
+        let (sk_msb, _) = gsw_keygen(&mut ChaCha20Rng::seed_from_u64(487), &msb_params);
+        let (ctx_lsb, ctx_msb) = (
+            DecryptionContext::new(&sk_lsb),
+            DecryptionContext::new(&sk_msb),
+        );
+        let mut acc = encrypt(&mut rng, &pk, 1);
+        let mut bit = 1;
+        for step in 0..3 {
+            let y = encrypt(&mut rng, &pk, step % 2);
+            acc = homomorphic_nand(&params, &acc, &y);
+            bit = 1 - (bit & (step % 2));
+            assert_eq!((ctx_lsb.decrypt(&acc), ctx_msb.decrypt(&acc)), (bit, bit));
+            assert_eq!(ctx_lsb.measure_noise(&acc), ctx_msb.measure_noise(&acc));
+        }
+
+        // The rules part only at phases exactly q/4 from a message: 3q/4
+        // rounds up to 2, which is even, but lies at the threshold.
+        let l = lsb.l;
+        let mut ct = trivial_ciphertext(&lsb, 1);
+        ct[l - 1][l - 2] = 1;
+        let lsb_ct = EncodedCiphertext::new(ct.clone(), PlaintextEncoding::LsbOfScale);
+        let msb_ct = EncodedCiphertext::new(ct, PlaintextEncoding::MsbThreshold);
+        assert_eq!(decrypt_encoded(&sk, &lsb_ct), 0);
+        assert_eq!(decrypt_encoded(&sk, &msb_ct), 1);
+
+        assert!(matches!(
+            lsb_ct.add(&lsb, &msb_ct),
+            Err(GswError::EncodingMismatch {
+                left: PlaintextEncoding::LsbOfScale,
+                right: PlaintextEncoding::MsbThreshold,
+            })
+        ));
+        assert!(msb_ct.mult(&lsb, &lsb_ct).is_err());
+        assert!(msb_ct.nand(&lsb, &lsb_ct).is_err());
+    }
+
+    /// A product left unflattened: decrypts fine, but has full-size entries.
+    fn raw_product(seed: u64) -> (Params, GswSecretKey, GswPublicKey, Ciphertext) {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ca = encrypt(&mut rng, &pk, 1);
+        let cb = encrypt(&mut rng, &pk, 1);
+        let raw = kernels::matrix_product(&ca, &cb, params.q);
+        (params, sk, pk, raw)
+    }
+
+    #[test]
+    #[cfg(any(debug_assertions, feature = "checked-arith"))]
+    #[should_panic(expected = "homomorphic_mult: left operand is not flattened")]
+    fn test_unflattened_operand_is_reported() {
+        let (params, _, _, raw) = raw_product(7);
+        homomorphic_mult(&params, &raw, &trivial_ciphertext(&params, 1));
+    }
+
+    #[test]
+    fn test_ensure_flattened() {
+        let (params, sk, pk, raw) = raw_product(7);
+        assert!(!is_canonical_ciphertext(&params, &raw));
+        assert_eq!(decrypt(&sk, &raw), 1);
+        let flat = ensure_flattened(&params, raw);
+        assert!(is_canonical_ciphertext(&params, &flat));
+        assert_eq!(ensure_flattened(&params, flat.clone()), flat);
+
+        let mut rng = ChaCha20Rng::seed_from_u64(8);
+        let ct = encrypt(&mut rng, &pk, 1);
+        assert_eq!(decrypt(&sk, &homomorphic_nand(&params, &flat, &ct)), 0);
+    }
+
+    #[test]
+    fn test_boundaries_finalize_unflattened_ciphertexts() {
+        let (params, sk, pk, raw) = raw_product(498);
+        let mut rng = ChaCha20Rng::seed_from_u64(498);
+        let ek = gen_evaluation_key(&mut rng, &sk, &pk);
+        // A sum of raw matrices, never reduced mod q.
+        let unreduced: Ciphertext = raw
+            .iter()
+            .map(|row| row.iter().map(|&x| x + params.q).collect())
+            .collect();
+        let inputs = [
+            ("canonical", encrypt(&mut rng, &pk, 1)),
+            ("unflattened", raw),
+            ("unreduced", unreduced),
+        ];
+        for (label, ct) in &inputs {
+            let flat = ensure_flattened(&params, ct.clone());
+            let bytes = ciphertext_to_bytes(&params, ct);
+            assert_eq!(
+                ciphertext_from_bytes(&params, &bytes, EntryPolicy::Reject).unwrap(),
+                flat,
+                "{}",
+                label
+            );
+            let bytes = encrypted_bits_to_bytes(&params, std::slice::from_ref(ct));
+            assert_eq!(
+                encrypted_bits_from_bytes(&params, &bytes).unwrap(),
+                std::slice::from_ref(&flat),
+                "{}",
+                label
+            );
+            assert_eq!(decrypt(&sk, ct), 1, "{}", label);
+            assert_eq!(
+                extract_lwe(&params, ct),
+                extract_lwe(&params, &flat),
+                "{}",
+                label
+            );
+            assert_eq!(
+                bootstrap(&params, ct, &ek),
+                bootstrap(&params, &flat, &ek),
+                "{}",
+                label
+            );
+            assert_eq!(
+                ciphertext_digest(&params, ct),
+                digest::digest(&flat),
+                "{}",
+                label
+            );
+        }
+    }
+
+    #[test]
+    fn test_recomposition_is_exact_for_any_entry() {
+        for params in [Params::toy(), Params::from_raw(1 << 62, 1, 4, 1)] {
+            let (q, l) = (params.q as u128, params.l);
+            // Entries just below, at, and past q, and the largest u64.
+            let edges = [params.q - 1, params.q, params.q + 1, u64::MAX - 1, u64::MAX];
+            let row: Vec<u64> = (0..2 * l).map(|i| edges[i % edges.len()]).collect();
+            // Reference: each term reduced mod q in 128 bits before summing.
+            let expected: Vec<u64> = row
+                .chunks(l)
+                .map(|block| {
+                    let sum = block.iter().enumerate().fold(0u128, |acc, (i, &x)| {
+                        (acc + (x as u128 % q) * (1u128 << i) % q) % q
+                    });
+                    sum as u64
+                })
+                .collect();
+            assert_eq!(bit_decomp_inverse(&row, &params), expected);
+            assert_eq!(flatten(&row, &params), bit_decomp(&expected, &params));
+        }
+    }
+
+    #[test]
+    fn test_tiny_dimensions() {
+        for params in [Params::from_raw(1 << 8, 1, 4, 1), Params::pedagogical()] {
+            for seed in 0..20 {
+                let mut rng = ChaCha20Rng::seed_from_u64(seed);
+                let (sk, pk) = gsw_keygen(&mut rng, &params);
+                for a in [0u8, 1] {
+                    for b in [0u8, 1] {
+                        let ca = encrypt(&mut rng, &pk, a);
+                        let cb = encrypt(&mut rng, &pk, b);
+                        assert_eq!(decrypt(&sk, &ca), a);
+                        assert_eq!(decrypt(&sk, &homomorphic_add(&params, &ca, &cb)), a ^ b);
+                        assert_eq!(decrypt(&sk, &homomorphic_mult(&params, &ca, &cb)), a & b);
+                    }
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_homomorphic_ops() {
         let params = Params::toy();
@@ -78,6 +630,210 @@ mod tests {
         assert_eq!(decrypt(&sk, &homomorphic_nand(&params, &ct1, &ct1)), 0);
     }
 
+    #[test]
+    fn test_homomorphic_not() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(504);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ctx = DecryptionContext::new(&sk);
+        for b in [0u8, 1] {
+            let ct = encrypt(&mut rng, &pk, b);
+            let not = homomorphic_not(&params, &ct);
+            let nand = homomorphic_nand(&params, &ct, &ct);
+            assert_eq!(decrypt(&sk, &not), 1 - b);
+            assert_eq!(decrypt(&sk, &nand), 1 - b);
+            assert_eq!(homomorphic_not(&params, &not), ct);
+
+            // The complement only negates the noise; NAND(ct, ct) multiplies.
+            assert_eq!(ctx.measure_noise(&not), -ctx.measure_noise(&ct));
+            assert!(
+                ctx.measure_noise(&not).abs() < ctx.measure_noise(&nand).abs(),
+                "bit {}",
+                b
+            );
+
+            for c in [0u8, 1] {
+                let y = encrypt(&mut rng, &pk, c);
+                assert_eq!(
+                    decrypt(&sk, &homomorphic_mult(&params, &not, &y)),
+                    (1 - b) & c
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_homomorphic_or() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(505);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ctx = DecryptionContext::new(&sk);
+        for a in [0u8, 1] {
+            for b in [0u8, 1] {
+                let (x, y) = (encrypt(&mut rng, &pk, a), encrypt(&mut rng, &pk, b));
+                let or = homomorphic_or(&params, &x, &y);
+                assert_eq!(decrypt(&sk, &or), a | b, "{} OR {}", a, b);
+
+                // Linear in the phase: e_x + e_y minus the product's noise.
+                let and = homomorphic_mult(&params, &x, &y);
+                assert_eq!(
+                    ctx.measure_noise(&or),
+                    ctx.measure_noise(&x) + ctx.measure_noise(&y) - ctx.measure_noise(&and)
+                );
+            }
+        }
+
+        let mut rng = thread_rng();
+        for _ in 0..20 {
+            let (sk, pk) = gsw_keygen(&mut rng, &params);
+            for a in [0u8, 1] {
+                for b in [0u8, 1] {
+                    let (x, y) = (encrypt(&mut rng, &pk, a), encrypt(&mut rng, &pk, b));
+                    assert_eq!(decrypt(&sk, &homomorphic_or(&params, &x, &y)), a | b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_homomorphic_nor_xnor() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(506);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ctx = DecryptionContext::new(&sk);
+        for a in [0u8, 1] {
+            for b in [0u8, 1] {
+                let (x, y) = (encrypt(&mut rng, &pk, a), encrypt(&mut rng, &pk, b));
+                let nor = homomorphic_nor(&params, &x, &y);
+                let xnor = homomorphic_xnor(&params, &x, &y);
+                assert_eq!(decrypt(&sk, &nor), 1 - (a | b), "{} NOR {}", a, b);
+                assert_eq!(decrypt(&sk, &xnor), 1 - (a ^ b), "{} XNOR {}", a, b);
+                assert_eq!(
+                    homomorphic_not(&params, &homomorphic_or(&params, &x, &y)),
+                    nor
+                );
+                assert_eq!(
+                    ctx.measure_noise(&xnor),
+                    -(ctx.measure_noise(&x) + ctx.measure_noise(&y))
+                );
+
+                // The outputs are ordinary flattened ciphertexts.
+                let z = encrypt(&mut rng, &pk, 1);
+                let and = homomorphic_mult(&params, &nor, &z);
+                assert_eq!(decrypt(&sk, &and), 1 - (a | b));
+                let nand = homomorphic_nand(&params, &xnor, &nor);
+                assert_eq!(decrypt(&sk, &nand), 1 - ((1 - (a ^ b)) & (1 - (a | b))));
+                let both = homomorphic_xnor(&params, &nor, &xnor);
+                assert_eq!(decrypt(&sk, &both), 1 - ((1 - (a | b)) ^ (1 - (a ^ b))));
+            }
+        }
+    }
+
+    #[test]
+    fn test_homomorphic_mux() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(507);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        for s in [0u8, 1] {
+            for a in [0u8, 1] {
+                for b in [0u8, 1] {
+                    let sel = encrypt(&mut rng, &pk, s);
+                    let (x, y) = (encrypt(&mut rng, &pk, a), encrypt(&mut rng, &pk, b));
+                    let mux = homomorphic_mux(&params, &sel, &x, &y);
+                    let expected = if s == 1 { a } else { b };
+                    assert_eq!(decrypt(&sk, &mux), expected, "{} ? {} : {}", s, a, b);
+
+                    // Depth 2: the first result feeds a second select.
+                    for t in [0u8, 1] {
+                        let sel2 = encrypt(&mut rng, &pk, t);
+                        let other = encrypt(&mut rng, &pk, 1 - expected);
+                        let chained = homomorphic_mux(&params, &sel2, &mux, &other);
+                        let want = if t == 1 { expected } else { 1 - expected };
+                        assert_eq!(decrypt(&sk, &chained), want);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_homomorphic_majority3() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(508);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ek = gen_evaluation_key(&mut rng, &sk, &pk);
+        for a in [0u8, 1] {
+            for b in [0u8, 1] {
+                for c in [0u8, 1] {
+                    let [x, y, z] = [a, b, c].map(|bit| encrypt(&mut rng, &pk, bit));
+                    let maj = homomorphic_majority3(&params, &x, &y, &z);
+                    let expected = u8::from(a + b + c >= 2);
+                    assert_eq!(decrypt(&sk, &maj), expected, "MAJ({}, {}, {})", a, b, c);
+
+                    // A well-formed bootstrap input: the diagnosis reproduces
+                    // its phase and agrees with the bootstrap output.
+                    let refreshed = try_bootstrap(&params, &maj, &ek).unwrap();
+                    let diagnosis = diagnose_bootstrap(&sk, &params, &maj, &ek).unwrap();
+                    assert_eq!(diagnosis.input_bit, expected);
+                    assert!(diagnosis.input_margin > 0 && diagnosis.coefficients_match);
+                    assert_eq!(diagnosis.output_bit, decrypt(&sk, &refreshed));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_homomorphic_and_or_many() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(509);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        assert_eq!(
+            homomorphic_and_many(&params, &[]),
+            trivial_ciphertext(&params, 1)
+        );
+        assert_eq!(
+            homomorphic_or_many(&params, &[]),
+            trivial_ciphertext(&params, 0)
+        );
+        let single = [encrypt(&mut rng, &pk, 1)];
+        assert_eq!(homomorphic_and_many(&params, &single), single[0]);
+        assert_eq!(homomorphic_or_many(&params, &single), single[0]);
+
+        for k in [8, 12, 16] {
+            let ones: Vec<Ciphertext> = (0..k).map(|_| encrypt(&mut rng, &pk, 1)).collect();
+            let zeros: Vec<Ciphertext> = (0..k).map(|_| encrypt(&mut rng, &pk, 0)).collect();
+            assert_eq!(
+                decrypt(&sk, &homomorphic_and_many(&params, &ones)),
+                1,
+                "k = {}",
+                k
+            );
+            assert_eq!(
+                decrypt(&sk, &homomorphic_or_many(&params, &zeros)),
+                0,
+                "k = {}",
+                k
+            );
+            for i in [0, k / 2, k - 1] {
+                let mut one_zero = ones.clone();
+                one_zero[i] = zeros[i].clone();
+                assert_eq!(decrypt(&sk, &homomorphic_and_many(&params, &one_zero)), 0);
+                let mut one_one = zeros.clone();
+                one_one[i] = ones[i].clone();
+                assert_eq!(decrypt(&sk, &homomorphic_or_many(&params, &one_one)), 1);
+            }
+        }
+
+        // Folding with the accumulator as the right operand instead scales
+        // its noise by the row weight of every fresh operand.
+        let ones: Vec<Ciphertext> = (0..16).map(|_| encrypt(&mut rng, &pk, 1)).collect();
+        let right_fold = ones[1..].iter().fold(ones[0].clone(), |acc, ct| {
+            homomorphic_mult(&params, ct, &acc)
+        });
+        assert_eq!(decrypt(&sk, &right_fold), 0);
+        assert_eq!(decrypt(&sk, &homomorphic_and_many(&params, &ones)), 1);
+    }
+
     #[test]
     fn test_homomorphic_ops_deterministic() {
         let params = Params::toy();
@@ -112,6 +868,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_decrypt_linear_part_clear_rows() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ct = encrypt(&mut rng, &pk, 1);
+
+        let all = decrypt_linear_parts_all(&sk, &ct);
+        assert_eq!(all.len(), params.n_expanded);
+        let default_row = decrypt_linear_part_clear(&sk, &ct, None).unwrap();
+        assert_eq!(default_row, all[params.l - 1]);
+        assert_eq!(
+            decrypt_linear_part_clear(&sk, &ct, Some(0)).unwrap(),
+            all[0]
+        );
+
+        // Row l-1 carries μ·q/2 plus small noise.
+        let centered =
+            modular::mod_q_centered(default_row as i64 - (params.q / 2) as i64, params.q);
+        assert!(centered.abs() < (params.q / 4) as i64);
+
+        assert!(matches!(
+            decrypt_linear_part_clear(&sk, &ct, Some(params.n_expanded)),
+            Err(GswError::RowOutOfRange { .. })
+        ));
+        let truncated: Ciphertext = ct.iter().map(|row| row[..row.len() - 1].to_vec()).collect();
+        assert!(matches!(
+            decrypt_linear_part_clear(&sk, &truncated, None),
+            Err(GswError::MalformedData(_))
+        ));
+        assert!(matches!(
+            decrypt_linear_part_clear(&sk, &Vec::new(), None),
+            Err(GswError::RowOutOfRange { .. })
+        ));
+    }
+
     #[test]
     fn test_bootstrap_nondeterministic() {
         // Bootstrap adds N encryptions; verify it works with thread_rng() (non-deterministic).
@@ -1,22 +1,105 @@
 //! LWE (Learning With Errors) primitives.
 
+use std::sync::Arc;
+
+#[cfg(feature = "client")]
 use rand::Rng;
 
+#[cfg(any(feature = "client", feature = "serde"))]
+use zeroize::Zeroizing;
+
+use crate::error::GswError;
+use crate::gadget::bit_decomp_inverse;
+#[cfg(feature = "client")]
+use crate::gadget::powers_of_2;
+use crate::gsw::AsCiphertextRows;
+#[cfg(feature = "client")]
 use crate::modular::mod_q;
 use crate::params::Params;
+use crate::verify::VerifyParams;
+use crate::wire::{self, ObjectKind};
 
 /// Secret key: vector t in Z_q^n. Stored as (1, -t) for GSW compatibility.
 #[derive(Clone, Debug)]
 pub struct SecretKey {
     /// Full secret vector s = (1, -t_1, ..., -t_n) in Z_q^{n+1}
     pub s: Vec<u64>,
-    params: Params,
+    params: Arc<Params>,
 }
 
 impl SecretKey {
+    pub(crate) fn from_parts(s: Vec<u64>, params: Arc<Params>) -> Self {
+        Self { s, params }
+    }
+
     pub fn params(&self) -> &Params {
         &self.params
     }
+
+    /// The shared parameter handle, for pointer-equality checks.
+    pub(crate) fn params_arc(&self) -> &Arc<Params> {
+        &self.params
+    }
+
+    /// The decryption vector v = PowersOf2(s): for each entry s_i, the values
+    /// s_i·2^j mod q for j = 0..l, so v has N entries and C · v ≈ μ·v.
+    ///
+    /// This is secret key material, as sensitive as `s` itself. The buffer is
+    /// wiped when the returned value is dropped; do not copy it out.
+    #[cfg(feature = "client")]
+    pub fn decryption_vector(&self) -> Zeroizing<Vec<u64>> {
+        Zeroizing::new(powers_of_2(&self.s, &self.params))
+    }
+
+    /// v[l-1] = 2^(l-1)·s_0 mod q, the value a 1 contributes to the decryption
+    /// row's inner product; decryption rounds C[l-1] · v to a multiple of it.
+    pub fn decryption_scale(&self) -> u64 {
+        (self.s[0] << (self.params.l - 1)) % self.params.q
+    }
+
+    /// What [`check_plaintext`](crate::verify::check_plaintext) needs to
+    /// know of the parameters, alongside [`decryption_vector`](Self::decryption_vector).
+    pub fn verify_params(&self) -> VerifyParams {
+        VerifyParams {
+            q: self.params.q,
+            l: self.params.l,
+            n_expanded: self.params.n_expanded,
+            scale: self.decryption_scale(),
+        }
+    }
+}
+
+/// Where the constant 1 sits in the secret vector, which fixes where the body
+/// sits in public-key rows and LWE ciphertexts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SecretLayout {
+    /// s = (1, -t), public-key rows (b, a): this crate's layout.
+    #[default]
+    OneFirst,
+    /// s = (t', 1), public-key rows (a, b) with b = -<a, t'> + e. The same key
+    /// as `OneFirst` with t' = -t, rotated by one coordinate.
+    OneLast,
+}
+
+impl SecretLayout {
+    /// n + 1 coordinates (of s, a public-key row, or an LWE ciphertext)
+    /// reordered from this crate's layout into this one.
+    pub fn from_native<T: Clone>(self, coords: &[T]) -> Vec<T> {
+        let mut coords = coords.to_vec();
+        if self == SecretLayout::OneLast {
+            coords.rotate_left(1);
+        }
+        coords
+    }
+
+    /// The inverse of [`from_native`](Self::from_native).
+    pub fn to_native<T: Clone>(self, coords: &[T]) -> Vec<T> {
+        let mut coords = coords.to_vec();
+        if self == SecretLayout::OneLast {
+            coords.rotate_right(1);
+        }
+        coords
+    }
 }
 
 /// Public key: LWE matrix A where b = A*s + e (approximately).
@@ -25,21 +108,104 @@ impl SecretKey {
 pub struct PublicKey {
     /// Matrix A where each row is (b_i, a_i1, ..., a_in)
     pub a: Vec<Vec<u64>>,
-    params: Params,
+    params: Arc<Params>,
 }
 
 impl PublicKey {
+    pub(crate) fn from_parts(a: Vec<Vec<u64>>, params: Arc<Params>) -> Self {
+        Self { a, params }
+    }
+
     pub fn params(&self) -> &Params {
         &self.params
     }
+
+    /// The shared parameter handle, for pointer-equality checks.
+    pub(crate) fn params_arc(&self) -> &Arc<Params> {
+        &self.params
+    }
+
+    /// Serialize: header, then the key (params, rows, columns, entries
+    /// row-major; see the `wire` module docs).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = wire::buffer(wire::HEADER_LEN as u64 + wire::public_key_len(&self.params));
+        wire::write_header(&mut out, ObjectKind::PublicKey);
+        wire::write_public_key(&mut out, self);
+        out
+    }
+
+    /// Deserialize a key written under `params`, rejecting one whose shape
+    /// or entries do not fit them.
+    pub fn from_bytes(params: &Params, bytes: &[u8]) -> Result<Self, GswError> {
+        let mut reader = wire::Reader::new(bytes);
+        reader.header(ObjectKind::PublicKey)?;
+        let pk = reader.public_key()?;
+        // Only the instance is stored, not the failure target.
+        let stored = pk.params();
+        if (stored.q, stored.n, stored.m, stored.error_bound)
+            != (params.q, params.n, params.m, params.error_bound)
+        {
+            return Err(GswError::ParamsMismatch);
+        }
+        reader.finish()?;
+        Ok(Self::from_parts(pk.a, Arc::new(params.clone())))
+    }
+}
+
+/// Serialized as the bytes a session stores for the key: its params, the
+/// length, and the entries. The buffers are zeroized after use.
+#[cfg(feature = "serde")]
+impl serde::Serialize for SecretKey {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut out = Zeroizing::new(wire::buffer(wire::secret_key_len(&self.params)));
+        wire::write_secret_key(&mut out, self);
+        serializer.serialize_bytes(&out)
+    }
+}
+
+/// Rejects bytes that decoding a session would, such as unreduced entries.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SecretKey {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = Zeroizing::new(<Vec<u8> as serde::Deserialize>::deserialize(deserializer)?);
+        let mut reader = wire::Reader::new(&bytes);
+        let sk = reader.secret_key().map_err(serde::de::Error::custom)?;
+        reader.finish().map_err(serde::de::Error::custom)?;
+        Ok(sk)
+    }
+}
+
+/// Serialized as the bytes a session stores for the key: its params, the
+/// shape of A, and the entries row by row.
+#[cfg(feature = "serde")]
+impl serde::Serialize for PublicKey {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut out = wire::buffer(wire::public_key_len(&self.params));
+        wire::write_public_key(&mut out, self);
+        serializer.serialize_bytes(&out)
+    }
+}
+
+/// Rejects bytes that decoding a session would, such as a misshapen A.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PublicKey {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = <Vec<u8> as serde::Deserialize>::deserialize(deserializer)?;
+        let mut reader = wire::Reader::new(&bytes);
+        let pk = reader.public_key().map_err(serde::de::Error::custom)?;
+        reader.finish().map_err(serde::de::Error::custom)?;
+        Ok(pk)
+    }
 }
 
 /// Generate a random value in Z_q.
+#[cfg(feature = "client")]
 fn rand_zq<R: Rng>(rng: &mut R, q: u64) -> u64 {
     rng.gen_range(0..q)
 }
 
 /// Generate a small error in [-B, B] for LWE.
+#[cfg(feature = "client")]
 fn sample_error<R: Rng>(rng: &mut R, bound: i64) -> i64 {
     if bound <= 0 {
         return 0;
@@ -48,35 +214,60 @@ fn sample_error<R: Rng>(rng: &mut R, bound: i64) -> i64 {
 }
 
 /// Generate secret key: random t in Z_q^n, return s = (1, -t).
+///
+/// Both keys share one `Arc<Params>`.
+#[cfg(feature = "client")]
 pub fn keygen<R: Rng>(rng: &mut R, params: &Params) -> (SecretKey, PublicKey) {
+    let t = sample_secret(rng, params);
+    let b_mat = sample_b(rng, params);
+    key_pair(rng, params, &t, &b_mat)
+}
+
+/// A uniform m×n matrix B, the public part of an LWE key.
+#[cfg(feature = "client")]
+pub(crate) fn sample_b<R: Rng>(rng: &mut R, params: &Params) -> Vec<Vec<u64>> {
+    (0..params.m)
+        .map(|_| (0..params.n).map(|_| rand_zq(rng, params.q)).collect())
+        .collect()
+}
+
+/// Secret vector t in Z_q^n.
+#[cfg(feature = "client")]
+pub(crate) fn sample_secret<R: Rng>(rng: &mut R, params: &Params) -> Vec<u64> {
+    (0..params.n).map(|_| rand_zq(rng, params.q)).collect()
+}
+
+/// Keys for secret t and matrix B, sampling the errors e.
+#[cfg(feature = "client")]
+pub(crate) fn key_pair<R: Rng>(
+    rng: &mut R,
+    params: &Params,
+    t: &[u64],
+    b_mat: &[Vec<u64>],
+) -> (SecretKey, PublicKey) {
     let n = params.n;
     let m = params.m;
     let q = params.q;
     let bound = params.error_bound;
 
-    // Secret vector t in Z_q^n
-    let t: Vec<u64> = (0..n).map(|_| rand_zq(rng, q)).collect();
-
     // s = (1, -t_1, ..., -t_n)
     let mut s = vec![1u64];
-    for &ti in &t {
+    for &ti in t {
         s.push(mod_q(-(ti as i64), q));
     }
 
     // Public key: A = [b | B] where b = B*t + e
-    let b_mat: Vec<Vec<u64>> = (0..m)
-        .map(|_| (0..n).map(|_| rand_zq(rng, q)).collect())
-        .collect();
-
     let e: Vec<i64> = (0..m).map(|_| sample_error(rng, bound)).collect();
 
     let mut b = vec![0u64; m];
     for i in 0..m {
-        let mut dot: i64 = 0;
+        // q is a power of two, so wrapping u64 arithmetic is exact mod q and
+        // cannot overflow for large moduli.
+        let mut dot: u64 = 0;
         for j in 0..n {
-            dot += b_mat[i][j] as i64 * t[j] as i64;
+            dot = dot.wrapping_add(b_mat[i][j].wrapping_mul(t[j]));
         }
-        b[i] = mod_q(dot + e[i], q);
+        b[i] = mod_q((dot % q) as i64 + e[i], q);
     }
 
     let mut a = vec![vec![0u64; n + 1]; m];
@@ -87,14 +278,50 @@ pub fn keygen<R: Rng>(rng: &mut R, params: &Params) -> (SecretKey, PublicKey) {
         }
     }
 
+    let params = Arc::new(params.clone());
     (
         SecretKey {
-            s: s.clone(),
-            params: params.clone(),
-        },
-        PublicKey {
-            a,
-            params: params.clone(),
+            s,
+            params: Arc::clone(&params),
         },
+        PublicKey { a, params },
     )
 }
+
+/// LWE ciphertext c in Z_q^{n+1} with <c, s> = μ·q/2 + e.
+///
+/// Same layout as the secret key: c[0] pairs with s[0] = 1, so it plays the
+/// role of the body, and c[1..] is the mask.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LweCiphertext {
+    pub c: Vec<u64>,
+}
+
+/// Extract the LWE ciphertext hidden in row l-1 of a GSW ciphertext.
+///
+/// Decryption computes C[l-1]·PowersOf2(s) = <BitDecompInverse(C[l-1]), s>,
+/// so the inverse decomposition of that row is an LWE encryption of the same
+/// bit with scale q/2. Only that row is read, so a view over it will do.
+///
+/// Panics if `ct` does not have row l-1.
+pub fn extract_lwe<C: AsCiphertextRows + ?Sized>(params: &Params, ct: &C) -> LweCiphertext {
+    let row = ct
+        .row(params.l - 1)
+        .expect("ciphertext is missing the decryption row l-1");
+    LweCiphertext {
+        c: bit_decomp_inverse(row, params),
+    }
+}
+
+/// Decrypt an LWE ciphertext: round <c, s> / (q/2) and reduce mod 2.
+#[cfg(feature = "client")]
+pub fn lwe_decrypt(sk: &SecretKey, ct: &LweCiphertext) -> u8 {
+    let q = sk.params().q;
+    let phase =
+        ct.c.iter()
+            .zip(&sk.s)
+            .fold(0u64, |acc, (&c, &s)| acc.wrapping_add(c.wrapping_mul(s)))
+            % q;
+    // Round to the nearest multiple of q/2.
+    (((phase + q / 4) % q) / (q / 2)) as u8
+}
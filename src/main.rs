@@ -1,12 +1,127 @@
 //! Demo: GSW FHE with bootstrapping.
+//!
+//! `gsw-rs info [--level toy|low|medium]` prints the serialized object sizes
+//! for a preset instead, and the estimated bootstrap time of every preset on
+//! this machine.
+//!
+//! `gsw-rs self-test [--level toy|low|medium] [--seed N]` runs
+//! [`gsw_rs::self_test`] (at Toy by default) and exits with status 1 if any
+//! stage fails, for use as a deployment health check.
 
 use gsw_rs::bootstrap::{bootstrap, gen_evaluation_key};
-use gsw_rs::gadget::powers_of_2;
+use gsw_rs::cost::{calibrate, CostOp};
 use gsw_rs::params::{Params, SecurityLevel};
 use gsw_rs::{decrypt, encrypt, gsw_keygen, homomorphic_add, homomorphic_mult, homomorphic_nand};
 use rand::thread_rng;
 
+fn parse_level(name: &str) -> Option<SecurityLevel> {
+    match name {
+        "toy" => Some(SecurityLevel::Toy),
+        "low" => Some(SecurityLevel::Low),
+        "medium" => Some(SecurityLevel::Medium),
+        _ => None,
+    }
+}
+
+fn info(args: &[String]) {
+    let level = match args {
+        [] => SecurityLevel::Medium,
+        [flag, name] if flag == "--level" => parse_level(name).unwrap_or_else(|| {
+            eprintln!("unknown level {:?}; expected toy, low, or medium", name);
+            std::process::exit(2)
+        }),
+        _ => {
+            eprintln!("usage: gsw-rs info [--level toy|low|medium]");
+            std::process::exit(2)
+        }
+    };
+    print!("{}", gsw_rs::format_report(&Params::new(level)));
+
+    let throughput = calibrate();
+    println!(
+        "estimated bootstrap time ({:.2e} multiply-adds/s):",
+        throughput
+    );
+    for name in ["toy", "low", "medium"] {
+        let model = Params::new(parse_level(name).expect("known level")).cost_model();
+        let seconds = model.estimate_seconds(CostOp::Bootstrap, throughput);
+        println!("  {:<20} {:>14.3} s", name, seconds);
+    }
+}
+
+/// The security assessment of `params`, printed wherever keys are made.
+fn print_security(params: &Params) {
+    let assessment = params.security_assessment();
+    let preset = assessment
+        .level
+        .map_or("custom parameters".to_string(), |level| {
+            format!("{:?} preset", level)
+        });
+    println!(
+        "Security: {}, estimated {} bits ({})",
+        preset, assessment.estimated_bits, assessment.citation
+    );
+    if !assessment.production_safe {
+        println!("WARNING: these parameters are NOT safe for production use.");
+    }
+    println!("{}", gsw_rs::SECURITY_DISCLAIMER);
+}
+
+fn self_test(args: &[String]) {
+    let usage = || -> ! {
+        eprintln!("usage: gsw-rs self-test [--level toy|low|medium] [--seed N]");
+        std::process::exit(2)
+    };
+    let mut level = SecurityLevel::Toy;
+    let mut seed = None;
+    for pair in args.chunks(2) {
+        match pair {
+            [flag, name] if flag == "--level" => {
+                level = parse_level(name).unwrap_or_else(|| usage());
+            }
+            [flag, n] if flag == "--seed" => seed = Some(n.parse().unwrap_or_else(|_| usage())),
+            _ => usage(),
+        }
+    }
+
+    let (seed, kernel, passed, failure) = match gsw_rs::self_test(level, seed) {
+        Ok(report) => (report.seed, report.kernel, report.stages, None),
+        Err(failure) => (
+            failure.seed,
+            failure.kernel,
+            failure.passed.clone(),
+            Some(failure),
+        ),
+    };
+    println!(
+        "self-test at {:?} (seed {}, kernel {})",
+        level, seed, kernel
+    );
+    for stage in &passed {
+        println!(
+            "  {:<14} ok    {:>10.3?}",
+            stage.stage.to_string(),
+            stage.elapsed
+        );
+    }
+    if let Some(failure) = failure {
+        println!(
+            "  {:<14} FAIL  {}",
+            failure.stage.to_string(),
+            failure.reason
+        );
+        std::process::exit(1);
+    }
+}
+
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("info") => return info(&args[1..]),
+        Some("self-test") => return self_test(&args[1..]),
+        _ => {}
+    }
+
     println!("GSW Lattice FHE Implementation - Demo\n");
 
     let params = Params::new(SecurityLevel::Toy);
@@ -21,6 +136,7 @@ fn main() {
 
     // Key generation
     println!("\n--- Key Generation ---");
+    print_security(&params);
     let (sk, pk) = gsw_keygen(&mut rng, &params);
     println!("Secret key length: {}", sk.s.len());
     println!("Public key matrix: {}x{}", pk.a.len(), pk.a[0].len());
@@ -148,7 +264,7 @@ fn main() {
     println!("\n--- Bootstrapping ---");
     println!("Generating evaluation key (encrypted secret key bits)...");
     let ek = gen_evaluation_key(&mut rng, &sk, &pk);
-    println!("Evaluation key: {} encrypted bits", ek.encryptions.len());
+    println!("Evaluation key: {} encrypted bits", ek.len());
 
     println!("Bootstrapping a ciphertext...");
 
@@ -156,8 +272,9 @@ fn main() {
 
     let ct_to_bootstrap = homomorphic_mult(&params, &ct1, &ct1);
     let msg_before = decrypt(&sk, &ct_to_bootstrap);
-    let val_clear = gsw_rs::bootstrap::decrypt_linear_part_clear(&sk, &ct_to_bootstrap);
-    let scale = powers_of_2(&sk.s, &params)[params.l - 1];
+    let val_clear = gsw_rs::bootstrap::decrypt_linear_part_clear(&sk, &ct_to_bootstrap, None)
+        .expect("well-formed ciphertext");
+    let scale = sk.decryption_scale();
     let true_msg = 1u8; // Enc(1)*Enc(1) = Enc(1)
     println!(
         "  Input: val={}, scale={}, noisy_decrypt={}",
@@ -169,7 +286,8 @@ fn main() {
     let duration = end_time.duration_since(start_time);
     println!("Time taken to bootstrap the ciphertext: {:?}", duration);
     let msg_after = decrypt(&sk, &ct_bootstrapped);
-    let val_bootstrap = gsw_rs::bootstrap::decrypt_linear_part_clear(&sk, &ct_bootstrapped);
+    let val_bootstrap = gsw_rs::bootstrap::decrypt_linear_part_clear(&sk, &ct_bootstrapped, None)
+        .expect("well-formed ciphertext");
     println!(
         "  Bootstrap output: val={}, decrypt={}",
         val_bootstrap, msg_after
@@ -0,0 +1,267 @@
+//! Memory high-water marks (the `metrics` feature).
+//!
+//! [`with_memory_tracking`] runs a closure and reports the most heap memory
+//! the current thread held at once beyond what it held on entry, so peak RSS
+//! of a bootstrap can be measured rather than guessed:
+//!
+//! ```ignore
+//! let (refreshed, stats) = with_memory_tracking(|| bootstrap(&params, &ct, &ek));
+//! println!("bootstrap peaked at {} bytes", stats.peak_bytes);
+//! ```
+//!
+//! The bytes are counted by [`TrackingAllocator`], which the binary installs
+//! as its global allocator; a library cannot do that for it:
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static ALLOCATOR: gsw_rs::TrackingAllocator = gsw_rs::TrackingAllocator::system();
+//! ```
+//!
+//! Without it every count is zero, which [`is_tracking_installed`] detects.
+//! Only the calling thread is counted: a `rayon` build runs products above
+//! the [parallel threshold](crate::kernels::parallel_threshold) on other
+//! threads, and their row buffers are missed.
+//!
+//! [`Evaluator::evaluate_circuit_with_memory`](crate::Evaluator::evaluate_circuit_with_memory)
+//! splits a circuit run into the ciphertexts held on wires, counted
+//! directly and so reported with or without the allocator, and the transient
+//! scratch of the gates, which needs it.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::mem::size_of;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::circuits::GateObserver;
+use crate::context::EncBool;
+use crate::gsw::Ciphertext;
+
+/// Heap use of one closure run under [`with_memory_tracking`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// Most bytes held at once beyond those held on entry.
+    pub peak_bytes: usize,
+    /// Bytes still held on return beyond those held on entry; negative if
+    /// the closure freed more than it kept.
+    pub retained_bytes: isize,
+    /// Bytes allocated in total, counting growth of reallocated blocks.
+    pub allocated_bytes: usize,
+    /// Number of allocations and reallocations.
+    pub allocations: usize,
+}
+
+/// Memory of one circuit run; see
+/// [`Evaluator::evaluate_circuit_with_memory`](crate::Evaluator::evaluate_circuit_with_memory).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CircuitMemoryStats {
+    /// Most ciphertext bytes held on wires at once, counting the outputs of
+    /// a conditional until they are read.
+    pub peak_ciphertext_bytes: usize,
+    /// Most wires holding a ciphertext at once.
+    pub peak_live_ciphertexts: usize,
+    /// Most bytes a single gate held beyond the wires and the values it
+    /// produced; a conditional's branches count as its scratch. Zero without
+    /// a [`TrackingAllocator`].
+    pub peak_scratch_bytes: usize,
+    /// The whole run, wires, scratch, and bookkeeping together.
+    pub total: MemoryStats,
+}
+
+#[derive(Clone, Copy)]
+struct Counters {
+    /// Nesting depth of [`with_memory_tracking`]; nothing is counted at 0.
+    depth: u32,
+    live: isize,
+    peak: isize,
+    allocated: usize,
+    allocations: usize,
+}
+
+thread_local! {
+    static COUNTERS: Cell<Counters> = const {
+        Cell::new(Counters {
+            depth: 0,
+            live: 0,
+            peak: 0,
+            allocated: 0,
+            allocations: 0,
+        })
+    };
+}
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Add `delta` bytes to the current thread's live count if it is tracking.
+fn record(delta: isize, allocation: bool) {
+    // Allocations during thread teardown are not tracked.
+    let _ = COUNTERS.try_with(|counters| {
+        let mut c = counters.get();
+        if c.depth == 0 {
+            return;
+        }
+        c.live += delta;
+        c.peak = c.peak.max(c.live);
+        if allocation {
+            c.allocated += delta.max(0) as usize;
+            c.allocations += 1;
+        }
+        counters.set(c);
+    });
+}
+
+/// A global allocator that forwards to `A` and counts the bytes each thread
+/// holds while it runs [`with_memory_tracking`]. Outside a tracked region
+/// the cost is one thread-local read per call.
+#[derive(Debug, Default)]
+pub struct TrackingAllocator<A = System> {
+    inner: A,
+}
+
+impl TrackingAllocator<System> {
+    /// Tracking over the system allocator.
+    pub const fn system() -> Self {
+        Self { inner: System }
+    }
+}
+
+impl<A> TrackingAllocator<A> {
+    /// Tracking over `inner`.
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for TrackingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            INSTALLED.store(true, Ordering::Relaxed);
+            record(layout.size() as isize, true);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            INSTALLED.store(true, Ordering::Relaxed);
+            record(layout.size() as isize, true);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout);
+        record(-(layout.size() as isize), false);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.inner.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            record(new_size as isize - layout.size() as isize, true);
+        }
+        new_ptr
+    }
+}
+
+/// Whether a [`TrackingAllocator`] serves this process's allocations.
+pub fn is_tracking_installed() -> bool {
+    drop(std::hint::black_box(Box::new(0u8)));
+    INSTALLED.load(Ordering::Relaxed)
+}
+
+/// Restores the enclosing region's state, even if the closure panics.
+struct Region {
+    outer: Counters,
+}
+
+impl Drop for Region {
+    fn drop(&mut self) {
+        COUNTERS.with(|counters| {
+            let mut c = counters.get();
+            c.depth -= 1;
+            // The enclosing region saw everything this one did.
+            c.peak = c.peak.max(self.outer.peak);
+            counters.set(c);
+        });
+    }
+}
+
+/// `f()`, with the heap use of the current thread while it ran. Regions
+/// nest: an enclosing region's peak includes the inner one's.
+pub fn with_memory_tracking<R>(f: impl FnOnce() -> R) -> (R, MemoryStats) {
+    let region = COUNTERS.with(|counters| {
+        let outer = counters.get();
+        counters.set(Counters {
+            depth: outer.depth + 1,
+            peak: outer.live,
+            ..outer
+        });
+        Region { outer }
+    });
+    let result = f();
+    let c = COUNTERS.with(Cell::get);
+    let outer = region.outer;
+    drop(region);
+    let stats = MemoryStats {
+        peak_bytes: (c.peak - outer.live).max(0) as usize,
+        retained_bytes: c.live - outer.live,
+        allocated_bytes: c.allocated - outer.allocated,
+        allocations: c.allocations - outer.allocations,
+    };
+    (result, stats)
+}
+
+/// Heap bytes owned by `ct`: its row vector and every row.
+pub(crate) fn ciphertext_heap_bytes(ct: &Ciphertext) -> usize {
+    ct.capacity() * size_of::<Vec<u64>>()
+        + ct.iter()
+            .map(|row| row.capacity() * size_of::<u64>())
+            .sum::<usize>()
+}
+
+/// Builds a [`CircuitMemoryStats`] from the events of one circuit run.
+#[derive(Default)]
+pub(crate) struct CircuitLedger {
+    live_bytes: usize,
+    live_ciphertexts: usize,
+    stats: CircuitMemoryStats,
+}
+
+impl CircuitLedger {
+    pub(crate) fn finish(self, total: MemoryStats) -> CircuitMemoryStats {
+        CircuitMemoryStats {
+            total,
+            ..self.stats
+        }
+    }
+}
+
+impl<'e> GateObserver<'e> for CircuitLedger {
+    fn gate(&mut self, gate: impl FnOnce() -> EncBool<'e>) -> EncBool<'e> {
+        let (value, memory) = with_memory_tracking(gate);
+        let kept = memory.retained_bytes.max(0) as usize;
+        let scratch = memory.peak_bytes.saturating_sub(kept);
+        self.stats.peak_scratch_bytes = self.stats.peak_scratch_bytes.max(scratch);
+        value
+    }
+
+    fn hold(&mut self, value: &EncBool<'e>) {
+        let bytes = value.ciphertext_bytes();
+        if bytes > 0 {
+            self.live_bytes += bytes;
+            self.live_ciphertexts += 1;
+        }
+        let stats = &mut self.stats;
+        stats.peak_ciphertext_bytes = stats.peak_ciphertext_bytes.max(self.live_bytes);
+        stats.peak_live_ciphertexts = stats.peak_live_ciphertexts.max(self.live_ciphertexts);
+    }
+
+    fn release(&mut self, value: &EncBool<'e>) {
+        let bytes = value.ciphertext_bytes();
+        if bytes > 0 {
+            self.live_bytes -= bytes;
+            self.live_ciphertexts -= 1;
+        }
+    }
+}
@@ -1,25 +1,254 @@
 //! Modular arithmetic utilities for Z_q.
+//!
+//! [`Modulus`] and [`Zq`] tag values with the modulus they live in, so an
+//! element mod q cannot be mixed with one mod q' without an explicit
+//! conversion: arithmetic between them fails with
+//! [`GswError::ModulusMismatch`]. Bulk matrix code keeps raw u64 entries for
+//! speed and uses the free functions below.
+
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+use crate::error::GswError;
+
+/// A modulus q ≥ 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Modulus(u64);
+
+impl Modulus {
+    pub fn new(q: u64) -> Self {
+        assert!(q != 0, "modulus must be nonzero");
+        Self(q)
+    }
+
+    pub fn value(self) -> u64 {
+        self.0
+    }
+
+    /// Reduce a u64 into [0, q).
+    pub fn reduce(self, x: u64) -> Zq {
+        Zq {
+            value: x % self.0,
+            modulus: self,
+        }
+    }
+
+    /// Reduce any i64, including `i64::MIN`, into [0, q).
+    pub fn reduce_from_i64(self, x: i64) -> Zq {
+        let value = if self.0 <= i64::MAX as u64 {
+            x.rem_euclid(self.0 as i64) as u64
+        } else {
+            (x as i128).rem_euclid(self.0 as i128) as u64
+        };
+        Zq {
+            value,
+            modulus: self,
+        }
+    }
+
+    /// Reduce any i128 into [0, q).
+    pub fn reduce_from_i128(self, x: i128) -> Zq {
+        Zq {
+            value: x.rem_euclid(self.0 as i128) as u64,
+            modulus: self,
+        }
+    }
+}
+
+impl fmt::Display for Modulus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// An element of Z_q, carrying its modulus.
+///
+/// Addition, subtraction and multiplication give a `Result`, failing with
+/// [`GswError::ModulusMismatch`] if the operands have different moduli.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Zq {
+    value: u64,
+    modulus: Modulus,
+}
+
+impl Zq {
+    /// Representative in [0, q).
+    pub fn value(self) -> u64 {
+        self.value
+    }
+
+    pub fn modulus(self) -> Modulus {
+        self.modulus
+    }
+
+    /// Representative in (-q/2, q/2].
+    pub fn centered(self) -> i64 {
+        let q = self.modulus.0;
+        if self.value > q / 2 {
+            (self.value as i128 - q as i128) as i64
+        } else {
+            self.value as i64
+        }
+    }
+
+    fn check_same(self, other: Zq) -> Result<Modulus, GswError> {
+        if self.modulus != other.modulus {
+            return Err(GswError::ModulusMismatch {
+                left: self.modulus.0,
+                right: other.modulus.0,
+            });
+        }
+        Ok(self.modulus)
+    }
+}
+
+impl Add for Zq {
+    type Output = Result<Zq, GswError>;
+
+    fn add(self, other: Zq) -> Result<Zq, GswError> {
+        let modulus = self.check_same(other)?;
+        Ok(modulus.reduce_from_i128(self.value as i128 + other.value as i128))
+    }
+}
+
+impl Sub for Zq {
+    type Output = Result<Zq, GswError>;
+
+    fn sub(self, other: Zq) -> Result<Zq, GswError> {
+        let modulus = self.check_same(other)?;
+        Ok(modulus.reduce_from_i128(self.value as i128 - other.value as i128))
+    }
+}
+
+impl Mul for Zq {
+    type Output = Result<Zq, GswError>;
+
+    fn mul(self, other: Zq) -> Result<Zq, GswError> {
+        let modulus = self.check_same(other)?;
+        let product = self.value as u128 * other.value as u128;
+        Ok(Zq {
+            value: (product % modulus.0 as u128) as u64,
+            modulus,
+        })
+    }
+}
+
+impl Neg for Zq {
+    type Output = Zq;
+
+    fn neg(self) -> Zq {
+        self.modulus.reduce_from_i128(-(self.value as i128))
+    }
+}
+
+impl fmt::Display for Zq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (mod {})", self.value, self.modulus)
+    }
+}
 
 /// Reduce value to range [0, q) for unsigned modulus.
 #[inline]
 pub fn mod_q(val: i64, q: u64) -> u64 {
-    let q = q as i64;
-    let mut r = val % q;
-    if r < 0 {
-        r += q;
-    }
-    r as u64
+    Modulus::new(q).reduce_from_i64(val).value()
 }
 
 /// Reduce value to range (-q/2, q/2] for centered modulus (used in decryption).
 #[inline]
 pub fn mod_q_centered(val: i64, q: u64) -> i64 {
-    let q = q as i64;
-    let mut r = val % q;
-    if r > q / 2 {
-        r -= q;
-    } else if r <= -q / 2 {
-        r += q;
-    }
-    r
+    Modulus::new(q).reduce_from_i64(val).centered()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zq_arithmetic_wraps() {
+        let q = Modulus::new(1 << 20);
+        let a = q.reduce((1 << 20) - 1);
+        let b = q.reduce(5);
+        assert_eq!((a + b).unwrap().value(), 4);
+        assert_eq!((b - a).unwrap().value(), 6);
+        assert_eq!((a * a).unwrap().value(), 1);
+        assert_eq!((-b).value(), (1 << 20) - 5);
+        assert_eq!((-q.reduce(0)).value(), 0);
+        assert_eq!(a.centered(), -1);
+
+        let big = Modulus::new(u64::MAX);
+        let x = big.reduce(u64::MAX - 1);
+        assert_eq!((x + x).unwrap().value(), u64::MAX - 2);
+        assert_eq!((x * x).unwrap().value(), 1);
+    }
+
+    #[test]
+    fn test_reduce_from_signed_edge_cases() {
+        let q = Modulus::new(7);
+        assert_eq!(
+            q.reduce_from_i64(i64::MIN).value(),
+            i64::MIN.rem_euclid(7) as u64
+        );
+        assert_eq!(q.reduce_from_i64(-1).value(), 6);
+        assert_eq!(
+            q.reduce_from_i128(i128::MIN).value(),
+            i128::MIN.rem_euclid(7) as u64
+        );
+        assert_eq!(Modulus::new(1 << 63).reduce_from_i64(i64::MIN).value(), 0);
+        assert_eq!(
+            Modulus::new(u64::MAX).reduce_from_i64(-1).value(),
+            u64::MAX - 1
+        );
+        assert_eq!(mod_q(i64::MIN, 3), i64::MIN.rem_euclid(3) as u64);
+        assert_eq!(mod_q_centered(-1, 1 << 20), -1);
+        assert_eq!(mod_q_centered(1 << 19, 1 << 20), 1 << 19);
+    }
+
+    #[test]
+    fn test_mod_q_whole_i64_domain() {
+        let moduli = [
+            2u64,
+            3,
+            7,
+            1 << 20,
+            1_000_003,
+            1 << 62,
+            i64::MAX as u64,
+            (1 << 63) + 1,
+            u64::MAX,
+        ];
+        for q in moduli {
+            let qi = q as i128;
+            let mut inputs = vec![i64::MIN, i64::MIN + 1, -1, 0, i64::MAX];
+            for v in [-qi, qi - 1, qi] {
+                if let Ok(v) = i64::try_from(v) {
+                    inputs.push(v);
+                }
+            }
+            for val in inputs {
+                let reduced = (val as i128).rem_euclid(qi);
+                assert_eq!(mod_q(val, q) as i128, reduced, "mod_q({}, {})", val, q);
+
+                let centered = if reduced > qi / 2 {
+                    reduced - qi
+                } else {
+                    reduced
+                };
+                let got = mod_q_centered(val, q) as i128;
+                assert_eq!(got, centered, "mod_q_centered({}, {})", val, q);
+                assert!(-qi < 2 * got && 2 * got <= qi);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mixed_moduli_fail() {
+        let (a, b) = (Modulus::new(16).reduce(1), Modulus::new(32).reduce(1));
+        for result in [a + b, a - b, a * b, b * a] {
+            assert!(matches!(
+                result,
+                Err(GswError::ModulusMismatch { left, right })
+                    if (left, right) == (16, 32) || (left, right) == (32, 16)
+            ));
+        }
+    }
 }
@@ -0,0 +1,171 @@
+//! Analytic noise bounds for choosing parameters.
+//!
+//! A fresh ciphertext row carries error Σ_k r_k·e_k, where r is a uniform
+//! binary row chosen at encryption time and e is the key's error vector with
+//! entries in [-B, B]. The key error is fixed for the life of the key, so its
+//! sum acts as a common bias on every row: the bound charges that bias at its
+//! worst case, m·B/2, and applies Hoeffding's inequality only to the
+//! fluctuation that comes from r.
+//!
+//! A product C_1·C_2 has error C_1·e_2 + μ_2·e_1. C_1 is binary with at most
+//! N ones per row, so the bound grows by a factor of N + 1 per level of
+//! multiplicative depth, with both operands at the previous level's bound.
+//! Decryption is correct while the error stays below q/4.
+//!
+//! Bounds are computed as base-2 logarithms so deep circuits cannot overflow,
+//! in fixed point with [`FRAC_BITS`] fractional bits rather than floating
+//! point: libm's `ln` and `log2` may differ in the last place between
+//! platforms, and a bound that lands on the other side of q/4 would change
+//! which parameters [`Params::for_depth`] picks and where evaluators
+//! bootstrap.
+
+use crate::params::Params;
+
+/// Fractional bits of the fixed-point logarithms.
+pub const FRAC_BITS: u32 = 32;
+
+/// ln 2 with [`FRAC_BITS`] fractional bits, rounded down.
+const LN_2: u128 = 0xb172_17f7;
+
+/// log2(x) with [`FRAC_BITS`] fractional bits, rounded down; x > 0.
+///
+/// The mantissa is squared once per fractional bit: each time it reaches 2,
+/// that bit of the logarithm is 1 and the mantissa is halved.
+fn log2_fixed(x: u128) -> i128 {
+    debug_assert!(x > 0);
+    let int = 127 - x.leading_zeros();
+    // x / 2^int with 63 fractional bits, in [2^63, 2^64).
+    let mut y = if int >= 63 {
+        x >> (int - 63)
+    } else {
+        x << (63 - int)
+    };
+    let mut frac = 0i128;
+    for bit in (0..FRAC_BITS).rev() {
+        y = (y * y) >> 63;
+        if y >= 1 << 64 {
+            y >>= 1;
+            frac |= 1 << bit;
+        }
+    }
+    ((int as i128) << FRAC_BITS) + frac
+}
+
+/// Bound on |row error| of a fresh ciphertext with [`FRAC_BITS`] fractional
+/// bits, or `None` if there is no error.
+///
+/// `log2_rows` is log2 of how many fresh rows the bound must cover at once,
+/// in fixed point; the per-row failure probability is 2^failure_exp / rows
+/// (union bound).
+fn fresh_error_bound(params: &Params, failure_exp: i32, log2_rows: i128) -> Option<u128> {
+    let m = params.m as u128;
+    let b = params.error_bound.max(0) as u128;
+    if b == 0 {
+        return None;
+    }
+    // P(|X - EX| >= t) <= 2 exp(-2t^2 / (m B^2)); solve for the target p:
+    // t = B sqrt(m ln(2/p) / 2), with ln(2/p) = ln 2 · log2(2 rows / 2^failure_exp).
+    let log2_two_over_p = ((1 - failure_exp as i128) << FRAC_BITS) + log2_rows;
+    let ln_two_over_p = (log2_two_over_p.max(0) as u128 * LN_2) >> FRAC_BITS;
+    // sqrt(m ln(2/p) / 2), from a value with 2·FRAC_BITS fractional bits.
+    let deviation = b * ((m * ln_two_over_p) << (FRAC_BITS - 1)).isqrt();
+    Some(((m * b) << (FRAC_BITS - 1)) + deviation)
+}
+
+/// log2 of a bound on |row error| after `depth` levels of multiplication, in
+/// fixed point; `None` if there is no error.
+fn log2_error_bound_fixed(params: &Params, depth: u32, failure_exp: i32) -> Option<i128> {
+    let log2_n = log2_fixed(params.n_expanded as u128);
+    // A depth-d circuit consumes up to 2^d fresh ciphertexts of N rows each.
+    let log2_rows = log2_n + ((depth.min(1024) as i128) << FRAC_BITS);
+    let fresh = fresh_error_bound(params, failure_exp, log2_rows)?;
+    let growth = log2_fixed(params.n_expanded as u128 + 1);
+    Some(log2_fixed(fresh) - ((FRAC_BITS as i128) << FRAC_BITS) + depth as i128 * growth)
+}
+
+/// log2 of q/4, the largest error decryption tolerates, in fixed point.
+fn log2_budget(params: &Params) -> i128 {
+    (params.l as i128 - 2) << FRAC_BITS
+}
+
+/// log2 of a bound on |row error| after `depth` levels of multiplication,
+/// holding except with probability 2^failure_exp. Negative infinity if the
+/// parameters add no error.
+///
+/// Computed in fixed point; the conversion to `f64` is exact up to rounding
+/// to 53 significant bits, so it is the same on every platform.
+pub fn log2_error_bound(params: &Params, depth: u32, failure_exp: i32) -> f64 {
+    match log2_error_bound_fixed(params, depth, failure_exp) {
+        Some(bound) => bound as f64 / (1u64 << FRAC_BITS) as f64,
+        None => f64::NEG_INFINITY,
+    }
+}
+
+/// Whether a circuit of the given multiplicative depth decrypts correctly
+/// except with probability 2^failure_exp.
+pub fn supports_depth(params: &Params, depth: u32, failure_exp: i32) -> bool {
+    // Error must stay below q/4 = 2^(l-2).
+    log2_error_bound_fixed(params, depth, failure_exp)
+        .is_none_or(|bound| bound < log2_budget(params))
+}
+
+/// Whether ciphertexts carrying an extra uniform error of magnitude up to
+/// 2^flood_bits on every row, on top of the fresh error, still support
+/// `depth` levels of multiplication.
+pub fn supports_flooding(params: &Params, flood_bits: u32, depth: u32, failure_exp: i32) -> bool {
+    // The flooding alone must stay below 2^(l-2) < 2^62.
+    if flood_bits >= 62 {
+        return false;
+    }
+    let log2_n = log2_fixed(params.n_expanded as u128);
+    let fresh = fresh_error_bound(params, failure_exp, log2_n).unwrap_or(0);
+    let flooded = fresh + (1u128 << (flood_bits + FRAC_BITS));
+    let growth = log2_fixed(params.n_expanded as u128 + 1);
+    log2_fixed(flooded) - ((FRAC_BITS as i128) << FRAC_BITS) + depth as i128 * growth
+        < log2_budget(params)
+}
+
+/// Largest multiplicative depth [`supports_depth`] accepts (0 if even fresh
+/// ciphertexts are out of budget).
+pub fn max_depth(params: &Params, failure_exp: i32) -> u32 {
+    (0..)
+        .take_while(|&depth| supports_depth(params, depth + 1, failure_exp))
+        .count() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::SecurityLevel;
+
+    #[test]
+    fn test_presets_support_one_level() {
+        for level in [
+            SecurityLevel::Toy,
+            SecurityLevel::Low,
+            SecurityLevel::Medium,
+        ] {
+            let params = Params::new(level);
+            assert!(supports_depth(&params, 0, -40));
+            assert!(supports_depth(&params, 1, -40));
+            assert!(max_depth(&params, -40) < 3);
+        }
+    }
+
+    #[test]
+    fn test_bound_grows_with_depth_and_confidence() {
+        let params = Params::toy();
+        let shallow = log2_error_bound(&params, 1, -40);
+        assert!(log2_error_bound(&params, 2, -40) > shallow);
+        assert!(log2_error_bound(&params, 1, -80) > shallow);
+    }
+
+    #[test]
+    fn test_flooding_budget() {
+        let params = Params::toy();
+        assert!(supports_flooding(&params, 0, 1, -40));
+        assert!(supports_flooding(&params, 6, 1, -40));
+        assert!(!supports_flooding(&params, params.l as u32, 0, -40));
+        assert!(!supports_flooding(&params, u32::MAX, 0, -40));
+    }
+}
@@ -3,6 +3,12 @@
 //! Parameters are chosen for correctness with conservative security levels.
 //! In production, use lattice estimators for proper security parameter selection.
 
+use std::sync::Arc;
+
+use crate::error::{GswError, ParamsError, ParamsViolation};
+use crate::modular::Modulus;
+use crate::noise_model;
+
 /// Security level in bits.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SecurityLevel {
@@ -14,7 +20,76 @@ pub enum SecurityLevel {
     Medium,
 }
 
+/// Estimated security, in bits, that parameters need to be fit for
+/// protecting real data.
+pub const PRODUCTION_SECURITY_BITS: u32 = 128;
+
+impl SecurityLevel {
+    /// Every preset, weakest first.
+    pub const ALL: [SecurityLevel; 3] = [
+        SecurityLevel::Toy,
+        SecurityLevel::Low,
+        SecurityLevel::Medium,
+    ];
+
+    /// The security the preset was chosen for, in bits.
+    pub fn nominal_bits(self) -> u32 {
+        match self {
+            SecurityLevel::Toy => 32,
+            SecurityLevel::Low => 64,
+            SecurityLevel::Medium => 128,
+        }
+    }
+
+    /// Whether the preset reaches [`PRODUCTION_SECURITY_BITS`]. Only Medium
+    /// does; the others are for tests and experiments.
+    pub fn is_production_safe(self) -> bool {
+        self.nominal_bits() >= PRODUCTION_SECURITY_BITS
+    }
+}
+
+/// What [`Params::security_assessment`] knows about a parameter set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SecurityAssessment {
+    /// The preset with these q, n, m, and B, if any.
+    pub level: Option<SecurityLevel>,
+    /// Estimated security in bits; 0 for parameters that have not been
+    /// assessed.
+    pub estimated_bits: u32,
+    /// Where the estimate comes from.
+    pub citation: &'static str,
+    /// Whether the estimate reaches [`PRODUCTION_SECURITY_BITS`].
+    pub production_safe: bool,
+}
+
+const PRESET_CITATION: &str = "nominal level of the gsw-rs SecurityLevel presets; \
+     check parameters for real data with a lattice estimator";
+const UNASSESSED_CITATION: &str = "not assessed: only the SecurityLevel presets carry an estimate";
+
+/// Vetted (n, log2 q, m, B) combinations searched by [`Params::for_depth`],
+/// cheapest (smallest N = (n+1)·l) first. Besides the three presets, the
+/// n = 8 points widen the toy modulus for deeper circuits; they share Toy's
+/// negligible security.
+const VETTED: [(usize, u32, usize, i64); 7] = [
+    (8, 20, 256, 1),
+    (8, 28, 256, 1),
+    (8, 40, 256, 1),
+    (8, 50, 256, 1),
+    (8, 62, 256, 1),
+    (24, 24, 384, 2),
+    (48, 26, 768, 4),
+];
+
+/// Failure target of the presets and of decoded parameters: a decryption
+/// fails with probability below 2^-40.
+pub const DEFAULT_FAILURE_EXP: i32 = -40;
+
 /// LWE/GSW instance parameters.
+///
+/// Two parameter sets are equal exactly when their fingerprints
+/// ([`digest`](crate::digest::digest)) are: equality compares the instance,
+/// q, n, m, and B, and ignores the failure target and the plaintext
+/// encoding, which are evaluation policies the wire format leaves out.
 #[derive(Clone, Debug)]
 pub struct Params {
     /// Modulus q (must be power of 2 for gadget)
@@ -29,6 +104,45 @@ pub struct Params {
     pub m: usize,
     /// Error bound B for discrete uniform error distribution [-B, B]
     pub error_bound: i64,
+    /// Target per-decryption failure probability 2^target_failure_exp, from
+    /// which the depth budget is derived (see [`Params::max_mult_depth`]).
+    /// An evaluation policy rather than part of the instance: it is not
+    /// serialized, hashed, or compared, and decoded parameters carry
+    /// [`DEFAULT_FAILURE_EXP`].
+    pub target_failure_exp: i32,
+    /// How decryption reads the bit off the phase. Like the failure target,
+    /// not serialized, hashed, or compared; decoded parameters carry the
+    /// default, so a key saved under [`PlaintextEncoding::MsbThreshold`] needs
+    /// [`Params::with_encoding`] again after loading.
+    pub encoding: PlaintextEncoding,
+}
+
+impl PartialEq for Params {
+    fn eq(&self, other: &Self) -> bool {
+        let instance = |p: &Self| (p.q, p.n, p.m, p.error_bound);
+        instance(self) == instance(other)
+    }
+}
+
+impl Eq for Params {}
+
+/// Where a bit sits in the decryption phase C[l-1] · v = μ·q/2 + e, and so
+/// how it is read back.
+///
+/// Encryption is the same under both: μ·I is added before Flatten, which puts
+/// μ·v[l-1] = μ·q/2 into the decryption row. The encodings differ in
+/// decoding, and agree except at the two phases exactly q/4 from a message.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlaintextEncoding {
+    /// The reference implementation's rule: round(phase / scale) mod 2,
+    /// where scale = v[l-1]. Ties round up, so a phase of q/4 decodes as 1
+    /// and one of 3q/4 as 0.
+    #[default]
+    LsbOfScale,
+    /// The most significant bit of the message, by threshold: 1 iff the
+    /// phase, centered in (-q/2, q/2], is at least q/4 in magnitude. Phases
+    /// of q/4 and 3q/4 both decode as 1.
+    MsbThreshold,
 }
 
 impl Params {
@@ -37,6 +151,10 @@ impl Params {
     /// Parameters are chosen so that correctness never fails:
     /// - Error growth: mult multiplies error by ~N=(n+1)*l, add doubles it
     /// - Requirement: N * sqrt(m) * B ≪ q/4 for one multiplication
+    ///
+    /// Every preset targets a failure probability of 2^-40
+    /// ([`DEFAULT_FAILURE_EXP`]), at which each supports one level of
+    /// multiplication.
     pub fn new(level: SecurityLevel) -> Self {
         let mut p = match level {
             SecurityLevel::Toy => Self {
@@ -46,6 +164,8 @@ impl Params {
                 error_bound: 1,
                 l: 0,
                 n_expanded: 0,
+                target_failure_exp: DEFAULT_FAILURE_EXP,
+                encoding: PlaintextEncoding::LsbOfScale,
             },
             SecurityLevel::Low => Self {
                 q: 1 << 24,
@@ -54,6 +174,8 @@ impl Params {
                 error_bound: 2,
                 l: 0,
                 n_expanded: 0,
+                target_failure_exp: DEFAULT_FAILURE_EXP,
+                encoding: PlaintextEncoding::LsbOfScale,
             },
             SecurityLevel::Medium => Self {
                 q: 1 << 26,
@@ -62,6 +184,8 @@ impl Params {
                 error_bound: 4,
                 l: 0,
                 n_expanded: 0,
+                target_failure_exp: DEFAULT_FAILURE_EXP,
+                encoding: PlaintextEncoding::LsbOfScale,
             },
         };
         p.with_derived();
@@ -73,8 +197,407 @@ impl Params {
         Self::new(SecurityLevel::Toy)
     }
 
+    /// Smallest vetted parameter set that evaluates circuits of multiplicative
+    /// depth `depth` with per-decryption failure probability below
+    /// 2^max_failure_exp, according to [`noise_model`](crate::noise_model).
+    /// The result targets that probability.
+    ///
+    /// The search minimizes cost only; it does not trade up for security.
+    pub fn for_depth(depth: u32, max_failure_exp: i32) -> Result<Self, ParamsError> {
+        VETTED
+            .iter()
+            .map(|&(n, log_q, m, error_bound)| Self {
+                target_failure_exp: max_failure_exp,
+                ..Self::from_raw(1 << log_q, n, m, error_bound)
+            })
+            .find(|params| noise_model::supports_depth(params, depth, max_failure_exp))
+            .ok_or(ParamsError::DepthUnsupported {
+                depth,
+                max_failure_exp,
+            })
+    }
+
+    /// Tiny parameters for tracing the scheme by hand: n = 2, q = 2^8.
+    ///
+    /// N = (n+1)·l = 24, so keys and ciphertexts are small enough to print.
+    /// With m = 4 samples and B = 1 a fresh row error is at most 4, and one
+    /// multiplication of fresh ciphertexts stays well under the q/4 = 64
+    /// decryption margin; deeper circuits are not supported. Offers no
+    /// security whatsoever.
+    ///
+    /// Smaller configurations also work: the minimum is n = 1, m = 1, and q a
+    /// power of two with q ≥ 4.
+    ///
+    /// ```
+    /// use gsw_rs::{decrypt, encrypt, gsw_keygen, homomorphic_mult, Params};
+    /// use rand::SeedableRng;
+    /// use rand_chacha::ChaCha20Rng;
+    ///
+    /// let params = Params::pedagogical();
+    /// assert_eq!((params.l, params.n_expanded), (8, 24));
+    ///
+    /// let mut rng = ChaCha20Rng::seed_from_u64(1);
+    /// let (sk, pk) = gsw_keygen(&mut rng, &params);
+    /// println!("s = {:?}", sk.s);
+    /// for row in &pk.a {
+    ///     println!("A row: {:?}", row);
+    /// }
+    ///
+    /// let ct = encrypt(&mut rng, &pk, 1);
+    /// for row in &ct {
+    ///     let bits: String = row.iter().map(|&x| if x == 1 { '1' } else { '0' }).collect();
+    ///     println!("{}", bits);
+    /// }
+    /// assert_eq!(decrypt(&sk, &ct), 1);
+    /// assert_eq!(decrypt(&sk, &homomorphic_mult(&params, &ct, &ct)), 1);
+    /// ```
+    pub fn pedagogical() -> Self {
+        Self::from_raw(1 << 8, 2, 4, 1)
+    }
+
+    /// Parameters from the independent fields, targeting a failure
+    /// probability of 2^target_failure_exp.
+    ///
+    /// Fails unless q is a power of two at least 4, n and m are at least 1,
+    /// B is non-negative, and fresh ciphertexts meet the target. A single
+    /// violation gives [`ParamsError::InvalidShape`] or
+    /// [`ParamsError::TargetUnachievable`]; several give
+    /// [`ParamsError::Multiple`] listing them all.
+    pub fn custom(
+        q: u64,
+        n: usize,
+        m: usize,
+        error_bound: i64,
+        target_failure_exp: i32,
+    ) -> Result<Self, ParamsError> {
+        let violations = Self::validate_all(q, n, m, error_bound, target_failure_exp);
+        if !violations.is_empty() {
+            return Err(violations.into());
+        }
+        Ok(Self {
+            target_failure_exp,
+            ..Self::from_raw(q, n, m, error_bound)
+        })
+    }
+
+    /// Every reason [`custom`](Self::custom) would reject these arguments, in
+    /// argument order; empty if it would accept them. The failure target is
+    /// only checked once the rest is valid, since the noise model needs a
+    /// well-formed parameter set.
+    pub fn validate_all(
+        q: u64,
+        n: usize,
+        m: usize,
+        error_bound: i64,
+        target_failure_exp: i32,
+    ) -> Vec<ParamsViolation> {
+        let mut violations = Vec::new();
+        if q < 4 || !q.is_power_of_two() {
+            violations.push(ParamsViolation::Modulus { q });
+        }
+        if n == 0 {
+            violations.push(ParamsViolation::ZeroDimension);
+        }
+        if m == 0 {
+            violations.push(ParamsViolation::ZeroRows);
+        }
+        if error_bound < 0 {
+            violations.push(ParamsViolation::NegativeErrorBound { error_bound });
+        }
+        if violations.is_empty()
+            && !noise_model::supports_depth(
+                &Self::from_raw(q, n, m, error_bound),
+                0,
+                target_failure_exp,
+            )
+        {
+            violations.push(ParamsViolation::TargetUnachievable {
+                failure_exp: target_failure_exp,
+            });
+        }
+        violations
+    }
+
+    /// These parameters with a different failure target. Fails if even fresh
+    /// ciphertexts miss it.
+    pub fn with_target_failure_exp(self, target_failure_exp: i32) -> Result<Self, ParamsError> {
+        if !noise_model::supports_depth(&self, 0, target_failure_exp) {
+            return Err(ParamsError::TargetUnachievable {
+                failure_exp: target_failure_exp,
+            });
+        }
+        Ok(Self {
+            target_failure_exp,
+            ..self
+        })
+    }
+
+    /// These parameters with a different plaintext encoding.
+    pub fn with_encoding(self, encoding: PlaintextEncoding) -> Self {
+        Self { encoding, ..self }
+    }
+
+    /// Largest multiplicative depth that meets the failure target, according
+    /// to [`noise_model::max_depth`].
+    pub fn max_mult_depth(&self) -> u32 {
+        noise_model::max_depth(self, self.target_failure_exp)
+    }
+
+    /// The estimated security of these parameters.
+    ///
+    /// Only the presets carry an estimate, their nominal level; any other
+    /// parameters, including the deeper [`Params::for_depth`] points, are
+    /// reported as unassessed and never production safe.
+    pub fn security_assessment(&self) -> SecurityAssessment {
+        let level = SecurityLevel::ALL.into_iter().find(|&level| {
+            let preset = Params::new(level);
+            (preset.q, preset.n, preset.m, preset.error_bound)
+                == (self.q, self.n, self.m, self.error_bound)
+        });
+        let estimated_bits = level.map_or(0, SecurityLevel::nominal_bits);
+        SecurityAssessment {
+            level,
+            estimated_bits,
+            citation: if level.is_some() {
+                PRESET_CITATION
+            } else {
+                UNASSESSED_CITATION
+            },
+            production_safe: estimated_bits >= PRODUCTION_SECURITY_BITS,
+        }
+    }
+
+    /// Fail with [`GswError::InsecureParams`] unless these parameters are
+    /// production safe (see [`Params::security_assessment`]).
+    pub fn ensure_production_safe(&self) -> Result<(), GswError> {
+        let assessment = self.security_assessment();
+        if !assessment.production_safe {
+            return Err(GswError::InsecureParams {
+                estimated_bits: assessment.estimated_bits,
+            });
+        }
+        Ok(())
+    }
+
+    /// The modulus q as a typed [`Modulus`].
+    pub fn modulus(&self) -> Modulus {
+        Modulus::new(self.q)
+    }
+
+    /// Compare shared parameter handles, skipping the field comparison when
+    /// both point at the same allocation.
+    pub(crate) fn same(a: &Arc<Self>, b: &Arc<Self>) -> bool {
+        Arc::ptr_eq(a, b) || a == b
+    }
+
+    /// Build parameters from the independent fields, deriving `l` and `n_expanded`.
+    ///
+    /// Panics below the minimum viable configuration (see [`Params::pedagogical`]).
+    pub(crate) fn from_raw(q: u64, n: usize, m: usize, error_bound: i64) -> Self {
+        assert!(
+            q >= 4 && q.is_power_of_two(),
+            "q must be a power of two, at least 4"
+        );
+        assert!(n >= 1 && m >= 1, "n and m must be at least 1");
+        let mut p = Self {
+            q,
+            n,
+            m,
+            error_bound,
+            l: 0,
+            n_expanded: 0,
+            target_failure_exp: DEFAULT_FAILURE_EXP,
+            encoding: PlaintextEncoding::LsbOfScale,
+        };
+        p.with_derived();
+        p
+    }
+
     fn with_derived(&mut self) {
-        self.l = (self.q as f64).log2() as usize;
+        self.l = self.q.trailing_zeros() as usize;
         self.n_expanded = (self.n + 1) * self.l;
     }
 }
+
+/// The instance fields as serde sees them: the wire format's four, plus the
+/// derived `l` and `n_expanded` for readers of the serialized form.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "Params")]
+struct ParamsFields {
+    q: u64,
+    n: u64,
+    l: u64,
+    n_expanded: u64,
+    m: u64,
+    error_bound: i64,
+}
+
+/// Like the wire format, leaves out the failure target and the encoding.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Params {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let fields = ParamsFields {
+            q: self.q,
+            n: self.n as u64,
+            l: self.l as u64,
+            n_expanded: self.n_expanded as u64,
+            m: self.m as u64,
+            error_bound: self.error_bound,
+        };
+        serde::Serialize::serialize(&fields, serializer)
+    }
+}
+
+/// Validates the independent fields as decoding does and rejects derived
+/// fields that disagree with them.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Params {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        let fields = <ParamsFields as serde::Deserialize>::deserialize(deserializer)?;
+        let params = crate::wire::checked_params(fields.q, fields.n, fields.m, fields.error_bound)
+            .map_err(D::Error::custom)?;
+        if (params.l as u64, params.n_expanded as u64) != (fields.l, fields.n_expanded) {
+            return Err(D::Error::custom(format!(
+                "l = {} and n_expanded = {} do not follow from q and n (expected {} and {})",
+                fields.l, fields.n_expanded, params.l, params.n_expanded
+            )));
+        }
+        Ok(params)
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::digest::digest;
+    use crate::gsw::{decrypt, encrypt, gsw_keygen, homomorphic_mult};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn test_security_assessment() {
+        for level in SecurityLevel::ALL {
+            let params = Params::new(level);
+            let assessment = params.security_assessment();
+            assert_eq!(assessment.level, Some(level));
+            assert_eq!(assessment.estimated_bits, level.nominal_bits());
+            assert!(!assessment.citation.is_empty());
+            assert_eq!(assessment.production_safe, level.is_production_safe());
+            assert_eq!(
+                params.ensure_production_safe().is_ok(),
+                level.is_production_safe()
+            );
+        }
+        assert!(SecurityLevel::Medium.is_production_safe());
+        assert!(!SecurityLevel::Toy.is_production_safe());
+
+        // Neither the failure target nor a deeper modulus is a preset.
+        let retargeted = Params::toy().with_target_failure_exp(-20).unwrap();
+        assert_eq!(
+            retargeted.security_assessment().level,
+            Some(SecurityLevel::Toy)
+        );
+        let deep = Params::for_depth(2, -40).unwrap();
+        let assessment = deep.security_assessment();
+        assert_eq!((assessment.level, assessment.estimated_bits), (None, 0));
+        assert!(!assessment.production_safe);
+        assert!(matches!(
+            deep.ensure_production_safe(),
+            Err(GswError::InsecureParams { estimated_bits: 0 })
+        ));
+    }
+
+    #[test]
+    fn test_for_depth_picks_smallest() {
+        assert_eq!(Params::for_depth(1, -40).unwrap(), Params::toy());
+        let deep = Params::for_depth(5, -40).unwrap();
+        assert!(deep.n_expanded > Params::toy().n_expanded);
+        assert!(deep.q > Params::toy().q);
+        assert_eq!(
+            Params::for_depth(100, -40),
+            Err(ParamsError::DepthUnsupported {
+                depth: 100,
+                max_failure_exp: -40
+            })
+        );
+    }
+
+    #[test]
+    fn test_for_depth_end_to_end() {
+        let depth = 5;
+        let params = Params::for_depth(depth, -40).unwrap();
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        for bit in [0u8, 1] {
+            // Repeated squaring: both operands sit at the previous level.
+            let mut ct = encrypt(&mut rng, &pk, bit);
+            for _ in 0..depth {
+                ct = homomorphic_mult(&params, &ct, &ct);
+            }
+            assert_eq!(decrypt(&sk, &ct), bit);
+        }
+    }
+
+    #[test]
+    fn test_failure_target() {
+        let toy = Params::toy();
+        assert_eq!(toy.target_failure_exp, DEFAULT_FAILURE_EXP);
+        assert_eq!(toy.max_mult_depth(), 1);
+        let tight = toy.clone().with_target_failure_exp(-1 << 15).unwrap();
+        assert_eq!(tight.max_mult_depth(), 0);
+        assert_eq!(tight, toy);
+        assert_eq!(digest(&tight), digest(&toy));
+        assert_eq!(Params::for_depth(2, -60).unwrap().target_failure_exp, -60);
+
+        assert_eq!(
+            Params::custom(1 << 8, 2, 4, 1, -40),
+            Ok(Params::pedagogical())
+        );
+        assert_eq!(
+            Params::custom(1 << 8, 2, 4, 1, -1_000_000),
+            Err(ParamsError::TargetUnachievable {
+                failure_exp: -1_000_000
+            })
+        );
+        for (q, n, m, error_bound) in [
+            (6, 2, 4, 1),
+            (2, 2, 4, 1),
+            (1 << 8, 0, 4, 1),
+            (1 << 8, 2, 4, -1),
+        ] {
+            assert_eq!(
+                Params::custom(q, n, m, error_bound, -40),
+                Err(ParamsError::InvalidShape)
+            );
+        }
+    }
+
+    #[test]
+    fn test_reports_every_violation() {
+        let err = Params::custom(6, 0, 4, -2, -40).unwrap_err();
+        assert_eq!(
+            err,
+            ParamsError::Multiple(vec![
+                ParamsViolation::Modulus { q: 6 },
+                ParamsViolation::ZeroDimension,
+                ParamsViolation::NegativeErrorBound { error_bound: -2 },
+            ])
+        );
+        assert_eq!(
+            err.to_string(),
+            "3 problems with the parameters:\n  - q = 6 is not a power of two at least 4\n  \
+             - n must be at least 1\n  - B = -2 is negative"
+        );
+        assert_eq!(
+            Params::validate_all(1 << 8, 2, 0, 1, -40),
+            [ParamsViolation::ZeroRows]
+        );
+        assert_eq!(
+            Params::custom(1 << 8, 2, 0, 1, -40),
+            Err(ParamsError::InvalidShape)
+        );
+        assert!(Params::validate_all(1 << 8, 2, 4, 1, -40).is_empty());
+    }
+}
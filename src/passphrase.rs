@@ -0,0 +1,477 @@
+//! Passphrase protection for secret keys and sessions at rest.
+//!
+//! [`SecretKey::export_encrypted`] and
+//! [`Session::save_encrypted`](crate::Session::save_encrypted) derive a key
+//! from a passphrase and a random salt, then encrypt the object's usual
+//! encoding with ChaCha20-Poly1305. The result is a wire object of its own
+//! kind (see the `wire` module docs): the header, the params fingerprint, the
+//! key-derivation parameters, the salt, and the nonce are stored in the clear
+//! and authenticated with the encryption, so none can be altered unnoticed.
+//!
+//! Opening needs the `passphrase` feature, which `client` enables along with
+//! sealing; without it the key-derivation types are still here, for reading
+//! the unencrypted fields.
+//!
+//! Key derivation is Argon2id with the `argon2` feature and
+//! PBKDF2-HMAC-SHA256 otherwise ([`KdfParams::recommended`]). The parameters
+//! travel with the data, so whoever can rewrite a file could weaken them to
+//! speed up guessing; imports therefore refuse parameters below a
+//! [`KdfPolicy`] before deriving anything.
+//!
+//! Failures are typed: [`GswError::WrongPassphrase`] when decryption fails,
+//! which is also what altered ciphertext bytes produce, since the
+//! authentication tag cannot tell the two apart;
+//! [`GswError::WeakKdf`] for parameters below the policy; and the usual
+//! decoding errors ([`GswError::BadMagic`], [`GswError::MalformedData`], ...)
+//! when the unencrypted fields do not parse.
+
+use std::fmt;
+
+#[cfg(feature = "passphrase")]
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+#[cfg(feature = "passphrase")]
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+#[cfg(feature = "client")]
+use rand::Rng;
+#[cfg(feature = "passphrase")]
+use zeroize::Zeroizing;
+
+#[cfg(feature = "client")]
+use crate::digest::{digest, Digest};
+use crate::error::GswError;
+#[cfg(feature = "passphrase")]
+use crate::lwe::SecretKey;
+#[cfg(feature = "client")]
+use crate::wire::{self, NONCE_LEN, SALT_LEN};
+#[cfg(feature = "passphrase")]
+use crate::wire::{ObjectKind, Reader};
+
+/// Largest PBKDF2 iteration count accepted, about ten seconds of work.
+const MAX_PBKDF2_ITERATIONS: u32 = 1 << 25;
+/// Largest Argon2 memory cost accepted, in KiB: 4 GiB.
+#[cfg(feature = "argon2")]
+const MAX_ARGON2_MEMORY_KIB: u32 = 1 << 22;
+/// Largest Argon2 pass count accepted.
+#[cfg(feature = "argon2")]
+const MAX_ARGON2_ITERATIONS: u32 = 64;
+
+/// How a passphrase becomes an encryption key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KdfParams {
+    /// PBKDF2 with HMAC-SHA256, always available.
+    Pbkdf2Sha256 { iterations: u32 },
+    /// Argon2id, version 1.3 (the `argon2` feature).
+    #[cfg(feature = "argon2")]
+    Argon2id {
+        memory_kib: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+}
+
+impl KdfParams {
+    /// The current OWASP recommendation: Argon2id with 19 MiB and 2 passes
+    /// with the `argon2` feature, PBKDF2 with 600,000 iterations otherwise.
+    pub fn recommended() -> Self {
+        #[cfg(feature = "argon2")]
+        return KdfParams::Argon2id {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        };
+        #[cfg(not(feature = "argon2"))]
+        KdfParams::Pbkdf2Sha256 {
+            iterations: 600_000,
+        }
+    }
+
+    /// Reject parameters that derive nothing or would take unreasonably
+    /// long, whether they come from a caller or from stored data.
+    pub(crate) fn validate(&self) -> Result<(), GswError> {
+        let valid = match *self {
+            KdfParams::Pbkdf2Sha256 { iterations } => {
+                (1..=MAX_PBKDF2_ITERATIONS).contains(&iterations)
+            }
+            #[cfg(feature = "argon2")]
+            KdfParams::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                (1..=MAX_ARGON2_ITERATIONS).contains(&iterations)
+                    && (1..=255).contains(&parallelism)
+                    && (8 * parallelism..=MAX_ARGON2_MEMORY_KIB).contains(&memory_kib)
+            }
+        };
+        if !valid {
+            return Err(GswError::MalformedData(format!(
+                "invalid key derivation: {}",
+                self
+            )));
+        }
+        Ok(())
+    }
+
+    /// The 32-byte key for `passphrase` and `salt`.
+    #[cfg(feature = "passphrase")]
+    fn derive(&self, passphrase: &[u8], salt: &[u8]) -> Result<Zeroizing<[u8; 32]>, GswError> {
+        self.validate()?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        match *self {
+            KdfParams::Pbkdf2Sha256 { iterations } => {
+                pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase, salt, iterations, &mut key[..]);
+            }
+            #[cfg(feature = "argon2")]
+            KdfParams::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                let invalid = |e: argon2::Error| {
+                    GswError::MalformedData(format!("invalid key derivation: {}", e))
+                };
+                let params = argon2::Params::new(memory_kib, iterations, parallelism, Some(32))
+                    .map_err(invalid)?;
+                argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+                    .hash_password_into(passphrase, salt, &mut key[..])
+                    .map_err(invalid)?;
+            }
+        }
+        Ok(key)
+    }
+}
+
+impl fmt::Display for KdfParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KdfParams::Pbkdf2Sha256 { iterations } => {
+                write!(f, "PBKDF2-HMAC-SHA256 with {} iterations", iterations)
+            }
+            #[cfg(feature = "argon2")]
+            KdfParams::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => write!(
+                f,
+                "Argon2id with {} KiB, {} passes, {} lanes",
+                memory_kib, iterations, parallelism
+            ),
+        }
+    }
+}
+
+/// The weakest key derivation an import accepts. The default is
+/// [`KdfParams::recommended`]'s strength for each function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KdfPolicy {
+    pub min_pbkdf2_iterations: u32,
+    #[cfg(feature = "argon2")]
+    pub min_argon2_memory_kib: u32,
+    #[cfg(feature = "argon2")]
+    pub min_argon2_iterations: u32,
+}
+
+impl Default for KdfPolicy {
+    fn default() -> Self {
+        Self {
+            min_pbkdf2_iterations: 600_000,
+            #[cfg(feature = "argon2")]
+            min_argon2_memory_kib: 19 * 1024,
+            #[cfg(feature = "argon2")]
+            min_argon2_iterations: 2,
+        }
+    }
+}
+
+impl KdfPolicy {
+    /// Fail with [`GswError::WeakKdf`] if `kdf` is weaker than allowed.
+    pub fn check(&self, kdf: &KdfParams) -> Result<(), GswError> {
+        let strong_enough = match *kdf {
+            KdfParams::Pbkdf2Sha256 { iterations } => iterations >= self.min_pbkdf2_iterations,
+            #[cfg(feature = "argon2")]
+            KdfParams::Argon2id {
+                memory_kib,
+                iterations,
+                ..
+            } => {
+                memory_kib >= self.min_argon2_memory_kib && iterations >= self.min_argon2_iterations
+            }
+        };
+        if !strong_enough {
+            return Err(GswError::WeakKdf { found: *kdf });
+        }
+        Ok(())
+    }
+}
+
+/// Encrypt `plaintext` as an object of `kind` under a key derived from
+/// `passphrase`, with a fresh salt and nonce.
+#[cfg(feature = "client")]
+pub(crate) fn seal<R: Rng>(
+    rng: &mut R,
+    kind: ObjectKind,
+    params_fingerprint: &Digest,
+    plaintext: &[u8],
+    passphrase: &[u8],
+    kdf: &KdfParams,
+) -> Result<Vec<u8>, GswError> {
+    let salt: [u8; SALT_LEN] = rng.gen();
+    let nonce: [u8; NONCE_LEN] = rng.gen();
+    let key = kdf.derive(passphrase, &salt)?;
+
+    let mut out = Vec::new();
+    wire::write_header(&mut out, kind);
+    out.extend_from_slice(params_fingerprint);
+    wire::write_kdf_params(&mut out, kdf);
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce);
+    let sealed = ChaCha20Poly1305::new(Key::from_slice(&key[..]))
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: &out,
+            },
+        )
+        .expect("ChaCha20-Poly1305 encrypts any message that fits in memory");
+    out.extend_from_slice(&sealed);
+    Ok(out)
+}
+
+/// Decrypt an object written by [`seal`], checking its key derivation
+/// against `policy` first.
+#[cfg(feature = "passphrase")]
+pub(crate) fn open(
+    bytes: &[u8],
+    kind: ObjectKind,
+    passphrase: &[u8],
+    policy: &KdfPolicy,
+) -> Result<Zeroizing<Vec<u8>>, GswError> {
+    let mut reader = Reader::new(bytes);
+    reader.header(kind)?;
+    reader.hash()?;
+    let (kdf, salt, nonce) = reader.sealed_frame()?;
+    policy.check(&kdf)?;
+    let (aad, sealed) = reader.split();
+    let key = kdf.derive(passphrase, &salt)?;
+    ChaCha20Poly1305::new(Key::from_slice(&key[..]))
+        .decrypt(Nonce::from_slice(&nonce), Payload { msg: sealed, aad })
+        .map(Zeroizing::new)
+        .map_err(|_| GswError::WrongPassphrase)
+}
+
+#[cfg(feature = "passphrase")]
+impl SecretKey {
+    /// This key's encoding, encrypted under `passphrase` with key
+    /// derivation `kdf` (see the [module docs](crate::passphrase)).
+    ///
+    /// Fails with [`GswError::MalformedData`] if `kdf` is out of range: no
+    /// iterations, or enough to take minutes.
+    #[cfg(feature = "client")]
+    pub fn export_encrypted<R: Rng>(
+        &self,
+        rng: &mut R,
+        passphrase: &[u8],
+        kdf: &KdfParams,
+    ) -> Result<Vec<u8>, GswError> {
+        let mut encoded = Zeroizing::new(Vec::new());
+        wire::write_secret_key(&mut encoded, self);
+        seal(
+            rng,
+            ObjectKind::SealedSecretKey,
+            &digest(self.params()),
+            &encoded,
+            passphrase,
+            kdf,
+        )
+    }
+
+    /// Decrypt a key written by [`export_encrypted`](Self::export_encrypted),
+    /// requiring the [default](KdfPolicy::default) key-derivation strength.
+    pub fn import_encrypted(bytes: &[u8], passphrase: &[u8]) -> Result<Self, GswError> {
+        Self::import_encrypted_with_policy(bytes, passphrase, &KdfPolicy::default())
+    }
+
+    /// [`import_encrypted`](Self::import_encrypted), requiring the strength
+    /// `policy` sets instead.
+    pub fn import_encrypted_with_policy(
+        bytes: &[u8],
+        passphrase: &[u8],
+        policy: &KdfPolicy,
+    ) -> Result<Self, GswError> {
+        let encoded = open(bytes, ObjectKind::SealedSecretKey, passphrase, policy)?;
+        let mut reader = Reader::new(&encoded);
+        let sk = reader.secret_key()?;
+        reader.finish()?;
+        Ok(sk)
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::gsw::{decrypt, encrypt, gsw_keygen};
+    use crate::params::Params;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    /// Fast enough for tests, and allowed by [`lenient`].
+    const FAST: KdfParams = KdfParams::Pbkdf2Sha256 { iterations: 1000 };
+
+    fn lenient() -> KdfPolicy {
+        allowing(1000)
+    }
+
+    // Without the argon2 feature, the policy has only this field.
+    #[allow(clippy::field_reassign_with_default)]
+    fn allowing(min_pbkdf2_iterations: u32) -> KdfPolicy {
+        let mut policy = KdfPolicy::default();
+        policy.min_pbkdf2_iterations = min_pbkdf2_iterations;
+        policy
+    }
+
+    fn exported() -> (SecretKey, crate::lwe::PublicKey, Vec<u8>) {
+        let mut rng = ChaCha20Rng::seed_from_u64(496);
+        let (sk, pk) = gsw_keygen(&mut rng, &Params::toy());
+        let bytes = sk
+            .export_encrypted(&mut rng, b"correct horse", &FAST)
+            .unwrap();
+        (sk, pk, bytes)
+    }
+
+    /// Offset of the PBKDF2 iteration count: header, fingerprint, KDF id.
+    const ITERATIONS_AT: usize = wire::HEADER_LEN + 32 + 1;
+
+    #[test]
+    fn test_round_trip_with_the_right_passphrase() {
+        let (sk, pk, bytes) = exported();
+        let imported =
+            SecretKey::import_encrypted_with_policy(&bytes, b"correct horse", &lenient()).unwrap();
+        assert_eq!(imported.s, sk.s);
+        assert_eq!(imported.params(), sk.params());
+        let mut rng = ChaCha20Rng::seed_from_u64(496);
+        assert_eq!(decrypt(&imported, &encrypt(&mut rng, &pk, 1)), 1);
+
+        // A fresh salt and nonce every time; the plaintext key never shows.
+        let mut rng = ChaCha20Rng::seed_from_u64(497);
+        let again = sk
+            .export_encrypted(&mut rng, b"correct horse", &FAST)
+            .unwrap();
+        assert_ne!(again, bytes);
+        let s_bytes = sk.s[1].to_le_bytes();
+        assert!(!bytes.windows(8).any(|w| w == s_bytes));
+    }
+
+    #[test]
+    fn test_wrong_passphrase_and_tampering_fail_decryption() {
+        let (_, _, bytes) = exported();
+        assert!(matches!(
+            SecretKey::import_encrypted_with_policy(&bytes, b"battery staple", &lenient()),
+            Err(GswError::WrongPassphrase)
+        ));
+
+        // The ciphertext, the salt, and the params fingerprint are all
+        // authenticated.
+        for at in [
+            bytes.len() - 1,
+            bytes.len() - 40,
+            ITERATIONS_AT + 12,
+            wire::HEADER_LEN,
+        ] {
+            let mut tampered = bytes.clone();
+            tampered[at] ^= 1;
+            assert!(
+                matches!(
+                    SecretKey::import_encrypted_with_policy(
+                        &tampered,
+                        b"correct horse",
+                        &lenient()
+                    ),
+                    Err(GswError::WrongPassphrase)
+                ),
+                "byte {}",
+                at
+            );
+        }
+
+        // Fields that do not parse are corruption, not a wrong passphrase.
+        let mut unknown_kdf = bytes.clone();
+        unknown_kdf[ITERATIONS_AT - 1] = 9;
+        let truncated = &bytes[..ITERATIONS_AT + 4];
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        for (corrupt, what) in [(&unknown_kdf[..], "unknown KDF"), (truncated, "truncated")] {
+            assert!(
+                matches!(
+                    SecretKey::import_encrypted_with_policy(corrupt, b"correct horse", &lenient()),
+                    Err(GswError::MalformedData(_))
+                ),
+                "{}",
+                what
+            );
+        }
+        assert!(matches!(
+            SecretKey::import_encrypted(&bad_magic, b"correct horse"),
+            Err(GswError::BadMagic)
+        ));
+    }
+
+    #[test]
+    fn test_weakened_kdf_is_rejected() {
+        let (_, _, bytes) = exported();
+        // Below the default minimum as exported.
+        assert!(matches!(
+            SecretKey::import_encrypted(&bytes, b"correct horse"),
+            Err(GswError::WeakKdf { found: FAST })
+        ));
+
+        // An attacker lowers the stored iteration count to speed up
+        // guessing; the policy stops it before any derivation.
+        let mut downgraded = bytes.clone();
+        downgraded[ITERATIONS_AT..ITERATIONS_AT + 4].copy_from_slice(&1u32.to_le_bytes());
+        assert!(matches!(
+            SecretKey::import_encrypted_with_policy(&downgraded, b"correct horse", &lenient()),
+            Err(GswError::WeakKdf {
+                found: KdfParams::Pbkdf2Sha256 { iterations: 1 }
+            })
+        ));
+        // A policy that allowed it would still fail authentication.
+        assert!(matches!(
+            SecretKey::import_encrypted_with_policy(&downgraded, b"correct horse", &allowing(1)),
+            Err(GswError::WrongPassphrase)
+        ));
+
+        let mut rng = ChaCha20Rng::seed_from_u64(496);
+        let (sk, _) = gsw_keygen(&mut rng, &Params::toy());
+        let none = KdfParams::Pbkdf2Sha256 { iterations: 0 };
+        assert!(matches!(
+            sk.export_encrypted(&mut rng, b"pw", &none),
+            Err(GswError::MalformedData(_))
+        ));
+    }
+
+    #[cfg(feature = "argon2")]
+    #[test]
+    fn test_argon2_round_trip() {
+        let mut rng = ChaCha20Rng::seed_from_u64(496);
+        let (sk, _) = gsw_keygen(&mut rng, &Params::toy());
+        let kdf = KdfParams::Argon2id {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let bytes = sk.export_encrypted(&mut rng, b"pw", &kdf).unwrap();
+        let policy = KdfPolicy {
+            min_argon2_memory_kib: 64,
+            min_argon2_iterations: 1,
+            ..KdfPolicy::default()
+        };
+        let imported = SecretKey::import_encrypted_with_policy(&bytes, b"pw", &policy).unwrap();
+        assert_eq!(imported.s, sk.s);
+        assert!(matches!(
+            SecretKey::import_encrypted(&bytes, b"pw"),
+            Err(GswError::WeakKdf { .. })
+        ));
+    }
+}
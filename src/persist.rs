@@ -0,0 +1,122 @@
+//! Crash-safe files: atomic replacement and a checksum footer.
+//!
+//! A file is the encoded object followed by its SHA-256 hash. The footer is
+//! SHA-256 whatever the `blake3` feature says, so a file saved by a client
+//! build loads in an evaluation-only build and vice versa. Writes go to a
+//! temporary file in the same directory, which is synced and then renamed over
+//! the target, so a crash leaves either the old file or the new one, never a
+//! mix. Loads recompute the hash, so truncation and bit rot surface as
+//! [`GswError::CorruptFile`] rather than as a confusing decode error.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::digest::{to_hex, Digest, GswHash, Sha256};
+use crate::error::GswError;
+
+/// Length of the checksum footer.
+pub(crate) const FOOTER_LEN: usize = std::mem::size_of::<Digest>();
+
+/// Atomically replace `path` with `body` and its checksum footer. With
+/// `secret`, the file is readable by its owner only (mode 0600 on Unix; no
+/// effect elsewhere), set before the file gets its final name.
+pub(crate) fn write_atomic(path: &Path, body: &[u8], secret: bool) -> Result<(), GswError> {
+    let tmp = temp_path(path);
+    let result = write_new(&tmp, body, secret).and_then(|()| Ok(fs::rename(&tmp, path)?));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+fn write_new(tmp: &Path, body: &[u8], secret: bool) -> Result<(), GswError> {
+    let mut file = File::create(tmp)?;
+    if secret {
+        restrict_to_owner(&file)?;
+    }
+    file.write_all(body)?;
+    file.write_all(&Sha256::hash(body))?;
+    file.sync_all()?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn restrict_to_owner(file: &File) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    file.set_permissions(fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+fn restrict_to_owner(_file: &File) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// `<name>.tmp-<pid>` next to `path`, so the rename stays on one file system.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".tmp-{}", std::process::id()));
+    path.with_file_name(name)
+}
+
+/// The body of a file written by [`write_atomic`], after checking its footer.
+pub(crate) fn read_checked(path: &Path) -> Result<Vec<u8>, GswError> {
+    let mut bytes = fs::read(path)?;
+    let corrupt = |expected: String, found: String| GswError::CorruptFile {
+        path: path.to_path_buf(),
+        expected,
+        found,
+    };
+    if bytes.len() < FOOTER_LEN {
+        return Err(corrupt(
+            format!("a {}-byte checksum footer", FOOTER_LEN),
+            format!("{} bytes in all", bytes.len()),
+        ));
+    }
+    let footer = bytes.split_off(bytes.len() - FOOTER_LEN);
+    let computed = Sha256::hash(&bytes);
+    if footer[..] != computed[..] {
+        return Err(corrupt(to_hex(&computed), hex(&footer)));
+    }
+    Ok(bytes)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut digest = Digest::default();
+    digest.copy_from_slice(bytes);
+    to_hex(&digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("gsw-rs-persist-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_round_trip_leaves_no_temp_file() {
+        let path = temp_file("round-trip");
+        write_atomic(&path, b"first", false).unwrap();
+        write_atomic(&path, b"second", false).unwrap();
+        assert_eq!(read_checked(&path).unwrap(), b"second");
+        assert_eq!(fs::read(&path).unwrap().len(), 6 + FOOTER_LEN);
+        assert!(!temp_path(&path).exists());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_failed_rename_cleans_up() {
+        // Renaming onto a directory fails after the temporary file is written.
+        let dir = temp_file("occupied");
+        fs::create_dir_all(&dir).unwrap();
+        assert!(matches!(
+            write_atomic(&dir, b"body", false),
+            Err(GswError::Io(_))
+        ));
+        assert!(!temp_path(&dir).exists());
+        assert!(dir.is_dir());
+        fs::remove_dir(&dir).unwrap();
+    }
+}
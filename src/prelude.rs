@@ -0,0 +1,30 @@
+//! The stable API: everything needed to generate keys, encrypt, evaluate
+//! gates and circuits, bootstrap, and save a session.
+//!
+//! ```ignore
+//! use gsw_rs::prelude::*;
+//! ```
+//!
+//! Items here change incompatibly only in a major version. The rest of the
+//! crate is public for analysis and tooling and moves faster; see the crate
+//! documentation.
+
+#[cfg(feature = "client")]
+pub use crate::bootstrap::gen_evaluation_key;
+pub use crate::bootstrap::{bootstrap, EvaluationKey};
+pub use crate::circuits::{Circuit, EncryptedBits};
+pub use crate::context::{EncBool, EncryptedVec, Evaluator};
+pub use crate::error::GswError;
+pub use crate::gsw::{
+    ciphertext_from_bytes, ciphertext_to_bytes, homomorphic_add, homomorphic_and_many,
+    homomorphic_majority3, homomorphic_mult, homomorphic_mux, homomorphic_nand, homomorphic_nor,
+    homomorphic_not, homomorphic_or, homomorphic_or_many, homomorphic_sub, homomorphic_xnor,
+    Ciphertext, EntryPolicy, GswPublicKey, GswSecretKey,
+};
+#[cfg(feature = "client")]
+pub use crate::gsw::{decrypt, encrypt, gsw_keygen, DecryptionContext};
+pub use crate::integer::EncryptedUint;
+#[cfg(feature = "client")]
+pub use crate::integer::{decrypt_uint, encrypt_uint};
+pub use crate::params::{Params, SecurityLevel};
+pub use crate::session::{Session, SessionRole};
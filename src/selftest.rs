@@ -0,0 +1,327 @@
+//! A randomized self-test for deployment health checks.
+//!
+//! [`self_test`] runs the crypto stack end to end on fresh keys, one stage at
+//! a time, and stops at the first stage that fails, so a miscompiled or
+//! misdispatched product kernel, broken serialization, or a bad build shows
+//! up as a named stage rather than as wrong answers later. A panic inside a
+//! stage is caught and reported as that stage's failure.
+//!
+//! Bootstrapping runs at Toy only; at the larger presets it takes seconds to
+//! minutes (see [`cost`](crate::cost)). Even at Toy the bootstrapped bit is
+//! right only for some keys (it is the parity of the input's phase, see
+//! [`diagnose_bootstrap`]), so the stage checks that the bootstrap agrees with
+//! its step-by-step recomputation and that the coefficients reproduce the
+//! phase, not the bit itself.
+
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+use crate::bootstrap::{bootstrap, diagnose_bootstrap, gen_evaluation_key};
+use crate::gsw::{
+    ciphertext_from_bytes, ciphertext_to_bytes, decrypt, encrypt, gsw_keygen, homomorphic_add,
+    homomorphic_mult, homomorphic_nand, homomorphic_not, Ciphertext, EntryPolicy, GswPublicKey,
+    GswSecretKey,
+};
+use crate::kernels;
+use crate::params::{Params, SecurityLevel};
+use crate::session::Session;
+
+/// A stage of [`self_test`], in the order they run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelfTestStage {
+    /// A key pair of the right shape.
+    Keygen,
+    /// Both bits encrypt and decrypt.
+    Encrypt,
+    /// XOR, AND, NAND, and NOT over every input combination.
+    Gates,
+    /// A product of three encryptions of 1, two multiplications deep.
+    DepthChain,
+    /// A ciphertext and a session survive encoding and decoding.
+    Serialization,
+    /// A bootstrap, at Toy only.
+    Bootstrap,
+}
+
+impl fmt::Display for SelfTestStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SelfTestStage::Keygen => "keygen",
+            SelfTestStage::Encrypt => "encrypt",
+            SelfTestStage::Gates => "gates",
+            SelfTestStage::DepthChain => "depth chain",
+            SelfTestStage::Serialization => "serialization",
+            SelfTestStage::Bootstrap => "bootstrap",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A stage that passed, and how long it took.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StageReport {
+    pub stage: SelfTestStage,
+    pub elapsed: Duration,
+}
+
+/// Every stage passed.
+#[derive(Clone, Debug)]
+pub struct SelfTestReport {
+    pub level: SecurityLevel,
+    /// The seed the keys and encryptions were drawn from; passing it back to
+    /// [`self_test`] repeats the run.
+    pub seed: u64,
+    /// The product kernel in use (see [`kernels`]).
+    pub kernel: &'static str,
+    pub stages: Vec<StageReport>,
+}
+
+/// The first stage that failed, and the ones that passed before it.
+#[derive(Clone, Debug)]
+pub struct SelfTestFailure {
+    pub stage: SelfTestStage,
+    pub reason: String,
+    pub seed: u64,
+    pub kernel: &'static str,
+    pub passed: Vec<StageReport>,
+}
+
+impl fmt::Display for SelfTestFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "self-test failed at {} (seed {}, kernel {}): {}",
+            self.stage, self.seed, self.kernel, self.reason
+        )
+    }
+}
+
+impl std::error::Error for SelfTestFailure {}
+
+/// Fails with `reason` unless `ok`.
+fn check(ok: bool, reason: impl FnOnce() -> String) -> Result<(), String> {
+    if ok {
+        Ok(())
+    } else {
+        Err(reason())
+    }
+}
+
+struct Run {
+    seed: u64,
+    kernel: &'static str,
+    passed: Vec<StageReport>,
+}
+
+impl Run {
+    /// Run `body` as `stage`, timing it and catching panics.
+    fn stage<T>(
+        &mut self,
+        stage: SelfTestStage,
+        body: impl FnOnce() -> Result<T, String>,
+    ) -> Result<T, SelfTestFailure> {
+        let start = Instant::now();
+        let outcome = panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            Err(format!("panicked: {}", message))
+        });
+        match outcome {
+            Ok(value) => {
+                self.passed.push(StageReport {
+                    stage,
+                    elapsed: start.elapsed(),
+                });
+                Ok(value)
+            }
+            Err(reason) => Err(SelfTestFailure {
+                stage,
+                reason,
+                seed: self.seed,
+                kernel: self.kernel,
+                passed: std::mem::take(&mut self.passed),
+            }),
+        }
+    }
+}
+
+/// Run the self-test at `level` with keys drawn from `seed`, or from a
+/// random seed (recorded in the result) if none is given.
+pub fn self_test(
+    level: SecurityLevel,
+    seed: Option<u64>,
+) -> Result<SelfTestReport, SelfTestFailure> {
+    let seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+    let mut rng = ChaCha20Rng::seed_from_u64(seed);
+    let params = Params::new(level);
+    let mut run = Run {
+        seed,
+        kernel: kernels::active_kernel_name(),
+        passed: Vec::new(),
+    };
+
+    let (sk, pk) = run.stage(SelfTestStage::Keygen, || {
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        check(sk.s.len() == params.n + 1 && sk.s[0] == 1, || {
+            "secret key is not (1, -t)".into()
+        })?;
+        check(
+            pk.a.len() == params.m
+                && pk
+                    .a
+                    .iter()
+                    .all(|row| row.len() == params.n + 1 && row.iter().all(|&x| x < params.q)),
+            || "public key has the wrong shape or unreduced entries".into(),
+        )?;
+        Ok((sk, pk))
+    })?;
+
+    let bits = run.stage(SelfTestStage::Encrypt, || {
+        let bits = [0, 1].map(|bit| encrypt(&mut rng, &pk, bit));
+        for (bit, ct) in bits.iter().enumerate() {
+            check(decrypt(&sk, ct) == bit as u8, || {
+                format!("Enc({}) decrypts to {}", bit, 1 - bit)
+            })?;
+        }
+        Ok(bits)
+    })?;
+
+    run.stage(SelfTestStage::Gates, || gates(&params, &sk, &bits))?;
+
+    run.stage(SelfTestStage::DepthChain, || {
+        let [a, b, c] = [(); 3].map(|_| encrypt(&mut rng, &pk, 1));
+        let chain = homomorphic_mult(&params, &homomorphic_mult(&params, &a, &b), &c);
+        check(decrypt(&sk, &chain) == 1, || "1·1·1 decrypts to 0".into())
+    })?;
+
+    run.stage(SelfTestStage::Serialization, || {
+        serialization(&params, &sk, &pk, &bits)
+    })?;
+
+    if level == SecurityLevel::Toy {
+        run.stage(SelfTestStage::Bootstrap, || {
+            let ek = gen_evaluation_key(&mut rng, &sk, &pk);
+            let noisy = homomorphic_mult(&params, &bits[1], &bits[1]);
+            let output = decrypt(&sk, &bootstrap(&params, &noisy, &ek));
+            let diagnosis =
+                diagnose_bootstrap(&sk, &params, &noisy, &ek).map_err(|e| e.to_string())?;
+            check(diagnosis.coefficients_match, || {
+                "bootstrap coefficients do not reproduce the phase".into()
+            })?;
+            check(diagnosis.output_bit == output, || {
+                "bootstrap disagrees with its step-by-step recomputation".into()
+            })
+        })?;
+    }
+
+    Ok(SelfTestReport {
+        level,
+        seed,
+        kernel: run.kernel,
+        stages: run.passed,
+    })
+}
+
+/// Truth tables of the gates on encryptions of 0 and 1.
+fn gates(params: &Params, sk: &GswSecretKey, bits: &[Ciphertext; 2]) -> Result<(), String> {
+    for a in 0..2u8 {
+        check(
+            decrypt(sk, &homomorphic_not(params, &bits[a as usize])) == 1 - a,
+            || format!("NOT {} is wrong", a),
+        )?;
+        for b in 0..2u8 {
+            let (x, y) = (&bits[a as usize], &bits[b as usize]);
+            for (name, ct, expected) in [
+                ("XOR", homomorphic_add(params, x, y), a ^ b),
+                ("AND", homomorphic_mult(params, x, y), a & b),
+                ("NAND", homomorphic_nand(params, x, y), 1 - (a & b)),
+            ] {
+                check(decrypt(sk, &ct) == expected, || {
+                    format!("{} {} {} is wrong", a, name, b)
+                })?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A ciphertext and a client session round-trip through their encodings.
+fn serialization(
+    params: &Params,
+    sk: &GswSecretKey,
+    pk: &GswPublicKey,
+    bits: &[Ciphertext; 2],
+) -> Result<(), String> {
+    for ct in bits {
+        let decoded = ciphertext_from_bytes(
+            params,
+            &ciphertext_to_bytes(params, ct),
+            EntryPolicy::Reject,
+        )
+        .map_err(|e| e.to_string())?;
+        check(&decoded == ct, || {
+            "ciphertext changed in a round trip".into()
+        })?;
+    }
+    let session = Session::from_parts(params.clone(), Some(sk.clone()), pk.clone(), None)
+        .map_err(|e| e.to_string())?;
+    let loaded = Session::from_bytes(&session.to_bytes()).map_err(|e| e.to_string())?;
+    check(loaded.public_key().a == pk.a, || {
+        "public key changed in a round trip".into()
+    })?;
+    for (bit, ct) in bits.iter().enumerate() {
+        check(
+            matches!(loaded.decrypt(ct), Ok(b) if b == bit as u8),
+            || "a reloaded session decrypts wrongly".into(),
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toy_self_test_passes_every_stage() {
+        let report = self_test(SecurityLevel::Toy, Some(473)).unwrap();
+        assert_eq!(report.seed, 473);
+        assert_eq!(report.kernel, kernels::active_kernel_name());
+        let stages: Vec<SelfTestStage> = report.stages.iter().map(|s| s.stage).collect();
+        assert_eq!(
+            stages,
+            [
+                SelfTestStage::Keygen,
+                SelfTestStage::Encrypt,
+                SelfTestStage::Gates,
+                SelfTestStage::DepthChain,
+                SelfTestStage::Serialization,
+                SelfTestStage::Bootstrap,
+            ]
+        );
+        assert!(report.stages.iter().all(|s| s.elapsed > Duration::ZERO));
+    }
+
+    #[test]
+    fn test_corrupted_kernel_fails_the_gates_stage() {
+        // Validation would catch the corruption and recompute the products.
+        let _settings = kernels::SETTINGS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        kernels::corrupt_products_on_this_thread(true);
+        let failure = self_test(SecurityLevel::Toy, Some(473)).unwrap_err();
+        kernels::corrupt_products_on_this_thread(false);
+        assert_eq!(failure.stage, SelfTestStage::Gates);
+        assert_eq!(failure.reason, "1 AND 1 is wrong");
+        let passed: Vec<SelfTestStage> = failure.passed.iter().map(|s| s.stage).collect();
+        assert_eq!(passed, [SelfTestStage::Keygen, SelfTestStage::Encrypt]);
+        assert!(failure.to_string().contains("failed at gates"));
+    }
+}
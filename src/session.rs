@@ -0,0 +1,692 @@
+//! Sessions: parameters, keys, and evaluation key bundled in one object.
+//!
+//! A session is saved to and loaded from a single file in the versioned
+//! binary format (see the `wire` module docs), so the components can never
+//! drift apart. Files end in a checksum of the encoding and are replaced
+//! atomically, so a crash mid-save or later corruption is caught at load.
+//! [`Session::save_encrypted`] protects the file with a passphrase (see the
+//! `passphrase` module).
+//! Which components are present determines the [`SessionRole`].
+
+#[cfg(feature = "persist")]
+use std::path::Path;
+use std::sync::Arc;
+
+#[cfg(feature = "client")]
+use rand::Rng;
+
+#[cfg(feature = "client")]
+use crate::bootstrap::gen_evaluation_key;
+use crate::bootstrap::{bootstrap, EvaluationKey};
+use crate::context::Evaluator;
+use crate::digest::digest;
+use crate::error::GswError;
+use crate::gsw::Ciphertext;
+#[cfg(feature = "client")]
+use crate::gsw::{decrypt, gsw_keygen, DecryptionContext};
+use crate::lwe::{PublicKey, SecretKey};
+use crate::params::Params;
+#[cfg(feature = "client")]
+use crate::params::SecurityLevel;
+#[cfg(feature = "client")]
+use crate::passphrase::KdfParams;
+#[cfg(feature = "passphrase")]
+use crate::passphrase::{self, KdfPolicy};
+#[cfg(feature = "persist")]
+use crate::persist;
+use crate::testing::FaultHook;
+#[cfg(feature = "test-utils")]
+use crate::testing::FaultInjector;
+use crate::wire::{self, ObjectKind, Reader};
+
+pub(crate) const FLAG_SECRET_KEY: u8 = 1;
+pub(crate) const FLAG_EVALUATION_KEY: u8 = 2;
+
+/// Encoded size of a session with the given components: header, flags,
+/// params, then each component.
+pub(crate) fn encoded_len(params: &Params, secret_key: bool, evaluation_key: bool) -> u64 {
+    let mut len = (wire::HEADER_LEN + 1 + wire::PARAMS_LEN) as u64 + wire::public_key_len(params);
+    if secret_key {
+        len += wire::secret_key_len(params);
+    }
+    if evaluation_key {
+        len += wire::evaluation_key_len(params);
+    }
+    len
+}
+
+/// What a session can do, derived from the components it holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionRole {
+    /// Holds the secret key: can encrypt, decrypt, and evaluate.
+    ClientFull,
+    /// No secret key, but an evaluation key: can evaluate and bootstrap.
+    ServerEvalOnly,
+    /// Public key only: can encrypt and evaluate without bootstrapping.
+    PublicOnly,
+}
+
+/// Params, public key, and the optional secret and evaluation keys.
+#[derive(Clone, Debug)]
+pub struct Session {
+    params: Arc<Params>,
+    secret_key: Option<SecretKey>,
+    public_key: PublicKey,
+    evaluation_key: Option<EvaluationKey>,
+    faults: FaultHook,
+}
+
+impl Session {
+    /// Generate a fresh key pair and evaluation key at the given level.
+    ///
+    /// Fails with [`GswError::InsecureParams`] below the production
+    /// security level; use [`Session::client`] with
+    /// [`allow_insecure`](ClientSessionBuilder::allow_insecure) for tests and
+    /// experiments at the other presets.
+    #[cfg(feature = "client")]
+    pub fn new_client<R: Rng>(rng: &mut R, level: SecurityLevel) -> Result<Self, GswError> {
+        Self::client(&Params::new(level)).build(rng)
+    }
+
+    /// A builder for a client session with fresh keys under `params`.
+    #[cfg(feature = "client")]
+    pub fn client(params: &Params) -> ClientSessionBuilder {
+        ClientSessionBuilder {
+            params: params.clone(),
+            allow_insecure: false,
+            evaluation_key: true,
+        }
+    }
+
+    /// Assemble a session from existing components, checking that they all
+    /// use `params` and that the evaluation key was generated under
+    /// `public_key`. The components are re-pointed at one shared params handle.
+    ///
+    /// Unlike `==` on [`Params`], the check also compares the failure target
+    /// and the plaintext encoding, since the session evaluates under them.
+    pub fn from_parts(
+        params: Params,
+        secret_key: Option<SecretKey>,
+        public_key: PublicKey,
+        evaluation_key: Option<EvaluationKey>,
+    ) -> Result<Self, GswError> {
+        let policy = |p: &Params| (p.target_failure_exp, p.encoding);
+        let shared = public_key.params_arc();
+        let matches =
+            |other: &Arc<Params>| Params::same(other, shared) && policy(other) == policy(shared);
+        let consistent = **shared == params
+            && policy(shared) == policy(&params)
+            && secret_key
+                .as_ref()
+                .is_none_or(|sk| matches(sk.params_arc()))
+            && evaluation_key
+                .as_ref()
+                .is_none_or(|ek| matches(ek.params_arc()));
+        if !consistent {
+            return Err(GswError::ParamsMismatch);
+        }
+        if evaluation_key
+            .as_ref()
+            .is_some_and(|ek| *ek.pk_fingerprint() != digest(&public_key))
+        {
+            return Err(GswError::KeyMismatch);
+        }
+        let params = Arc::clone(shared);
+        Ok(Self {
+            secret_key: secret_key.map(|sk| SecretKey::from_parts(sk.s, Arc::clone(&params))),
+            evaluation_key: evaluation_key.map(|ek| {
+                let pk_fingerprint = *ek.pk_fingerprint();
+                EvaluationKey::from_parts(ek.into_entries(), Arc::clone(&params), pk_fingerprint)
+            }),
+            params,
+            public_key,
+            faults: FaultHook::default(),
+        })
+    }
+
+    /// The view handed to an evaluation server: everything but the secret key.
+    pub fn server_view(client: &Session) -> Self {
+        Self {
+            secret_key: None,
+            ..client.clone()
+        }
+    }
+
+    /// The view handed to an encrypting party: parameters and public key only.
+    pub fn public_view(session: &Session) -> Self {
+        Self {
+            params: Arc::clone(&session.params),
+            secret_key: None,
+            public_key: session.public_key.clone(),
+            evaluation_key: None,
+            faults: FaultHook::default(),
+        }
+    }
+
+    /// This session with faults injected into its operations; see
+    /// [`crate::testing::FaultInjector`].
+    #[cfg(feature = "test-utils")]
+    pub fn with_fault_injector(self, injector: Arc<FaultInjector>) -> Self {
+        Self {
+            faults: FaultHook::new(injector),
+            ..self
+        }
+    }
+
+    /// An evaluator sharing this session's parameter handle and fault
+    /// injector.
+    pub fn evaluator(&self) -> Evaluator {
+        Evaluator::shared(Arc::clone(&self.params), self.faults.clone())
+    }
+
+    pub fn role(&self) -> SessionRole {
+        match (&self.secret_key, &self.evaluation_key) {
+            (Some(_), _) => SessionRole::ClientFull,
+            (None, Some(_)) => SessionRole::ServerEvalOnly,
+            (None, None) => SessionRole::PublicOnly,
+        }
+    }
+
+    pub fn params(&self) -> &Params {
+        &self.params
+    }
+
+    pub fn secret_key(&self) -> Option<&SecretKey> {
+        self.secret_key.as_ref()
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    pub fn evaluation_key(&self) -> Option<&EvaluationKey> {
+        self.evaluation_key.as_ref()
+    }
+
+    /// Decrypt with the session's secret key.
+    #[cfg(feature = "client")]
+    pub fn decrypt(&self, ct: &Ciphertext) -> Result<u8, GswError> {
+        self.faults.begin().check()?;
+        let sk = self.secret_key.as_ref().ok_or(GswError::MissingSecretKey)?;
+        Ok(decrypt(sk, ct))
+    }
+
+    /// Decrypt, failing with [`GswError::DecryptionSuspect`] once the noise
+    /// reaches q/8, half the margin decryption tolerates, rather than return
+    /// a bit that may be wrong.
+    #[cfg(feature = "client")]
+    pub fn decrypt_checked(&self, ct: &Ciphertext) -> Result<u8, GswError> {
+        let mut planned = self.faults.begin();
+        planned.check()?;
+        let sk = self.secret_key.as_ref().ok_or(GswError::MissingSecretKey)?;
+        let ctx = DecryptionContext::new(sk);
+        let limit = (self.params.q / 8) as i64;
+        let noise = planned.noise(ctx.measure_noise(ct), limit);
+        if noise.abs() >= limit {
+            return Err(GswError::DecryptionSuspect { noise, limit });
+        }
+        Ok(ctx.decrypt(ct))
+    }
+
+    /// Bootstrap with the session's evaluation key.
+    pub fn bootstrap(&self, ct: &Ciphertext) -> Result<Ciphertext, GswError> {
+        let mut planned = self.faults.begin();
+        planned.check()?;
+        let ek = self
+            .evaluation_key
+            .as_ref()
+            .ok_or(GswError::MissingEvaluationKey)?;
+        Ok(planned.output(bootstrap(&self.params, ct, ek)))
+    }
+
+    /// Serialize: header, presence flags, params, then each present component.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = wire::buffer(encoded_len(
+            &self.params,
+            self.secret_key.is_some(),
+            self.evaluation_key.is_some(),
+        ));
+        wire::write_header(&mut out, ObjectKind::Session);
+        let mut flags = 0;
+        if self.secret_key.is_some() {
+            flags |= FLAG_SECRET_KEY;
+        }
+        if self.evaluation_key.is_some() {
+            flags |= FLAG_EVALUATION_KEY;
+        }
+        out.push(flags);
+        wire::write_params(&mut out, &self.params);
+        if let Some(sk) = &self.secret_key {
+            wire::write_secret_key(&mut out, sk);
+        }
+        wire::write_public_key(&mut out, &self.public_key);
+        if let Some(ek) = &self.evaluation_key {
+            wire::write_evaluation_key(&mut out, ek);
+        }
+        out
+    }
+
+    /// Deserialize, rejecting components whose embedded params differ from the
+    /// session's.
+    ///
+    /// Fails with [`GswError::PassphraseRequired`] on a session written by
+    /// [`save_encrypted`](Self::save_encrypted).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GswError> {
+        if wire::peek_header(bytes).is_ok_and(|h| h.kind == ObjectKind::SealedSession) {
+            return Err(GswError::PassphraseRequired);
+        }
+        let mut reader = Reader::new(bytes);
+        reader.header(ObjectKind::Session)?;
+        let flags = reader.session_flags()?;
+        let params = reader.params()?;
+        let secret_key = if flags & FLAG_SECRET_KEY != 0 {
+            Some(reader.secret_key()?)
+        } else {
+            None
+        };
+        let public_key = reader.public_key()?;
+        let evaluation_key = if flags & FLAG_EVALUATION_KEY != 0 {
+            Some(reader.evaluation_key(digest(&public_key))?)
+        } else {
+            None
+        };
+        reader.finish()?;
+        Self::from_parts(params, secret_key, public_key, evaluation_key)
+    }
+
+    /// Write [`to_bytes`](Self::to_bytes) and a checksum footer to `path`,
+    /// atomically replacing any existing file. A session holding the secret
+    /// key is saved readable by its owner only (mode 0600 on Unix).
+    #[cfg(feature = "persist")]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), GswError> {
+        self.faults.begin().check()?;
+        persist::write_atomic(path.as_ref(), &self.to_bytes(), self.secret_key.is_some())
+    }
+
+    /// Read a file written by [`save`](Self::save), failing with
+    /// [`GswError::CorruptFile`] if its checksum does not match.
+    #[cfg(feature = "persist")]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, GswError> {
+        Self::from_bytes(&persist::read_checked(path.as_ref())?)
+    }
+
+    /// [`save`](Self::save), with the session encrypted under `passphrase`
+    /// and key derivation `kdf`.
+    #[cfg(feature = "client")]
+    pub fn save_encrypted<R: Rng, P: AsRef<Path>>(
+        &self,
+        rng: &mut R,
+        path: P,
+        passphrase: &[u8],
+        kdf: &KdfParams,
+    ) -> Result<(), GswError> {
+        self.faults.begin().check()?;
+        let sealed = passphrase::seal(
+            rng,
+            ObjectKind::SealedSession,
+            &digest(&*self.params),
+            &zeroize::Zeroizing::new(self.to_bytes()),
+            passphrase,
+            kdf,
+        )?;
+        persist::write_atomic(path.as_ref(), &sealed, true)
+    }
+
+    /// Read a file written by [`save_encrypted`](Self::save_encrypted) given
+    /// a passphrase, or by [`save`](Self::save) given none, requiring the
+    /// [default](KdfPolicy::default) key-derivation strength.
+    ///
+    /// With a passphrase, a file saved without one is rejected rather than
+    /// trusted, since anyone able to replace the file could have written it.
+    #[cfg(feature = "passphrase")]
+    pub fn load_with_passphrase<P: AsRef<Path>>(
+        path: P,
+        passphrase: Option<&[u8]>,
+    ) -> Result<Self, GswError> {
+        let bytes = persist::read_checked(path.as_ref())?;
+        match passphrase {
+            Some(passphrase) => {
+                let policy = KdfPolicy::default();
+                let encoded =
+                    passphrase::open(&bytes, ObjectKind::SealedSession, passphrase, &policy)?;
+                Self::from_bytes(&encoded)
+            }
+            None => Self::from_bytes(&bytes),
+        }
+    }
+}
+
+/// Generates the keys of a client session; see [`Session::client`].
+///
+/// Building refuses parameters that are not production safe (see
+/// [`Params::security_assessment`]) unless
+/// [`allow_insecure`](Self::allow_insecure) was called, so that test
+/// parameters cannot reach a deployment by accident.
+#[cfg(feature = "client")]
+#[derive(Clone, Debug)]
+pub struct ClientSessionBuilder {
+    params: Params,
+    allow_insecure: bool,
+    evaluation_key: bool,
+}
+
+#[cfg(feature = "client")]
+impl ClientSessionBuilder {
+    /// Accept parameters below the production security level.
+    pub fn allow_insecure(mut self) -> Self {
+        self.allow_insecure = true;
+        self
+    }
+
+    /// Skip the evaluation key, for clients that never hand work to a
+    /// bootstrapping server.
+    pub fn without_evaluation_key(mut self) -> Self {
+        self.evaluation_key = false;
+        self
+    }
+
+    /// Check the parameters and generate the keys.
+    pub fn build<R: Rng>(self, rng: &mut R) -> Result<Session, GswError> {
+        if !self.allow_insecure {
+            self.params.ensure_production_safe()?;
+        }
+        let (sk, pk) = gsw_keygen(rng, &self.params);
+        let ek = self
+            .evaluation_key
+            .then(|| gen_evaluation_key(rng, &sk, &pk));
+        Ok(Session {
+            params: Arc::clone(pk.params_arc()),
+            secret_key: Some(sk),
+            public_key: pk,
+            evaluation_key: ek,
+            faults: FaultHook::default(),
+        })
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::gsw::{encrypt, homomorphic_mult};
+    use crate::params::PlaintextEncoding;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("gsw-rs-{}-{}.session", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_session_roles_round_trip() {
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let client = Session::client(&Params::toy())
+            .allow_insecure()
+            .build(&mut rng)
+            .unwrap();
+        let server = Session::server_view(&client);
+        let public = Session::public_view(&client);
+        assert_eq!(client.role(), SessionRole::ClientFull);
+        assert_eq!(server.role(), SessionRole::ServerEvalOnly);
+        assert_eq!(public.role(), SessionRole::PublicOnly);
+
+        let ct1 = encrypt(&mut rng, public.public_key(), 1);
+        for (name, session) in [
+            ("client", &client),
+            ("server", &server),
+            ("public", &public),
+        ] {
+            let path = temp_path(name);
+            session.save(&path).unwrap();
+            let loaded = Session::load(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(loaded.role(), session.role());
+            assert_eq!(loaded.params(), client.params());
+            assert_eq!(loaded.public_key().a, client.public_key().a);
+            assert_eq!(
+                loaded.evaluation_key().map(EvaluationKey::encryptions),
+                session.evaluation_key().map(EvaluationKey::encryptions)
+            );
+            // Every role can evaluate; the client can read the result.
+            let product = homomorphic_mult(loaded.params(), &ct1, &ct1);
+            assert_eq!(client.decrypt(&product).unwrap(), 1);
+        }
+    }
+
+    #[test]
+    fn test_passphrase_protected_session() {
+        let mut rng = ChaCha20Rng::seed_from_u64(496);
+        let client = Session::client(&Params::toy())
+            .allow_insecure()
+            .without_evaluation_key()
+            .build(&mut rng)
+            .unwrap();
+        let path = temp_path("sealed");
+        let kdf = KdfParams::recommended();
+        client
+            .save_encrypted(&mut rng, &path, b"open sesame", &kdf)
+            .unwrap();
+
+        let loaded = Session::load_with_passphrase(&path, Some(b"open sesame")).unwrap();
+        assert_eq!(
+            loaded.secret_key().unwrap().s,
+            client.secret_key().unwrap().s
+        );
+        assert_eq!(loaded.public_key().a, client.public_key().a);
+        assert!(matches!(
+            Session::load_with_passphrase(&path, Some(b"open barley")),
+            Err(GswError::WrongPassphrase)
+        ));
+        assert!(matches!(
+            Session::load(&path),
+            Err(GswError::PassphraseRequired)
+        ));
+
+        // A plaintext file in its place is not accepted as protected.
+        client.save(&path).unwrap();
+        assert!(matches!(
+            Session::load_with_passphrase(&path, Some(b"open sesame")),
+            Err(GswError::MalformedData(_))
+        ));
+        assert!(Session::load_with_passphrase(&path, None).is_ok());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_saved_files_detect_corruption() {
+        let mut rng = ChaCha20Rng::seed_from_u64(5);
+        let (sk, pk) = gsw_keygen(&mut rng, &Params::pedagogical());
+        let session = Session::from_parts(Params::pedagogical(), Some(sk), pk, None).unwrap();
+        let path = temp_path("corrupt");
+        session.save(&path).unwrap();
+        let saved = std::fs::read(&path).unwrap();
+        assert_eq!(saved.len(), session.to_bytes().len() + persist::FOOTER_LEN);
+
+        // A write cut short by a crash, and a flipped byte.
+        let mut flipped = saved.clone();
+        flipped[saved.len() / 2] ^= 0x10;
+        for bytes in [&saved[..saved.len() - 5], &saved[..10], &flipped[..]] {
+            std::fs::write(&path, bytes).unwrap();
+            match Session::load(&path) {
+                Err(GswError::CorruptFile {
+                    path: reported,
+                    expected,
+                    found,
+                }) => {
+                    assert_eq!(reported, path);
+                    assert_ne!(expected, found);
+                }
+                other => panic!("expected CorruptFile, got {:?}", other.map(|_| ())),
+            }
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_secret_sessions_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut rng = ChaCha20Rng::seed_from_u64(6);
+        let (sk, pk) = gsw_keygen(&mut rng, &Params::pedagogical());
+        let client = Session::from_parts(Params::pedagogical(), Some(sk), pk, None).unwrap();
+        let public = Session::public_view(&client);
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        // Files without secrets get whatever the umask gives a new file.
+        let reference = temp_path("reference");
+        std::fs::write(&reference, b"").unwrap();
+        let default_mode = mode(&reference);
+        std::fs::remove_file(&reference).unwrap();
+
+        for (name, session, expected) in [
+            ("private", &client, 0o600),
+            ("public", &public, default_mode),
+        ] {
+            let path = temp_path(name);
+            session.save(&path).unwrap();
+            assert_eq!(mode(&path), expected, "{} session", name);
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_client_sessions_need_production_params() {
+        let mut rng = ChaCha20Rng::seed_from_u64(491);
+        assert!(matches!(
+            Session::new_client(&mut rng, SecurityLevel::Toy),
+            Err(GswError::InsecureParams { estimated_bits: 32 })
+        ));
+        assert!(matches!(
+            Session::client(&Params::new(SecurityLevel::Low)).build(&mut rng),
+            Err(GswError::InsecureParams { estimated_bits: 64 })
+        ));
+        let toy = Session::client(&Params::toy())
+            .allow_insecure()
+            .without_evaluation_key()
+            .build(&mut rng)
+            .unwrap();
+        assert_eq!(toy.role(), SessionRole::ClientFull);
+        assert!(toy.evaluation_key().is_none());
+
+        // An evaluation key at Medium takes minutes; the check comes first.
+        let medium = Session::client(&Params::new(SecurityLevel::Medium))
+            .without_evaluation_key()
+            .build(&mut rng)
+            .unwrap();
+        assert_eq!(medium.params(), &Params::new(SecurityLevel::Medium));
+    }
+
+    #[test]
+    fn test_server_session_cannot_decrypt() {
+        let mut rng = ChaCha20Rng::seed_from_u64(1);
+        let client = Session::client(&Params::toy())
+            .allow_insecure()
+            .build(&mut rng)
+            .unwrap();
+        let server = Session::from_bytes(&Session::server_view(&client).to_bytes()).unwrap();
+        let ct = encrypt(&mut rng, server.public_key(), 1);
+        assert!(matches!(
+            server.decrypt(&ct),
+            Err(GswError::MissingSecretKey)
+        ));
+        assert!(server.bootstrap(&ct).is_ok());
+
+        let public = Session::public_view(&client);
+        assert!(matches!(
+            public.bootstrap(&ct),
+            Err(GswError::MissingEvaluationKey)
+        ));
+    }
+
+    #[test]
+    fn test_session_components_share_params() {
+        let mut rng = ChaCha20Rng::seed_from_u64(4);
+        let client = Session::client(&Params::toy())
+            .allow_insecure()
+            .build(&mut rng)
+            .unwrap();
+        let loaded = Session::from_bytes(&client.to_bytes()).unwrap();
+        for session in [&client, &loaded] {
+            let shared = &session.params;
+            assert!(Arc::ptr_eq(shared, session.public_key().params_arc()));
+            assert!(Arc::ptr_eq(
+                shared,
+                session.secret_key().unwrap().params_arc()
+            ));
+            assert!(Arc::ptr_eq(
+                shared,
+                session.evaluation_key().unwrap().params_arc()
+            ));
+            assert!(std::ptr::eq(session.evaluator().params(), session.params()));
+        }
+    }
+
+    #[test]
+    fn test_session_rejects_mismatched_params() {
+        let mut rng = ChaCha20Rng::seed_from_u64(2);
+        let toy = Params::toy();
+        let (sk, pk) = gsw_keygen(&mut rng, &toy);
+        let (_, pk_low) = gsw_keygen(&mut rng, &Params::new(SecurityLevel::Low));
+        assert!(matches!(
+            Session::from_parts(toy.clone(), Some(sk.clone()), pk_low, None),
+            Err(GswError::ParamsMismatch)
+        ));
+
+        // Parameters equal under `==` still differ in the evaluation policy.
+        let msb = toy.clone().with_encoding(PlaintextEncoding::MsbThreshold);
+        let tight = toy.clone().with_target_failure_exp(-60).unwrap();
+        for other in [msb, tight] {
+            assert_eq!(other, toy);
+            assert!(matches!(
+                Session::from_parts(other, Some(sk.clone()), pk.clone(), None),
+                Err(GswError::ParamsMismatch)
+            ));
+        }
+        let (msb_sk, msb_pk) = gsw_keygen(
+            &mut rng,
+            &toy.clone().with_encoding(PlaintextEncoding::MsbThreshold),
+        );
+        assert!(matches!(
+            Session::from_parts(toy.clone(), Some(msb_sk), pk, None),
+            Err(GswError::ParamsMismatch)
+        ));
+        assert!(matches!(
+            Session::from_parts(toy, None, msb_pk, None),
+            Err(GswError::ParamsMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_session_rejects_corrupt_bytes() {
+        let mut rng = ChaCha20Rng::seed_from_u64(3);
+        let (sk, pk) = gsw_keygen(&mut rng, &Params::toy());
+        let session = Session::from_parts(Params::toy(), Some(sk), pk, None).unwrap();
+        let bytes = session.to_bytes();
+
+        assert!(matches!(
+            Session::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(GswError::MalformedData(_))
+        ));
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] ^= 0xff;
+        assert!(matches!(
+            Session::from_bytes(&bad_magic),
+            Err(GswError::BadMagic)
+        ));
+        let mut bad_version = bytes.clone();
+        bad_version[4] = 0xee;
+        assert!(matches!(
+            Session::from_bytes(&bad_version),
+            Err(GswError::UnsupportedVersion(_))
+        ));
+        // The secret key embeds its own params right after the session's; make
+        // their error bounds disagree.
+        let mut mismatched = bytes.clone();
+        let sk_error_bound = 7 + 1 + 32 + 24;
+        mismatched[sk_error_bound] += 1;
+        assert!(matches!(
+            Session::from_bytes(&mismatched),
+            Err(GswError::ParamsMismatch)
+        ));
+    }
+}
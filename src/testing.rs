@@ -0,0 +1,119 @@
+//! Structurally valid stand-ins for keys and ciphertexts.
+//!
+//! Tests of serialization, transport, and other plumbing need objects of the
+//! right shape, not ones that decrypt to anything. The functions here build
+//! them deterministically from a seed, without key generation or encryption,
+//! and without the `client` feature's RNG stack, so evaluation-only builds can
+//! use them too.
+//!
+//! **These are not encryptions.** A pseudo ciphertext decrypts to an
+//! arbitrary bit under every key, and a pseudo public key has no secret key.
+//!
+//! The [`vectors`] submodule, by contrast, generates real keys and
+//! ciphertexts from a seed, as golden files for compatibility tests, and
+//! `FaultInjector` (the `test-utils` feature) makes sessions and evaluators
+//! fail on cue.
+
+use std::sync::Arc;
+
+use crate::gsw::Ciphertext;
+use crate::lwe::PublicKey;
+use crate::params::Params;
+
+#[cfg(feature = "client")]
+pub mod vectors;
+
+mod faults;
+
+pub(crate) use faults::FaultHook;
+#[cfg(feature = "test-utils")]
+pub use faults::FaultInjector;
+
+/// SplitMix64: a fast, well-mixed stream from a 64-bit seed.
+struct Stream(u64);
+
+impl Stream {
+    /// `seed` mixed with a per-object `domain`, so a ciphertext and a public
+    /// key from the same seed are unrelated.
+    fn new(seed: u64, domain: u64) -> Self {
+        Stream(seed ^ domain.wrapping_mul(0xd6e8_feb8_6659_fd93))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// An N×N matrix of uniform bits, shaped and flattened like a ciphertext
+/// under `params`. Not an encryption of anything.
+pub fn pseudo_ciphertext(params: &Params, seed: u64) -> Ciphertext {
+    let n = params.n_expanded;
+    let mut stream = Stream::new(seed, 1);
+    let mut word = 0;
+    (0..n * n)
+        .map(|idx| {
+            if idx % 64 == 0 {
+                word = stream.next();
+            }
+            (word >> (idx % 64)) & 1
+        })
+        .collect::<Vec<u64>>()
+        .chunks(n)
+        .map(<[u64]>::to_vec)
+        .collect()
+}
+
+/// An m×(n+1) matrix of uniform entries mod q, shaped like a public key under
+/// `params`. No secret key corresponds to it.
+pub fn pseudo_public_key(params: &Params, seed: u64) -> PublicKey {
+    let mut stream = Stream::new(seed, 2);
+    // q is a power of two, so masking is uniform.
+    let a = (0..params.m)
+        .map(|_| {
+            (0..=params.n)
+                .map(|_| stream.next() & (params.q - 1))
+                .collect()
+        })
+        .collect();
+    PublicKey::from_parts(a, Arc::new(params.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gsw::{
+        ciphertext_from_bytes, ciphertext_to_bytes, is_canonical_ciphertext, EntryPolicy,
+    };
+    use crate::session::Session;
+
+    #[test]
+    fn test_pseudo_objects_are_well_formed_and_deterministic() {
+        for params in [Params::pedagogical(), Params::toy()] {
+            let ct = pseudo_ciphertext(&params, 7);
+            assert!(is_canonical_ciphertext(&params, &ct));
+            let bytes = ciphertext_to_bytes(&params, &ct);
+            assert_eq!(
+                ciphertext_from_bytes(&params, &bytes, EntryPolicy::Reject).unwrap(),
+                ct
+            );
+            assert_eq!(
+                ciphertext_to_bytes(&params, &pseudo_ciphertext(&params, 7)),
+                bytes
+            );
+            assert_ne!(pseudo_ciphertext(&params, 8), ct);
+
+            let pk = pseudo_public_key(&params, 7);
+            let session = Session::from_parts(params.clone(), None, pk.clone(), None).unwrap();
+            let bytes = session.to_bytes();
+            assert_eq!(Session::from_bytes(&bytes).unwrap().public_key().a, pk.a);
+            let again =
+                Session::from_parts(params.clone(), None, pseudo_public_key(&params, 7), None);
+            assert_eq!(again.unwrap().to_bytes(), bytes);
+            assert_ne!(pseudo_public_key(&params, 8).a, pk.a);
+        }
+    }
+}
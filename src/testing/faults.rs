@@ -0,0 +1,316 @@
+//! Deterministic faults, for testing code that handles this crate's errors.
+//!
+//! With the `test-utils` feature, a [`FaultInjector`] installed on a
+//! [`Session`](crate::Session) or an [`Evaluator`](crate::Evaluator) counts
+//! their operations and, at the ones it was told to, fails the operation
+//! with a chosen error, flips an entry of the ciphertext it produces, or
+//! inflates the noise [`Session::decrypt_checked`](crate::Session::decrypt_checked)
+//! measures past its limit. Applications use it to exercise their handling
+//! of [`GswError::DecryptionSuspect`], [`GswError::Cancelled`],
+//! [`GswError::KeyMismatch`], and the rest without producing those
+//! conditions for real.
+//!
+//! Operations are numbered from 0 in the order they start:
+//!
+//! - on a session, [`decrypt`](crate::Session::decrypt),
+//!   [`decrypt_checked`](crate::Session::decrypt_checked),
+//!   [`bootstrap`](crate::Session::bootstrap), and
+//!   [`save`](crate::Session::save);
+//! - on an evaluator, each product and NAND. A gate has one
+//!   failure state, so an injected failure of any kind shows as a cancelled
+//!   value there.
+//!
+//! Without the feature the hooks are zero-sized and every check is a
+//! constant, so release builds carry no trace of them.
+
+#[cfg(feature = "test-utils")]
+use std::sync::{Arc, Mutex};
+
+use crate::error::GswError;
+use crate::gsw::Ciphertext;
+
+/// Faults to inject, by operation number; see the module docs.
+#[cfg(feature = "test-utils")]
+#[derive(Debug, Default)]
+pub struct FaultInjector {
+    state: Mutex<InjectorState>,
+}
+
+#[cfg(feature = "test-utils")]
+#[derive(Debug, Default)]
+struct InjectorState {
+    operations: usize,
+    fired: usize,
+    failures: Vec<(usize, GswError)>,
+    corruptions: Vec<usize>,
+    inflations: Vec<usize>,
+}
+
+#[cfg(feature = "test-utils")]
+impl FaultInjector {
+    /// An injector with no faults planned.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail operation `n` with `error`.
+    pub fn fail_nth(self, n: usize, error: GswError) -> Self {
+        self.lock().failures.push((n, error));
+        self
+    }
+
+    /// Flip entry (0, 0) of the ciphertext operation `n` produces.
+    pub fn corrupt_nth(self, n: usize) -> Self {
+        self.lock().corruptions.push(n);
+        self
+    }
+
+    /// Report noise past the limit if operation `n` measures it.
+    pub fn inflate_noise_nth(self, n: usize) -> Self {
+        self.lock().inflations.push(n);
+        self
+    }
+
+    /// Operations started so far.
+    pub fn operations(&self) -> usize {
+        self.lock().operations
+    }
+
+    /// Faults injected so far.
+    pub fn fired(&self) -> usize {
+        self.lock().fired
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, InjectorState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn next(&self) -> Planned {
+        let mut state = self.lock();
+        let n = state.operations;
+        state.operations += 1;
+        let fail = state
+            .failures
+            .iter()
+            .position(|(at, _)| *at == n)
+            .map(|i| state.failures.swap_remove(i).1);
+        let planned = Planned {
+            fail,
+            corrupt: state.corruptions.contains(&n),
+            inflate: state.inflations.contains(&n),
+        };
+        state.fired +=
+            planned.fail.is_some() as usize + planned.corrupt as usize + planned.inflate as usize;
+        planned
+    }
+}
+
+/// An optional [`FaultInjector`], held by sessions and evaluators.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct FaultHook {
+    #[cfg(feature = "test-utils")]
+    injector: Option<Arc<FaultInjector>>,
+}
+
+/// What the injector planned for one operation.
+#[derive(Default)]
+pub(crate) struct Planned {
+    #[cfg(feature = "test-utils")]
+    fail: Option<GswError>,
+    #[cfg(feature = "test-utils")]
+    corrupt: bool,
+    #[cfg(feature = "test-utils")]
+    inflate: bool,
+}
+
+// The hooks cost nothing without the feature.
+#[cfg(not(feature = "test-utils"))]
+const _: () = assert!(size_of::<FaultHook>() == 0 && size_of::<Planned>() == 0);
+
+impl FaultHook {
+    #[cfg(feature = "test-utils")]
+    pub(crate) fn new(injector: Arc<FaultInjector>) -> Self {
+        Self {
+            injector: Some(injector),
+        }
+    }
+
+    /// Start an operation.
+    #[inline(always)]
+    pub(crate) fn begin(&self) -> Planned {
+        #[cfg(feature = "test-utils")]
+        if let Some(injector) = &self.injector {
+            return injector.next();
+        }
+        Planned::default()
+    }
+}
+
+impl Planned {
+    /// `Err` if the operation is to fail.
+    #[inline(always)]
+    pub(crate) fn check(&mut self) -> Result<(), GswError> {
+        #[cfg(feature = "test-utils")]
+        if let Some(error) = self.fail.take() {
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    /// `ct`, corrupted if planned.
+    #[inline(always)]
+    pub(crate) fn output(&self, ct: Ciphertext) -> Ciphertext {
+        #[cfg(feature = "test-utils")]
+        if self.corrupt {
+            let mut ct = ct;
+            if let Some(entry) = ct.first_mut().and_then(|row| row.first_mut()) {
+                *entry ^= 1;
+            }
+            return ct;
+        }
+        ct
+    }
+
+    /// `noise`, or past `limit` if planned.
+    #[cfg(feature = "client")]
+    #[inline(always)]
+    pub(crate) fn noise(&self, noise: i64, limit: i64) -> i64 {
+        #[cfg(feature = "test-utils")]
+        if self.inflate {
+            return noise.signum().max(1) * limit;
+        }
+        let _ = limit;
+        noise
+    }
+}
+
+#[cfg(all(test, not(feature = "test-utils")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hooks_are_no_ops() {
+        assert_eq!(size_of::<FaultHook>(), 0);
+        let mut planned = FaultHook::default().begin();
+        assert!(planned.check().is_ok());
+        assert_eq!(planned.output(vec![vec![1]]), vec![vec![1]]);
+        #[cfg(feature = "client")]
+        assert_eq!(planned.noise(-3, 10), -3);
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "test-utils"))]
+mod tests {
+    use super::*;
+    use crate::gsw::encrypt;
+    use crate::params::Params;
+    use crate::session::Session;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    /// What a downstream application does with a result: use it, retry,
+    /// or give up.
+    #[derive(Debug, PartialEq, Eq)]
+    enum Handling {
+        Bit(u8),
+        Retry,
+        Abort,
+    }
+
+    fn handle(result: Result<u8, GswError>) -> Handling {
+        match result {
+            Ok(bit) => Handling::Bit(bit),
+            Err(GswError::DecryptionSuspect { .. } | GswError::Cancelled) => Handling::Retry,
+            Err(_) => Handling::Abort,
+        }
+    }
+
+    fn session(seed: u64) -> Session {
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+        Session::client(&Params::toy())
+            .allow_insecure()
+            .build(&mut rng)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_session_faults_reach_the_consumer() {
+        let mut rng = ChaCha20Rng::seed_from_u64(495);
+        let injector = Arc::new(
+            FaultInjector::new()
+                .fail_nth(1, GswError::KeyMismatch)
+                .fail_nth(2, GswError::Cancelled)
+                .inflate_noise_nth(3),
+        );
+        let session = session(495).with_fault_injector(Arc::clone(&injector));
+        let ct = encrypt(&mut rng, session.public_key(), 1);
+
+        let outcomes: Vec<Handling> = (0..5)
+            .map(|_| handle(session.decrypt_checked(&ct)))
+            .collect();
+        assert_eq!(
+            outcomes,
+            [
+                Handling::Bit(1),
+                Handling::Abort,
+                Handling::Retry,
+                Handling::Retry,
+                Handling::Bit(1)
+            ]
+        );
+        assert!(matches!(session.decrypt_checked(&ct), Ok(1)));
+        assert_eq!((injector.operations(), injector.fired()), (6, 3));
+
+        // A failed save surfaces the chosen error.
+        let injector = Arc::new(FaultInjector::new().fail_nth(
+            0,
+            GswError::CorruptFile {
+                path: "session.gsw".into(),
+                expected: "00".into(),
+                found: "ff".into(),
+            },
+        ));
+        let session = session.with_fault_injector(injector);
+        let path = std::env::temp_dir().join("gsw-faults-never-written.session");
+        assert!(matches!(
+            session.save(&path),
+            Err(GswError::CorruptFile { .. })
+        ));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_corrupted_output_is_observed() {
+        let mut rng = ChaCha20Rng::seed_from_u64(495);
+        let session = session(495);
+        let pk = session.public_key().clone();
+        let ct = encrypt(&mut rng, &pk, 1);
+
+        let injector = Arc::new(FaultInjector::new().corrupt_nth(0));
+        let faulty = session.clone().with_fault_injector(Arc::clone(&injector));
+        let clean = session.bootstrap(&ct).unwrap();
+        let corrupted = faulty.bootstrap(&ct).unwrap();
+        assert_ne!(corrupted, clean);
+        assert_eq!(corrupted[0][0], clean[0][0] ^ 1);
+        assert_eq!(faulty.bootstrap(&ct).unwrap(), clean);
+
+        // On an evaluator, the second product is corrupted and the third
+        // fails, which the gate reports as a cancelled value.
+        let eval = session.evaluator().with_fault_injector(Arc::new(
+            FaultInjector::new()
+                .corrupt_nth(1)
+                .fail_nth(2, GswError::KeyMismatch),
+        ));
+        let x = eval.input(ct.clone());
+        let clean_product = x.and(&x).into_ciphertext();
+        let corrupted_product = x.and(&x).into_ciphertext();
+        assert_eq!(corrupted_product[0][0], clean_product[0][0] ^ 1);
+        assert!(matches!(
+            x.and(&x).try_into_ciphertext(),
+            Err(GswError::Cancelled)
+        ));
+        assert_eq!(x.and(&x).into_ciphertext(), clean_product);
+    }
+}
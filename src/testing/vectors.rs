@@ -0,0 +1,173 @@
+//! Golden files for checking compatibility across releases.
+//!
+//! [`GoldenVectors::generate`] derives a key pair, ten ciphertexts of known
+//! bits, every gate applied to the four operand pairs, and a bootstrap from a
+//! master seed, and encodes them with the current wire format. The output is
+//! a pure function of the seed, so a build that decodes the committed files
+//! and regenerates them byte for byte has kept both the format and the
+//! algorithms. The `gsw-vectors` binary writes a set to disk.
+//!
+//! Each kind of object is drawn from its own ChaCha20 stream keyed by the
+//! seed, so that a change to, say, evaluation key generation leaves the keys
+//! and ciphertexts as they were.
+//!
+//! Files, relative to the output directory:
+//!
+//! | file             | contents                                               |
+//! |------------------|--------------------------------------------------------|
+//! | `manifest.txt`   | tag, seed, and every ciphertext file with its bit      |
+//! | `params.json`    | the parameter set                                      |
+//! | `keys.session`   | a [`Session`] holding the secret and public key        |
+//! | `ct/NN.bin`      | the ten fresh ciphertexts                              |
+//! | `ops/OP_AB.bin`  | gate `OP` on fresh ciphertexts of bits `A` and `B`     |
+//! | `ops/not_A.bin`  | NOT of a fresh ciphertext of bit `A`                   |
+//! | `bootstrap.txt`  | SHA-256 of the evaluation key and of one bootstrap     |
+//!
+//! Digests are SHA-256 in every build (see [`crate::digest`]).
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use crate::bootstrap::{bootstrap, gen_evaluation_key};
+use crate::digest::{digest_with, to_hex, Sha256};
+use crate::error::GswError;
+use crate::gsw::{
+    ciphertext_to_bytes, encrypt, gsw_keygen, homomorphic_add, homomorphic_mult, homomorphic_nand,
+    homomorphic_not, homomorphic_sub, Ciphertext,
+};
+use crate::params::Params;
+use crate::session::Session;
+use crate::wire::FORMAT_VERSION;
+
+/// Bits of the fresh ciphertexts. Ciphertexts 2i and 2i+1 are the operands
+/// of the i-th pair, so pairs 0..4 are (0,0), (0,1), (1,0), and (1,1).
+pub const BITS: [u8; 10] = [0, 0, 0, 1, 1, 0, 1, 1, 1, 0];
+
+/// The seed of the committed vectors.
+pub const DEFAULT_SEED: u64 = 0x6773_772d_7273;
+
+const STREAM_KEYS: u64 = 0;
+const STREAM_CIPHERTEXTS: u64 = 1;
+const STREAM_EVALUATION_KEY: u64 = 2;
+
+/// The tag of vectors written by this build: the wire format version.
+pub fn current_tag() -> String {
+    format!("wire-v{}", FORMAT_VERSION)
+}
+
+/// A generated set of golden files, in the order they are listed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GoldenVectors {
+    pub tag: String,
+    pub seed: u64,
+    /// Paths relative to the output directory, with `/` separators, and
+    /// their contents.
+    pub files: Vec<(String, Vec<u8>)>,
+}
+
+fn stream(seed: u64, id: u64) -> ChaCha20Rng {
+    let mut rng = ChaCha20Rng::seed_from_u64(seed);
+    rng.set_stream(id);
+    rng
+}
+
+fn params_json(params: &Params) -> String {
+    format!(
+        "{{\n  \"q\": {},\n  \"n\": {},\n  \"m\": {},\n  \"error_bound\": {},\n  \
+         \"l\": {},\n  \"n_expanded\": {},\n  \"target_failure_exp\": {}\n}}\n",
+        params.q,
+        params.n,
+        params.m,
+        params.error_bound,
+        params.l,
+        params.n_expanded,
+        params.target_failure_exp
+    )
+}
+
+impl GoldenVectors {
+    /// Generate the set for `seed` at Toy parameters, labelled `tag`.
+    pub fn generate(tag: &str, seed: u64) -> Self {
+        let params = Params::toy();
+        let (sk, pk) = gsw_keygen(&mut stream(seed, STREAM_KEYS), &params);
+        let mut rng = stream(seed, STREAM_CIPHERTEXTS);
+        let cts: Vec<Ciphertext> = BITS
+            .iter()
+            .map(|&bit| encrypt(&mut rng, &pk, bit))
+            .collect();
+        let ek = gen_evaluation_key(&mut stream(seed, STREAM_EVALUATION_KEY), &sk, &pk);
+
+        let mut manifest = format!("# gsw-vectors {} seed {}\n", tag, seed);
+        let mut files = Vec::new();
+        let mut add_ciphertext = |name: String, bit: u8, ct: &Ciphertext| {
+            writeln!(manifest, "{} {}", name, bit).expect("writing to a String");
+            files.push((name, ciphertext_to_bytes(&params, ct)));
+        };
+        for (i, (ct, &bit)) in cts.iter().zip(&BITS).enumerate() {
+            add_ciphertext(format!("ct/{:02}.bin", i), bit, ct);
+        }
+        for i in 0..4 {
+            let (x, y) = (&cts[2 * i], &cts[2 * i + 1]);
+            let (a, b) = (BITS[2 * i], BITS[2 * i + 1]);
+            let name = |op: &str| format!("ops/{}_{}{}.bin", op, a, b);
+            let sub = homomorphic_sub(&params, x, y).expect("fresh ciphertexts are N×N");
+            add_ciphertext(name("add"), a ^ b, &homomorphic_add(&params, x, y));
+            add_ciphertext(name("sub"), a ^ b, &sub);
+            add_ciphertext(name("mult"), a & b, &homomorphic_mult(&params, x, y));
+            add_ciphertext(name("nand"), 1 - (a & b), &homomorphic_nand(&params, x, y));
+        }
+        for i in [0, 3] {
+            let name = format!("ops/not_{}.bin", BITS[i]);
+            add_ciphertext(name, 1 - BITS[i], &homomorphic_not(&params, &cts[i]));
+        }
+
+        let session = Session::from_parts(params.clone(), Some(sk), pk, None)
+            .expect("keys share their params");
+        let bootstrapped = bootstrap(&params, &cts[BITS.len() - 1], &ek);
+        let bootstrap_txt = format!(
+            "evaluation_key {}\noutput {}\n",
+            to_hex(&digest_with::<Sha256, _>(&ek)),
+            to_hex(&digest_with::<Sha256, _>(&bootstrapped))
+        );
+        let mut all = vec![
+            ("manifest.txt".to_string(), manifest.into_bytes()),
+            ("params.json".to_string(), params_json(&params).into_bytes()),
+            ("keys.session".to_string(), session.to_bytes()),
+        ];
+        all.extend(files);
+        all.push(("bootstrap.txt".to_string(), bootstrap_txt.into_bytes()));
+        Self {
+            tag: tag.to_string(),
+            seed,
+            files: all,
+        }
+    }
+
+    /// Write every file under `dir`, creating directories as needed and
+    /// replacing existing files.
+    pub fn write_to(&self, dir: &Path) -> Result<(), GswError> {
+        for (name, bytes) in &self.files {
+            let path = dir.join(name);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Names of the files whose contents under `dir` differ from this set,
+    /// including missing ones; empty if `dir` holds exactly these bytes.
+    /// Files in `dir` not in the set are ignored.
+    pub fn differences(&self, dir: &Path) -> Vec<String> {
+        self.files
+            .iter()
+            .filter(|(name, bytes)| fs::read(dir.join(name)).ok().as_ref() != Some(bytes))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+}
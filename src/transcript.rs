@@ -0,0 +1,347 @@
+//! Transcripts of homomorphic computations, for after-the-fact verification.
+//!
+//! A [`TranscriptRecorder`] evaluates gates like the free functions do and
+//! logs each one: the op, the wires it read, the content digests of those
+//! operands, and the digest of the result. Wires are numbered inputs
+//! first, then one per recorded gate in order. A verifier holding the same
+//! inputs replays the log with [`verify_transcript`], which stops at the first
+//! entry whose operands or output do not match.
+//!
+//! Digests are computed with [`crate::digest`], so transcripts verify only
+//! between builds that agree on the `blake3` feature.
+//!
+//! Serialized layout, after the object header: params fingerprint (32 bytes),
+//! input count and input hashes, entry count, then per entry the op code
+//! (u8), each operand's wire index (u64) and hash, and the output hash.
+
+use crate::digest::{digest, Digest};
+use crate::error::{GswError, TranscriptError};
+use crate::gsw::{
+    homomorphic_add, homomorphic_mult, homomorphic_nand, homomorphic_not, Ciphertext,
+};
+use crate::params::Params;
+use crate::wire::{self, ObjectKind, Reader};
+
+/// A recorded gate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TranscriptOp {
+    Add = 1,
+    Mult = 2,
+    Nand = 3,
+    Not = 4,
+}
+
+impl TranscriptOp {
+    fn arity(self) -> usize {
+        match self {
+            TranscriptOp::Not => 1,
+            _ => 2,
+        }
+    }
+
+    fn from_code(code: u8) -> Option<Self> {
+        [
+            TranscriptOp::Add,
+            TranscriptOp::Mult,
+            TranscriptOp::Nand,
+            TranscriptOp::Not,
+        ]
+        .into_iter()
+        .find(|op| *op as u8 == code)
+    }
+
+    fn apply(self, params: &Params, operands: &[&Ciphertext]) -> Ciphertext {
+        match self {
+            TranscriptOp::Add => homomorphic_add(params, operands[0], operands[1]),
+            TranscriptOp::Mult => homomorphic_mult(params, operands[0], operands[1]),
+            TranscriptOp::Nand => homomorphic_nand(params, operands[0], operands[1]),
+            TranscriptOp::Not => homomorphic_not(params, operands[0]),
+        }
+    }
+}
+
+/// One gate: the wires it read, their hashes, and the hash of its output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TranscriptEntry {
+    pub op: TranscriptOp,
+    pub operands: Vec<usize>,
+    pub operand_hashes: Vec<Digest>,
+    pub output_hash: Digest,
+}
+
+/// Everything a verifier needs besides the inputs themselves.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComputationTranscript {
+    pub params_fingerprint: Digest,
+    pub input_hashes: Vec<Digest>,
+    pub entries: Vec<TranscriptEntry>,
+}
+
+impl ComputationTranscript {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        wire::write_header(&mut out, ObjectKind::Transcript);
+        out.extend_from_slice(&self.params_fingerprint);
+        out.extend_from_slice(&(self.input_hashes.len() as u64).to_le_bytes());
+        for hash in &self.input_hashes {
+            out.extend_from_slice(hash);
+        }
+        out.extend_from_slice(&(self.entries.len() as u64).to_le_bytes());
+        for entry in &self.entries {
+            out.push(entry.op as u8);
+            for (wire, hash) in entry.operands.iter().zip(&entry.operand_hashes) {
+                out.extend_from_slice(&(*wire as u64).to_le_bytes());
+                out.extend_from_slice(hash);
+            }
+            out.extend_from_slice(&entry.output_hash);
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GswError> {
+        let mut reader = Reader::new(bytes);
+        reader.header(ObjectKind::Transcript)?;
+        let params_fingerprint = reader.hash()?;
+        let inputs = reader.count(32)?;
+        let input_hashes = (0..inputs)
+            .map(|_| reader.hash())
+            .collect::<Result<_, _>>()?;
+        // Smallest entry: a unary op, one operand, and the output hash.
+        let count = reader.count(1 + 8 + 32 + 32)?;
+        let mut entries = Vec::with_capacity(count);
+        for _ in 0..count {
+            let code = reader.u8()?;
+            let op = TranscriptOp::from_code(code)
+                .ok_or_else(|| GswError::MalformedData(format!("unknown op code {}", code)))?;
+            let mut operands = Vec::with_capacity(op.arity());
+            let mut operand_hashes = Vec::with_capacity(op.arity());
+            for _ in 0..op.arity() {
+                operands.push(reader.index()?);
+                operand_hashes.push(reader.hash()?);
+            }
+            entries.push(TranscriptEntry {
+                op,
+                operands,
+                operand_hashes,
+                output_hash: reader.hash()?,
+            });
+        }
+        reader.finish()?;
+        Ok(Self {
+            params_fingerprint,
+            input_hashes,
+            entries,
+        })
+    }
+}
+
+/// Evaluates gates over a growing list of wires and records each one.
+#[derive(Clone, Debug)]
+pub struct TranscriptRecorder {
+    params: Params,
+    wires: Vec<Ciphertext>,
+    transcript: ComputationTranscript,
+}
+
+impl TranscriptRecorder {
+    /// Start a computation over `inputs`, which become wires 0..inputs.len().
+    pub fn new(params: &Params, inputs: &[Ciphertext]) -> Self {
+        Self {
+            params: params.clone(),
+            wires: inputs.to_vec(),
+            transcript: ComputationTranscript {
+                params_fingerprint: digest(params),
+                input_hashes: inputs.iter().map(digest).collect(),
+                entries: Vec::new(),
+            },
+        }
+    }
+
+    /// The ciphertext on a wire. Panics if the wire does not exist yet.
+    pub fn wire(&self, wire: usize) -> &Ciphertext {
+        &self.wires[wire]
+    }
+
+    pub fn add(&mut self, a: usize, b: usize) -> usize {
+        self.record(TranscriptOp::Add, &[a, b])
+    }
+
+    pub fn mult(&mut self, a: usize, b: usize) -> usize {
+        self.record(TranscriptOp::Mult, &[a, b])
+    }
+
+    pub fn nand(&mut self, a: usize, b: usize) -> usize {
+        self.record(TranscriptOp::Nand, &[a, b])
+    }
+
+    pub fn not(&mut self, a: usize) -> usize {
+        self.record(TranscriptOp::Not, &[a])
+    }
+
+    /// The log so far.
+    pub fn transcript(&self) -> &ComputationTranscript {
+        &self.transcript
+    }
+
+    /// The finished log and every wire, inputs first.
+    pub fn finish(self) -> (ComputationTranscript, Vec<Ciphertext>) {
+        (self.transcript, self.wires)
+    }
+
+    fn record(&mut self, op: TranscriptOp, operands: &[usize]) -> usize {
+        let inputs: Vec<&Ciphertext> = operands.iter().map(|&w| &self.wires[w]).collect();
+        let output = op.apply(&self.params, &inputs);
+        self.transcript.entries.push(TranscriptEntry {
+            op,
+            operands: operands.to_vec(),
+            operand_hashes: inputs.iter().map(|ct| digest(*ct)).collect(),
+            output_hash: digest(&output),
+        });
+        self.wires.push(output);
+        self.wires.len() - 1
+    }
+}
+
+/// Replay `transcript` on `inputs`, checking every recorded hash.
+///
+/// Returns the gate outputs in order. Fails at the first entry that refers to
+/// a wire not yet computed, whose operands hash differently from the record,
+/// or whose recomputed output does not match the recorded hash.
+pub fn verify_transcript(
+    params: &Params,
+    transcript: &ComputationTranscript,
+    inputs: &[Ciphertext],
+) -> Result<Vec<Ciphertext>, TranscriptError> {
+    if transcript.params_fingerprint != digest(params) {
+        return Err(TranscriptError::ParamsMismatch);
+    }
+    if inputs.len() != transcript.input_hashes.len() {
+        return Err(TranscriptError::InputCount {
+            expected: transcript.input_hashes.len(),
+            found: inputs.len(),
+        });
+    }
+    if let Some(input) = inputs
+        .iter()
+        .zip(&transcript.input_hashes)
+        .position(|(ct, hash)| digest(ct) != *hash)
+    {
+        return Err(TranscriptError::InputMismatch { input });
+    }
+
+    let mut wires = inputs.to_vec();
+    for (entry_index, entry) in transcript.entries.iter().enumerate() {
+        if entry.operands.len() != entry.op.arity()
+            || entry.operand_hashes.len() != entry.op.arity()
+        {
+            return Err(TranscriptError::Malformed { entry: entry_index });
+        }
+        let mut operands = Vec::with_capacity(entry.operands.len());
+        for (&wire, hash) in entry.operands.iter().zip(&entry.operand_hashes) {
+            let ct = wires.get(wire).ok_or(TranscriptError::UnknownWire {
+                entry: entry_index,
+                wire,
+            })?;
+            if digest(ct) != *hash {
+                return Err(TranscriptError::OperandMismatch {
+                    entry: entry_index,
+                    wire,
+                });
+            }
+            operands.push(ct);
+        }
+        let output = entry.op.apply(params, &operands);
+        if digest(&output) != entry.output_hash {
+            return Err(TranscriptError::OutputMismatch { entry: entry_index });
+        }
+        wires.push(output);
+    }
+    Ok(wires.split_off(inputs.len()))
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::gsw::{decrypt, encrypt, gsw_keygen};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    /// (a NAND b) AND (NOT c), plus an independent a + c.
+    fn record(params: &Params, inputs: &[Ciphertext]) -> TranscriptRecorder {
+        let mut recorder = TranscriptRecorder::new(params, inputs);
+        let nand = recorder.nand(0, 1);
+        let not_c = recorder.not(2);
+        recorder.mult(nand, not_c);
+        recorder.add(0, 2);
+        recorder
+    }
+
+    #[test]
+    fn test_honest_replay_verifies() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(42);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let inputs: Vec<Ciphertext> = [1, 1, 0]
+            .iter()
+            .map(|&b| encrypt(&mut rng, &pk, b))
+            .collect();
+
+        let (transcript, wires) = record(&params, &inputs).finish();
+        let outputs = verify_transcript(&params, &transcript, &inputs).unwrap();
+        assert_eq!(outputs, wires[inputs.len()..]);
+        assert_eq!(decrypt(&sk, &outputs[2]), 0);
+        assert_eq!(decrypt(&sk, &outputs[3]), 1);
+
+        let restored = ComputationTranscript::from_bytes(&transcript.to_bytes()).unwrap();
+        assert_eq!(restored, transcript);
+    }
+
+    #[test]
+    fn test_tampering_is_pinpointed() {
+        let params = Params::toy();
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let (_, pk) = gsw_keygen(&mut rng, &params);
+        let inputs: Vec<Ciphertext> = [1, 0, 1]
+            .iter()
+            .map(|&b| encrypt(&mut rng, &pk, b))
+            .collect();
+        let transcript = record(&params, &inputs).transcript().clone();
+
+        let mut forged = transcript.clone();
+        forged.entries[1].output_hash[0] ^= 1;
+        assert_eq!(
+            verify_transcript(&params, &forged, &inputs),
+            Err(TranscriptError::OutputMismatch { entry: 1 })
+        );
+
+        // Entry 2 reads wire 4, which entry 1 produces; moved first, it reads a
+        // wire that does not exist yet.
+        let mut swapped = transcript.clone();
+        swapped.entries.swap(1, 2);
+        assert_eq!(
+            verify_transcript(&params, &swapped, &inputs),
+            Err(TranscriptError::UnknownWire { entry: 1, wire: 4 })
+        );
+
+        // Independent gates swapped: the later gate reading wire 3 or 4 sees
+        // the wrong value there.
+        let mut reordered = transcript.clone();
+        reordered.entries.swap(0, 1);
+        assert_eq!(
+            verify_transcript(&params, &reordered, &inputs),
+            Err(TranscriptError::OperandMismatch { entry: 2, wire: 3 })
+        );
+
+        let mut other_inputs = inputs.clone();
+        other_inputs[1] = encrypt(&mut rng, &pk, 0);
+        assert_eq!(
+            verify_transcript(&params, &transcript, &other_inputs),
+            Err(TranscriptError::InputMismatch { input: 1 })
+        );
+        assert_eq!(
+            verify_transcript(&Params::pedagogical(), &transcript, &inputs),
+            Err(TranscriptError::ParamsMismatch)
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! Checking a claimed plaintext with the smallest possible code path.
+//!
+//! An auditor who holds the secret key and wants to confirm that a
+//! ciphertext encrypts a claimed bit needs one inner product, not the whole
+//! crate. [`check_plaintext`] takes the decryption vector
+//! ([`SecretKey::decryption_vector`](crate::lwe::SecretKey::decryption_vector)),
+//! the four numbers of [`VerifyParams`]
+//! ([`SecretKey::verify_params`](crate::lwe::SecretKey::verify_params)), and
+//! the decryption row l-1 of the ciphertext
+//! ([`AsCiphertextRows::row`](crate::gsw::AsCiphertextRows::row) of a
+//! ciphertext or a view), and compares the rounded phase with the claim.
+//!
+//! This module imports nothing from the rest of the crate and depends on no
+//! external crate, so it can be reviewed on its own or copied into the
+//! checker's process; `tests/verify.rs` keeps it that way.
+
+/// The parameters [`check_plaintext`] needs: q = 2^l, the ciphertext
+/// dimension N, and the decryption scale v[l-1].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifyParams {
+    pub q: u64,
+    pub l: usize,
+    pub n_expanded: usize,
+    pub scale: u64,
+}
+
+/// What [`check_plaintext`] concluded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifyOutcome {
+    /// The row decrypts to the claimed bit with room to spare.
+    Match,
+    /// The row decrypts to the other bit with room to spare.
+    Mismatch,
+    /// The phase lies within a quarter scale of a decision boundary, where
+    /// noise could have flipped the bit, or the row is not a canonical row
+    /// of N binary entries. `margin` is the phase's distance to the nearest
+    /// boundary, 0 for a malformed row.
+    Inconclusive { margin: u64 },
+}
+
+/// Whether `ct_row`, row l-1 of a ciphertext, encrypts `claimed_bit` under
+/// the key with decryption vector `sk_decryption_vector`.
+///
+/// The phase ⟨row, v⟩ mod q is rounded to the nearest multiple k·scale as
+/// decryption does, and the bit is k mod 2. The verdict stands only if the
+/// phase is within a quarter scale of that multiple, half the distance
+/// decryption tolerates.
+///
+/// Panics if `params` is inconsistent (q is not 2^l, the scale is 0, or the
+/// vector does not have N entries) or `claimed_bit` is not 0 or 1.
+pub fn check_plaintext(
+    sk_decryption_vector: &[u64],
+    params: &VerifyParams,
+    ct_row: &[u64],
+    claimed_bit: u8,
+) -> VerifyOutcome {
+    assert!(params.l < 64 && params.q == 1 << params.l, "q must be 2^l");
+    assert!(params.scale != 0, "the decryption scale must be nonzero");
+    assert_eq!(
+        sk_decryption_vector.len(),
+        params.n_expanded,
+        "the decryption vector must have N entries"
+    );
+    assert!(claimed_bit <= 1, "the claimed bit must be 0 or 1");
+    if ct_row.len() != params.n_expanded || ct_row.iter().any(|&x| x > 1) {
+        return VerifyOutcome::Inconclusive { margin: 0 };
+    }
+
+    // Wrapping is exact mod q because q divides 2^64.
+    let phase = ct_row
+        .iter()
+        .zip(sk_decryption_vector)
+        .fold(0u64, |dot, (&c, &v)| dot.wrapping_add(c.wrapping_mul(v)))
+        % params.q;
+    let scale = params.scale as u128;
+    let multiple = (phase as u128 + scale / 2) / scale;
+    let error = (phase as u128).abs_diff(multiple * scale);
+    let margin = (scale / 2 - error.min(scale / 2)) as u64;
+    if error > scale / 4 {
+        VerifyOutcome::Inconclusive { margin }
+    } else if (multiple % 2) as u8 == claimed_bit {
+        VerifyOutcome::Match
+    } else {
+        VerifyOutcome::Mismatch
+    }
+}
@@ -0,0 +1,942 @@
+//! Versioned little-endian binary encoding of parameters, keys, and ciphertexts.
+//!
+//! Every top-level object starts with a header:
+//!
+//! | bytes | field                        |
+//! |-------|------------------------------|
+//! | 4     | magic `GSWR`                 |
+//! | 2     | format version (u16)         |
+//! | 1     | object kind ([`ObjectKind`]) |
+//!
+//! All integers are little-endian; lengths and dimensions are u64.
+//!
+//! Component layouts (keys embed their own parameters):
+//!
+//! - Params: `q`, `n`, `m` as u64, `error_bound` as i64. `l` and `n_expanded`
+//!   are derived on load, never stored.
+//! - SecretKey: params, length, then `s` as u64 entries.
+//! - PublicKey: params, rows, columns, then `a` row-major as u64 entries.
+//! - Ciphertext: rows, columns, an encoding byte, then the entries. Encoding 1
+//!   packs binary (flattened) matrices at one bit per entry, row-major, least
+//!   significant bit first within each byte; encoding 0 stores u64 entries.
+//! - EvaluationKey: params, count, then each ciphertext in index order.
+//!   Standalone, it is preceded by the public-key fingerprint and followed by
+//!   a hash of all preceding bytes, header included.
+//! - Standalone ciphertext: params, then the ciphertext. Raw entries from
+//!   other producers may not be reduced mod q; see [`EntryPolicy`].
+//! - Standalone public key: the public key as above.
+//! - Transcript: see the `transcript` module docs.
+//! - Encrypted bits: params fingerprint, width (u64), bit order (u8, see
+//!   [`BitOrder`]), then each bit as a standalone ciphertext's body (params,
+//!   then the ciphertext).
+//! - Sealed secret key or session: params fingerprint, key derivation (a
+//!   function id, then three u32 parameters, see [`KdfParams`]), a 16-byte
+//!   salt, a 12-byte nonce, then the ChaCha20-Poly1305 encryption of the
+//!   secret key's or session's encoding, authenticated together with every
+//!   preceding byte. See the `passphrase` module docs.
+//! - Evaluation state: see the `circuits::suspend` module docs.
+//! - Encryption context: params, the public-key fingerprint, rows (m) and
+//!   columns (n + 1), the public matrix A row-major, then a hash of all
+//!   preceding bytes, header included.
+//!
+//! The header and the fixed-size fields after it (flags, params, fingerprints,
+//! a ciphertext's shape) say what an object is and how long it is.
+//! [`peek_header`] and [`peek_header_from_reader`] read only those, so a
+//! router can inspect an evaluation key of hundreds of megabytes without
+//! loading it; the full deserializers parse the same fields the same way.
+
+use std::io::{self, Read};
+use std::sync::Arc;
+
+use crate::bootstrap::EvaluationKey;
+use crate::digest::{digest, Digest};
+use crate::error::GswError;
+use crate::gsw::{Ciphertext, EntryPolicy};
+use crate::integer::BitOrder;
+use crate::lwe::{PublicKey, SecretKey};
+use crate::params::Params;
+use crate::passphrase::KdfParams;
+use crate::session::{self, FLAG_EVALUATION_KEY, FLAG_SECRET_KEY};
+
+/// Magic bytes opening every serialized object.
+pub(crate) const MAGIC: [u8; 4] = *b"GSWR";
+
+/// Current format version.
+pub(crate) const FORMAT_VERSION: u16 = 1;
+
+const ENCODING_RAW: u8 = 0;
+const ENCODING_PACKED: u8 = 1;
+
+/// Kind tag stored in the object header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ObjectKind {
+    Session = 1,
+    Transcript = 2,
+    EvaluationKey = 3,
+    Ciphertext = 4,
+    EncryptedBits = 5,
+    SealedSecretKey = 6,
+    SealedSession = 7,
+    EvaluationState = 8,
+    EncryptionContext = 9,
+    PublicKey = 10,
+}
+
+impl ObjectKind {
+    fn from_code(code: u8) -> Option<Self> {
+        [
+            ObjectKind::Session,
+            ObjectKind::Transcript,
+            ObjectKind::EvaluationKey,
+            ObjectKind::Ciphertext,
+            ObjectKind::EncryptedBits,
+            ObjectKind::SealedSecretKey,
+            ObjectKind::SealedSession,
+            ObjectKind::EvaluationState,
+            ObjectKind::EncryptionContext,
+            ObjectKind::PublicKey,
+        ]
+        .into_iter()
+        .find(|kind| *kind as u8 == code)
+    }
+
+    /// Bytes of fixed-size fields between the header and the variable-size
+    /// body.
+    pub(crate) fn metadata_len(self) -> usize {
+        match self {
+            ObjectKind::Session => 1 + PARAMS_LEN,
+            ObjectKind::Transcript => 32,
+            ObjectKind::EvaluationKey => 32 + PARAMS_LEN,
+            ObjectKind::Ciphertext => PARAMS_LEN + 2 * 8 + 1,
+            ObjectKind::EncryptedBits => 32 + 8 + 1,
+            ObjectKind::SealedSecretKey | ObjectKind::SealedSession => {
+                32 + KDF_LEN + SALT_LEN + NONCE_LEN
+            }
+            ObjectKind::EvaluationState => PARAMS_LEN + 32 + 8 + 32,
+            ObjectKind::EncryptionContext => PARAMS_LEN + 32,
+            ObjectKind::PublicKey => PARAMS_LEN,
+        }
+    }
+}
+
+/// Largest header plus metadata of any kind: a sealed object's.
+const MAX_PEEK_LEN: usize = HEADER_LEN + 32 + KDF_LEN + SALT_LEN + NONCE_LEN;
+
+/// What the fixed-size start of a serialized object says about it; see
+/// [`peek_header`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WireHeader {
+    pub version: u16,
+    pub kind: ObjectKind,
+    /// The object's parameters; `None` for a transcript, encrypted bits, or
+    /// a sealed object, which store only their fingerprint up front.
+    pub params: Option<Params>,
+    /// [`digest`] of the parameters (for a transcript, encrypted bits, or a
+    /// sealed object, the stored one).
+    pub params_fingerprint: Digest,
+    /// Length of the whole encoded object, header included, as the metadata
+    /// implies; `None` for a transcript, encrypted bits, a sealed object, or
+    /// an evaluation state, whose length depends on their contents. Routers can reject oversized objects before reading them.
+    pub encoded_len: Option<u64>,
+    /// Bytes read to produce this: the header and the kind's metadata.
+    pub peeked_len: usize,
+}
+
+/// Read the header and fixed-size metadata at the start of `bytes`, without
+/// touching the rest.
+///
+/// Fails on bad magic, an unsupported version, an unknown kind, metadata that
+/// a full deserializer would reject, or fewer bytes than the metadata needs.
+pub fn peek_header(bytes: &[u8]) -> Result<WireHeader, GswError> {
+    let mut reader = Reader::new(bytes);
+    let kind = reader.object_header()?;
+    let params = match kind {
+        ObjectKind::Session => {
+            let flags = reader.session_flags()?;
+            let params = reader.params()?;
+            let len = session::encoded_len(
+                &params,
+                flags & FLAG_SECRET_KEY != 0,
+                flags & FLAG_EVALUATION_KEY != 0,
+            );
+            Some((params, Some(len)))
+        }
+        ObjectKind::Transcript
+        | ObjectKind::EncryptedBits
+        | ObjectKind::SealedSecretKey
+        | ObjectKind::SealedSession => None,
+        ObjectKind::EvaluationKey => {
+            reader.hash()?;
+            let params = reader.params()?;
+            let len = (HEADER_LEN + 2 * 32) as u64 + evaluation_key_len(&params);
+            Some((params, Some(len)))
+        }
+        ObjectKind::EvaluationState => {
+            let params = reader.params()?;
+            reader.hash()?;
+            reader.index()?;
+            reader.hash()?;
+            Some((params, None))
+        }
+        ObjectKind::EncryptionContext => {
+            let params = reader.params()?;
+            reader.hash()?;
+            let len = encryption_context_len(&params);
+            Some((params, Some(len)))
+        }
+        ObjectKind::PublicKey => {
+            let params = reader.params()?;
+            let len = HEADER_LEN as u64 + public_key_len(&params);
+            Some((params, Some(len)))
+        }
+        ObjectKind::Ciphertext => {
+            let params = reader.params()?;
+            let packed = reader.ciphertext_shape(&params)?;
+            let len = (HEADER_LEN + PARAMS_LEN) as u64 + ciphertext_len(&params, packed);
+            Some((params, Some(len)))
+        }
+    };
+    let header = match params {
+        Some((params, len)) => WireHeader {
+            version: FORMAT_VERSION,
+            kind,
+            params_fingerprint: digest(&params),
+            params: Some(params),
+            encoded_len: len,
+            peeked_len: reader.pos,
+        },
+        None => {
+            let params_fingerprint = reader.hash()?;
+            match kind {
+                ObjectKind::EncryptedBits => {
+                    reader.bits_frame()?;
+                }
+                ObjectKind::SealedSecretKey | ObjectKind::SealedSession => {
+                    reader.sealed_frame()?;
+                }
+                _ => {}
+            }
+            WireHeader {
+                version: FORMAT_VERSION,
+                kind,
+                params_fingerprint,
+                params: None,
+                encoded_len: None,
+                peeked_len: reader.pos,
+            }
+        }
+    };
+    debug_assert_eq!(header.peeked_len, HEADER_LEN + kind.metadata_len());
+    Ok(header)
+}
+
+/// [`peek_header`] on a stream, reading exactly the header and the kind's
+/// metadata and nothing past them.
+///
+/// Fails as [`peek_header`] does; a stream that ends early is malformed data
+/// and other read errors are [`GswError::Io`].
+pub fn peek_header_from_reader(mut r: impl Read) -> Result<WireHeader, GswError> {
+    let mut buf = [0u8; MAX_PEEK_LEN];
+    let mut read_exact = |into: &mut [u8]| {
+        r.read_exact(into).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => {
+                GswError::MalformedData("unexpected end of data".into())
+            }
+            _ => GswError::Io(e),
+        })
+    };
+    read_exact(&mut buf[..HEADER_LEN])?;
+    let kind = Reader::new(&buf[..HEADER_LEN]).object_header()?;
+    let len = HEADER_LEN + kind.metadata_len();
+    read_exact(&mut buf[HEADER_LEN..len])?;
+    peek_header(&buf[..len])
+}
+
+// Encoded sizes, in bytes. The writers below produce exactly these; the
+// `introspect` module reports them. They are u64 because an evaluation key
+// for larger parameters can exceed 4 GiB, more than a 32-bit usize holds.
+
+/// Magic, version, and kind.
+pub(crate) const HEADER_LEN: usize = MAGIC.len() + 2 + 1;
+/// q, n, m, and the error bound.
+pub(crate) const PARAMS_LEN: usize = 4 * 8;
+/// A key-derivation function id and its three parameters.
+pub(crate) const KDF_LEN: usize = 1 + 3 * 4;
+pub(crate) const SALT_LEN: usize = 16;
+pub(crate) const NONCE_LEN: usize = 12;
+
+const KDF_PBKDF2_SHA256: u8 = 1;
+const KDF_ARGON2ID: u8 = 2;
+
+pub(crate) fn secret_key_len(params: &Params) -> u64 {
+    (PARAMS_LEN + 8) as u64 + 8 * (params.n as u64 + 1)
+}
+
+pub(crate) fn public_key_len(params: &Params) -> u64 {
+    (PARAMS_LEN + 2 * 8) as u64 + 8 * params.m as u64 * (params.n as u64 + 1)
+}
+
+/// An N×N ciphertext, packed (binary entries) or raw.
+pub(crate) fn ciphertext_len(params: &Params, packed: bool) -> u64 {
+    let entries = params.n_expanded as u64 * params.n_expanded as u64;
+    let body = if packed {
+        entries.div_ceil(8)
+    } else {
+        8 * entries
+    };
+    2 * 8 + 1 + body
+}
+
+/// A standalone encryption context: header, params, fingerprint, the
+/// public matrix, and the trailing hash.
+pub(crate) fn encryption_context_len(params: &Params) -> u64 {
+    (HEADER_LEN + 2 * 32) as u64 + public_key_len(params)
+}
+
+/// An evaluation key of N fresh, hence packed, ciphertexts.
+pub(crate) fn evaluation_key_len(params: &Params) -> u64 {
+    (PARAMS_LEN + 8) as u64 + params.n_expanded as u64 * ciphertext_len(params, true)
+}
+
+/// Parameters from decoded independent fields, rejecting any that
+/// [`Params::from_raw`] would panic on or that are too large to allocate for.
+pub(crate) fn checked_params(q: u64, n: u64, m: u64, error_bound: i64) -> Result<Params, GswError> {
+    if q < 4 || !q.is_power_of_two() || q > 1 << 62 {
+        return Err(GswError::MalformedData(format!("invalid modulus {}", q)));
+    }
+    // Cap dimensions so derived sizes cannot overflow or drive huge allocations.
+    if n == 0 || n > 1 << 16 || m == 0 || m > 1 << 20 || error_bound < 0 {
+        return Err(GswError::MalformedData("invalid dimensions".into()));
+    }
+    Ok(Params::from_raw(q, n as usize, m as usize, error_bound))
+}
+
+/// A buffer reserving `len` bytes, or nothing if that is more than the
+/// address space holds (writing will then fail to allocate as it grows).
+pub(crate) fn buffer(len: u64) -> Vec<u8> {
+    Vec::with_capacity(usize::try_from(len).unwrap_or(0))
+}
+
+pub(crate) fn write_header(out: &mut Vec<u8>, kind: ObjectKind) {
+    out.extend_from_slice(&MAGIC);
+    out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    out.push(kind as u8);
+}
+
+fn put_u64(out: &mut Vec<u8>, v: u64) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+pub(crate) fn write_params(out: &mut Vec<u8>, params: &Params) {
+    put_u64(out, params.q);
+    put_u64(out, params.n as u64);
+    put_u64(out, params.m as u64);
+    out.extend_from_slice(&params.error_bound.to_le_bytes());
+}
+
+/// The function id, then its parameters padded with zeros to three u32s.
+#[cfg(feature = "client")]
+pub(crate) fn write_kdf_params(out: &mut Vec<u8>, kdf: &KdfParams) {
+    let (id, fields) = match *kdf {
+        KdfParams::Pbkdf2Sha256 { iterations } => (KDF_PBKDF2_SHA256, [iterations, 0, 0]),
+        #[cfg(feature = "argon2")]
+        KdfParams::Argon2id {
+            memory_kib,
+            iterations,
+            parallelism,
+        } => (KDF_ARGON2ID, [memory_kib, iterations, parallelism]),
+    };
+    out.push(id);
+    for field in fields {
+        out.extend_from_slice(&field.to_le_bytes());
+    }
+}
+
+pub(crate) fn write_secret_key(out: &mut Vec<u8>, sk: &SecretKey) {
+    write_params(out, sk.params());
+    put_u64(out, sk.s.len() as u64);
+    for &x in &sk.s {
+        put_u64(out, x);
+    }
+}
+
+pub(crate) fn write_public_key(out: &mut Vec<u8>, pk: &PublicKey) {
+    write_params(out, pk.params());
+    put_u64(out, pk.a.len() as u64);
+    put_u64(out, pk.a.first().map_or(0, |row| row.len()) as u64);
+    for &x in pk.a.iter().flatten() {
+        put_u64(out, x);
+    }
+}
+
+/// An m×(n + 1) public matrix held row-major in one buffer: the body of
+/// [`write_public_key`] without the params.
+#[cfg(feature = "client")]
+pub(crate) fn write_public_matrix(out: &mut Vec<u8>, params: &Params, a: &[u64]) {
+    put_u64(out, params.m as u64);
+    put_u64(out, params.n as u64 + 1);
+    for &x in a {
+        put_u64(out, x);
+    }
+}
+
+pub(crate) fn write_ciphertext(out: &mut Vec<u8>, ct: &Ciphertext) {
+    let cols = ct.first().map_or(0, |row| row.len());
+    put_u64(out, ct.len() as u64);
+    put_u64(out, cols as u64);
+    if ct.iter().flatten().all(|&x| x <= 1) {
+        out.push(ENCODING_PACKED);
+        let mut byte = 0u8;
+        let mut filled = 0;
+        for &x in ct.iter().flatten() {
+            byte |= (x as u8) << filled;
+            filled += 1;
+            if filled == 8 {
+                out.push(byte);
+                byte = 0;
+                filled = 0;
+            }
+        }
+        if filled > 0 {
+            out.push(byte);
+        }
+    } else {
+        out.push(ENCODING_RAW);
+        for &x in ct.iter().flatten() {
+            put_u64(out, x);
+        }
+    }
+}
+
+pub(crate) fn write_evaluation_key(out: &mut Vec<u8>, ek: &EvaluationKey) {
+    write_params(out, ek.params());
+    put_u64(out, ek.len() as u64);
+    for ct in ek.entries() {
+        write_ciphertext(out, ct);
+    }
+}
+
+/// Cursor over a byte slice; every read checks bounds.
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], GswError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| GswError::MalformedData("unexpected end of data".into()))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, GswError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, GswError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, GswError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, GswError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn i64(&mut self) -> Result<i64, GswError> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// A 32-byte digest.
+    pub(crate) fn hash(&mut self) -> Result<[u8; 32], GswError> {
+        Ok(self.take(32)?.try_into().unwrap())
+    }
+
+    /// An index stored as u64.
+    pub(crate) fn index(&mut self) -> Result<usize, GswError> {
+        usize::try_from(self.u64()?)
+            .map_err(|_| GswError::MalformedData("index out of range".into()))
+    }
+
+    /// An element count, rejected up front if that many elements of at least
+    /// `min_size` bytes each cannot fit in the remaining data.
+    pub(crate) fn count(&mut self, min_size: usize) -> Result<usize, GswError> {
+        let count = self.index()?;
+        let remaining = self.bytes.len() - self.pos;
+        if count
+            .checked_mul(min_size)
+            .is_none_or(|needed| needed > remaining)
+        {
+            return Err(GswError::MalformedData("unexpected end of data".into()));
+        }
+        Ok(count)
+    }
+
+    /// Read a length field and check it against the value the params imply.
+    fn expect_len(&mut self, expected: usize, what: &str) -> Result<(), GswError> {
+        let found = self.u64()?;
+        if found != expected as u64 {
+            return Err(GswError::MalformedData(format!(
+                "{}: expected {}, found {}",
+                what, expected, found
+            )));
+        }
+        Ok(())
+    }
+
+    /// Read `count` u64 entries, each of which must lie in [0, q).
+    fn entries_mod_q(&mut self, count: usize, q: u64) -> Result<Vec<u64>, GswError> {
+        let bytes = self.take(
+            count
+                .checked_mul(8)
+                .ok_or_else(|| GswError::MalformedData("length overflow".into()))?,
+        )?;
+        bytes
+            .chunks_exact(8)
+            .map(|chunk| {
+                let x = u64::from_le_bytes(chunk.try_into().unwrap());
+                if x < q {
+                    Ok(x)
+                } else {
+                    Err(GswError::MalformedData(format!("entry {} not below q", x)))
+                }
+            })
+            .collect()
+    }
+
+    /// The header of an object of any kind.
+    fn object_header(&mut self) -> Result<ObjectKind, GswError> {
+        if self.take(4)? != MAGIC {
+            return Err(GswError::BadMagic);
+        }
+        let version = self.u16()?;
+        if version != FORMAT_VERSION {
+            return Err(GswError::UnsupportedVersion(version));
+        }
+        let code = self.u8()?;
+        ObjectKind::from_code(code)
+            .ok_or_else(|| GswError::MalformedData(format!("unknown object kind {}", code)))
+    }
+
+    pub(crate) fn header(&mut self, kind: ObjectKind) -> Result<(), GswError> {
+        let found = self.object_header()?;
+        if found != kind {
+            return Err(GswError::MalformedData(format!(
+                "expected object kind {}, found {}",
+                kind as u8, found as u8
+            )));
+        }
+        Ok(())
+    }
+
+    /// The width and bit order of encrypted bits, after their fingerprint.
+    pub(crate) fn bits_frame(&mut self) -> Result<(usize, BitOrder), GswError> {
+        let width = self.index()?;
+        let code = self.u8()?;
+        let order = BitOrder::from_code(code)
+            .ok_or_else(|| GswError::MalformedData(format!("unknown bit order {}", code)))?;
+        Ok((width, order))
+    }
+
+    /// The key derivation, salt, and nonce of a sealed object, after its
+    /// fingerprint. Key derivation out of range, or needing a feature this
+    /// build lacks, is malformed data.
+    pub(crate) fn sealed_frame(
+        &mut self,
+    ) -> Result<(KdfParams, [u8; SALT_LEN], [u8; NONCE_LEN]), GswError> {
+        let id = self.u8()?;
+        let fields = [self.u32()?, self.u32()?, self.u32()?];
+        let kdf = match (id, fields) {
+            (KDF_PBKDF2_SHA256, [iterations, 0, 0]) => KdfParams::Pbkdf2Sha256 { iterations },
+            #[cfg(feature = "argon2")]
+            (KDF_ARGON2ID, [memory_kib, iterations, parallelism]) => KdfParams::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            },
+            #[cfg(not(feature = "argon2"))]
+            (KDF_ARGON2ID, _) => {
+                return Err(GswError::MalformedData(
+                    "Argon2id key derivation needs the argon2 feature".into(),
+                ))
+            }
+            _ => {
+                return Err(GswError::MalformedData(format!(
+                    "unknown key derivation {}",
+                    id
+                )))
+            }
+        };
+        kdf.validate()?;
+        let salt = self.take(SALT_LEN)?.try_into().unwrap();
+        let nonce = self.take(NONCE_LEN)?.try_into().unwrap();
+        Ok((kdf, salt, nonce))
+    }
+
+    /// The bytes read so far and the rest.
+    #[cfg(feature = "passphrase")]
+    pub(crate) fn split(self) -> (&'a [u8], &'a [u8]) {
+        self.bytes.split_at(self.pos)
+    }
+
+    /// Whether every byte has been read.
+    pub(crate) fn is_at_end(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    /// A session's component flags, rejecting unknown ones.
+    pub(crate) fn session_flags(&mut self) -> Result<u8, GswError> {
+        let flags = self.u8()?;
+        if flags & !(FLAG_SECRET_KEY | FLAG_EVALUATION_KEY) != 0 {
+            return Err(GswError::MalformedData(format!(
+                "unknown flags {:#x}",
+                flags
+            )));
+        }
+        Ok(flags)
+    }
+
+    pub(crate) fn params(&mut self) -> Result<Params, GswError> {
+        let q = self.u64()?;
+        let n = self.u64()?;
+        let m = self.u64()?;
+        let error_bound = self.i64()?;
+        checked_params(q, n, m, error_bound)
+    }
+
+    pub(crate) fn secret_key(&mut self) -> Result<SecretKey, GswError> {
+        let params = self.params()?;
+        self.expect_len(params.n + 1, "secret key length")?;
+        let s = self.entries_mod_q(params.n + 1, params.q)?;
+        Ok(SecretKey::from_parts(s, Arc::new(params)))
+    }
+
+    pub(crate) fn public_key(&mut self) -> Result<PublicKey, GswError> {
+        let params = self.params()?;
+        self.expect_len(params.m, "public key rows")?;
+        self.expect_len(params.n + 1, "public key columns")?;
+        let a = (0..params.m)
+            .map(|_| self.entries_mod_q(params.n + 1, params.q))
+            .collect::<Result<_, _>>()?;
+        Ok(PublicKey::from_parts(a, Arc::new(params)))
+    }
+
+    /// What [`write_public_matrix`] wrote, row-major in one buffer.
+    #[cfg(feature = "client")]
+    pub(crate) fn public_matrix(&mut self, params: &Params) -> Result<Vec<u64>, GswError> {
+        self.expect_len(params.m, "public key rows")?;
+        self.expect_len(params.n + 1, "public key columns")?;
+        self.entries_mod_q(params.m * (params.n + 1), params.q)
+    }
+
+    pub(crate) fn ciphertext(&mut self, params: &Params) -> Result<Ciphertext, GswError> {
+        self.ciphertext_with(params, EntryPolicy::Reject)
+    }
+
+    /// A ciphertext whose raw entries at or above q are handled by `policy`.
+    pub(crate) fn ciphertext_with(
+        &mut self,
+        params: &Params,
+        policy: EntryPolicy,
+    ) -> Result<Ciphertext, GswError> {
+        let n = params.n_expanded;
+        if self.ciphertext_shape(params)? {
+            let entries = n
+                .checked_mul(n)
+                .ok_or_else(|| GswError::MalformedData("length overflow".into()))?;
+            let bytes = self.take(entries.div_ceil(8))?;
+            Ok((0..n)
+                .map(|i| {
+                    (0..n)
+                        .map(|j| {
+                            let idx = i * n + j;
+                            ((bytes[idx / 8] >> (idx % 8)) & 1) as u64
+                        })
+                        .collect()
+                })
+                .collect())
+        } else {
+            (0..n)
+                .map(|row| {
+                    let entries = self.take(8 * n)?.chunks_exact(8).enumerate();
+                    entries
+                        .map(|(col, chunk)| {
+                            let value = u64::from_le_bytes(chunk.try_into().unwrap());
+                            match policy {
+                                _ if value < params.q => Ok(value),
+                                EntryPolicy::Reject => {
+                                    Err(GswError::UnreducedEntry { row, col, value })
+                                }
+                                EntryPolicy::ReduceModQ => Ok(value % params.q),
+                            }
+                        })
+                        .collect()
+                })
+                .collect()
+        }
+    }
+
+    /// A ciphertext's dimensions, which must be N×N, and encoding; true if
+    /// packed.
+    fn ciphertext_shape(&mut self, params: &Params) -> Result<bool, GswError> {
+        let n = params.n_expanded;
+        self.expect_len(n, "ciphertext rows")?;
+        self.expect_len(n, "ciphertext columns")?;
+        match self.u8()? {
+            ENCODING_PACKED => Ok(true),
+            ENCODING_RAW => Ok(false),
+            other => Err(GswError::MalformedData(format!(
+                "unknown ciphertext encoding {}",
+                other
+            ))),
+        }
+    }
+
+    /// An evaluation key body; the fingerprint is stored by the caller's
+    /// format, not here.
+    pub(crate) fn evaluation_key(
+        &mut self,
+        pk_fingerprint: [u8; 32],
+    ) -> Result<EvaluationKey, GswError> {
+        let params = self.params()?;
+        self.expect_len(params.n_expanded, "evaluation key entries")?;
+        let encryptions = (0..params.n_expanded)
+            .map(|_| self.ciphertext(&params))
+            .collect::<Result<_, _>>()?;
+        Ok(EvaluationKey::from_parts(
+            encryptions,
+            Arc::new(params),
+            pk_fingerprint,
+        ))
+    }
+
+    /// Fail if any bytes are left over.
+    pub(crate) fn finish(self) -> Result<(), GswError> {
+        if self.pos != self.bytes.len() {
+            return Err(GswError::MalformedData(format!(
+                "{} trailing bytes",
+                self.bytes.len() - self.pos
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::bootstrap::gen_evaluation_key;
+    use crate::gsw::{ciphertext_from_bytes, ciphertext_to_bytes, encrypt, gsw_keygen};
+    use crate::params::SecurityLevel;
+    use crate::session::Session;
+    use crate::testing::pseudo_ciphertext;
+    use crate::transcript::TranscriptRecorder;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    /// A reader that counts the bytes it hands out.
+    struct CountingReader<R> {
+        inner: R,
+        read: u64,
+    }
+
+    impl<R: Read> Read for CountingReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.inner.read(buf)?;
+            self.read += n as u64;
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_peek_every_kind() {
+        let params = Params::pedagogical();
+        let mut rng = ChaCha20Rng::seed_from_u64(486);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ek = gen_evaluation_key(&mut rng, &sk, &pk);
+        let ct = encrypt(&mut rng, &pk, 1);
+        let mut raw = ct.clone();
+        raw[0][0] = params.q - 1;
+        let session =
+            Session::from_parts(params.clone(), Some(sk), pk.clone(), Some(ek.clone())).unwrap();
+        let mut recorder = TranscriptRecorder::new(&params, &[ct.clone(), ct.clone()]);
+        recorder.nand(0, 1);
+
+        let objects = [
+            (ObjectKind::Session, session.to_bytes()),
+            (
+                ObjectKind::Session,
+                Session::public_view(&session).to_bytes(),
+            ),
+            (ObjectKind::EvaluationKey, ek.to_bytes()),
+            (ObjectKind::PublicKey, pk.to_bytes()),
+            (ObjectKind::Ciphertext, ciphertext_to_bytes(&params, &ct)),
+            (ObjectKind::Ciphertext, ciphertext_to_bytes(&params, &raw)),
+            (
+                ObjectKind::EncryptionContext,
+                crate::EncryptionContext::build(&pk, &params)
+                    .unwrap()
+                    .to_bytes(),
+            ),
+        ];
+        for (kind, bytes) in &objects {
+            let header = peek_header(bytes).unwrap();
+            assert_eq!(header.kind, *kind);
+            assert_eq!(header.version, FORMAT_VERSION);
+            assert_eq!(header.params.as_ref(), Some(&params));
+            assert_eq!(header.params_fingerprint, digest(&params));
+            assert_eq!(header.encoded_len, Some(bytes.len() as u64));
+            assert_eq!(peek_header_from_reader(&bytes[..]).unwrap(), header);
+        }
+
+        let bytes = recorder.transcript().to_bytes();
+        let header = peek_header(&bytes).unwrap();
+        assert_eq!(header.kind, ObjectKind::Transcript);
+        assert_eq!((header.params, header.encoded_len), (None, None));
+        assert_eq!(header.params_fingerprint, digest(&params));
+
+        let bytes = crate::integer::encrypted_bits_to_bytes(&params, &[ct.clone(), ct]);
+        let header = peek_header(&bytes).unwrap();
+        assert_eq!(header.kind, ObjectKind::EncryptedBits);
+        assert_eq!((header.params, header.encoded_len), (None, None));
+        assert_eq!(header.params_fingerprint, digest(&params));
+        assert_eq!(
+            peek_header_from_reader(&bytes[..]).unwrap().peeked_len,
+            header.peeked_len
+        );
+        let eval = crate::context::Evaluator::new(&params);
+        let mut circuit = crate::circuits::Circuit::new(1);
+        let not = circuit.not(0);
+        circuit.set_outputs(&[not]);
+        let state = eval
+            .suspend_after(&circuit, &[eval.encrypt(&mut rng, &pk, true)], 0)
+            .unwrap();
+        let bytes = state.to_bytes();
+        let header = peek_header(&bytes).unwrap();
+        assert_eq!(header.kind, ObjectKind::EvaluationState);
+        assert_eq!(
+            (header.params.as_ref(), header.encoded_len),
+            (Some(&params), None)
+        );
+        assert_eq!(header.params_fingerprint, digest(&params));
+
+        let kdf = crate::passphrase::KdfParams::Pbkdf2Sha256 { iterations: 1 };
+        let bytes = session
+            .secret_key()
+            .unwrap()
+            .export_encrypted(&mut rng, b"pw", &kdf)
+            .unwrap();
+        let header = peek_header_from_reader(&bytes[..]).unwrap();
+        assert_eq!(header.kind, ObjectKind::SealedSecretKey);
+        assert_eq!((header.params, header.encoded_len), (None, None));
+        assert_eq!(header.params_fingerprint, digest(&params));
+    }
+
+    #[test]
+    fn test_peek_truncated_header_fails() {
+        let params = Params::toy();
+        let bytes = ciphertext_to_bytes(&params, &pseudo_ciphertext(&params, 486));
+        let peeked = peek_header(&bytes).unwrap().peeked_len;
+        for len in 0..peeked {
+            assert!(
+                matches!(peek_header(&bytes[..len]), Err(GswError::MalformedData(_))),
+                "{} bytes",
+                len
+            );
+            assert!(matches!(
+                peek_header_from_reader(&bytes[..len]),
+                Err(GswError::MalformedData(_))
+            ));
+        }
+        assert!(matches!(
+            peek_header(b"GSWX\x01\x00\x04"),
+            Err(GswError::BadMagic)
+        ));
+        assert!(matches!(
+            peek_header(b"GSWR\x09\x00\x04"),
+            Err(GswError::UnsupportedVersion(9))
+        ));
+        assert!(matches!(
+            peek_header(b"GSWR\x01\x00\xff"),
+            Err(GswError::MalformedData(_))
+        ));
+    }
+
+    /// Ciphertexts, public keys, and evaluation keys encode deterministically,
+    /// round-trip exactly, and reject every truncation and other params.
+    #[test]
+    fn test_standalone_objects_round_trip() {
+        let params = Params::pedagogical();
+        let other = Params::from_raw(params.q, params.n, params.m + 1, params.error_bound);
+        let mut rng = ChaCha20Rng::seed_from_u64(502);
+        let (sk, pk) = gsw_keygen(&mut rng, &params);
+        let ek = gen_evaluation_key(&mut rng, &sk, &pk);
+        let ct = encrypt(&mut rng, &pk, 1);
+
+        let bytes = ciphertext_to_bytes(&params, &ct);
+        assert_eq!(bytes, ciphertext_to_bytes(&params, &ct));
+        let read = |bytes: &[u8]| ciphertext_from_bytes(&params, bytes, EntryPolicy::Reject);
+        assert_eq!(read(&bytes).unwrap(), ct);
+        assert!((0..bytes.len()).all(|len| read(&bytes[..len]).is_err()));
+        assert!(matches!(
+            ciphertext_from_bytes(&other, &bytes, EntryPolicy::Reject),
+            Err(GswError::ParamsMismatch)
+        ));
+
+        let bytes = pk.to_bytes();
+        assert_eq!(bytes, pk.to_bytes());
+        let read = |bytes: &[u8]| PublicKey::from_bytes(&params, bytes);
+        assert_eq!(read(&bytes).unwrap().a, pk.a);
+        assert!((0..bytes.len()).all(|len| read(&bytes[..len]).is_err()));
+        assert!(matches!(
+            PublicKey::from_bytes(&other, &bytes),
+            Err(GswError::ParamsMismatch)
+        ));
+        let mut misshapen = bytes.clone();
+        misshapen[HEADER_LEN + PARAMS_LEN] += 1;
+        assert!(matches!(
+            PublicKey::from_bytes(&params, &misshapen),
+            Err(GswError::MalformedData(_))
+        ));
+
+        let bytes = ek.to_bytes();
+        assert_eq!(bytes, ek.to_bytes());
+        assert_eq!(EvaluationKey::from_bytes(&bytes).unwrap().to_bytes(), bytes);
+        assert!((0..bytes.len()).all(|len| EvaluationKey::from_bytes(&bytes[..len]).is_err()));
+    }
+
+    #[test]
+    fn test_peek_reads_only_the_header() {
+        // The start of a Medium evaluation key, followed by 50 MB that are
+        // never needed.
+        let params = Params::new(SecurityLevel::Medium);
+        let mut start = Vec::new();
+        write_header(&mut start, ObjectKind::EvaluationKey);
+        start.extend_from_slice(&[0; 32]);
+        write_params(&mut start, &params);
+        let mut reader = CountingReader {
+            inner: start.as_slice().chain(io::repeat(0).take(50 << 20)),
+            read: 0,
+        };
+
+        let header = peek_header_from_reader(&mut reader).unwrap();
+        assert_eq!(reader.read, start.len() as u64);
+        assert_eq!(header.peeked_len, start.len());
+        assert_eq!(
+            header.encoded_len,
+            Some((HEADER_LEN + 2 * 32) as u64 + evaluation_key_len(&params))
+        );
+    }
+}
@@ -0,0 +1,20 @@
+//! The API tiers hold at compile time: the prelude alone runs the README
+//! example, sealed traits reject outside implementations, and the
+//! experimental module is absent without its feature and warns with it.
+//!
+//! Expected compiler output lives next to each case in `tests/ui/`; after a
+//! toolchain upgrade changes the wording, regenerate it with
+//! `TRYBUILD=overwrite cargo test --test api_tiers`.
+
+#![cfg(feature = "client")]
+
+#[test]
+fn test_api_tiers() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/ui/prelude_readme.rs");
+    cases.compile_fail("tests/ui/sealed_rows.rs");
+    #[cfg(not(feature = "experimental"))]
+    cases.compile_fail("tests/ui/experimental_needs_feature.rs");
+    #[cfg(feature = "experimental")]
+    cases.compile_fail("tests/ui/experimental_warns.rs");
+}
@@ -0,0 +1,107 @@
+//! Known-answer vectors that every platform must reproduce exactly.
+//!
+//! Library results must not depend on the target: parameter derivation and
+//! the noise model use integer and fixed-point arithmetic, decryption rounds
+//! in integers, and sizes that can pass 4 GiB are u64. These vectors were
+//! produced on x86-64; any divergence on another target fails here. To check
+//! a 32-bit target without CI, cross-compile and run, for example:
+//!
+//! ```text
+//! rustup target add i686-unknown-linux-musl
+//! cargo test --target i686-unknown-linux-musl --no-default-features --features client
+//! ```
+//!
+//! (The `blake3` feature builds C code that needs the target's C headers, so
+//! digests here use SHA-256 in every configuration.)
+
+#![cfg(feature = "client")]
+
+use gsw_rs::digest::{digest_with, to_hex, Hashable, Sha256};
+use gsw_rs::noise_model::log2_error_bound;
+use gsw_rs::testing::pseudo_ciphertext;
+use gsw_rs::{
+    decrypt, encrypt, format_report, gsw_keygen, homomorphic_add, homomorphic_mult,
+    homomorphic_nand, Params, SecurityLevel,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+fn sha256<T: Hashable + ?Sized>(value: &T) -> String {
+    to_hex(&digest_with::<Sha256, _>(value))
+}
+
+#[test]
+fn test_params_derivation() {
+    let presets: Vec<_> = [
+        SecurityLevel::Toy,
+        SecurityLevel::Low,
+        SecurityLevel::Medium,
+    ]
+    .into_iter()
+    .map(|level| {
+        let p = Params::new(level);
+        (p.l, p.n_expanded, p.max_mult_depth())
+    })
+    .collect();
+    assert_eq!(presets, KAT_PRESETS);
+
+    let deep = Params::for_depth(2, -40).unwrap();
+    assert_eq!((deep.q, deep.n, deep.m, deep.error_bound), KAT_FOR_DEPTH_2);
+    // The bound itself, to the last bit of its f64 conversion.
+    assert_eq!(
+        log2_error_bound(&Params::toy(), 1, -40).to_bits(),
+        KAT_TOY_BOUND_BITS
+    );
+    // An evaluation key far past what a 32-bit usize can count.
+    let report = format_report(&Params::custom(1 << 62, 4096, 1 << 16, 1, -40).unwrap());
+    assert_eq!(report.evaluation_key, KAT_LARGE_EVALUATION_KEY_BYTES);
+}
+
+#[test]
+fn test_keygen_encrypt_and_gates() {
+    let params = Params::toy();
+    let mut rng = ChaCha20Rng::seed_from_u64(476);
+    let (sk, pk) = gsw_keygen(&mut rng, &params);
+    let zero = encrypt(&mut rng, &pk, 0);
+    let one = encrypt(&mut rng, &pk, 1);
+    let and = homomorphic_mult(&params, &one, &one);
+    let nand = homomorphic_nand(&params, &one, &zero);
+    let xor = homomorphic_add(&params, &one, &one);
+
+    let digests = [
+        sha256(&sk),
+        sha256(&pk),
+        sha256(&zero),
+        sha256(&one),
+        sha256(&and),
+        sha256(&nand),
+        sha256(&xor),
+        sha256(&pseudo_ciphertext(&params, 476)),
+    ];
+    assert_eq!(digests, KAT_DIGESTS);
+    let bits = [&zero, &one, &and, &nand, &xor].map(|ct| decrypt(&sk, ct));
+    assert_eq!(bits, [0, 1, 1, 1, 0]);
+}
+
+const KAT_PRESETS: [(usize, usize, u32); 3] = [(20, 180, 1), (24, 600, 1), (26, 1274, 1)];
+const KAT_FOR_DEPTH_2: (u64, usize, usize, i64) = (1 << 28, 8, 256, 1);
+const KAT_TOY_BOUND_BITS: u64 = 0x402e_3415_c1e6_0000;
+const KAT_LARGE_EVALUATION_KEY_BYTES: u64 = 2_048_721_732_114_628;
+const KAT_DIGESTS: [&str; 8] = [
+    // secret key
+    "e91276a2b46d52fd0d32f41df8a30b79ad157f908fb7146e3608ca2d68e9c6dd",
+    // public key
+    "0578c87bf89b22e715bf1257f1d8900d75cb4ea5dcf938796eda0c95ad2653bf",
+    // Enc(0)
+    "aaae49e74a7ea32440de5a215cefc66ebe5674a36fab46a90f6ff78985db4a51",
+    // Enc(1)
+    "05ab50dc435ca3b9b25fb1693a91e21ae330701ad6dcb7f148bcea5fe910d9cb",
+    // 1 AND 1
+    "a696bdbfc8f8bee85db68225e3642930de52c8af22935ef4ed7ea97f1c93b46c",
+    // 1 NAND 0
+    "b2bffac70cc06f16dc810958c26526779b6cc9e6e367e2e7fba129a3523a3133",
+    // 1 XOR 1
+    "04d4a91dffc4b338de32396d61a576bfa576f47b7894bfcf2035188c5f4287b1",
+    // pseudo ciphertext
+    "87c27ed81ac95bf011dc8bf3eec3bc132cbb8072f25521d38bafeaca89abedb5",
+];
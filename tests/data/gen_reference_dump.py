@@ -0,0 +1,63 @@
+"""Regenerate reference_dump.json: a key pair and ciphertexts in the Python
+reference's conventions, at pedagogical size (n = 2, q = 2^8, m = 4).
+
+- s = (t, 1); public-key rows are (a, b) with b = -<a, t> + e.
+- BitDecomp is MSB first: coordinate k occupies columns k*l .. k*l + l - 1,
+  holding bits l-1 down to 0.
+- C = Flatten(mu*I + BitDecomp(R*A)) for a binary N x m matrix R.
+
+Run with `python3 tests/data/gen_reference_dump.py > tests/data/reference_dump.json`.
+"""
+
+import json
+import random
+
+N_DIM, L, M, BOUND = 2, 8, 4, 1
+Q = 1 << L
+N = (N_DIM + 1) * L
+
+rng = random.Random(467)
+
+
+def bit_decomp(v):
+    return [(x >> (L - 1 - i)) & 1 for x in v for i in range(L)]
+
+
+def bit_decomp_inverse(bits):
+    return [
+        sum(bits[k * L + i] << (L - 1 - i) for i in range(L)) % Q
+        for k in range(len(bits) // L)
+    ]
+
+
+def flatten(v):
+    return bit_decomp(bit_decomp_inverse(v))
+
+
+t = [rng.randrange(Q) for _ in range(N_DIM)]
+sk = t + [1]
+pk = []
+for _ in range(M):
+    a = [rng.randrange(Q) for _ in range(N_DIM)]
+    e = rng.randint(-BOUND, BOUND)
+    pk.append(a + [(-sum(x * y for x, y in zip(a, t)) + e) % Q])
+
+
+def encrypt(mu):
+    r = [[rng.randrange(2) for _ in range(M)] for _ in range(N)]
+    ra = [[sum(r[i][k] * pk[k][j] for k in range(M)) % Q for j in range(N_DIM + 1)]
+          for i in range(N)]
+    rows = [bit_decomp(row) for row in ra]
+    for i in range(N):
+        rows[i][i] += mu
+    return [flatten(row) for row in rows]
+
+
+print(json.dumps({
+    "q": Q,
+    "n": N_DIM,
+    "l": L,
+    "sk": sk,
+    "pk": pk,
+    "ct": [encrypt(mu) for mu in (0, 1, 1, 0)],
+}))
@@ -0,0 +1,151 @@
+//! Every public function that takes a slice, given an empty one, behaves as
+//! documented under "Empty inputs" on [`GswError`]: an empty output, the
+//! identity of the fold, or [`GswError::EmptyInput`], and never a panic.
+
+#![cfg(feature = "client")]
+
+use gsw_rs::bootstrap::{decrypt_linear_parts_all, gen_evaluation_key, EvaluationKey};
+use gsw_rs::circuits::{homomorphic_one_hot, homomorphic_table_lookup, AnfPolynomial, Circuit};
+use gsw_rs::error::GswError;
+use gsw_rs::{
+    bit_decomp, bit_decomp_inverse, ciphertext_from_bytes, decrypt, decrypt_bits, decrypt_uint,
+    encrypt, encrypt_bits, flatten, flatten_matrix, gsw_keygen, homomorphic_add_saturating_uint,
+    homomorphic_and_many, homomorphic_compare_uint, homomorphic_conditional_increment,
+    homomorphic_div_const_uint, homomorphic_ge_const, homomorphic_gt_const,
+    homomorphic_inner_product_bits, homomorphic_is_nonzero, homomorphic_is_zero,
+    homomorphic_le_const, homomorphic_lt_const, homomorphic_mean, homomorphic_or_many,
+    homomorphic_xor_const, powers_of_2, reverse_bit_order, Ciphertext, DecryptionContext,
+    EncryptedUint, EntryPolicy, Evaluator, Params, Permutation, Session,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+#[test]
+fn test_maps_give_empty_outputs() {
+    let params = Params::toy();
+    assert!(bit_decomp(&[], &params).is_empty());
+    assert!(bit_decomp_inverse(&[], &params).is_empty());
+    assert!(reverse_bit_order::<u64>(&[], &params).is_empty());
+    assert!(flatten(&[], &params).is_empty());
+    assert!(powers_of_2(&[], &params).is_empty());
+    assert!(gsw_rs::gadget::g_inverse_matrix(&[], &params).is_empty());
+    assert!(flatten_matrix(&[], &params).is_empty());
+    assert!(homomorphic_xor_const(&params, &[], u64::MAX).is_empty());
+    assert!(Permutation::new(Vec::new())
+        .apply::<Ciphertext>(&[])
+        .is_empty());
+
+    let mut rng = ChaCha20Rng::seed_from_u64(481);
+    let (sk, pk) = gsw_keygen(&mut rng, &params);
+    assert!(DecryptionContext::new(&sk)
+        .decrypt_many::<Ciphertext>(&[])
+        .is_empty());
+    assert!(encrypt_bits(&mut rng, &pk, &[]).is_empty());
+    assert!(decrypt_bits(&sk, &[]).is_empty());
+    assert!(decrypt_linear_parts_all(&sk, &Vec::new()).is_empty());
+
+    let eval = Evaluator::new(&params);
+    assert!(Circuit::new(0).evaluate(&eval, &[]).is_empty());
+    let mut circuit = Circuit::new(1);
+    let branch = Circuit::new(0);
+    assert!(circuit
+        .cond_subcircuit(0, branch.clone(), branch, &[])
+        .is_empty());
+}
+
+#[test]
+fn test_folds_give_their_identity() {
+    let params = Params::toy();
+    let mut rng = ChaCha20Rng::seed_from_u64(481);
+    let (sk, pk) = gsw_keygen(&mut rng, &params);
+    let bit = |ct: &Ciphertext| decrypt(&sk, ct);
+
+    // The empty integer is 0.
+    assert_eq!(bit(&homomorphic_is_zero(&params, &[])), 1);
+    assert_eq!(bit(&homomorphic_is_nonzero(&params, &[])), 0);
+    for c in [0u64, 1, u64::MAX] {
+        let positive = (c > 0) as u8;
+        assert_eq!(bit(&homomorphic_lt_const(&params, &[], c)), positive);
+        assert_eq!(bit(&homomorphic_le_const(&params, &[], c)), 1);
+        assert_eq!(bit(&homomorphic_gt_const(&params, &[], c)), 0);
+        assert_eq!(bit(&homomorphic_ge_const(&params, &[], c)), 1 - positive);
+    }
+    let empty = EncryptedUint::from_bits(Vec::new());
+    assert_eq!(decrypt_uint(&sk, &empty), 0);
+    let order = homomorphic_compare_uint(&params, &empty, &empty);
+    assert_eq!([&order.lt, &order.eq, &order.gt].map(bit), [0, 1, 0]);
+    let three = gsw_rs::encrypt_uint(&mut rng, &pk, 3, 2);
+    let order = homomorphic_compare_uint(&params, &empty, &three);
+    assert_eq!([&order.lt, &order.eq, &order.gt].map(bit), [1, 0, 0]);
+    assert_eq!(
+        homomorphic_add_saturating_uint(&params, &empty, &empty).width(),
+        0
+    );
+    let count = homomorphic_inner_product_bits(&params, &[], &[]);
+    assert_eq!((count.width(), decrypt_uint(&sk, &count)), (0, 0));
+    let mut counter = empty.clone();
+    homomorphic_conditional_increment(&params, &mut counter, &encrypt(&mut rng, &pk, 1));
+    assert_eq!(counter.width(), 0);
+    assert!(matches!(
+        homomorphic_div_const_uint(&params, &empty, 1),
+        Err(GswError::InvalidDivisor { .. })
+    ));
+
+    // An AND of no bits is 1, an OR is 0.
+    assert_eq!(bit(&homomorphic_and_many(&params, &[])), 1);
+    assert_eq!(bit(&homomorphic_or_many(&params, &[])), 0);
+
+    // The one-hot decoding of no bits is the single line [0 == 0].
+    let lines = homomorphic_one_hot(&params, &[]);
+    assert_eq!(lines.iter().map(bit).collect::<Vec<_>>(), [1]);
+    let looked_up = homomorphic_table_lookup(&params, &[], &[0b101], 3);
+    assert_eq!(looked_up.iter().map(bit).collect::<Vec<_>>(), [1, 0, 1]);
+
+    // No terms sum to 0; the empty monomial is 1.
+    let zero = AnfPolynomial::new(0, &[]).unwrap();
+    assert_eq!(bit(&zero.evaluate(&params, &[])), 0);
+    let one = AnfPolynomial::new(0, &[Vec::new()]).unwrap();
+    assert_eq!(bit(&one.evaluate(&params, &[])), 1);
+}
+
+#[test]
+fn test_no_sensible_value_is_an_error() {
+    let params = Params::toy();
+    assert!(matches!(
+        homomorphic_mean(&params, &[]),
+        Err(GswError::EmptyInput("values"))
+    ));
+    #[cfg(feature = "experimental")]
+    #[allow(deprecated)]
+    {
+        assert!(matches!(
+            gsw_rs::experimental::multikey::concat_evaluation_keys(&[]),
+            Err(GswError::EmptyInput("parts"))
+        ));
+    }
+    assert_eq!(
+        GswError::EmptyInput("values").to_string(),
+        "values must not be empty"
+    );
+
+    let eval = Evaluator::new(&params);
+    assert!(eval.input_vec(Vec::new()).reduce(|a, b| a.xor(b)).is_none());
+
+    // Decoders treat empty bytes as truncated data.
+    assert!(ciphertext_from_bytes(&params, &[], EntryPolicy::Reject).is_err());
+    assert!(EvaluationKey::from_bytes(&[]).is_err());
+    assert!(Session::from_bytes(&[]).is_err());
+    assert!(Circuit::from_bristol("").is_err());
+    assert!(!Circuit::validate_all("").is_empty());
+}
+
+#[test]
+fn test_refreshing_no_entries_changes_nothing() {
+    let params = Params::pedagogical();
+    let mut rng = ChaCha20Rng::seed_from_u64(481);
+    let (sk, pk) = gsw_keygen(&mut rng, &params);
+    let mut ek = gen_evaluation_key(&mut rng, &sk, &pk);
+    let before = ek.to_bytes();
+    ek.refresh_entries(&mut rng, &sk, &pk, &[]).unwrap();
+    assert_eq!(ek.to_bytes(), before);
+}
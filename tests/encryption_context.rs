@@ -0,0 +1,65 @@
+//! `EncryptionContext`: the same ciphertexts as `encrypt`, before and after a
+//! save and reload, and only for the key it was built from.
+
+#![cfg(feature = "client")]
+
+use gsw_rs::{decrypt, encrypt, gsw_keygen, EncryptionContext, GswError, Params};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+#[test]
+fn test_context_matches_encrypt() {
+    let params = Params::toy();
+    let mut rng = ChaCha20Rng::seed_from_u64(501);
+    let (sk, pk) = gsw_keygen(&mut rng, &params);
+    let ctx = EncryptionContext::build(&pk, &params).unwrap();
+
+    let path = std::env::temp_dir().join(format!("gsw-rs-ctx-{}.bin", std::process::id()));
+    ctx.save(&path).unwrap();
+    let loaded = EncryptionContext::load(&path, &pk).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    for bit in [0, 1, 1, 0] {
+        let seed = rng.next_u64();
+        let expected = encrypt(&mut ChaCha20Rng::seed_from_u64(seed), &pk, bit);
+        let ct = ctx.encrypt(&mut ChaCha20Rng::seed_from_u64(seed), bit);
+        assert_eq!(ct, expected);
+        assert_eq!(
+            loaded.encrypt(&mut ChaCha20Rng::seed_from_u64(seed), bit),
+            ct
+        );
+        assert_eq!(decrypt(&sk, &ct), bit);
+    }
+}
+
+#[test]
+fn test_context_rejects_other_key() {
+    let params = Params::toy();
+    let mut rng = ChaCha20Rng::seed_from_u64(501);
+    let (_, pk) = gsw_keygen(&mut rng, &params);
+    let (_, other) = gsw_keygen(&mut rng, &params);
+    let bytes = EncryptionContext::build(&pk, &params).unwrap().to_bytes();
+    assert!(EncryptionContext::from_bytes(&bytes, &pk).is_ok());
+    assert!(matches!(
+        EncryptionContext::from_bytes(&bytes, &other),
+        Err(GswError::KeyMismatch)
+    ));
+
+    let pedagogical = Params::pedagogical();
+    let (_, small) = gsw_keygen(&mut rng, &pedagogical);
+    assert!(matches!(
+        EncryptionContext::build(&pk, &pedagogical),
+        Err(GswError::ParamsMismatch)
+    ));
+    assert!(matches!(
+        EncryptionContext::from_bytes(&bytes, &small),
+        Err(GswError::ParamsMismatch)
+    ));
+
+    let mut tampered = bytes.clone();
+    tampered[bytes.len() / 2] ^= 1;
+    assert!(matches!(
+        EncryptionContext::from_bytes(&tampered, &pk),
+        Err(GswError::MalformedData(_))
+    ));
+}
@@ -0,0 +1,91 @@
+//! The evaluation-only build (`--no-default-features`): no RNG, no key
+//! generation, encryption, or decryption. A server loads a session from disk,
+//! evaluates gates, and bootstraps with the loaded evaluation key.
+//!
+//! Run in that configuration with
+//! `cargo test --no-default-features --features blake3,persist --test eval_only`;
+//! the test also passes in the default build.
+
+#![cfg(feature = "persist")]
+
+use gsw_rs::{bootstrap, extract_lwe, homomorphic_nand, trivial_ciphertext, Ciphertext, Session};
+
+/// Pedagogical-parameter session with all three keys. Regenerate with
+/// `cargo test --test eval_only -- --ignored`.
+const FIXTURE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/pedagogical.session"
+);
+
+/// Decryption spelled out by hand, since `decrypt` is not compiled in.
+fn decrypt_by_hand(session: &Session, ct: &Ciphertext) -> u8 {
+    let q = session.params().q;
+    let sk = session.secret_key().expect("fixture holds the secret key");
+    let lwe = extract_lwe(session.params(), ct);
+    let phase = lwe
+        .c
+        .iter()
+        .zip(&sk.s)
+        .fold(0u64, |acc, (&c, &s)| acc.wrapping_add(c.wrapping_mul(s)))
+        % q;
+    (((phase + q / 4) % q) / (q / 2)) as u8
+}
+
+#[test]
+fn test_nand_and_bootstrap_from_fixture() {
+    let session = Session::load(FIXTURE).unwrap();
+    let params = session.params();
+    for a in 0..2 {
+        for b in 0..2 {
+            let nand = homomorphic_nand(
+                params,
+                &trivial_ciphertext(params, a),
+                &trivial_ciphertext(params, b),
+            );
+            let refreshed = session.bootstrap(&nand).unwrap();
+            assert_eq!(
+                refreshed,
+                bootstrap(params, &nand, session.evaluation_key().unwrap())
+            );
+            assert_eq!(
+                decrypt_by_hand(&session, &refreshed),
+                1 - a * b,
+                "{} {}",
+                a,
+                b
+            );
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+#[test]
+#[ignore]
+fn regenerate_fixture() {
+    use gsw_rs::{decrypt, gen_evaluation_key, gsw_keygen, Params};
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    let params = Params::pedagogical();
+    // Bootstrapping at these parameters is only correct for some keys; this
+    // seed gives one for which it is.
+    let mut rng = ChaCha20Rng::seed_from_u64(1);
+    let (sk, pk) = gsw_keygen(&mut rng, &params);
+    let ek = gen_evaluation_key(&mut rng, &sk, &pk);
+    let session = Session::from_parts(params.clone(), Some(sk), pk, Some(ek)).unwrap();
+    for a in 0..2 {
+        for b in 0..2 {
+            let nand = homomorphic_nand(
+                &params,
+                &trivial_ciphertext(&params, a),
+                &trivial_ciphertext(&params, b),
+            );
+            let refreshed = session.bootstrap(&nand).unwrap();
+            assert_eq!(
+                decrypt(session.secret_key().unwrap(), &refreshed),
+                1 - a * b
+            );
+        }
+    }
+    session.save(FIXTURE).unwrap();
+}
@@ -0,0 +1,106 @@
+//! The `try_` variants reject malformed ciphertexts and mismatched
+//! parameters with the matching error, and agree with the infallible
+//! functions on well-formed input.
+
+#![cfg(feature = "client")]
+
+use gsw_rs::{
+    bootstrap, decrypt, encrypt, gen_evaluation_key, gsw_keygen, homomorphic_add, homomorphic_mult,
+    try_bootstrap, try_decrypt, try_homomorphic_add, try_homomorphic_mult, CiphertextView,
+    GswError, Params,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+#[test]
+fn test_well_formed_inputs_succeed() {
+    let params = Params::pedagogical();
+    let mut rng = ChaCha20Rng::seed_from_u64(503);
+    let (sk, pk) = gsw_keygen(&mut rng, &params);
+    let ek = gen_evaluation_key(&mut rng, &sk, &pk);
+    let (x, y) = (encrypt(&mut rng, &pk, 1), encrypt(&mut rng, &pk, 1));
+
+    assert_eq!(try_decrypt(&sk, &x).unwrap(), decrypt(&sk, &x));
+    let view = CiphertextView::rows(&x, params.l - 1..params.l).unwrap();
+    assert_eq!(try_decrypt(&sk, &view).unwrap(), 1);
+    assert_eq!(
+        try_homomorphic_add(&params, &x, &y).unwrap(),
+        homomorphic_add(&params, &x, &y)
+    );
+    assert_eq!(
+        try_homomorphic_mult(&params, &x, &y).unwrap(),
+        homomorphic_mult(&params, &x, &y)
+    );
+    assert_eq!(
+        try_bootstrap(&params, &x, &ek).unwrap(),
+        bootstrap(&params, &x, &ek)
+    );
+}
+
+#[test]
+fn test_malformed_inputs_are_rejected() {
+    let params = Params::pedagogical();
+    let n = params.n_expanded;
+    let mut rng = ChaCha20Rng::seed_from_u64(503);
+    let (sk, pk) = gsw_keygen(&mut rng, &params);
+    let ek = gen_evaluation_key(&mut rng, &sk, &pk);
+    let x = encrypt(&mut rng, &pk, 1);
+
+    let few_rows = x[..params.l - 1].to_vec();
+    let short_row = {
+        let mut ct = x.clone();
+        ct[params.l - 1].pop();
+        ct
+    };
+    assert!(matches!(
+        try_decrypt(&sk, &few_rows),
+        Err(GswError::RowOutOfRange { row, rows }) if row == params.l - 1 && rows == params.l - 1
+    ));
+    assert!(matches!(
+        try_decrypt(&sk, &short_row),
+        Err(GswError::DimensionMismatch { cols, .. }) if cols == n - 1
+    ));
+
+    for bad in [&few_rows, &short_row] {
+        for result in [
+            try_homomorphic_add(&params, &x, bad),
+            try_homomorphic_add(&params, bad, &x),
+            try_homomorphic_mult(&params, &x, bad),
+            try_homomorphic_mult(&params, bad, &x),
+            try_bootstrap(&params, bad, &ek),
+        ] {
+            assert!(matches!(
+                result,
+                Err(GswError::DimensionMismatch { expected, .. }) if expected == n
+            ));
+        }
+    }
+
+    let mut unflattened = x.clone();
+    unflattened[3][5] = 2;
+    assert!(matches!(
+        try_homomorphic_mult(&params, &x, &unflattened),
+        Err(GswError::NotFlattened {
+            row: 3,
+            col: 5,
+            value: 2
+        })
+    ));
+
+    let toy = Params::toy();
+    let (_, toy_pk) = gsw_keygen(&mut rng, &toy);
+    let toy_ct = encrypt(&mut rng, &toy_pk, 1);
+    assert!(matches!(
+        try_bootstrap(&toy, &toy_ct, &ek),
+        Err(GswError::ParamsMismatch)
+    ));
+    assert!(matches!(
+        try_homomorphic_add(&params, &x, &toy_ct),
+        Err(GswError::DimensionMismatch { .. })
+    ));
+    assert!(matches!(
+        try_decrypt(&sk, &toy_ct),
+        Err(GswError::DimensionMismatch { .. })
+    ));
+
+}
@@ -0,0 +1,51 @@
+//! The committed golden files under `tests/golden/` decode, decrypt to the
+//! bits their manifest lists, and are exactly what this build generates.
+//!
+//! A failure here means the wire format or an algorithm changed. Either bump
+//! the format version, or regenerate the files deliberately with
+//! `cargo run --bin gsw-vectors -- tests/golden/wire-v1` and review the diff.
+
+#![cfg(feature = "client")]
+
+use std::path::PathBuf;
+
+use gsw_rs::testing::vectors::{current_tag, GoldenVectors, BITS, DEFAULT_SEED};
+use gsw_rs::{ciphertext_from_bytes, decrypt, EntryPolicy, Session};
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(current_tag())
+}
+
+#[test]
+fn test_regenerated_vectors_match_committed() {
+    let vectors = GoldenVectors::generate(&current_tag(), DEFAULT_SEED);
+    assert_eq!(vectors.differences(&golden_dir()), Vec::<String>::new());
+}
+
+#[test]
+fn test_committed_vectors_decrypt() {
+    let dir = golden_dir();
+    let session = Session::from_bytes(&std::fs::read(dir.join("keys.session")).unwrap()).unwrap();
+    let sk = session
+        .secret_key()
+        .expect("the golden session holds the secret key");
+    let manifest = std::fs::read_to_string(dir.join("manifest.txt")).unwrap();
+    let mut lines = manifest.lines();
+    assert_eq!(
+        lines.next(),
+        Some(format!("# gsw-vectors {} seed {}", current_tag(), DEFAULT_SEED).as_str())
+    );
+
+    let mut checked = 0;
+    for line in lines {
+        let (name, bit) = line.split_once(' ').unwrap();
+        let bytes = std::fs::read(dir.join(name)).unwrap();
+        let ct = ciphertext_from_bytes(session.params(), &bytes, EntryPolicy::Reject).unwrap();
+        assert_eq!(decrypt(sk, &ct).to_string(), bit, "{}", name);
+        checked += 1;
+    }
+    // Fresh ciphertexts, four gates on four pairs, and two NOTs.
+    assert_eq!(checked, BITS.len() + 4 * 4 + 2);
+}
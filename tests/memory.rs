@@ -0,0 +1,98 @@
+//! Memory high-water marks: a product holds the matrices the analysis
+//! predicts, and a circuit's ciphertexts are bounded by its live wires, not
+//! its length.
+
+#![cfg(all(feature = "client", feature = "metrics"))]
+
+use gsw_rs::metrics::is_tracking_installed;
+use gsw_rs::{
+    decrypt, encrypt, gsw_keygen, homomorphic_mult, with_memory_tracking, Circuit, Evaluator,
+    Params, TrackingAllocator,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator::system();
+
+/// Heap bytes of one N×N ciphertext: the rows and the row vector.
+fn matrix_bytes(params: &Params) -> usize {
+    let n = params.n_expanded;
+    n * n * 8 + n * std::mem::size_of::<Vec<u64>>()
+}
+
+#[test]
+fn test_mult_peak_matches_analysis() {
+    assert!(is_tracking_installed());
+    let params = Params::toy();
+    let mut rng = ChaCha20Rng::seed_from_u64(492);
+    let (sk, pk) = gsw_keygen(&mut rng, &params);
+    let a = encrypt(&mut rng, &pk, 1);
+    let b = encrypt(&mut rng, &pk, 1);
+
+    let (product, stats) = with_memory_tracking(|| homomorphic_mult(&params, &a, &b));
+    assert_eq!(decrypt(&sk, &product), 1);
+    // The operands were held before the region; inside it, the raw product
+    // and its flattening coexist, plus a row or so of scratch.
+    let matrix = matrix_bytes(&params);
+    let row = params.n_expanded * 8;
+    assert!(
+        stats.peak_bytes >= 2 * matrix && stats.peak_bytes <= 2 * matrix + 4 * row,
+        "peak {} bytes, expected about {}",
+        stats.peak_bytes,
+        2 * matrix
+    );
+    // Only the result outlives the region: with the operands, three
+    // matrices.
+    assert_eq!(stats.retained_bytes, matrix as isize);
+
+    // Regions nest, and the inner peak shows in the outer one.
+    let ((_, inner), outer) = with_memory_tracking(|| {
+        let held = homomorphic_mult(&params, &a, &b);
+        let inner = with_memory_tracking(|| homomorphic_mult(&params, &held, &b));
+        (held, inner.1)
+    });
+    assert_eq!(inner.peak_bytes, stats.peak_bytes);
+    assert!(outer.peak_bytes >= matrix + inner.peak_bytes);
+}
+
+/// x XOR y XOR y ... with `gates` XORs; two wires are live at any time.
+fn xor_chain(gates: usize) -> Circuit {
+    let mut circuit = Circuit::new(2);
+    let mut x = 0;
+    for _ in 0..gates {
+        x = circuit.xor(x, 1);
+    }
+    circuit.set_outputs(&[x]);
+    circuit
+}
+
+#[test]
+fn test_circuit_memory_follows_live_wires() {
+    let params = Params::toy();
+    let mut rng = ChaCha20Rng::seed_from_u64(492);
+    let (sk, pk) = gsw_keygen(&mut rng, &params);
+    let eval = Evaluator::new(&params);
+    let inputs = [
+        eval.encrypt(&mut rng, &pk, true),
+        eval.encrypt(&mut rng, &pk, true),
+    ];
+    let matrix = matrix_bytes(&params);
+
+    let mut peaks = Vec::new();
+    for gates in [4, 40] {
+        let circuit = xor_chain(gates);
+        let (outputs, stats) = eval
+            .evaluate_circuit_with_memory(&circuit, &inputs)
+            .unwrap();
+        assert_eq!(outputs[0].decrypt(&sk), gates % 2 == 0);
+        // Both inputs, the running value, and the gate's fresh result.
+        assert_eq!(stats.peak_live_ciphertexts, 3);
+        assert_eq!(stats.peak_ciphertext_bytes, 3 * matrix);
+        assert!(stats.peak_scratch_bytes > 0);
+        assert!(stats.peak_scratch_bytes <= 2 * matrix);
+        peaks.push(stats.total.peak_bytes);
+    }
+    // Ten times the gates, the same high-water mark.
+    assert!(peaks[1] <= peaks[0] + matrix / 4, "peaks {:?}", peaks);
+}
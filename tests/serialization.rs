@@ -0,0 +1,82 @@
+//! The `serde` feature: params, keys, evaluation keys, and ciphertexts
+//! survive JSON and bincode, and inconsistent params are rejected.
+//!
+//! Run with `cargo test --features serde --test serialization`.
+
+#![cfg(all(feature = "client", feature = "serde"))]
+
+use gsw_rs::{
+    decrypt, encrypt, gen_evaluation_key, gsw_keygen, homomorphic_mult, Ciphertext, EvaluationKey,
+    Params, PublicKey, SecretKey,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// `value` through JSON and through bincode, each of which must agree with
+/// the other on the result.
+fn round_trip<T: Serialize + DeserializeOwned>(value: &T) -> T {
+    let json: T = serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap();
+    let binary: T = bincode::deserialize(&bincode::serialize(value).unwrap()).unwrap();
+    assert_eq!(
+        serde_json::to_string(&json).unwrap(),
+        serde_json::to_string(&binary).unwrap()
+    );
+    binary
+}
+
+#[test]
+fn test_keys_and_ciphertexts_round_trip() {
+    let params = Params::toy();
+    let mut rng = ChaCha20Rng::seed_from_u64(502);
+    let (sk, pk) = gsw_keygen(&mut rng, &params);
+    let ek = gen_evaluation_key(&mut rng, &sk, &pk);
+
+    let params2: Params = round_trip(&params);
+    let sk2: SecretKey = round_trip(&sk);
+    let pk2: PublicKey = round_trip(&pk);
+    let ek2: EvaluationKey = round_trip(&ek);
+    assert_eq!(params2, params);
+    assert_eq!((sk2.s.clone(), sk2.params()), (sk.s.clone(), &params));
+    assert_eq!((pk2.a.clone(), pk2.params()), (pk.a.clone(), &params));
+    assert_eq!(ek2.to_bytes(), ek.to_bytes());
+
+    for (a, b) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+        let ct_a: Ciphertext = round_trip(&encrypt(&mut rng, &pk2, a));
+        let ct_b: Ciphertext = round_trip(&encrypt(&mut rng, &pk, b));
+        let product: Ciphertext = round_trip(&homomorphic_mult(&params2, &ct_a, &ct_b));
+        assert_eq!(decrypt(&sk2, &product), a & b);
+        assert_eq!(decrypt(&sk, &product), a & b);
+    }
+}
+
+#[test]
+fn test_inconsistent_params_are_rejected() {
+    let params = Params::toy();
+    let json = serde_json::to_value(&params).unwrap();
+    assert_eq!(json["l"], params.l as u64);
+    assert!(json.get("target_failure_exp").is_none());
+
+    for (field, value) in [
+        ("l", params.l as u64 + 1),
+        ("n_expanded", params.n_expanded as u64 - 1),
+        ("q", params.q + 1),
+        ("n", 0),
+    ] {
+        let mut tampered = json.clone();
+        tampered[field] = value.into();
+        assert!(
+            serde_json::from_value::<Params>(tampered).is_err(),
+            "accepted {} = {}",
+            field,
+            value
+        );
+    }
+
+    let mut rng = ChaCha20Rng::seed_from_u64(502);
+    let (_, pk) = gsw_keygen(&mut rng, &params);
+    let mut bytes = bincode::serialize(&pk).unwrap();
+    bytes.pop();
+    assert!(bincode::deserialize::<PublicKey>(&bytes).is_err());
+}
@@ -0,0 +1,7 @@
+// Without the `experimental` feature, gsw_rs::experimental does not exist.
+
+use gsw_rs::experimental::multikey::bootstrap_multikey;
+
+fn main() {
+    let _ = bootstrap_multikey;
+}
@@ -0,0 +1,10 @@
+// With the `experimental` feature, using gsw_rs::experimental warns until
+// acknowledged; denying the warning makes that visible here.
+
+#![deny(deprecated)]
+
+use gsw_rs::experimental::multikey::bootstrap_multikey;
+
+fn main() {
+    let _ = bootstrap_multikey;
+}
@@ -0,0 +1,33 @@
+// The README's usage example, with nothing imported from gsw_rs but the
+// prelude. It is compiled, not run: bootstrapping at Toy parameters does not
+// reliably refresh.
+
+use gsw_rs::prelude::*;
+use rand::thread_rng;
+
+#[allow(dead_code)]
+fn readme_example() {
+    let params = Params::toy();
+    let mut rng = thread_rng();
+    let (sk, pk) = gsw_keygen(&mut rng, &params);
+
+    // Encrypt bits
+    let ct0 = encrypt(&mut rng, &pk, 0);
+    let ct1 = encrypt(&mut rng, &pk, 1);
+
+    // Homomorphic XOR (addition mod 2)
+    let ct_xor = homomorphic_add(&params, &ct0, &ct1);
+    assert_eq!(decrypt(&sk, &ct_xor), 1);
+
+    // Homomorphic AND (multiplication)
+    let ct_and = homomorphic_mult(&params, &ct1, &ct1);
+    assert_eq!(decrypt(&sk, &ct_and), 1);
+
+    // Bootstrapping (requires evaluation key)
+    let ek = gen_evaluation_key(&mut rng, &sk, &pk);
+    let ct_noisy = homomorphic_mult(&params, &ct1, &ct1);
+    let ct_refreshed = bootstrap(&params, &ct_noisy, &ek);
+    assert_eq!(decrypt(&sk, &ct_refreshed), 1);
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+// AsCiphertextRows is sealed: no type outside gsw_rs can implement it.
+
+use std::ops::Range;
+
+use gsw_rs::AsCiphertextRows;
+
+struct Rows(Vec<Vec<u64>>);
+
+impl AsCiphertextRows for Rows {
+    fn row(&self, i: usize) -> Option<&[u64]> {
+        self.0.get(i).map(Vec::as_slice)
+    }
+
+    fn row_range(&self) -> Range<usize> {
+        0..self.0.len()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,97 @@
+//! The auditor's check in `gsw_rs::verify`: verdicts on fresh, noisy,
+//! misclaimed, and malformed rows, and that the module stays standalone.
+
+#![cfg(feature = "client")]
+
+use gsw_rs::verify::{check_plaintext, VerifyOutcome};
+use gsw_rs::{
+    encrypt, gsw_keygen, homomorphic_add, homomorphic_mult, trivial_ciphertext, AsCiphertextRows,
+    CiphertextView, DecryptionContext, Params,
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+#[test]
+fn test_claims_are_checked() {
+    let params = Params::toy();
+    let l = params.l;
+    let mut rng = ChaCha20Rng::seed_from_u64(499);
+    let (sk, pk) = gsw_keygen(&mut rng, &params);
+    let v = sk.decryption_vector();
+    let lite = sk.verify_params();
+    let check = |ct_row: &[u64], bit| check_plaintext(&v, &lite, ct_row, bit);
+
+    for bit in [0, 1] {
+        let ct = encrypt(&mut rng, &pk, bit);
+        let view = CiphertextView::rows(&ct, l - 1..l).unwrap();
+        let row = view.row(l - 1).unwrap();
+        assert_eq!(check(row, bit), VerifyOutcome::Match);
+        assert_eq!(check(row, 1 - bit), VerifyOutcome::Mismatch);
+    }
+
+    // Noisier than fresh, still well inside the decryption bound.
+    let ctx = DecryptionContext::new(&sk);
+    let ones: Vec<_> = (0..3).map(|_| encrypt(&mut rng, &pk, 1)).collect();
+    let noisy = homomorphic_add(
+        &params,
+        &homomorphic_mult(&params, &ones[0], &ones[1]),
+        &ones[2],
+    );
+    assert!(ctx.measure_noise(&noisy).abs() > ctx.measure_noise(&ones[2]).abs());
+    assert_eq!(check(&noisy[l - 1], 0), VerifyOutcome::Match);
+    assert_eq!(check(&noisy[l - 1], 1), VerifyOutcome::Mismatch);
+
+    // Phase 3q/16: nearer 0 than q/2, but past the quarter-scale threshold.
+    let mut row = trivial_ciphertext(&params, 0)[l - 1].clone();
+    row[l - 3] = 1;
+    row[l - 4] = 1;
+    let outcome = VerifyOutcome::Inconclusive {
+        margin: params.q / 16,
+    };
+    assert_eq!((check(&row, 0), check(&row, 1)), (outcome, outcome));
+
+    // Garbage: full-size entries, and rows of the wrong length.
+    let garbage: Vec<u64> = (0..params.n_expanded)
+        .map(|_| rng.gen_range(0..params.q))
+        .collect();
+    let malformed = VerifyOutcome::Inconclusive { margin: 0 };
+    for row in [&garbage[..], &garbage[..0], &noisy[l - 1][1..]] {
+        assert_eq!(check(row, 0), malformed);
+        assert_eq!(check(row, 1), malformed);
+    }
+}
+
+/// The module is meant to be reviewed alone: its code (comments aside)
+/// names no other part of the crate than `modular` and no external crate.
+#[test]
+fn test_module_is_standalone() {
+    let source = include_str!("../src/verify.rs");
+    let code: Vec<&str> = source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with("//"))
+        .collect();
+    for line in &code {
+        for (i, _) in line.match_indices("crate::") {
+            assert!(
+                line[i..].starts_with("crate::modular"),
+                "intra-crate path in verify.rs: {}",
+                line
+            );
+        }
+        assert!(
+            !line.contains("super::"),
+            "parent path in verify.rs: {}",
+            line
+        );
+        if let Some(path) = line.strip_prefix("use ") {
+            assert!(
+                ["std::", "core::", "crate::modular"]
+                    .iter()
+                    .any(|allowed| path.starts_with(allowed)),
+                "import in verify.rs: {}",
+                line
+            );
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! [`CiphertextView`]s read rows in place: a view over the decryption row
+//! agrees with the owned ciphertext and decrypts without touching the heap.
+
+#![cfg(feature = "client")]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use gsw_rs::bootstrap::decrypt_linear_part_clear;
+use gsw_rs::error::GswError;
+use gsw_rs::{
+    decrypt, encrypt, extract_lwe, gsw_keygen, homomorphic_mult, lwe_decrypt, AsCiphertextRows,
+    CiphertextView, DecryptionContext, Params,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+/// The system allocator, counting allocations made by the current thread so
+/// that tests running alongside do not interfere.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+#[test]
+fn test_view_of_decryption_row_matches_owned() {
+    let params = Params::toy();
+    let mut rng = ChaCha20Rng::seed_from_u64(483);
+    let (sk, pk) = gsw_keygen(&mut rng, &params);
+    let ctx = DecryptionContext::new(&sk);
+    let row = params.l - 1;
+    for (a, b) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+        let ct = homomorphic_mult(
+            &params,
+            &encrypt(&mut rng, &pk, a),
+            &encrypt(&mut rng, &pk, b),
+        );
+        let view = CiphertextView::rows(&ct, row..row + 1).unwrap();
+        assert_eq!(view.row_range(), row..row + 1);
+        assert_eq!(view.row(row), Some(ct[row].as_slice()));
+        assert_eq!(view.row(0), None);
+
+        assert_eq!(decrypt(&sk, &view), a & b);
+        assert_eq!(ctx.decrypt(&view), ctx.decrypt(&ct));
+        assert_eq!(ctx.measure_noise(&view), ctx.measure_noise(&ct));
+        assert_eq!(extract_lwe(&params, &view), extract_lwe(&params, &ct));
+        assert_eq!(lwe_decrypt(&sk, &extract_lwe(&params, &view)), a & b);
+        assert_eq!(
+            decrypt_linear_part_clear(&sk, &view, None).unwrap(),
+            decrypt_linear_part_clear(&sk, &ct, None).unwrap()
+        );
+        assert!(matches!(
+            decrypt_linear_part_clear(&sk, &view, Some(0)),
+            Err(GswError::RowOutOfRange { row: 0, .. })
+        ));
+        assert_eq!(
+            ctx.decrypt_many(&[view, CiphertextView::new(&ct)]),
+            [a & b; 2]
+        );
+    }
+}
+
+#[test]
+fn test_view_decryption_does_not_allocate() {
+    let params = Params::toy();
+    let mut rng = ChaCha20Rng::seed_from_u64(483);
+    let (sk, pk) = gsw_keygen(&mut rng, &params);
+    let ctx = DecryptionContext::new(&sk);
+    let cts: Vec<_> = (0..8).map(|i| encrypt(&mut rng, &pk, i & 1)).collect();
+
+    let before = allocations();
+    let mut bits = 0u8;
+    for (i, ct) in cts.iter().enumerate() {
+        let view = CiphertextView::rows(ct, params.l - 1..params.l).unwrap();
+        bits |= ctx.decrypt(&view) << i;
+        let _ = ctx.measure_noise(&view);
+    }
+    assert_eq!(allocations(), before);
+    assert_eq!(bits, 0b1010_1010);
+}
+
+#[test]
+fn test_view_selection_stays_in_range() {
+    let params = Params::toy();
+    let ct = vec![vec![0u64; params.n_expanded]; params.n_expanded];
+    let n = params.n_expanded;
+    let whole = CiphertextView::new(&ct);
+    assert_eq!(whole.len(), n);
+
+    let middle = whole.select(10..20).unwrap();
+    assert_eq!(middle.row_range(), 10..20);
+    assert_eq!(middle.select(12..14).unwrap().row_range(), 12..14);
+    assert!(middle.select(15..15).unwrap().is_empty());
+    assert!(matches!(
+        middle.select(5..15),
+        Err(GswError::RowOutOfRange { row: 5, rows: 20 })
+    ));
+    assert!(matches!(
+        CiphertextView::rows(&ct, 0..n + 1),
+        Err(GswError::RowOutOfRange { row, rows }) if row == n && rows == n
+    ));
+}